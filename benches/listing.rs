@@ -0,0 +1,28 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mystore::manager::{FileManager, SortOrder};
+use std::fs;
+use tempfile::TempDir;
+
+const FILES: usize = 2_000;
+
+fn list_by_modification_time(c: &mut Criterion) {
+    let dir = TempDir::new().unwrap();
+    for id in 0..FILES {
+        fs::write(dir.path().join(format!("file-{:05}.txt", id)), "").unwrap();
+    }
+    let mut manager = FileManager::new(dir.path().to_str().unwrap()).unwrap();
+    manager.set_sort_order(SortOrder::Modified).unwrap();
+
+    let mut group = c.benchmark_group("list 2k files by modification time");
+    for parallel in [false, true] {
+        manager.set_parallel_read(parallel);
+        let name = if parallel { "parallel" } else { "serial" };
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| manager.refresh().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, list_by_modification_time);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use mystore::{
+    app::{App, AppEvent},
+    ui,
+};
+use std::fs;
+use tempfile::TempDir;
+use tui::{backend::TestBackend, Terminal};
+
+const ENTITIES: usize = 10_000;
+
+fn render_manager(c: &mut Criterion) {
+    let dir = TempDir::new().unwrap();
+    for id in 0..ENTITIES {
+        fs::write(dir.path().join(format!("file-{:05}.txt", id)), "").unwrap();
+    }
+    let mut app = App::new(dir.path().to_str().unwrap(), "secret").unwrap();
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Down,
+        KeyModifiers::NONE,
+    )));
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+    c.bench_function("draw 10k-entity manager", |b| {
+        b.iter(|| {
+            terminal.draw(|f| ui::draw(f, &app)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, render_manager);
+criterion_main!(benches);
@@ -0,0 +1,2887 @@
+use crate::{
+    batch::{BackgroundBatch, BatchOp},
+    bookmarks::Bookmarks,
+    checksum::{BackgroundHasher, Checksum},
+    clipboard::{Clipboard, ClipboardWatch, SystemClipboard, CLIPBOARD_POLL_INTERVAL},
+    duplicates::BackgroundDuplicates,
+    editor::Editor,
+    error::{MystoreError, Result},
+    git::GitSync,
+    grep::{self, GrepResult},
+    journal::{Journal, Operation},
+    keybindings::{AppMode, KeyBindings},
+    keyring::SessionKey,
+    loader::{self, BackgroundLoader, FsLoader, Loader},
+    locks::BackgroundLockSweep,
+    manager::{ConflictResolution, FileManager, ManagerEntity, Respond, SshConfig},
+    s3::S3Config,
+    session::SessionState,
+    sizes::{self, format_size, BackgroundSizer},
+    tags::{self, TagIndex},
+    timings::Timings,
+    urls::{self, SystemOpener, UrlOpener},
+    viewer::{Viewer, ViewerEntity},
+};
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use git2::Oid;
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use subtle::ConstantTimeEq;
+use tempfile::TempDir;
+use tui::layout::Rect;
+use tui_textarea::TextArea;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Tick,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Confirm {
+    CleanupLocks,
+    CopyChecksum(Checksum),
+    DeleteDuplicates,
+    /// Asks before writing decrypted text out in plain.
+    ExportDecrypted,
+    LeaveEditor,
+}
+
+/// How the right-hand pane is shared by the two viewers of a split view.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SplitLayout {
+    #[default]
+    Stacked,
+    SideBySide,
+}
+
+/// Two files shown next to each other, see `Mode::Split`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Split {
+    pub layout: SplitLayout,
+    /// Whether the scroll keys go to the second viewer.
+    pub second_focused: bool,
+    /// Whether the scroll keys move both viewers.
+    pub scroll_lock: bool,
+}
+
+/// What the letter typed after `m` or `'` in the viewer does.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mark {
+    Set,
+    Jump,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Prompt {
+    ExportMarkdown,
+    MergeDir,
+    EditTags,
+    FilterExtensions,
+    GoTo,
+    ExportZip {
+        encrypt: bool,
+    },
+    SearchViewer,
+    /// Asks for the column the lines of the editor are wrapped at.
+    HardWrap,
+    /// Asks for the name of a new note on its first save.
+    SaveNote {
+        encrypt: bool,
+        exit: bool,
+    },
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prompt::ExportMarkdown => write!(f, "Export as markdown to"),
+            Prompt::MergeDir => write!(f, "Copy the files of the folder"),
+            Prompt::EditTags => write!(f, "Tags, separated by commas"),
+            Prompt::FilterExtensions => write!(f, "Show only the extensions, separated by commas"),
+            Prompt::GoTo => write!(f, "Go to the path, Tab completes"),
+            Prompt::ExportZip { encrypt: false } => write!(f, "Export the created files as ZIP to"),
+            Prompt::ExportZip { encrypt: true } => {
+                write!(f, "Export the created files as encrypted ZIP to")
+            }
+            Prompt::SearchViewer => write!(f, "Search for the regex"),
+            Prompt::HardWrap => write!(f, "Wrap the lines at the column"),
+            Prompt::SaveNote { encrypt: false, .. } => write!(f, "Save the note as"),
+            Prompt::SaveNote { encrypt: true, .. } => write!(f, "Save the encrypted note as"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    Manager,
+    Viewer,
+    Editor,
+    Templates,
+    Tags,
+    /// Lists the URLs found in the viewed text to pick the one to open.
+    UrlList,
+    Rename,
+    /// Asks for the message to commit the files created in the session with.
+    Commit,
+    Grep,
+    Duplicates,
+    /// Shows the selected JSON line in full over the viewer.
+    JsonRecord,
+    /// Charts the sizes of the listed files.
+    SizeChart,
+    /// Charts how many listed files were modified lately.
+    AgeChart,
+    /// Waits for the letter of a mark, listing the marks over the viewer.
+    Mark(Mark),
+    /// Picks the file to show next to the viewed one in the manager.
+    PickSplit,
+    /// Picks the file to insert into the editor, from the manager shown
+    /// over it.
+    InsertFile,
+    /// Shows the viewed file and a second one in a split pane.
+    Split,
+    Confirm(Confirm),
+    Prompt(Prompt),
+    /// Asks for the password again before going back to the mode it holds.
+    Reauth(Box<Mode>),
+    Exit,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mode::Manager => {
+                let help_manager = [
+                    String::from("Esc: End the session"),
+                    String::from("Down: Select next item"),
+                    String::from("Up: Select previous item"),
+                    String::from("PgDn, PgUp: Next or previous page"),
+                    String::from("Home, End: Select the first or last item, End reads more of a large folder"),
+                    String::from("W: Wrap around at the ends of the list or stop there"),
+                    String::from(".: Show or hide files named with a leading dot"),
+                    String::from("<, >: Narrow or widen the manager"),
+                    String::from("Enter: Action on the selected item"),
+                    String::from("Backspace, Left: Go back"),
+                    String::from("~: Go to the root folder"),
+                    String::from("G: Go to a path relative to the root"),
+                    String::from("Ctrl + G: Search the listed files"),
+                    String::from("E: Open the editor"),
+                    String::from("N: Create a new editor instance"),
+                    String::from("D: Delete the selected item"),
+                    String::from("Space: Mark or unmark the selected file"),
+                    String::from("U: Compute the size of the selected folder, shown next to it"),
+                    String::from("Ctrl + U: Undo the last deletion or rename"),
+                    String::from("Ctrl + S: Tell the size of the selected folder on the message line"),
+                    String::from("Ctrl + Shift + S: Chart the sizes of the listed files"),
+                    String::from("Ctrl + Shift + A: Chart the ages of the listed files"),
+                    String::from("H: Compute the SHA-256 of the selected file"),
+                    String::from("R: Open the latest text recovered from a crash"),
+                    String::from("Ctrl + F: Find files with the same contents"),
+                    String::from("S: Sort files by modification time, size or name"),
+                    String::from("Ctrl + R: Rename the marked files"),
+                    String::from("Ctrl + Shift + C: Commit the created files to git"),
+                    String::from("Ctrl + E, Ctrl + D: Encrypt or decrypt the marked files"),
+                    String::from("Y: Copy the absolute path of the selected item"),
+                    String::from("Ctrl + Y: Copy the path relative to the root"),
+                    String::from("Ctrl + T: Create a new file from a template"),
+                    String::from("F: Filter files by tag"),
+                    String::from("X: Filter files by extension, Esc clears the filter"),
+                    String::from("Ctrl + M: Copy files of another folder here"),
+                    String::from("Z: Export the files created in the session as ZIP"),
+                    String::from("Ctrl + Z: Export them as ZIP encrypted with the session key"),
+                    String::from("L: Lock or unlock the selected file"),
+                    String::from("Ctrl + L: Remove stale lock files"),
+                    String::from("Ctrl + Q: Start or stop recording a macro"),
+                    String::from("Ctrl + Shift + Q: Replay the macro"),
+                    String::from("Ctrl + Shift + V: Save every text copied to the clipboard"),
+                    String::from("F1: Show or hide this help"),
+                ];
+                write!(f, "Manager mode\n{}", help_manager.join("; "))
+            }
+            Mode::Viewer => {
+                let help_viewer = [
+                    String::from("Esc, Q: Quit"),
+                    String::from("Down, Up: Scroll the viewer, or select a JSON line"),
+                    String::from("Space, F: Page down"),
+                    String::from("B: Page up"),
+                    String::from("D, U: Half a page down or up"),
+                    String::from("G, Shift + G: Go to the top or the bottom"),
+                    String::from("/: Search for a regex"),
+                    String::from("N, Shift + N: Go to the next or previous match"),
+                    String::from("Enter: Show the selected JSON line in full"),
+                    String::from("A: Toggle stripping of ANSI escape codes"),
+                    String::from("W: Show tabs, trailing spaces and control characters"),
+                    String::from("E: Decode the text with the next encoding"),
+                    String::from("R: Reload the file"),
+                    String::from("Ctrl + A: Reload the file every second"),
+                    String::from("Ctrl + B: Bookmark the top line or remove its bookmark"),
+                    String::from("[, ]: Jump to the previous or next bookmark"),
+                    String::from("M, letter: Mark the position under the letter"),
+                    String::from("', letter: Go back to the marked position"),
+                    String::from("C: Compare with another file in a split view"),
+                    String::from("O: Open a URL of the text in the browser"),
+                    String::from("V: View the text in the editor without changing it"),
+                    String::from("Ctrl + M: Export as markdown"),
+                    String::from("T: Switch tables and JSON to raw text and back"),
+                    String::from("#: Edit the tags"),
+                ];
+                write!(f, "Viewer mode\n{}", help_viewer.join("; "))
+            }
+            Mode::Editor => {
+                let help_editor = [
+                    String::from("Esc: Quit"),
+                    String::from("Ctrl + S: Save, the first save asks for the name"),
+                    String::from("Ctrl + E: Save encrypted, later saves keep the first choice"),
+                    String::from("Ctrl + Shift + S, Ctrl + Shift + E: Save and close"),
+                    String::from("Ctrl + K: Check spelling"),
+                    String::from("Ctrl + I: Insert the text of a file"),
+                    String::from("Ctrl + W: Wrap long lines at a column"),
+                    String::from("Ctrl + T, Ctrl + Shift + T: Insert the local time or date"),
+                    String::from("Ctrl + V: Expand $DATE, $USER and $FILENAME on save"),
+                    String::from("Other: See TextArea help"),
+                ];
+                write!(f, "Editor mode\n{}", help_editor.join("; "))
+            }
+            Mode::Templates => {
+                let help_templates = [
+                    String::from("Esc: Quit"),
+                    String::from("Down: Select next template"),
+                    String::from("Up: Select previous template"),
+                    String::from("Enter: Create a new file from the selected template"),
+                ];
+                write!(f, "Templates mode\n{}", help_templates.join("; "))
+            }
+            Mode::Rename => {
+                let help_rename = [
+                    String::from("Esc: Quit"),
+                    String::from("First line: Regex pattern"),
+                    String::from("Second line: Replacement, $1 refers to a group"),
+                    String::from("Enter: Go to the replacement, then rename"),
+                ];
+                write!(f, "Rename mode\n{}", help_rename.join("; "))
+            }
+            Mode::Commit => {
+                let help_commit = [
+                    String::from("Esc: Quit"),
+                    String::from("Ctrl + S: Commit the created files with the message"),
+                    String::from("Other: See TextArea help"),
+                ];
+                write!(f, "Commit mode\n{}", help_commit.join("; "))
+            }
+            Mode::Tags => {
+                let help_tags = [
+                    String::from("Esc: Quit"),
+                    String::from("Down: Select next tag"),
+                    String::from("Up: Select previous tag"),
+                    String::from("Enter: List the files with the selected tag"),
+                ];
+                write!(f, "Tags mode\n{}", help_tags.join("; "))
+            }
+            Mode::UrlList => {
+                let help_urls = [
+                    String::from("Esc: Back to the viewer"),
+                    String::from("Down: Select next URL"),
+                    String::from("Up: Select previous URL"),
+                    String::from("Enter: Open the selected URL"),
+                ];
+                write!(f, "URLs\n{}", help_urls.join("; "))
+            }
+            Mode::Grep => {
+                let help_grep = [
+                    String::from("Esc: Quit"),
+                    String::from("Enter: Search the listed files, then open the selected line"),
+                    String::from("Down: Select next line"),
+                    String::from("Up: Select previous line"),
+                ];
+                write!(f, "Grep mode\n{}", help_grep.join("; "))
+            }
+            Mode::Duplicates => {
+                let help_duplicates = [
+                    String::from("Esc: Quit"),
+                    String::from("Down: Select next copy"),
+                    String::from("Up: Select previous copy"),
+                    String::from("Enter: Go to the selected copy"),
+                    String::from("Space: Mark or unmark the copy for deletion"),
+                    String::from("D: Delete the marked copies"),
+                ];
+                write!(f, "Duplicates mode\n{}", help_duplicates.join("; "))
+            }
+            Mode::JsonRecord => {
+                let help_record = [
+                    String::from("Down, Up: Scroll the JSON"),
+                    String::from("Other: Close"),
+                ];
+                write!(f, "JSON line\n{}", help_record.join("; "))
+            }
+            Mode::SizeChart => write!(f, "Size chart\nAny key: Back to the manager"),
+            Mode::AgeChart => write!(f, "Age chart\nAny key: Back to the manager"),
+            Mode::PickSplit => {
+                let help_pick = [
+                    String::from("Esc: Back to the viewer"),
+                    String::from("Down, Up, PgDn, PgUp, Home, End: Select a file"),
+                    String::from("Backspace, Left, ~: Go back or to the root folder"),
+                    String::from("Enter: Compare with the selected file, or open the folder"),
+                ];
+                write!(f, "Pick a file to compare with\n{}", help_pick.join("; "))
+            }
+            Mode::InsertFile => {
+                let help_insert = [
+                    String::from("Esc: Back to the editor"),
+                    String::from("Down, Up, PgDn, PgUp, Home, End: Select a file"),
+                    String::from("Backspace, Left, ~: Go back or to the root folder"),
+                    String::from("Enter: Insert the selected file, or open the folder"),
+                ];
+                write!(f, "Pick a file to insert\n{}", help_insert.join("; "))
+            }
+            Mode::Split => {
+                let help_split = [
+                    String::from("Esc, Q: Close the second file"),
+                    String::from("Tab: Switch the viewer the keys scroll"),
+                    String::from("L: Scroll both viewers together or apart"),
+                    String::from("V: Stack the viewers or put them side by side"),
+                    String::from("Down, Up: Scroll"),
+                    String::from("Space, F, B: Page down or up"),
+                    String::from("D, U: Half a page down or up"),
+                    String::from("G, Shift + G: Go to the top or the bottom"),
+                ];
+                write!(f, "Split view\n{}", help_split.join("; "))
+            }
+            Mode::Mark(mark) => {
+                let help_mark = [
+                    String::from(match mark {
+                        Mark::Set => "A-Z, a-z: Mark the position under the letter",
+                        Mark::Jump => "A-Z, a-z: Go back to the position marked with the letter",
+                    }),
+                    String::from("Other: Cancel"),
+                ];
+                write!(f, "Marks\n{}", help_mark.join("; "))
+            }
+            Mode::Confirm(confirm) => {
+                let question = match confirm {
+                    Confirm::CleanupLocks => {
+                        String::from("Remove stale lock files under the root?")
+                    }
+                    Confirm::CopyChecksum(checksum) => format!(
+                        "Copy the SHA-256 of {} to the clipboard?",
+                        checksum
+                            .path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                    ),
+                    Confirm::DeleteDuplicates => String::from("Delete the marked copies for good?"),
+                    Confirm::ExportDecrypted => {
+                        String::from("The text is decrypted, export it unencrypted anyway?")
+                    }
+                    Confirm::LeaveEditor => String::from(
+                        "Leave the editor with unsaved changes? E opens the text again",
+                    ),
+                };
+                write!(f, "{}\nY: Yes; Other: No", question)
+            }
+            Mode::Prompt(prompt) => write!(f, "{}\nEnter: Confirm; Esc: Cancel", prompt),
+            Mode::Reauth(_back) => write!(
+                f,
+                "Type the session password to continue\nEnter: Confirm; Esc: End the session"
+            ),
+            Mode::Exit => write!(f, "End the session"),
+        }
+    }
+}
+
+impl Mode {
+    /// Whether the text of the editor is shown and kept in the mode.
+    pub fn is_editing(&self) -> bool {
+        matches!(
+            self,
+            Mode::Editor
+                | Mode::InsertFile
+                | Mode::Prompt(Prompt::HardWrap | Prompt::SaveNote { .. })
+                | Mode::Confirm(Confirm::LeaveEditor)
+        )
+    }
+
+    /// The short name of the mode, for the mode line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Manager => "MANAGER",
+            Mode::Viewer => "VIEWER",
+            Mode::Editor => "EDITOR",
+            Mode::Templates => "TEMPLATES",
+            Mode::Tags => "TAGS",
+            Mode::UrlList => "URLS",
+            Mode::Rename => "RENAME",
+            Mode::Commit => "COMMIT",
+            Mode::Grep => "GREP",
+            Mode::Duplicates => "DUPLICATES",
+            Mode::JsonRecord => "JSON",
+            Mode::SizeChart => "SIZES",
+            Mode::AgeChart => "AGES",
+            Mode::Mark(_mark) => "MARK",
+            Mode::PickSplit => "PICK",
+            Mode::InsertFile => "INSERT",
+            Mode::Split => "SPLIT",
+            Mode::Confirm(_confirm) => "CONFIRM",
+            Mode::Prompt(_prompt) => "PROMPT",
+            Mode::Reauth(_back) => "LOCKED",
+            Mode::Exit => "EXIT",
+        }
+    }
+}
+
+pub struct App<'a> {
+    manager: FileManager,
+    viewer: Viewer,
+    second_viewer: Viewer,
+    editor: Editor<'a>,
+    loader: BackgroundLoader,
+    second_loader: BackgroundLoader,
+    split: Option<Split>,
+    mode: Mode,
+    status: Result<()>,
+    message: Option<String>,
+    macro_recording: Option<Vec<KeyEvent>>,
+    macro_keys: Vec<KeyEvent>,
+    choices: Vec<String>,
+    choice_selected: usize,
+    viewed: Option<PathBuf>,
+    key: SessionKey,
+    rename: TextArea<'a>,
+    grep: TextArea<'a>,
+    commit: TextArea<'a>,
+    last_commit: Option<Oid>,
+    grep_query: String,
+    grep_results: Vec<GrepResult>,
+    grep_selected: usize,
+    input: String,
+    git: Option<GitSync>,
+    git_dirty: Option<bool>,
+    /// Deletions and renames of the session that can be undone, dropped
+    /// with the session.
+    journal: Journal,
+    keybindings: KeyBindings,
+    /// What the debug overlay shows, collected only while it is shown.
+    timings: Timings,
+    /// Whether the keys of the mode are shown over the panes.
+    help: bool,
+    warning: Option<MystoreError>,
+    scratch: bool,
+    clipboard: Arc<dyn Clipboard + Send + Sync>,
+    opener: Box<dyn UrlOpener>,
+    /// The file every created file is recorded in, see `from_history`.
+    history: Option<PathBuf>,
+    /// Recovery files left by crashes and not opened yet, the latest first.
+    recovered: Vec<PathBuf>,
+    /// Whether the editor text differs from its file, as of the last key.
+    unsaved: bool,
+    /// Set while every text copied to the clipboard is saved as a new file.
+    clipboard_watch: Option<ClipboardWatch>,
+    /// A message that goes away by itself once the time has come.
+    message_expires: Option<(String, Instant)>,
+    sizer: BackgroundSizer,
+    hasher: BackgroundHasher,
+    finder: BackgroundDuplicates,
+    lock_sweep: BackgroundLockSweep,
+    duplicates: Vec<Vec<PathBuf>>,
+    duplicate_selected: (usize, usize),
+    duplicate_marked: Vec<PathBuf>,
+    size_requested: Option<PathBuf>,
+    batch: BackgroundBatch,
+    bookmarks: Bookmarks,
+    free_space: Option<u64>,
+    free_space_checked: Option<Instant>,
+    low_space_threshold: u64,
+    reauth_interval: Option<Duration>,
+    last_key_confirm: Instant,
+    password: String,
+    /// The manager and the right-hand pane as last drawn, to find what the
+    /// mouse is over.
+    panes: (Rect, Rect),
+    /// The share of the screen width the manager takes, in percent.
+    manager_width: u16,
+    wheel_lines: u16,
+    record_scroll: u16,
+    size_chart: Vec<(String, u64)>,
+    age_chart: Vec<(String, usize)>,
+}
+
+/// How often the free disk space is read again.
+const FREE_SPACE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long the message about a saved clipboard text is shown.
+const CLIPBOARD_NOTICE: Duration = Duration::from_secs(3);
+/// How long the message with the size of a folder is shown.
+const SIZE_NOTICE: Duration = Duration::from_secs(5);
+/// Saving below this much free disk space warns.
+const LOW_SPACE_THRESHOLD: u64 = 100 * 1024 * 1024;
+/// The share of the screen width the manager takes, in percent.
+pub const DEFAULT_MANAGER_WIDTH: u16 = 25;
+const MIN_MANAGER_WIDTH: u16 = 10;
+const MAX_MANAGER_WIDTH: u16 = 75;
+/// How much `<` and `>` change the width of the manager, in percent.
+const MANAGER_WIDTH_STEP: u16 = 5;
+
+impl App<'_> {
+    fn apply_key(&mut self, key: KeyEvent) -> bool {
+        let start = self.timings.start();
+        let was_editing = self.mode.is_editing();
+        let applied = self.update_mode(key);
+        // The file is compared as the editor is shown and when it is saved,
+        // keys changing the text since compare with the text as saved.
+        if self.mode.is_editing() && !was_editing {
+            self.unsaved = self.manager.has_unsaved_changes(&self.editor);
+        } else if self.mode.is_editing() && self.editor.is_modified() {
+            self.unsaved = self.editor.has_unsaved_changes();
+        }
+        self.timings
+            .record_update(start, || format!("Key {:?} {:?}", key.code, key.modifiers));
+        applied
+    }
+
+    fn update_mode(&mut self, key: KeyEvent) -> bool {
+        match self.update(key) {
+            Ok(new_mode) => {
+                self.status = self.warning.take().map_or(Ok(()), Err);
+                self.mode = new_mode;
+                true
+            }
+            Err(err) => {
+                self.warning = None;
+                self.status = Err(err);
+                false
+            }
+        }
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        match self.macro_recording.take() {
+            Some(keys) => {
+                self.message = Some(format!("Recorded a macro of {} keys", keys.len()));
+                self.macro_keys = keys;
+            }
+            None => self.macro_recording = Some(Vec::new()),
+        }
+    }
+
+    fn replay_macro(&mut self) {
+        // Stop at the first failing key, the rest of the macro depends on it.
+        for key in self.macro_keys.clone() {
+            if !self.apply_key(key) || self.is_finished() {
+                break;
+            }
+        }
+    }
+
+    /// Adds a created file to the end of the history file, if there is one.
+    fn record_history(&self, path: &Path) -> io::Result<()> {
+        let Some(history) = &self.history else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(history)?;
+        writeln!(file, "{}", std::path::absolute(path)?.display())
+    }
+
+    /// Reverses the latest deletion or rename of the journal. One that
+    /// cannot be reversed, e.g. as a deleted file's name is taken again,
+    /// stays in the journal with only the part that is still to be undone.
+    fn undo(&mut self) -> Result<()> {
+        let Some(mut operation) = self.journal.pop() else {
+            self.message = Some(String::from("Nothing to undo"));
+            return Ok(());
+        };
+        let name = |path: &Path| {
+            path.file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned())
+        };
+        match &mut operation {
+            Operation::Delete(files) => {
+                let paths: Vec<PathBuf> = files.iter().map(|file| file.path.clone()).collect();
+                let result = self.manager.restore_files(files);
+                let restored: Vec<&PathBuf> = paths
+                    .iter()
+                    .filter(|path| files.iter().all(|file| file.path != **path))
+                    .collect();
+                for path in restored {
+                    self.snapshot(path, "restore");
+                }
+                if let Err(err) = result {
+                    self.journal.push(operation);
+                    return Err(err);
+                }
+                self.message = Some(match paths.as_slice() {
+                    [path] => format!("Undone: restored {}", name(path)),
+                    paths => format!("Undone: restored {} files", paths.len()),
+                });
+            }
+            Operation::Rename(renames) => {
+                let count = renames.len();
+                let first = renames.first().map(|(from, _to)| name(from));
+                if let Err(err) = self.manager.undo_renames(renames) {
+                    self.journal.push(operation);
+                    return Err(err);
+                }
+                self.message = Some(match (count, first) {
+                    (1, Some(first)) => format!("Undone: renamed {} back", first),
+                    (count, _first) => format!("Undone: renamed {} files back", count),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commits a saved or deleted file, git failures never undo the change.
+    fn snapshot(&mut self, path: &Path, verb: &str) {
+        if let Some(git) = &self.git {
+            let result = git.commit(path, verb).and_then(|_| git.is_dirty());
+            match result {
+                Ok(dirty) => self.git_dirty = Some(dirty),
+                Err(err) => self.warning = Some(err),
+            }
+        }
+    }
+
+    fn start_batch(&mut self, op: BatchOp) -> Result<Mode> {
+        let files = self.manager.get_marked_sorted();
+        if files.is_empty() {
+            self.message = Some(String::from("No files are marked"));
+        } else if !self.batch.start(files, op, self.key.clone()) {
+            self.message = Some(String::from("Another batch is still running"));
+        }
+
+        Ok(Mode::Manager)
+    }
+
+    /// Shows the size of the folder asked for with Ctrl + S once it is known.
+    fn announce_size(&mut self) {
+        let Some(path) = &self.size_requested else {
+            return;
+        };
+        if let Some(size) = self.sizer.get_size(path) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let message = format!("{}: {}", name, format_size(size.bytes));
+            self.message_expires = Some((message.clone(), Instant::now() + SIZE_NOTICE));
+            self.message = Some(message);
+            self.size_requested = None;
+        }
+    }
+
+    /// Reports a finished batch and picks up the converted files.
+    fn finish_batch(&mut self) {
+        if let Some(summary) = self.batch.poll() {
+            self.manager.clear_marks();
+            for path in &summary.converted {
+                self.snapshot(path, summary.op.to_string().as_str());
+            }
+            if let Err(err) = self.manager.refresh() {
+                self.warning = Some(err);
+            }
+            // No key is handled on a tick to pass the warning on.
+            if let Some(err) = self.warning.take() {
+                self.status = Err(err);
+            }
+            self.message = Some(summary.to_string());
+        }
+    }
+
+    /// Shows a finished checksum, offering to copy it if nothing else is going on.
+    fn show_checksum(&mut self) {
+        match self.hasher.poll() {
+            Some(Ok(checksum)) if self.mode == Mode::Manager => {
+                self.message = None;
+                self.mode = Mode::Confirm(Confirm::CopyChecksum(checksum));
+            }
+            Some(Ok(checksum)) => {
+                let name = checksum.path.file_name().unwrap_or_default();
+                self.message = Some(format!(
+                    "SHA-256 of {}: {}",
+                    name.to_string_lossy(),
+                    checksum.digest
+                ));
+            }
+            Some(Err(err)) => self.status = Err(err),
+            None => (),
+        }
+    }
+
+    /// Lists the groups of identical files once the search is over.
+    fn show_duplicates(&mut self) {
+        match self.finder.poll() {
+            Some(Ok(groups)) if groups.is_empty() => {
+                self.message = Some(String::from("No files have the same contents"));
+            }
+            Some(Ok(groups)) => {
+                self.message = Some(format!("{} groups of identical files", groups.len()));
+                self.duplicates = groups;
+                self.duplicate_selected = (0, 0);
+                self.duplicate_marked.clear();
+                if self.mode == Mode::Manager {
+                    self.mode = Mode::Duplicates;
+                }
+            }
+            Some(Err(err)) => self.status = Err(err),
+            None => (),
+        }
+    }
+
+    fn finish_lock_sweep(&mut self) {
+        let Some(cleanup) = self.lock_sweep.poll() else {
+            return;
+        };
+        if cleanup.removed > 0 {
+            if let Err(err) = self.manager.refresh() {
+                self.status = Err(err);
+            }
+        }
+        if cleanup.failed > 0 || cleanup.unreadable > 0 {
+            self.message = Some(cleanup.to_string());
+        }
+    }
+
+    fn check_free_space(&mut self) {
+        self.free_space = sizes::available_space(self.manager.get_root());
+        self.free_space_checked = Some(Instant::now());
+    }
+
+    /// Commits a saved file and warns if the disk is about to fill up.
+    fn saved(&mut self, path: &Path) {
+        self.snapshot(path, "save");
+        if let Err(err) = self.record_history(path) {
+            self.warning = Some(err.into());
+        }
+        self.check_free_space();
+        match self.free_space {
+            Some(free) if free < self.low_space_threshold => {
+                self.warning = Some(MystoreError::LowSpace(free))
+            }
+            _ => (),
+        }
+    }
+
+    /// The key the action bound to `key` is handled on, `key` itself when
+    /// it is bound to nothing.
+    fn bound_key(&self, key: KeyEvent) -> KeyEvent {
+        let mode = match self.mode {
+            Mode::Manager => AppMode::Manager,
+            Mode::Viewer => AppMode::Viewer,
+            Mode::Editor if !self.editor.is_read_only() => AppMode::Editor,
+            _ => return key,
+        };
+        match self.keybindings.get(mode, &key) {
+            Some(action) => {
+                let (code, modifiers) = action.default_key();
+                KeyEvent::new(code, modifiers)
+            }
+            None => key,
+        }
+    }
+
+    fn update(&mut self, key: KeyEvent) -> Result<Mode> {
+        let key = self.bound_key(key);
+        match self.mode.clone() {
+            Mode::Manager => self.update_manager(key),
+            Mode::Viewer => self.update_viewer(key),
+            Mode::Editor if self.editor.is_read_only() => self.update_read_only(key),
+            Mode::Editor => self.update_editor(key),
+            Mode::Rename => self.update_rename(key),
+            Mode::Commit => self.update_commit(key),
+            Mode::Grep => self.update_grep(key),
+            Mode::Duplicates => self.update_duplicates(key),
+            Mode::JsonRecord => self.update_json_record(key),
+            Mode::SizeChart | Mode::AgeChart => Ok(Mode::Manager),
+            Mode::Mark(mark) => self.update_mark(key, mark),
+            Mode::PickSplit => self.update_pick_split(key),
+            Mode::InsertFile => self.update_insert_file(key),
+            Mode::Split => self.update_split(key),
+            Mode::Templates | Mode::Tags | Mode::UrlList => self.update_choices(key),
+            Mode::Confirm(confirm) => self.update_confirm(key, confirm),
+            Mode::Prompt(prompt) => self.update_prompt(key, prompt),
+            Mode::Reauth(back) => self.update_reauth(key, *back),
+            Mode::Exit => Ok(Mode::Exit),
+        }
+    }
+
+    fn update_manager(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Esc if self.manager.get_extension_filter().is_some() => {
+                self.manager.set_extension_filter("")?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Esc => Ok(Mode::Exit),
+            KeyCode::Up => {
+                self.manager.previous();
+                Ok(Mode::Manager)
+            }
+            KeyCode::Down => {
+                self.manager.next();
+                Ok(Mode::Manager)
+            }
+            KeyCode::PageDown => {
+                self.manager.page_down()?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Backspace | KeyCode::Left => {
+                self.sizer.cancel();
+                self.manager.go_back()?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('~') => {
+                self.sizer.cancel();
+                self.manager.go_root()?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::PageUp => {
+                self.manager.page_up()?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Home => {
+                self.manager.first();
+                Ok(Mode::Manager)
+            }
+            // A folder read incrementally loads its next page first.
+            KeyCode::End if self.manager.has_pending_entities() => {
+                self.manager.load_more_entities()?;
+                self.manager.last();
+                Ok(Mode::Manager)
+            }
+            KeyCode::End => {
+                self.manager.last();
+                Ok(Mode::Manager)
+            }
+            KeyCode::Enter => match self.open_selected()? {
+                Respond::File(path) => {
+                    self.open_file(path);
+                    Ok(Mode::Viewer)
+                }
+                Respond::Data(path, data) => {
+                    self.open_data(path, data);
+                    Ok(Mode::Viewer)
+                }
+                Respond::None => {
+                    // Sizes of the folders left behind are not needed anymore.
+                    self.sizer.cancel();
+                    Ok(Mode::Manager)
+                }
+            },
+            KeyCode::Char('e') | KeyCode::Char('E')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.start_batch(BatchOp::Encrypt)
+            }
+            KeyCode::Char('d') | KeyCode::Char('D')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.start_batch(BatchOp::Decrypt)
+            }
+            KeyCode::Char('e') | KeyCode::Char('E') => Ok(Mode::Editor),
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.editor.init();
+                Ok(Mode::Editor)
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                let held = self.manager.delete_selected()?;
+                if let Some(file) = &held {
+                    self.snapshot(&file.path, "delete");
+                }
+                self.journal
+                    .push(Operation::Delete(held.into_iter().collect()));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('t') | KeyCode::Char('T')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.choices = self.manager.get_templates()?;
+                self.choice_selected = 0;
+                if self.choices.is_empty() {
+                    self.message = Some(String::from("No templates found"));
+                    Ok(Mode::Manager)
+                } else {
+                    Ok(Mode::Templates)
+                }
+            }
+            KeyCode::Char('m') | KeyCode::Char('M')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.input.clear();
+                Ok(Mode::Prompt(Prompt::MergeDir))
+            }
+            KeyCode::Char('u') | KeyCode::Char('U')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.undo()?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                match self.manager.get_selected_entity() {
+                    Some(ManagerEntity::Folder(path)) => self.sizer.start(path.clone()),
+                    _ => return Err(MystoreError::NotAFile("size", "file")),
+                }
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('a') | KeyCode::Char('A')
+                if key
+                    .modifiers
+                    .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+            {
+                self.age_chart = self.manager.age_histogram();
+                Ok(Mode::AgeChart)
+            }
+            KeyCode::Char('s') | KeyCode::Char('S')
+                if key
+                    .modifiers
+                    .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+            {
+                self.size_chart = self.manager.size_histogram();
+                if self.size_chart.is_empty() {
+                    self.message = Some(String::from("No files are listed"));
+                    Ok(Mode::Manager)
+                } else {
+                    Ok(Mode::SizeChart)
+                }
+            }
+            KeyCode::Char('s') | KeyCode::Char('S')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                match self.manager.get_selected_entity() {
+                    Some(ManagerEntity::Folder(path)) => {
+                        let path = path.clone();
+                        self.sizer.start(path.clone());
+                        self.size_requested = Some(path);
+                        self.announce_size();
+                    }
+                    _ => return Err(MystoreError::NotAFile("size", "file")),
+                }
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('r') | KeyCode::Char('R')
+                if !self.recovered.is_empty() && !key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                let path = self.recovered.remove(0);
+                self.editor.init();
+                self.editor.insert_file_contents(&path)?;
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.message = Some(format!("Recovered {}, delete it once saved", name));
+                Ok(Mode::Editor)
+            }
+            KeyCode::Char('h') | KeyCode::Char('H') => {
+                let path = match self.manager.get_selected_entity() {
+                    Some(ManagerEntity::TextFile(path)) => path.clone(),
+                    Some(ManagerEntity::Folder(_path)) => {
+                        return Err(MystoreError::NotAFile("hash", "folder"))
+                    }
+                    _ => return Err(MystoreError::NotAFile("hash", "action")),
+                };
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.message = Some(format!("Computing the SHA-256 of {}…", name));
+                self.hasher.start(path);
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('f') | KeyCode::Char('F')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.finder.start(self.manager.get_roots().to_vec());
+                self.message = Some(String::from("Looking for files with the same contents…"));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('.') => {
+                let show = !self.manager.get_show_hidden();
+                self.manager.set_show_hidden(show)?;
+                self.message = Some(String::from(if show {
+                    "Hidden files are listed"
+                } else {
+                    "Hidden files are not listed"
+                }));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('<') => {
+                self.set_manager_width(self.manager_width.saturating_sub(MANAGER_WIDTH_STEP));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('>') => {
+                self.set_manager_width(self.manager_width + MANAGER_WIDTH_STEP);
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('w') | KeyCode::Char('W') => {
+                let wrap = !self.manager.get_wrap_navigation();
+                self.manager.set_wrap_navigation(wrap);
+                self.message = Some(String::from(if wrap {
+                    "The list wraps around at the ends"
+                } else {
+                    "The list stops at the ends"
+                }));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                let order = self.manager.get_sort_order().next();
+                self.manager.set_sort_order(order)?;
+                self.message = Some(format!("Sorted by {}", order));
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('g') | KeyCode::Char('G')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                Ok(Mode::Grep)
+            }
+            KeyCode::Char('g') | KeyCode::Char('G') => {
+                self.input.clear();
+                Ok(Mode::Prompt(Prompt::GoTo))
+            }
+            KeyCode::Char('x') | KeyCode::Char('X') => {
+                self.input = self
+                    .manager
+                    .get_extension_filter()
+                    .map_or(String::new(), |extensions| extensions.join(", "));
+                Ok(Mode::Prompt(Prompt::FilterExtensions))
+            }
+            KeyCode::Char(' ') => {
+                self.manager.toggle_mark();
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('z') | KeyCode::Char('Z') => {
+                let encrypt = key.modifiers.contains(KeyModifiers::CONTROL);
+                self.input = String::from("session.zip");
+                Ok(Mode::Prompt(Prompt::ExportZip { encrypt }))
+            }
+            KeyCode::Char('c') | KeyCode::Char('C')
+                if key
+                    .modifiers
+                    .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+            {
+                self.commit = TextArea::default();
+                Ok(Mode::Commit)
+            }
+            KeyCode::Char('r') | KeyCode::Char('R')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                if self.manager.get_marked_ref().is_empty() {
+                    self.message = Some(String::from("No files are marked"));
+                    Ok(Mode::Manager)
+                } else {
+                    self.rename = TextArea::default();
+                    Ok(Mode::Rename)
+                }
+            }
+            KeyCode::Char('f') | KeyCode::Char('F') => {
+                self.choices = self.refresh_tags()?.get_tags();
+                self.choice_selected = 0;
+                if self.choices.is_empty() {
+                    self.message = Some(String::from("No tags found"));
+                    Ok(Mode::Manager)
+                } else {
+                    Ok(Mode::Tags)
+                }
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let relative = key.modifiers.contains(KeyModifiers::CONTROL);
+                self.message = match self.manager.get_selected_entity() {
+                    None => Some(String::from("Nothing to copy: no item is selected")),
+                    Some(ManagerEntity::Action(_act)) => {
+                        Some(String::from("Nothing to copy: actions have no path"))
+                    }
+                    Some(_entity) => {
+                        let path = self.manager.get_selected_path(relative).unwrap_or_default();
+                        self.clipboard.copy(&path)?;
+                        Some(format!("Copied {}", path))
+                    }
+                };
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char('l') | KeyCode::Char('L')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                Ok(Mode::Confirm(Confirm::CleanupLocks))
+            }
+            KeyCode::Char('l') | KeyCode::Char('L') => {
+                match self.manager.get_selected_entity() {
+                    Some(ManagerEntity::TextFile(path)) if self.manager.is_locked(path) => {
+                        self.manager.unlock_file()?
+                    }
+                    _ => self.manager.lock_file()?,
+                }
+                Ok(Mode::Manager)
+            }
+            _ => Ok(Mode::Manager),
+        }
+    }
+
+    fn update_viewer(&mut self, key: KeyEvent) -> Result<Mode> {
+        let is_json_lines = matches!(self.viewer.get_entity_ref(), ViewerEntity::JsonLines(_));
+        match key.code {
+            KeyCode::Up if is_json_lines => {
+                let selected = self.viewer.get_selected_record();
+                self.viewer.select_record(selected.saturating_sub(1));
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Down if is_json_lines => {
+                let selected = self.viewer.get_selected_record();
+                self.viewer.select_record(selected + 1);
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Enter if is_json_lines => {
+                self.record_scroll = 0;
+                Ok(Mode::JsonRecord)
+            }
+            KeyCode::Up => {
+                self.viewer.scroll_up(1);
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Down => {
+                self.viewer.scroll_down(1);
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('a') | KeyCode::Char('A')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.viewer.toggle_auto_refresh();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('b') | KeyCode::Char('B')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                let (line, added) = self.viewer.toggle_bookmark();
+                if let Some(path) = &self.viewed {
+                    let path = std::path::absolute(path)?;
+                    self.bookmarks
+                        .set(&path, self.viewer.get_line_bookmarks().to_vec());
+                    self.bookmarks.save()?;
+                }
+                self.message = Some(if added {
+                    format!("Bookmarked line {}", line)
+                } else {
+                    format!("Removed the bookmark on line {}", line)
+                });
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char(' ') | KeyCode::Char('f') => {
+                self.viewer.scroll_down(self.viewer.get_page_height());
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('b') => {
+                self.viewer.scroll_up(self.viewer.get_page_height());
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('d') => {
+                self.viewer.scroll_down(self.viewer.get_page_height() / 2);
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('u') => {
+                self.viewer.scroll_up(self.viewer.get_page_height() / 2);
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('g') => {
+                self.viewer.scroll_to_top();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('G') => {
+                self.viewer.scroll_to_bottom();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('/') => {
+                self.input.clear();
+                Ok(Mode::Prompt(Prompt::SearchViewer))
+            }
+            KeyCode::Char(c @ ('n' | 'N')) => {
+                if !self.viewer.search_next(c == 'n') {
+                    self.message = Some(match self.viewer.get_search() {
+                        Some(pattern) => format!("Pattern not found: {}", pattern),
+                        None => String::from("Nothing searched yet, / starts a search"),
+                    });
+                }
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('[') => {
+                self.viewer.previous_bookmark();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char(']') => {
+                self.viewer.next_bookmark();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                self.viewer.toggle_strip_ansi();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('w') | KeyCode::Char('W') => {
+                self.viewer.toggle_show_whitespace();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('e') | KeyCode::Char('E') => {
+                self.message = Some(match self.viewer.cycle_encoding() {
+                    Some(encoding) => format!("Decoded as {}", encoding.name()),
+                    None => String::from("Only plain text can be decoded again"),
+                });
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('t') | KeyCode::Char('T') => {
+                self.viewer.toggle_structured();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                self.reload_viewed()?;
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('#') => {
+                self.input = match self.viewer.get_source_ref() {
+                    ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => {
+                        tags::parse_tags(text).join(", ")
+                    }
+                    ViewerEntity::Binary(_bin) => {
+                        return Err(MystoreError::NotAFile("tag", "binary"))
+                    }
+                    ViewerEntity::Table(_rows) => {
+                        return Err(MystoreError::NotAFile("tag", "table"))
+                    }
+                    ViewerEntity::Json(_value) => {
+                        return Err(MystoreError::NotAFile("tag", "JSON"))
+                    }
+                    ViewerEntity::JsonLines(_records) => {
+                        return Err(MystoreError::NotAFile("tag", "JSON"))
+                    }
+                };
+                Ok(Mode::Prompt(Prompt::EditTags))
+            }
+            // Terminals that send Ctrl + M as Enter only export from the
+            // command line, Enter is not taken for it.
+            KeyCode::Char('m') | KeyCode::Char('M')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.input.clear();
+                match self.viewer.get_entity_ref() {
+                    ViewerEntity::DecryptedText(_text) => {
+                        Ok(Mode::Confirm(Confirm::ExportDecrypted))
+                    }
+                    _ => Ok(Mode::Prompt(Prompt::ExportMarkdown)),
+                }
+            }
+            KeyCode::Char('m') => Ok(Mode::Mark(Mark::Set)),
+            KeyCode::Char('c') | KeyCode::Char('C') => {
+                self.message = Some(String::from(
+                    "Select the file to compare with, Enter opens it",
+                ));
+                Ok(Mode::PickSplit)
+            }
+            KeyCode::Char('\'') => Ok(Mode::Mark(Mark::Jump)),
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                let text = self.viewed_text("edit")?;
+                let name = self.viewer.get_name().map(String::from);
+                self.editor.open_read_only(name, &text);
+                Ok(Mode::Editor)
+            }
+            KeyCode::Char('o') | KeyCode::Char('O') => {
+                let mut found = urls::find_urls(&self.viewed_text("open URLs of")?)?;
+                match found.len() {
+                    0 => self.message = Some(String::from("No URLs in the text")),
+                    1 => self.open_url(&found.remove(0))?,
+                    _ => {
+                        self.choices = found;
+                        self.choice_selected = 0;
+                        return Ok(Mode::UrlList);
+                    }
+                }
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.loader.cancel();
+                self.viewer.clear();
+                Ok(Mode::Manager)
+            }
+            // Keys meant for other pagers must not lose the reading position.
+            _ => Ok(Mode::Viewer),
+        }
+    }
+
+    /// Runs the action of the selected entity. A file locked by another
+    /// session is still opened, with a warning.
+    fn open_selected(&mut self) -> Result<Respond> {
+        let respond = self.manager.action()?;
+        if let Respond::File(path) | Respond::Data(path, _) = &respond {
+            if self.manager.is_locked_elsewhere(path) {
+                self.warning = Some(MystoreError::Locked(path.clone()));
+            }
+        }
+        Ok(respond)
+    }
+
+    /// Lets the manager only move while a file is picked for another mode.
+    /// Returns what Enter opened, folders are opened in place.
+    fn pick_file(&mut self, key: KeyEvent) -> Result<Option<Respond>> {
+        match key.code {
+            KeyCode::Enter => self.open_selected().map(Some),
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::Left
+            | KeyCode::Backspace
+            | KeyCode::Char('~') => {
+                self.update_manager(key)?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Enter on a file opens it as the second file of the split view.
+    fn update_pick_split(&mut self, key: KeyEvent) -> Result<Mode> {
+        if key.code == KeyCode::Esc {
+            return Ok(Mode::Viewer);
+        }
+        match self.pick_file(key)? {
+            Some(Respond::File(path)) => {
+                self.open_second(path, None);
+                Ok(Mode::Split)
+            }
+            Some(Respond::Data(path, data)) => {
+                self.open_second(path, Some(data));
+                Ok(Mode::Split)
+            }
+            Some(Respond::None) | None => Ok(Mode::PickSplit),
+        }
+    }
+
+    /// Enter on a file inserts its text into the editor at the cursor.
+    fn update_insert_file(&mut self, key: KeyEvent) -> Result<Mode> {
+        if key.code == KeyCode::Esc {
+            return Ok(Mode::Editor);
+        }
+        let path = match self.pick_file(key)? {
+            Some(Respond::File(path)) => {
+                self.editor.insert_file_contents(&path)?;
+                path
+            }
+            Some(Respond::Data(path, data)) => {
+                self.editor.insert_data(data)?;
+                path
+            }
+            Some(Respond::None) | None => return Ok(Mode::InsertFile),
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.message = Some(format!("Inserted {}", name));
+
+        Ok(Mode::Editor)
+    }
+
+    /// Shows `path` in the second viewer, from `data` if it is already read.
+    fn open_second(&mut self, path: PathBuf, data: Option<Vec<u8>>) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.second_viewer.set_loading(name.clone());
+        match data {
+            Some(data) => {
+                self.second_loader.cancel();
+                let decoded = Viewer::decode(loader::entity_from_bytes(data), &self.key);
+                self.second_viewer.set_decoded(decoded, name);
+            }
+            None => {
+                self.second_loader.start(path, name);
+            }
+        }
+        self.split = Some(Split {
+            layout: SplitLayout::default(),
+            second_focused: true,
+            scroll_lock: false,
+        });
+    }
+
+    fn close_split(&mut self) {
+        self.second_loader.cancel();
+        self.second_viewer.clear();
+        self.split = None;
+    }
+
+    /// Scrolls the focused viewer, or both while the scroll is locked.
+    fn scroll_split(&mut self, scroll: impl Fn(&mut Viewer)) {
+        let Some(split) = self.split else {
+            return;
+        };
+        if split.scroll_lock || !split.second_focused {
+            scroll(&mut self.viewer);
+        }
+        if split.scroll_lock || split.second_focused {
+            scroll(&mut self.second_viewer);
+        }
+    }
+
+    fn update_split(&mut self, key: KeyEvent) -> Result<Mode> {
+        let Some(split) = self.split.as_mut() else {
+            return Ok(Mode::Viewer);
+        };
+        match key.code {
+            KeyCode::Tab => split.second_focused = !split.second_focused,
+            KeyCode::Char('l') | KeyCode::Char('L') => {
+                split.scroll_lock = !split.scroll_lock;
+                self.message = Some(String::from(match split.scroll_lock {
+                    true => "Both viewers scroll together",
+                    false => "Only the focused viewer scrolls",
+                }));
+            }
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                split.layout = match split.layout {
+                    SplitLayout::Stacked => SplitLayout::SideBySide,
+                    SplitLayout::SideBySide => SplitLayout::Stacked,
+                };
+            }
+            KeyCode::Up => self.scroll_split(|viewer| viewer.scroll_up(1)),
+            KeyCode::Down => self.scroll_split(|viewer| viewer.scroll_down(1)),
+            KeyCode::Char(' ') | KeyCode::Char('f') => {
+                self.scroll_split(|viewer| viewer.scroll_down(viewer.get_page_height()))
+            }
+            KeyCode::Char('b') => {
+                self.scroll_split(|viewer| viewer.scroll_up(viewer.get_page_height()))
+            }
+            KeyCode::Char('d') => {
+                self.scroll_split(|viewer| viewer.scroll_down(viewer.get_page_height() / 2))
+            }
+            KeyCode::Char('u') => {
+                self.scroll_split(|viewer| viewer.scroll_up(viewer.get_page_height() / 2))
+            }
+            KeyCode::Char('g') => self.scroll_split(Viewer::scroll_to_top),
+            KeyCode::Char('G') => self.scroll_split(Viewer::scroll_to_bottom),
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.close_split();
+                return Ok(Mode::Viewer);
+            }
+            _ => (),
+        }
+
+        Ok(Mode::Split)
+    }
+
+    fn update_mark(&mut self, key: KeyEvent, mark: Mark) -> Result<Mode> {
+        let KeyCode::Char(letter) = key.code else {
+            return Ok(Mode::Viewer);
+        };
+        match mark {
+            Mark::Set if self.viewer.set_mark(letter) => {
+                self.message = Some(format!("Marked the position as '{}", letter));
+            }
+            Mark::Jump if letter.is_ascii_alphabetic() && !self.viewer.jump_to_mark(letter) => {
+                self.message = Some(format!("No position is marked as '{}", letter));
+            }
+            Mark::Set | Mark::Jump => {}
+        }
+
+        Ok(Mode::Viewer)
+    }
+
+    fn update_json_record(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Up => {
+                self.record_scroll = self.record_scroll.saturating_sub(1);
+                Ok(Mode::JsonRecord)
+            }
+            KeyCode::Down => {
+                self.record_scroll = self.record_scroll.saturating_add(1);
+                Ok(Mode::JsonRecord)
+            }
+            _ => Ok(Mode::Viewer),
+        }
+    }
+
+    fn update_editor(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key {
+            KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: _,
+                kind: _,
+                state: _,
+            } if self.manager.has_unsaved_changes(&self.editor) => {
+                Ok(Mode::Confirm(Confirm::LeaveEditor))
+            }
+            KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: _,
+                kind: _,
+                state: _,
+            } => Ok(Mode::Manager),
+            KeyEvent {
+                code:
+                    code @ (KeyCode::Char('s')
+                    | KeyCode::Char('S')
+                    | KeyCode::Char('e')
+                    | KeyCode::Char('E')),
+                modifiers,
+                kind: _,
+                state: _,
+            } if modifiers.contains(KeyModifiers::CONTROL) => {
+                let encrypt = matches!(code, KeyCode::Char('e') | KeyCode::Char('E'));
+                self.save_editor(encrypt, modifiers.contains(KeyModifiers::SHIFT))
+            }
+            KeyEvent {
+                code: KeyCode::Char('v') | KeyCode::Char('V'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            } => {
+                self.editor.toggle_expand_variables();
+                self.message = Some(String::from(if self.editor.get_expand_variables() {
+                    "$DATE, $USER and $FILENAME are expanded on save"
+                } else {
+                    "Variables are saved as typed"
+                }));
+                Ok(Mode::Editor)
+            }
+            KeyEvent {
+                code: KeyCode::Char('t') | KeyCode::Char('T'),
+                modifiers,
+                kind: _,
+                state: _,
+            } if modifiers.contains(KeyModifiers::CONTROL) => {
+                let date_only = modifiers.contains(KeyModifiers::SHIFT);
+                self.editor.insert_time(Local::now(), date_only);
+                Ok(Mode::Editor)
+            }
+            // Told apart from Tab as the terminal disambiguates escape codes.
+            KeyEvent {
+                code: KeyCode::Char('i') | KeyCode::Char('I'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            } => Ok(Mode::InsertFile),
+            KeyEvent {
+                code: KeyCode::Char('w') | KeyCode::Char('W'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            } => {
+                self.input = String::from("80");
+                Ok(Mode::Prompt(Prompt::HardWrap))
+            }
+            KeyEvent {
+                code: KeyCode::Char('k') | KeyCode::Char('K'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            } => {
+                let errors = self.editor.spell_check()?;
+                self.editor.highlight_spelling_errors(&errors);
+                self.message = Some(format!("{} spelling errors", errors.len()));
+                Ok(Mode::Editor)
+            }
+            _ => {
+                self.editor.input(key);
+                Ok(Mode::Editor)
+            }
+        }
+    }
+
+    /// Shows a file in the editor without saving anything, Esc goes back to
+    /// the viewer.
+    fn update_read_only(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Esc => {
+                self.editor.close_read_only();
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Char('s') | KeyCode::Char('S') | KeyCode::Char('e') | KeyCode::Char('E')
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.message = Some(String::from("Nothing is saved from a read-only view"));
+                Ok(Mode::Editor)
+            }
+            _ => {
+                self.editor.input(key);
+                Ok(Mode::Editor)
+            }
+        }
+    }
+
+    /// Saves the text of the editor and stays in it, or goes back to the
+    /// manager on `exit`. The first save asks for the name, later ones
+    /// replace that file and keep it encrypted or not.
+    fn save_editor(&mut self, encrypt: bool, exit: bool) -> Result<Mode> {
+        let Some((path, encrypted)) = self
+            .editor
+            .get_target()
+            .map(|(path, encrypted)| (path.to_path_buf(), encrypted))
+        else {
+            // New notes are named after the time they are saved at.
+            self.input = self.manager.get_time_formats().file_name(&Local::now());
+            return Ok(Mode::Prompt(Prompt::SaveNote { encrypt, exit }));
+        };
+        let data = self.editor.snapshot(encrypted)?;
+        self.manager.overwrite_file(&path, &data)?;
+        self.editor_saved(&path, exit)
+    }
+
+    /// Saves the text of the editor as a new note named as typed.
+    fn save_new_note(&mut self, encrypt: bool, exit: bool) -> Result<Mode> {
+        self.editor.set_title(Some(self.input.clone()));
+        let data = self.editor.snapshot(encrypt)?;
+        let path = self.manager.create_file(data, Some(self.input.clone()))?;
+        self.editor.set_target(path.clone(), encrypt);
+        self.editor_saved(&path, exit)
+    }
+
+    fn editor_saved(&mut self, path: &Path, exit: bool) -> Result<Mode> {
+        self.editor.mark_saved();
+        self.unsaved = self.manager.has_unsaved_changes(&self.editor);
+        self.saved(path);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.message = Some(format!("Saved {}", name));
+        if exit {
+            self.editor.close();
+            return Ok(Mode::Manager);
+        }
+
+        Ok(Mode::Editor)
+    }
+
+    fn update_choices(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Up => {
+                self.choice_selected = match self.choice_selected {
+                    0 => self.choices.len() - 1,
+                    value => value - 1,
+                };
+                Ok(self.mode.clone())
+            }
+            KeyCode::Down => {
+                self.choice_selected = (self.choice_selected + 1) % self.choices.len();
+                Ok(self.mode.clone())
+            }
+            KeyCode::Enter if self.mode == Mode::UrlList => {
+                let url = self.choices[self.choice_selected].clone();
+                self.open_url(&url)?;
+                Ok(Mode::Viewer)
+            }
+            KeyCode::Esc if self.mode == Mode::UrlList => Ok(Mode::Viewer),
+            KeyCode::Enter => {
+                let choice = &self.choices[self.choice_selected];
+                if self.mode == Mode::Tags {
+                    let index = self.refresh_tags()?;
+                    let files = index.get_files_with(choice);
+                    self.manager.set_filter(choice.clone(), files);
+                } else {
+                    let path = self.manager.create_file_from_template(choice, None)?;
+                    self.saved(&path);
+                }
+                Ok(Mode::Manager)
+            }
+            KeyCode::Esc => Ok(Mode::Manager),
+            _ => Ok(self.mode.clone()),
+        }
+    }
+
+    /// The viewed file as text, tables and JSON as they are written. Binary
+    /// files cannot be used for `action`.
+    fn viewed_text(&self, action: &'static str) -> Result<String> {
+        Ok(match self.viewer.get_entity_ref() {
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => text.clone(),
+            ViewerEntity::Table(rows) => rows
+                .iter()
+                .map(|row| row.join(" "))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            ViewerEntity::Json(value) => value.to_string(),
+            ViewerEntity::JsonLines(records) => records
+                .iter()
+                .map(serde_json::Value::to_string)
+                .collect::<Vec<String>>()
+                .join("\n"),
+            ViewerEntity::Binary(_bin) => return Err(MystoreError::NotAFile(action, "binary")),
+        })
+    }
+
+    fn open_url(&mut self, url: &str) -> Result<()> {
+        self.opener.open(url)?;
+        self.message = Some(format!("Opened {}", url));
+        Ok(())
+    }
+
+    /// Rewrites the tags line of the viewed note, encrypted notes stay encrypted.
+    fn save_tags(&mut self) -> Result<()> {
+        let path = self
+            .viewed
+            .clone()
+            .ok_or(MystoreError::NotAFile("tag", "missing"))?;
+        let tags: Vec<String> = self
+            .input
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+        let (entity, data) = match self.viewer.get_source_ref() {
+            ViewerEntity::Text(text) => {
+                let text = tags::set_tags(text, &tags);
+                (ViewerEntity::Text(text.clone()), text.into_bytes())
+            }
+            ViewerEntity::DecryptedText(text) => {
+                let text = tags::set_tags(text, &tags);
+                let data = self.key.encrypt(&text)?;
+                (ViewerEntity::DecryptedText(text), data)
+            }
+            ViewerEntity::Binary(_bin) => return Err(MystoreError::NotAFile("tag", "binary")),
+            ViewerEntity::Table(_rows) => return Err(MystoreError::NotAFile("tag", "table")),
+            ViewerEntity::Json(_value) => return Err(MystoreError::NotAFile("tag", "JSON")),
+            ViewerEntity::JsonLines(_records) => return Err(MystoreError::NotAFile("tag", "JSON")),
+        };
+        self.manager.overwrite_file(&path, &data)?;
+
+        let name = self.viewer.get_name().map(String::from);
+        self.viewer.set_entity(entity, name);
+        self.snapshot(&path, "tag");
+        self.message = Some(String::from("Tags saved"));
+
+        Ok(())
+    }
+
+    /// Brings the tag index of the root up to date with the notes.
+    fn refresh_tags(&self) -> Result<TagIndex> {
+        let mut index = TagIndex::open(self.manager.get_root(), &self.key);
+        if index.refresh()? {
+            index.save()?;
+        }
+
+        Ok(index)
+    }
+
+    fn update_rename(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Esc => Ok(Mode::Manager),
+            KeyCode::Enter if self.rename.lines().len() == 1 => {
+                self.rename.insert_newline();
+                Ok(Mode::Rename)
+            }
+            KeyCode::Enter => {
+                let (pattern, replacement) = self.get_rename_pattern();
+                let (pattern, replacement) = (pattern.to_string(), replacement.to_string());
+                let result = self.manager.rename_batch(&pattern, &replacement);
+                // Renames done before a failure are undone like the others.
+                self.journal
+                    .push(Operation::Rename(self.manager.take_applied_renames()));
+                let count = result?;
+                self.message = Some(format!("Renamed {} files", count));
+                Ok(Mode::Manager)
+            }
+            _ => {
+                self.rename.input(key);
+                Ok(Mode::Rename)
+            }
+        }
+    }
+
+    fn update_commit(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key {
+            KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: _,
+                kind: _,
+                state: _,
+            } => Ok(Mode::Manager),
+            KeyEvent {
+                code: KeyCode::Char('s') | KeyCode::Char('S'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            } => {
+                let message = self.commit.lines().join("\n");
+                if message.trim().is_empty() {
+                    self.message = Some(String::from("The commit message is empty"));
+                    return Ok(Mode::Commit);
+                }
+                let oid = self.manager.commit_changes(&message)?;
+                self.last_commit = Some(oid);
+                let short = oid.to_string();
+                self.message = Some(format!("Committed {}", &short[..7]));
+                Ok(Mode::Manager)
+            }
+            _ => {
+                self.commit.input(key);
+                Ok(Mode::Commit)
+            }
+        }
+    }
+
+    fn update_grep(&mut self, key: KeyEvent) -> Result<Mode> {
+        match key.code {
+            KeyCode::Esc => Ok(Mode::Manager),
+            KeyCode::Up => {
+                self.grep_selected = self.grep_selected.saturating_sub(1);
+                Ok(Mode::Grep)
+            }
+            KeyCode::Down => {
+                if self.grep_selected + 1 < self.grep_results.len() {
+                    self.grep_selected += 1;
+                }
+                Ok(Mode::Grep)
+            }
+            KeyCode::Enter => {
+                let query = self.grep.lines().join("");
+                if query != self.grep_query || self.grep_results.is_empty() {
+                    let files: Vec<PathBuf> = self
+                        .manager
+                        .get_entities_ref()
+                        .iter()
+                        .filter_map(|entity| match entity {
+                            ManagerEntity::TextFile(path) => Some(path.clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    self.grep_results = grep::grep(&files, &query, &self.key);
+                    self.grep_query = query;
+                    self.grep_selected = 0;
+                    self.message = Some(format!("{} matching lines", self.grep_results.len()));
+                    return Ok(Mode::Grep);
+                }
+
+                let result = self.grep_results[self.grep_selected].clone();
+                let entity = ManagerEntity::TextFile(result.path.clone());
+                if let Some(id) = self
+                    .manager
+                    .get_entities_ref()
+                    .iter()
+                    .position(|elem| *elem == entity)
+                {
+                    self.manager.select(id);
+                }
+                self.open_file(result.path);
+                self.viewer.goto_line(result.line_number);
+                Ok(Mode::Viewer)
+            }
+            _ => {
+                self.grep.input(key);
+                Ok(Mode::Grep)
+            }
+        }
+    }
+
+    fn update_duplicates(&mut self, key: KeyEvent) -> Result<Mode> {
+        let (group, copy) = self.duplicate_selected;
+        match key.code {
+            KeyCode::Esc => Ok(Mode::Manager),
+            KeyCode::Up => {
+                self.duplicate_selected = match (group, copy) {
+                    (0, 0) => (0, 0),
+                    (group, 0) => (group - 1, self.duplicates[group - 1].len() - 1),
+                    (group, copy) => (group, copy - 1),
+                };
+                Ok(Mode::Duplicates)
+            }
+            KeyCode::Down => {
+                if copy + 1 < self.duplicates[group].len() {
+                    self.duplicate_selected = (group, copy + 1);
+                } else if group + 1 < self.duplicates.len() {
+                    self.duplicate_selected = (group + 1, 0);
+                }
+                Ok(Mode::Duplicates)
+            }
+            KeyCode::Enter => {
+                let path = self.duplicates[group][copy].clone();
+                self.manager.reveal(&path)?;
+                Ok(Mode::Manager)
+            }
+            KeyCode::Char(' ') => {
+                let path = &self.duplicates[group][copy];
+                let kept = self.duplicates[group]
+                    .iter()
+                    .filter(|elem| !self.duplicate_marked.contains(elem))
+                    .count();
+                if let Some(id) = self.duplicate_marked.iter().position(|elem| elem == path) {
+                    self.duplicate_marked.remove(id);
+                } else if kept > 1 {
+                    self.duplicate_marked.push(path.clone());
+                } else {
+                    self.message = Some(String::from("At least one copy is kept"));
+                }
+                Ok(Mode::Duplicates)
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                if self.duplicate_marked.is_empty() {
+                    self.message = Some(String::from("No copies are marked"));
+                    Ok(Mode::Duplicates)
+                } else {
+                    Ok(Mode::Confirm(Confirm::DeleteDuplicates))
+                }
+            }
+            _ => Ok(Mode::Duplicates),
+        }
+    }
+
+    /// Deletes the marked copies, forgetting the groups left with one file.
+    fn delete_duplicates(&mut self) -> Result<()> {
+        let marked = std::mem::take(&mut self.duplicate_marked);
+        let mut deleted = 0;
+        let mut held = Vec::new();
+        let mut result = Ok(());
+        for path in &marked {
+            match self.manager.delete_copy(path) {
+                Ok(file) => held.push(file),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+            self.snapshot(path, "delete");
+            for group in &mut self.duplicates {
+                group.retain(|elem| elem != path);
+            }
+            deleted += 1;
+        }
+        self.duplicates.retain(|group| group.len() > 1);
+        self.duplicate_marked = marked[deleted..].to_vec();
+        self.duplicate_selected = (0, 0);
+        self.message = Some(format!("Deleted {} copies", deleted));
+        self.journal.push(Operation::Delete(held));
+
+        result
+    }
+
+    /// Locks the session once the password was last typed too long ago.
+    fn check_reauth(&mut self) {
+        let is_due = self
+            .reauth_interval
+            .is_some_and(|interval| self.last_key_confirm.elapsed() >= interval);
+        if is_due && !matches!(self.mode, Mode::Reauth(_) | Mode::Exit) {
+            self.password.clear();
+            self.mode = Mode::Reauth(Box::new(self.mode.clone()));
+        }
+    }
+
+    fn update_reauth(&mut self, key: KeyEvent, back: Mode) -> Result<Mode> {
+        match key.code {
+            KeyCode::Char(c) => {
+                self.password.push(c);
+                Ok(Mode::Reauth(Box::new(back)))
+            }
+            KeyCode::Backspace => {
+                self.password.pop();
+                Ok(Mode::Reauth(Box::new(back)))
+            }
+            KeyCode::Enter => {
+                let password = mem::take(&mut self.password);
+                if bool::from(
+                    password
+                        .as_bytes()
+                        .ct_eq(self.key.get_password().as_bytes()),
+                ) {
+                    self.last_key_confirm = Instant::now();
+                    Ok(back)
+                } else {
+                    Err(MystoreError::PasswordMismatch)
+                }
+            }
+            KeyCode::Esc => Ok(Mode::Exit),
+            _ => Ok(Mode::Reauth(Box::new(back))),
+        }
+    }
+
+    fn open_file(&mut self, path: PathBuf) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.viewed = Some(path.clone());
+        self.viewer.set_loading(name.clone());
+        if let Ok(absolute) = std::path::absolute(&path) {
+            self.viewer
+                .set_line_bookmarks(self.bookmarks.get(&absolute).to_vec());
+        }
+        self.loader.start(path, name);
+    }
+
+    /// Scrolls the pane under the mouse with the wheel, other mouse events
+    /// are ignored. The manager's selection stops at the ends of the list.
+    fn apply_mouse(&mut self, mouse: MouseEvent) {
+        // Moves come with every twitch of the mouse, only the wheel counts.
+        if !matches!(
+            mouse.kind,
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp
+        ) {
+            return;
+        }
+        let start = self.timings.start();
+        self.scroll_with_mouse(mouse);
+        self.timings
+            .record_update(start, || format!("Mouse {:?}", mouse.kind));
+    }
+
+    fn scroll_with_mouse(&mut self, mouse: MouseEvent) {
+        let lines = i16::try_from(self.wheel_lines).unwrap_or(i16::MAX);
+        let lines = match mouse.kind {
+            MouseEventKind::ScrollDown => lines,
+            MouseEventKind::ScrollUp => -lines,
+            _ => return,
+        };
+        let is_over = |area: Rect| {
+            (area.left()..area.right()).contains(&mouse.column)
+                && (area.top()..area.bottom()).contains(&mouse.row)
+        };
+        let (manager_area, right_area) = self.panes;
+        match self.mode {
+            Mode::Manager if is_over(manager_area) => {
+                let last = self.manager.get_entities_ref().len().saturating_sub(1);
+                let selected = match self.manager.get_selected_id() {
+                    Some(id) => id.saturating_add_signed(isize::from(lines)).min(last),
+                    None => 0,
+                };
+                self.manager.select(selected);
+            }
+            Mode::Viewer if is_over(right_area) => {
+                if lines < 0 {
+                    self.viewer.scroll_up(lines.unsigned_abs());
+                } else {
+                    self.viewer.scroll_down(lines.unsigned_abs());
+                }
+            }
+            Mode::Editor if is_over(right_area) => {
+                if let Some(textarea) = self.editor.get_textarea_mut() {
+                    textarea.scroll((lines, 0));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Shows a file read from an archive. It is in memory already, so it is
+    /// decoded right away and cannot be refreshed.
+    fn open_data(&mut self, path: PathBuf, data: Vec<u8>) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.viewed = None;
+        self.loader.cancel();
+        self.viewer.set_loading(name.clone());
+        let decoded = Viewer::decode(loader::entity_from_bytes(data), &self.key);
+        self.viewer.set_decoded(decoded, name);
+    }
+
+    fn update_confirm(&mut self, key: KeyEvent, confirm: Confirm) -> Result<Mode> {
+        let back = match confirm {
+            Confirm::DeleteDuplicates => Mode::Duplicates,
+            Confirm::ExportDecrypted => Mode::Viewer,
+            Confirm::LeaveEditor => Mode::Editor,
+            _ => Mode::Manager,
+        };
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                match confirm {
+                    Confirm::CleanupLocks => {
+                        self.manager.cleanup_locks()?;
+                        self.message = Some(self.manager.get_last_cleanup().to_string());
+                    }
+                    Confirm::CopyChecksum(checksum) => {
+                        self.clipboard.copy(&checksum.digest)?;
+                        self.message = Some(format!("Copied {}", checksum.digest));
+                    }
+                    Confirm::DeleteDuplicates => {
+                        self.delete_duplicates()?;
+                        if self.duplicates.is_empty() {
+                            return Ok(Mode::Manager);
+                        }
+                    }
+                    Confirm::ExportDecrypted => return Ok(Mode::Prompt(Prompt::ExportMarkdown)),
+                    Confirm::LeaveEditor => return Ok(Mode::Manager),
+                }
+                Ok(back)
+            }
+            _ => Ok(back),
+        }
+    }
+
+    fn update_prompt(&mut self, key: KeyEvent, prompt: Prompt) -> Result<Mode> {
+        let back = match prompt {
+            Prompt::ExportMarkdown => Mode::Viewer,
+            Prompt::MergeDir => Mode::Manager,
+            Prompt::EditTags => Mode::Viewer,
+            Prompt::FilterExtensions => Mode::Manager,
+            Prompt::GoTo => Mode::Manager,
+            Prompt::ExportZip { .. } => Mode::Manager,
+            Prompt::SearchViewer => Mode::Viewer,
+            Prompt::HardWrap => Mode::Editor,
+            Prompt::SaveNote { .. } => Mode::Editor,
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                Ok(Mode::Prompt(prompt))
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                Ok(Mode::Prompt(prompt))
+            }
+            KeyCode::Tab if prompt == Prompt::GoTo => {
+                self.input = self.manager.complete_path(&self.input);
+                Ok(Mode::Prompt(prompt))
+            }
+            KeyCode::Enter => {
+                match prompt {
+                    Prompt::ExportMarkdown => {
+                        let path = self.manager.resolve_in_current(&self.input)?;
+                        let markdown = self.viewer.to_markdown()?;
+                        self.manager.write_new_file(&path, markdown.as_bytes())?;
+                        self.manager.refresh()?;
+                        self.snapshot(&path, "export");
+                        self.message = Some(format!("Exported to {}", path.display()));
+                    }
+                    Prompt::MergeDir => {
+                        let source = self.manager.get_current().join(&self.input);
+                        let merged = self.manager.merge_dirs(&source, ConflictResolution::Skip)?;
+                        self.message = Some(format!(
+                            "Copied {} files, skipped {} existing",
+                            merged.copied, merged.skipped_existing
+                        ));
+                    }
+                    Prompt::EditTags => self.save_tags()?,
+                    Prompt::FilterExtensions => self.manager.set_extension_filter(&self.input)?,
+                    Prompt::GoTo => self.manager.go_to(&self.input)?,
+                    Prompt::ExportZip { encrypt } => {
+                        let path = self.manager.resolve_in_current(&self.input)?;
+                        self.manager.export_as_zip(&path, encrypt)?;
+                        self.manager.clear_cache();
+                        self.manager.refresh()?;
+                        self.message = Some(format!("Exported to {}", path.display()));
+                    }
+                    Prompt::SearchViewer => {
+                        if !self.viewer.search(&self.input)? {
+                            self.message = Some(format!("Pattern not found: {}", self.input));
+                        }
+                    }
+                    Prompt::HardWrap => {
+                        let column = self
+                            .input
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|column| *column > 0)
+                            .ok_or(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("Not a column: {}", self.input),
+                            ))?;
+                        if !self.editor.apply_hard_wrap(column) {
+                            self.message = Some(format!("No line is longer than {}", column));
+                        }
+                    }
+                    Prompt::SaveNote { encrypt, exit } => return self.save_new_note(encrypt, exit),
+                }
+                Ok(back)
+            }
+            KeyCode::Esc => Ok(back),
+            _ => Ok(Mode::Prompt(prompt)),
+        }
+    }
+
+    /// Reloads the viewed file when auto-refresh is due, e.g. to follow a log.
+    fn refresh_viewed(&mut self) {
+        if self.mode != Mode::Viewer || !self.viewer.is_refresh_due() {
+            return;
+        }
+        if let Some(path) = self.viewed.clone() {
+            self.viewer.start_refresh();
+            let name = self.viewer.get_name().map(String::from);
+            self.loader.start(path, name);
+        }
+    }
+
+    /// Reads the viewed file again, e.g. after it was synced from another
+    /// machine. The shown text stays if the file is gone.
+    fn reload_viewed(&mut self) -> Result<()> {
+        let source = if self.manager.is_remote() {
+            "remote"
+        } else {
+            "archived"
+        };
+        let path = self
+            .viewed
+            .clone()
+            .ok_or(MystoreError::NotAFile("reload", source))?;
+        if !path.is_file() {
+            return Err(MystoreError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} no longer exists", path.display()),
+            )));
+        }
+        self.viewer.start_reload();
+        let name = self.viewer.get_name().map(String::from);
+        self.loader.start(path, name);
+
+        Ok(())
+    }
+
+    fn install_loaded(&mut self) {
+        if let Some(loaded) = self.second_loader.poll() {
+            match loaded.result {
+                Ok(decoded) => self.second_viewer.set_decoded(decoded, loaded.name),
+                Err(err) => {
+                    self.close_split();
+                    if self.mode == Mode::Split {
+                        self.mode = Mode::Viewer;
+                    }
+                    self.status = Err(err);
+                }
+            }
+        }
+        if let Some(loaded) = self.loader.poll() {
+            match loaded.result {
+                Ok(decoded) => self.viewer.set_decoded(decoded, loaded.name),
+                Err(err) => {
+                    self.viewer.clear();
+                    self.mode = Mode::Manager;
+                    self.status = Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> App<'a> {
+    pub fn new(root: &str, key: &str) -> Result<App<'a>> {
+        Self::with_loader(root, key, Arc::new(FsLoader))
+    }
+
+    /// Runs the session over the files matching a glob pattern.
+    pub fn new_from_glob(pattern: &str, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_from_glob(pattern)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over the files listed in a history file, and adds
+    /// the files created in the session to it.
+    pub fn from_history(history: &Path, key: &str) -> Result<App<'a>> {
+        let manager = FileManager::new_from_clipboard_history(history)?;
+        let mut app = Self::with_manager(manager, SessionKey::from(key), Arc::new(FsLoader))?;
+        app.history = Some(history.to_path_buf());
+
+        Ok(app)
+    }
+
+    /// Runs the session over the files of a ZIP archive, read-only.
+    pub fn from_zip(path: &str, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_readonly_from_zip(path)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over the files of a tarball, read-only.
+    pub fn from_tar(path: &str, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_readonly_from_tar(path)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over the files git tracks at `revision`, read-only.
+    pub fn from_git_revision(repo_path: &str, revision: &str, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_from_git_revision(repo_path, revision)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over the home folder of a remote host, see
+    /// `FileManager::new_from_ssh` for `ssh_key`.
+    pub fn from_ssh(config: SshConfig, ssh_key: &str, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_from_ssh(config, ssh_key)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over the objects under a prefix of an S3 bucket.
+    pub fn from_s3(config: S3Config, key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_from_s3(config)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session over several root folders listed as one.
+    pub fn new_multi_root(roots: &[&str], key: &str) -> Result<App<'a>> {
+        Self::with_manager(
+            FileManager::new_multi_root(roots)?,
+            SessionKey::from(key),
+            Arc::new(FsLoader),
+        )
+    }
+
+    /// Runs the session in a scratch folder that lives as long as the `TempDir`.
+    pub fn new_scratch(key: &str) -> Result<(App<'a>, TempDir)> {
+        let (manager, dir) = FileManager::new_scratch()?;
+        let mut app = Self::with_manager(manager, SessionKey::from(key), Arc::new(FsLoader))?;
+        app.scratch = true;
+
+        Ok((app, dir))
+    }
+
+    /// Runs the session over a local root folder read a page at a time, see
+    /// `FileManager::new_incremental`.
+    pub fn new_incremental(root: &str, key: &str) -> Result<App<'a>> {
+        let manager = FileManager::new_incremental(root)?;
+        let key = SessionKey::open(manager.get_root(), key)?;
+        Self::with_manager(manager, key, Arc::new(FsLoader))
+    }
+
+    /// Runs the session over a local root folder, whose keyring holds the
+    /// master key files are encrypted with.
+    pub fn with_loader(root: &str, key: &str, loader: Arc<dyn Loader>) -> Result<App<'a>> {
+        let manager = FileManager::new(root)?;
+        let key = SessionKey::open(manager.get_root(), key)?;
+        Self::with_manager(manager, key, loader)
+    }
+
+    fn with_manager(
+        manager: FileManager,
+        key: SessionKey,
+        loader: Arc<dyn Loader>,
+    ) -> Result<App<'a>> {
+        let mut manager = manager;
+        manager.set_key(key.clone());
+        Ok(App {
+            manager,
+            viewer: Viewer::with_key(key.clone())?,
+            second_viewer: Viewer::with_key(key.clone())?,
+            editor: Editor::with_key(key.clone()),
+            loader: BackgroundLoader::new(Arc::clone(&loader), &key),
+            second_loader: BackgroundLoader::new(loader, &key),
+            split: None,
+            mode: Mode::Manager,
+            status: Ok(()),
+            message: None,
+            macro_recording: None,
+            macro_keys: Vec::new(),
+            choices: Vec::new(),
+            choice_selected: 0,
+            viewed: None,
+            key,
+            input: String::new(),
+            git: None,
+            git_dirty: None,
+            journal: Journal::default(),
+            keybindings: KeyBindings::default(),
+            timings: Timings::default(),
+            help: false,
+            warning: None,
+            scratch: false,
+            clipboard: Arc::new(SystemClipboard),
+            opener: Box::new(SystemOpener),
+            history: None,
+            recovered: Vec::new(),
+            unsaved: false,
+            clipboard_watch: None,
+            message_expires: None,
+            rename: TextArea::default(),
+            grep: TextArea::default(),
+            commit: TextArea::default(),
+            last_commit: None,
+            grep_query: String::new(),
+            grep_results: Vec::new(),
+            grep_selected: 0,
+            sizer: BackgroundSizer::new(),
+            hasher: BackgroundHasher::new(),
+            finder: BackgroundDuplicates::new(),
+            lock_sweep: BackgroundLockSweep::new(),
+            duplicates: Vec::new(),
+            duplicate_selected: (0, 0),
+            duplicate_marked: Vec::new(),
+            size_requested: None,
+            batch: BackgroundBatch::new(),
+            bookmarks: Bookmarks::default(),
+            free_space: None,
+            free_space_checked: None,
+            low_space_threshold: LOW_SPACE_THRESHOLD,
+            reauth_interval: None,
+            last_key_confirm: Instant::now(),
+            password: String::new(),
+            panes: (Rect::default(), Rect::default()),
+            manager_width: DEFAULT_MANAGER_WIDTH,
+            wheel_lines: 3,
+            record_scroll: 0,
+            size_chart: Vec::new(),
+            age_chart: Vec::new(),
+        })
+    }
+
+    pub fn handle_event(&mut self, event: AppEvent) {
+        if let AppEvent::Key(KeyEvent {
+            code: KeyCode::F(12),
+            ..
+        }) = event
+        {
+            self.toggle_debug_overlay();
+            return;
+        }
+        if let AppEvent::Key(KeyEvent {
+            code: KeyCode::F(1),
+            ..
+        }) = event
+        {
+            self.help = !self.help;
+            return;
+        }
+        self.dispatch_event(event);
+        if let Some(duration) = self.manager.take_listing_time() {
+            let entities = self.manager.get_entities_ref().len();
+            self.timings.record_listing(duration, || {
+                format!(
+                    "Listed {} ({} entries)",
+                    self.manager.get_current().display(),
+                    entities
+                )
+            });
+        }
+    }
+
+    /// Shows or hides the timings of drawing, events and listings.
+    pub fn toggle_debug_overlay(&mut self) {
+        let enabled = !self.timings.is_enabled();
+        self.timings.set_enabled(enabled);
+        self.manager.set_time_listings(enabled);
+    }
+
+    pub fn is_help_shown(&self) -> bool {
+        self.help
+    }
+
+    pub fn get_timings_ref(&self) -> &Timings {
+        &self.timings
+    }
+
+    pub fn get_timings_mut(&mut self) -> &mut Timings {
+        &mut self.timings
+    }
+
+    fn dispatch_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Key(key) => {
+                self.message = None;
+                // The password is never recorded into a macro.
+                if matches!(self.mode, Mode::Reauth(_)) {
+                    self.apply_key(key);
+                    return;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Char('Q')
+                        if key
+                            .modifiers
+                            .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                    {
+                        if self.macro_recording.is_none() {
+                            self.replay_macro();
+                        }
+                    }
+                    KeyCode::Char('q') | KeyCode::Char('Q')
+                        if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        self.toggle_macro_recording()
+                    }
+                    KeyCode::Char('v') | KeyCode::Char('V')
+                        if key
+                            .modifiers
+                            .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                    {
+                        self.toggle_clipboard_watch()
+                    }
+                    _ => {
+                        if let Some(keys) = self.macro_recording.as_mut() {
+                            keys.push(key);
+                        }
+                        self.apply_key(key);
+                    }
+                }
+            }
+            AppEvent::Mouse(mouse) => self.apply_mouse(mouse),
+            AppEvent::Tick => {
+                self.expire_message();
+                self.save_clipboard();
+                self.install_loaded();
+                self.refresh_viewed();
+                let is_due = self
+                    .free_space_checked
+                    .is_none_or(|checked| checked.elapsed() >= FREE_SPACE_INTERVAL);
+                if is_due {
+                    self.check_free_space();
+                }
+                if self.sizer.poll().is_some() {
+                    self.announce_size();
+                }
+                self.manager.poll_tree_hash();
+                self.finish_batch();
+                self.show_checksum();
+                self.show_duplicates();
+                self.finish_lock_sweep();
+                self.check_reauth();
+            }
+        }
+    }
+
+    /// Starts or stops saving every text copied to the clipboard as a new
+    /// file in the current folder.
+    pub fn toggle_clipboard_watch(&mut self) {
+        self.message = Some(String::from(match self.clipboard_watch.take() {
+            Some(_watch) => "Stopped watching the clipboard",
+            None => {
+                self.clipboard_watch = Some(ClipboardWatch::start(
+                    Arc::clone(&self.clipboard),
+                    CLIPBOARD_POLL_INTERVAL,
+                ));
+                "Every text copied to the clipboard is saved"
+            }
+        }));
+    }
+
+    pub fn is_clipboard_watched(&self) -> bool {
+        self.clipboard_watch.is_some()
+    }
+
+    fn save_clipboard(&mut self) {
+        let Some(watch) = &self.clipboard_watch else {
+            return;
+        };
+        for text in watch.poll() {
+            match self.manager.create_file(text.into_bytes(), None) {
+                Ok(path) => {
+                    self.saved(&path);
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let message = format!("Saved clipboard to {}", name);
+                    self.message_expires =
+                        Some((message.clone(), Instant::now() + CLIPBOARD_NOTICE));
+                    self.message = Some(message);
+                }
+                Err(err) => self.status = Err(err),
+            }
+        }
+    }
+
+    /// Clears a timed message once it is due, unless another one replaced it.
+    fn expire_message(&mut self) {
+        if let Some((message, expires)) = self.message_expires.take() {
+            if Instant::now() < expires {
+                self.message_expires = Some((message, expires));
+            } else if self.message.as_ref() == Some(&message) {
+                self.message = None;
+            }
+        }
+    }
+
+    /// Commits every save and delete to the git repository holding the root.
+    pub fn enable_git(&mut self) {
+        let git = GitSync::new(self.manager.get_root());
+        match git.is_dirty() {
+            Ok(dirty) => self.git_dirty = Some(dirty),
+            Err(err) => self.status = Err(err),
+        }
+        self.git = Some(git);
+    }
+
+    /// Free disk space under the root, if known on this platform.
+    pub fn get_free_space(&self) -> Option<u64> {
+        self.free_space
+    }
+
+    /// How far the expanded JSON line is scrolled.
+    pub fn get_record_scroll(&self) -> u16 {
+        self.record_scroll
+    }
+
+    /// Names and sizes of the listed files when the chart was asked for.
+    pub fn get_size_chart_ref(&self) -> &[(String, u64)] {
+        &self.size_chart
+    }
+
+    /// File counts of the age buckets when the chart was asked for.
+    pub fn get_age_chart_ref(&self) -> &[(String, usize)] {
+        &self.age_chart
+    }
+
+    /// Where the manager and the right-hand pane are drawn, see `ui::pane_areas`.
+    pub fn set_pane_areas(&mut self, panes: (Rect, Rect)) {
+        self.panes = panes;
+    }
+
+    /// The share of the screen width the manager takes, in percent, kept
+    /// between 10 and 75.
+    pub fn set_manager_width(&mut self, width: u16) {
+        self.manager_width = width.clamp(MIN_MANAGER_WIDTH, MAX_MANAGER_WIDTH);
+    }
+
+    pub fn get_manager_width(&self) -> u16 {
+        self.manager_width
+    }
+
+    /// Where the manager is, to be saved with `SessionState::save`.
+    pub fn get_session_state(&self) -> SessionState {
+        let current = self.manager.get_current();
+        SessionState {
+            dir: current
+                .strip_prefix(self.manager.get_root())
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+            selected: self.manager.get_selected_entity_name().map(String::from),
+            sort_order: self.manager.get_sort_order(),
+            show_hidden: self.manager.get_show_hidden(),
+            manager_width: self.manager_width,
+        }
+    }
+
+    /// Goes back to where a previous session left the manager. A folder or
+    /// entry that no longer exists leaves the root listed or nothing
+    /// selected.
+    pub fn restore_session(&mut self, state: &SessionState) -> Result<()> {
+        self.set_manager_width(state.manager_width);
+        self.manager.set_show_hidden(state.show_hidden)?;
+        self.manager.set_sort_order(state.sort_order)?;
+        let dir = self.manager.get_root().join(&state.dir);
+        if dir != self.manager.get_current()
+            && dir.is_dir()
+            && self
+                .manager
+                .go_to(&format!("./{}", state.dir.display()))
+                .is_err()
+        {
+            self.manager.go_root()?;
+        }
+        let position = state.selected.as_ref().and_then(|name| {
+            self.manager
+                .get_entities_ref()
+                .iter()
+                .position(|entity| match entity {
+                    ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => path
+                        .file_name()
+                        .is_some_and(|file_name| file_name == name.as_str()),
+                    ManagerEntity::Action(_act) => false,
+                })
+        });
+        if let Some(id) = position {
+            self.manager.select(id);
+        }
+
+        Ok(())
+    }
+
+    /// The actions keys are bound to, on top of the default keys.
+    pub fn set_keybindings(&mut self, keybindings: KeyBindings) {
+        self.keybindings = keybindings;
+    }
+
+    /// Deletions and renames that `Ctrl + U` can undo.
+    pub fn get_journal_ref(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// How many lines one notch of the mouse wheel scrolls.
+    pub fn set_wheel_lines(&mut self, lines: u16) {
+        self.wheel_lines = lines;
+    }
+
+    /// Asks for the password again once `interval` has passed since it was
+    /// last typed, never if `None`.
+    pub fn set_reauth_interval(&mut self, interval: Option<Duration>) {
+        self.reauth_interval = interval;
+        self.last_key_confirm = Instant::now();
+    }
+
+    /// The password typed so far while the session is locked.
+    pub fn get_password(&self) -> &str {
+        &self.password
+    }
+
+    pub fn set_low_space_threshold(&mut self, bytes: u64) {
+        self.low_space_threshold = bytes;
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Bookmarks) {
+        self.bookmarks = bookmarks;
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard + Send + Sync>) {
+        self.clipboard = Arc::from(clipboard);
+    }
+
+    pub fn set_url_opener(&mut self, opener: Box<dyn UrlOpener>) {
+        self.opener = opener;
+    }
+
+    pub fn set_recovery_files(&mut self, files: Vec<PathBuf>) {
+        self.recovered = files;
+    }
+
+    pub fn get_recovery_files(&self) -> &[PathBuf] {
+        &self.recovered
+    }
+
+    /// Whether the editor has text its file does not, as of the last key in
+    /// an editing mode, see `FileManager::has_unsaved_changes`.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved
+    }
+
+    pub fn is_scratch(&self) -> bool {
+        self.scratch
+    }
+
+    pub fn is_git_dirty(&self) -> Option<bool> {
+        self.git_dirty
+    }
+
+    /// Starts removing the locks of sessions that are gone without asking,
+    /// as done at the session start. Only local roots can be swept. This is
+    /// best-effort: skipped folders and failures are only told about once
+    /// the sweep is over.
+    pub fn cleanup_locks(&mut self) {
+        self.lock_sweep.start(self.manager.get_roots().to_vec());
+    }
+
+    pub fn is_sweeping_locks(&self) -> bool {
+        self.lock_sweep.is_running()
+    }
+
+    pub fn get_key_ref(&self) -> &SessionKey {
+        &self.key
+    }
+
+    /// The fingerprint of the master key, see `SessionKey::fingerprint`.
+    pub fn get_key_fingerprint(&self) -> Option<String> {
+        self.key.fingerprint()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.macro_recording.is_some()
+    }
+
+    pub fn get_input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn get_message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub fn get_mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    pub fn get_status(&self) -> Option<&MystoreError> {
+        self.status.as_ref().err()
+    }
+
+    pub fn get_manager_ref(&self) -> &FileManager {
+        &self.manager
+    }
+
+    pub fn get_manager_mut(&mut self) -> &mut FileManager {
+        &mut self.manager
+    }
+
+    pub fn get_sizer_ref(&self) -> &BackgroundSizer {
+        &self.sizer
+    }
+
+    pub fn get_batch_ref(&self) -> &BackgroundBatch {
+        &self.batch
+    }
+
+    pub fn get_rename_ref(&self) -> &TextArea<'a> {
+        &self.rename
+    }
+
+    pub fn get_commit_ref(&self) -> &TextArea<'a> {
+        &self.commit
+    }
+
+    /// The commit made last with `Mode::Commit`.
+    pub fn get_last_commit(&self) -> Option<Oid> {
+        self.last_commit
+    }
+
+    pub fn get_grep_ref(&self) -> &TextArea<'a> {
+        &self.grep
+    }
+
+    pub fn get_grep_results_ref(&self) -> &Vec<GrepResult> {
+        &self.grep_results
+    }
+
+    pub fn get_grep_selected(&self) -> usize {
+        self.grep_selected
+    }
+
+    /// The pattern and the replacement typed in the rename prompt.
+    pub fn get_rename_pattern(&self) -> (&str, &str) {
+        let lines = self.rename.lines();
+        (
+            lines.first().map_or("", String::as_str),
+            lines.get(1).map_or("", String::as_str),
+        )
+    }
+
+    /// Groups of files with the same contents from the last search.
+    pub fn get_duplicates_ref(&self) -> &Vec<Vec<PathBuf>> {
+        &self.duplicates
+    }
+
+    /// The group and the copy within it selected in the duplicates list.
+    pub fn get_duplicate_selected(&self) -> (usize, usize) {
+        self.duplicate_selected
+    }
+
+    pub fn get_duplicate_marked_ref(&self) -> &Vec<PathBuf> {
+        &self.duplicate_marked
+    }
+
+    pub fn get_choices_ref(&self) -> &Vec<String> {
+        &self.choices
+    }
+
+    pub fn get_choice_selected(&self) -> usize {
+        self.choice_selected
+    }
+
+    pub fn get_viewer_ref(&self) -> &Viewer {
+        &self.viewer
+    }
+
+    pub fn get_viewer_mut(&mut self) -> &mut Viewer {
+        &mut self.viewer
+    }
+
+    /// The viewer of the file compared with in the split view.
+    pub fn get_second_viewer_ref(&self) -> &Viewer {
+        &self.second_viewer
+    }
+
+    pub fn get_second_viewer_mut(&mut self) -> &mut Viewer {
+        &mut self.second_viewer
+    }
+
+    /// The split view, `None` while a single file is viewed.
+    pub fn get_split(&self) -> Option<Split> {
+        self.split
+    }
+
+    pub fn get_editor_ref(&self) -> &Editor<'a> {
+        &self.editor
+    }
+
+    pub fn get_editor_mut(&mut self) -> &mut Editor<'a> {
+        &mut self.editor
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.mode == Mode::Exit
+    }
+}
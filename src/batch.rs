@@ -0,0 +1,179 @@
+use crate::{
+    error::{MystoreError, Result},
+    keyring::SessionKey,
+    loader::{FsLoader, Loader},
+    manager::FileManager,
+    viewer::{Viewer, ViewerEntity},
+};
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+use tempfile::NamedTempFile;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchOp {
+    Encrypt,
+    Decrypt,
+}
+
+impl fmt::Display for BatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchOp::Encrypt => write!(f, "encrypt"),
+            BatchOp::Decrypt => write!(f, "decrypt"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BatchSummary {
+    pub op: BatchOp,
+    pub converted: Vec<PathBuf>,
+    pub skipped: usize,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchSummary {
+    fn new(op: BatchOp) -> BatchSummary {
+        BatchSummary {
+            op,
+            converted: Vec::new(),
+            skipped: 0,
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}ed, {} skipped, {} failed",
+            self.converted.len(),
+            self.op,
+            self.skipped,
+            self.failed.len()
+        )?;
+        // The first reason is usually the same for the rest.
+        if let Some((path, reason)) = self.failed.first() {
+            write!(f, ": {}: {}", path.display(), reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the contents of `path` so that readers see either the old or the new file.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let permissions = fs::metadata(path)?.permissions();
+    let mut file = NamedTempFile::new_in(parent)?;
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    fs::set_permissions(file.path(), permissions)?;
+    file.persist(path).map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+/// Encrypts or decrypts a file in place, returns false if it is in that state already.
+/// A file locked by a session is left alone and reported.
+pub fn convert_file(path: &Path, op: BatchOp, key: &SessionKey) -> Result<bool> {
+    if FileManager::lock_path(path).exists() {
+        return Err(MystoreError::Locked(path.to_path_buf()));
+    }
+    let decoded = Viewer::decode(FsLoader.load(path)?, key);
+    let contents = match (op, decoded.get_entity_ref()) {
+        (BatchOp::Encrypt, ViewerEntity::Text(text)) => key.encrypt(text)?,
+        (BatchOp::Decrypt, ViewerEntity::DecryptedText(text)) => text.clone().into_bytes(),
+        (BatchOp::Decrypt, ViewerEntity::Binary(_)) => return Err(MystoreError::DecryptFailed),
+        _ => return Ok(false),
+    };
+    write_atomically(path, &contents)?;
+
+    Ok(true)
+}
+
+enum BatchEvent {
+    Progress(usize),
+    Done(BatchSummary),
+}
+
+/// Converts the files on a worker thread, reporting how many are done.
+pub struct BackgroundBatch {
+    receiver: Option<Receiver<BatchEvent>>,
+    progress: (usize, usize),
+    op: BatchOp,
+}
+
+impl Default for BackgroundBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundBatch {
+    pub fn new() -> BackgroundBatch {
+        BackgroundBatch {
+            receiver: None,
+            progress: (0, 0),
+            op: BatchOp::Encrypt,
+        }
+    }
+
+    /// Starts converting `files` unless another batch is still running.
+    pub fn start(&mut self, files: Vec<PathBuf>, op: BatchOp, key: SessionKey) -> bool {
+        if self.receiver.is_some() {
+            return false;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        self.progress = (0, files.len());
+        self.op = op;
+        thread::spawn(move || {
+            let mut summary = BatchSummary::new(op);
+            for (done, path) in files.into_iter().enumerate() {
+                match convert_file(&path, op, &key) {
+                    Ok(true) => summary.converted.push(path),
+                    Ok(false) => summary.skipped += 1,
+                    Err(err) => summary.failed.push((path, err.to_string())),
+                }
+                // The receiver only goes away with the session itself.
+                let _ = sender.send(BatchEvent::Progress(done + 1));
+            }
+            let _ = sender.send(BatchEvent::Done(summary));
+        });
+
+        true
+    }
+
+    pub fn get_op(&self) -> BatchOp {
+        self.op
+    }
+
+    /// Files done and in total while a batch is running.
+    pub fn get_progress(&self) -> Option<(usize, usize)> {
+        self.receiver.as_ref().map(|_receiver| self.progress)
+    }
+
+    /// Returns the summary once the batch is over.
+    pub fn poll(&mut self) -> Option<BatchSummary> {
+        let receiver = self.receiver.as_ref()?;
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                BatchEvent::Progress(done) => self.progress.0 = done,
+                BatchEvent::Done(summary) => {
+                    self.receiver = None;
+                    return Some(summary);
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::Result;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const BOOKMARKS_FILE: &str = ".mystore_bookmarks.toml";
+const SCROLL_FILE: &str = ".mystore_scroll.toml";
+
+/// Escapes `key` for a TOML basic string, control characters as `\uXXXX`.
+fn escape(key: &str) -> String {
+    key.chars()
+        .map(|ch| match ch {
+            '\\' => String::from("\\\\"),
+            '"' => String::from("\\\""),
+            ch if ch.is_control() => format!("\\u{:04X}", u32::from(ch)),
+            ch => ch.to_string(),
+        })
+        .collect()
+}
+
+/// Parses a `"key" = value` line as TOML into the key and the value.
+fn parse_entry(line: &str) -> Option<(String, toml::Value)> {
+    let entry: toml::Table = line.parse().ok()?;
+    entry.into_iter().next()
+}
+
+fn home_file(name: &str) -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(name))
+}
+
+/// Bookmarked lines per file, kept in a small TOML file where every file
+/// is a quoted key holding its line numbers: `"/notes/a.txt" = [3, 40]`.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    /// Where the bookmarks are saved, nowhere for a session-only store.
+    path: Option<PathBuf>,
+    files: BTreeMap<PathBuf, Vec<usize>>,
+}
+
+impl Bookmarks {
+    /// Parses the quoted key and the array of one line, skipping anything else.
+    fn parse_line(line: &str) -> Option<(PathBuf, Vec<usize>)> {
+        let (key, value) = parse_entry(line)?;
+        let lines = value
+            .as_array()?
+            .iter()
+            .map(|item| usize::try_from(item.as_integer()?).ok())
+            .collect::<Option<Vec<usize>>>()?;
+
+        Some((PathBuf::from(key), lines))
+    }
+
+    fn parse(text: &str) -> BTreeMap<PathBuf, Vec<usize>> {
+        text.lines().filter_map(Self::parse_line).collect()
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        home_file(BOOKMARKS_FILE)
+    }
+
+    /// Reads the bookmarks saved at `path`, none if the file is missing.
+    pub fn load(path: &Path) -> Bookmarks {
+        let files = fs::read_to_string(path).map_or(BTreeMap::new(), |text| Self::parse(&text));
+        Bookmarks {
+            path: Some(path.to_path_buf()),
+            files,
+        }
+    }
+
+    pub fn get(&self, file: &Path) -> &[usize] {
+        self.files.get(file).map_or(&[], |lines| lines.as_slice())
+    }
+
+    pub fn set(&mut self, file: &Path, lines: Vec<usize>) {
+        if lines.is_empty() {
+            self.files.remove(file);
+        } else {
+            self.files.insert(file.to_path_buf(), lines);
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (file, lines) in &self.files {
+            let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+            text.push_str(&format!(
+                "\"{}\" = [{}]\n",
+                escape(&file.to_string_lossy()),
+                lines.join(", ")
+            ));
+        }
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+}
+
+/// The last scroll position per file name, kept like the bookmarks as
+/// `"a.txt" = 120` lines.
+#[derive(Debug, Default)]
+pub struct ScrollPositions {
+    /// Where the positions are saved, nowhere for a session-only store.
+    path: Option<PathBuf>,
+    files: HashMap<String, u16>,
+}
+
+impl ScrollPositions {
+    fn parse(text: &str) -> HashMap<String, u16> {
+        text.lines()
+            .filter_map(parse_entry)
+            .filter_map(|(key, value)| Some((key, u16::try_from(value.as_integer()?).ok()?)))
+            .collect()
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        home_file(SCROLL_FILE)
+    }
+
+    /// Reads the positions saved at `path`, none if the file is missing.
+    pub fn load(path: &Path) -> ScrollPositions {
+        let files = fs::read_to_string(path).map_or(HashMap::new(), |text| Self::parse(&text));
+        ScrollPositions {
+            path: Some(path.to_path_buf()),
+            files,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.files.get(name).copied()
+    }
+
+    /// Remembers the position, forgetting files left at the top.
+    pub fn set(&mut self, name: &str, scroll: u16) {
+        if scroll == 0 {
+            self.files.remove(name);
+        } else {
+            self.files.insert(name.to_string(), scroll);
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut files: Vec<_> = self.files.iter().collect();
+        files.sort();
+        let mut text = String::new();
+        for (name, scroll) in files {
+            text.push_str(&format!("\"{}\" = {}\n", escape(name), scroll));
+        }
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+}
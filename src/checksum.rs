@@ -0,0 +1,129 @@
+use crate::error::Result;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+/// How much of a file is read and hashed at a time.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksum {
+    pub path: PathBuf,
+    pub size: u64,
+    /// The SHA-256 digest in lowercase hex.
+    pub digest: String,
+}
+
+/// Hashes everything `reader` yields chunk by chunk, gives up once
+/// `cancelled` is set. Returns the hex digest and the number of bytes read.
+pub fn sha256_hex<R: Read>(
+    mut reader: R,
+    cancelled: &AtomicBool,
+) -> io::Result<Option<(String, u64)>> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut size = 0;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let count = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        hasher.update(&buffer[..count]);
+        size += count as u64;
+    }
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Ok(Some((digest, size)))
+}
+
+pub fn file_sha256(path: &Path, cancelled: &AtomicBool) -> io::Result<Option<Checksum>> {
+    let hashed = sha256_hex(File::open(path)?, cancelled)?;
+    Ok(hashed.map(|(digest, size)| Checksum {
+        path: path.to_path_buf(),
+        size,
+        digest,
+    }))
+}
+
+/// Hashes one file at a time on a worker thread.
+pub struct BackgroundHasher {
+    sender: Sender<(PathBuf, io::Result<Checksum>)>,
+    receiver: Receiver<(PathBuf, io::Result<Checksum>)>,
+    pending: Option<(PathBuf, Arc<AtomicBool>)>,
+}
+
+impl Default for BackgroundHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundHasher {
+    pub fn new() -> BackgroundHasher {
+        let (sender, receiver) = mpsc::channel();
+        BackgroundHasher {
+            sender,
+            receiver,
+            pending: None,
+        }
+    }
+
+    /// Starts hashing `path`, cancelling the file hashed so far.
+    pub fn start(&mut self, path: PathBuf) {
+        self.cancel();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending = Some((path.clone(), Arc::clone(&cancelled)));
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = file_sha256(&path, &cancelled).transpose();
+            if let Some(result) = result {
+                // The receiver only goes away with the session itself.
+                let _ = sender.send((path, result));
+            }
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some((_path, cancelled)) = self.pending.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_pending(&self) -> Option<&Path> {
+        self.pending
+            .as_ref()
+            .map(|(path, _cancelled)| path.as_path())
+    }
+
+    /// Returns the checksum of the pending file once it is done.
+    pub fn poll(&mut self) -> Option<Result<Checksum>> {
+        while let Ok((path, result)) = self.receiver.try_recv() {
+            // A cancelled run may still finish before it notices.
+            if self.get_pending() == Some(&path) {
+                self.pending = None;
+                return Some(result.map_err(Into::into));
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often a watched clipboard is read.
+pub const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub trait Clipboard {
+    fn copy(&self, text: &str) -> Result<()>;
+
+    /// The text in the clipboard, for clipboards that can be read.
+    fn paste(&self) -> Result<String> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "The clipboard cannot be read").into())
+    }
+}
+
+/// Copies with the first available system tool, otherwise asks the terminal
+/// to do it with an OSC 52 sequence, which also works over SSH.
+pub struct SystemClipboard;
+
+impl SystemClipboard {
+    const TOOLS: [(&'static str, &'static [&'static str]); 3] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+    ];
+    const PASTE_TOOLS: [(&'static str, &'static [&'static str]); 3] = [
+        ("pbpaste", &[]),
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-selection", "clipboard", "-o"]),
+    ];
+
+    fn copy_with(tool: &str, args: &[&str], text: &str) -> io::Result<()> {
+        let mut child = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("{} failed", tool)))
+        }
+    }
+
+    fn paste_with(tool: &str, args: &[&str]) -> io::Result<String> {
+        let output = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        if output.status.success() {
+            String::from_utf8(output.stdout).map_err(io::Error::other)
+        } else {
+            Err(io::Error::other(format!("{} failed", tool)))
+        }
+    }
+
+    fn copy_osc52(text: &str) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+        stdout.flush()
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn copy(&self, text: &str) -> Result<()> {
+        let copied = Self::TOOLS
+            .iter()
+            .any(|(tool, args)| Self::copy_with(tool, args, text).is_ok());
+        if !copied {
+            Self::copy_osc52(text)?;
+        }
+
+        Ok(())
+    }
+
+    /// Terminals rarely let OSC 52 read the clipboard, only the system
+    /// tools are tried.
+    fn paste(&self) -> Result<String> {
+        Self::PASTE_TOOLS
+            .iter()
+            .find_map(|(tool, args)| Self::paste_with(tool, args).ok())
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "No clipboard tool found").into())
+    }
+}
+
+/// Reads a clipboard on a worker thread and reports every new text copied
+/// to it, until dropped.
+pub struct ClipboardWatch {
+    receiver: Receiver<String>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl ClipboardWatch {
+    /// Starts watching `clipboard` every `interval`. What it holds already
+    /// is not reported.
+    pub fn start(clipboard: Arc<dyn Clipboard + Send + Sync>, interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut last = clipboard.paste().ok();
+        let watching = Arc::clone(&stopped);
+        thread::spawn(move || {
+            while !watching.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                // An unreadable clipboard is tried again, it may come back.
+                let Ok(text) = clipboard.paste() else {
+                    continue;
+                };
+                if text.trim().is_empty() || last.as_ref() == Some(&text) {
+                    continue;
+                }
+                last = Some(text.clone());
+                if sender.send(text).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ClipboardWatch { receiver, stopped }
+    }
+
+    /// The texts copied since the last call, oldest first.
+    pub fn poll(&self) -> Vec<String> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for ClipboardWatch {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
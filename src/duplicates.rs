@@ -0,0 +1,116 @@
+use crate::{checksum, error::Result};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+/// Groups the files under `roots` with the same contents, every group
+/// sorted by path and the groups by their first path. Files are compared
+/// by size first and only hashed if another file has the same size. Empty
+/// files and symbolic links are left out. Gives up once `cancelled` is set.
+pub fn find_duplicates(
+    roots: &[PathBuf],
+    cancelled: &AtomicBool,
+) -> io::Result<Option<Vec<Vec<PathBuf>>>> {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut dirs = roots.to_vec();
+    while let Some(dir) = dirs.pop() {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                let size = entry.metadata()?.len();
+                if size > 0 {
+                    by_size.entry(size).or_default().push(entry.path());
+                }
+            }
+        }
+    }
+
+    let mut by_digest: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for paths in by_size.into_values().filter(|paths| paths.len() > 1) {
+        for path in paths {
+            match checksum::file_sha256(&path, cancelled)? {
+                Some(sum) => by_digest.entry(sum.digest).or_default().push(path),
+                None => return Ok(None),
+            }
+        }
+    }
+    let mut groups: Vec<Vec<PathBuf>> = by_digest
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|mut paths| {
+            paths.sort();
+            paths
+        })
+        .collect();
+    groups.sort();
+
+    Ok(Some(groups))
+}
+
+/// Looks for duplicates on a worker thread, one search at a time.
+pub struct BackgroundDuplicates {
+    receiver: Option<Receiver<io::Result<Vec<Vec<PathBuf>>>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Default for BackgroundDuplicates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundDuplicates {
+    pub fn new() -> BackgroundDuplicates {
+        BackgroundDuplicates {
+            receiver: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts searching under `roots`, cancelling the search running so far.
+    pub fn start(&mut self, roots: Vec<PathBuf>) {
+        self.cancel();
+
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.receiver = Some(receiver);
+        self.cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            if let Some(result) = find_duplicates(&roots, &cancelled).transpose() {
+                // The receiver is dropped if the search was cancelled.
+                let _ = sender.send(result);
+            }
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.receiver = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Returns the groups of identical files once the search is over.
+    pub fn poll(&mut self) -> Option<Result<Vec<Vec<PathBuf>>>> {
+        let result = self.receiver.as_ref()?.try_recv().ok()?;
+        self.receiver = None;
+
+        Some(result.map_err(Into::into))
+    }
+}
@@ -0,0 +1,924 @@
+use crate::{
+    error::Result,
+    keyring::SessionKey,
+    loader,
+    viewer::{Viewer, ViewerEntity},
+};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, TimeZone, Utc,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use regex::{Captures, Regex};
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+use tui::style::{Color, Modifier, Style};
+use tui_textarea::TextArea;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpellingError {
+    pub line: usize,
+    pub column: usize,
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+/// The note being written, put aside while a file is shown read-only.
+struct Draft<'a> {
+    textarea: Option<TextArea<'a>>,
+    word_count: Option<usize>,
+    title: Option<String>,
+    modified: bool,
+    saved_hash: u64,
+}
+
+pub struct Editor<'a> {
+    textarea: Option<TextArea<'a>>,
+    key: SessionKey,
+    /// The words of the text, `None` once the text may have been changed
+    /// outside of the editor's own methods.
+    word_count: Option<usize>,
+    /// The name the text is saved under, once known.
+    title: Option<String>,
+    /// The file the text was saved to first and whether it is encrypted,
+    /// later saves replace it.
+    target: Option<(PathBuf, bool)>,
+    expand_variables: bool,
+    /// Columns between tab stops.
+    tab_width: u8,
+    /// Whether Tab inserts spaces up to the next tab stop instead of a tab.
+    insert_spaces: bool,
+    /// Whether the text changed since the editor was opened or saved.
+    modified: bool,
+    /// A hash of the lines as last saved, or as opened for a new note.
+    saved_hash: u64,
+    /// Set while a file is shown that must not be changed.
+    read_only: bool,
+    stashed: Option<Draft<'a>>,
+    /// How the time is inserted, in `strftime` format.
+    timestamp_format: String,
+    /// How the date alone is inserted, in `strftime` format.
+    date_format: String,
+}
+
+/// Columns between tab stops unless configured otherwise.
+pub const DEFAULT_TAB_WIDTH: u8 = 4;
+/// ISO 8601 with the offset of the time zone.
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Checks a `strftime` format for specifiers `chrono` does not know, which
+/// would otherwise only fail once a time is formatted.
+pub fn parse_time_format(format: &str) -> std::result::Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!(
+            "invalid time format {:?}, expected strftime specifiers such as %Y-%m-%d",
+            format
+        ));
+    }
+
+    Ok(String::from(format))
+}
+
+impl Editor<'_> {
+    fn crypt_add(c: i32, count: usize, key: &str) -> i32 {
+        let crypt: Vec<_> = key.bytes().collect();
+        (c + crypt[count] as i32) % 256
+    }
+
+    pub(crate) fn encrypt_string(str: &str, key: &str) -> Vec<u8> {
+        let mut encrypt_text: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        for byte in str.as_bytes() {
+            let ch = Self::crypt_add(*byte as i32, count, key);
+            encrypt_text.push(ch as u8);
+            count = (count + 1) % 5;
+        }
+
+        encrypt_text
+    }
+
+    /// Parses the `aspell -a` pipe output, where every input line is answered
+    /// by a block of results terminated with an empty line.
+    fn parse_aspell_output(output: &str) -> Vec<SpellingError> {
+        let mut errors = Vec::new();
+        let mut line = 0;
+        // Skip the version banner.
+        for result in output.lines().skip(1) {
+            let mut parts = result.splitn(2, ": ");
+            let head: Vec<&str> = parts.next().unwrap_or_default().split(' ').collect();
+            let suggestions = parts.next().map_or(Vec::new(), |list| {
+                list.split(", ").map(String::from).collect()
+            });
+            // Offsets count the leading '^' sent with every line.
+            let (word, offset) = match head.as_slice() {
+                ["&", word, _count, offset] => (word, offset),
+                ["#", word, offset] => (word, offset),
+                [""] => {
+                    line += 1;
+                    continue;
+                }
+                _ => continue,
+            };
+            errors.push(SpellingError {
+                line,
+                column: offset.parse::<usize>().unwrap_or(1).saturating_sub(1),
+                word: word.to_string(),
+                suggestions,
+            });
+        }
+
+        errors
+    }
+
+    /// Replaces `$DATE`, `$USER` and `$FILENAME`; variables without a value
+    /// are kept as typed.
+    fn expand(&self, text: &str) -> Result<String> {
+        let regex = Regex::new(r"\$(DATE|USER|FILENAME)\b")
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        let expanded = regex.replace_all(text, |caps: &Captures| {
+            let value = match &caps[1] {
+                "DATE" => Some(Utc::now().format("%Y-%m-%d").to_string()),
+                "USER" => env::var("USER").ok(),
+                _ => self.title.clone(),
+            };
+            value.unwrap_or_else(|| caps[0].to_string())
+        });
+
+        Ok(expanded.into_owned())
+    }
+
+    /// The text as it is saved, with the variables expanded if asked to.
+    fn current_text(&self) -> Result<Option<String>> {
+        let Some(textarea) = self.textarea.as_ref() else {
+            return Ok(None);
+        };
+        let text = textarea.lines().join("\n");
+        if self.expand_variables {
+            return self.expand(&text).map(Some);
+        }
+
+        Ok(Some(text))
+    }
+
+    /// Takes the text out of the editor, expanding the variables if asked to.
+    fn take_text(&mut self) -> Result<Option<String>> {
+        let text = self.current_text()?;
+        self.textarea = None;
+        self.modified = false;
+
+        Ok(text)
+    }
+}
+
+impl<'a> Editor<'a> {
+    pub fn new(key: &str) -> Editor<'a> {
+        Self::with_key(SessionKey::from(key))
+    }
+
+    pub fn with_key(key: SessionKey) -> Editor<'a> {
+        Editor {
+            textarea: None,
+            key,
+            word_count: Some(0),
+            title: None,
+            target: None,
+            expand_variables: false,
+            tab_width: DEFAULT_TAB_WIDTH,
+            insert_spaces: false,
+            modified: false,
+            saved_hash: Self::hash_lines(&[String::new()]),
+            read_only: false,
+            stashed: None,
+            timestamp_format: String::from(DEFAULT_TIMESTAMP_FORMAT),
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+        }
+    }
+
+    /// A text area that shows and types tabs as configured. Tabs already in
+    /// the text stay tabs.
+    fn new_textarea(&self) -> TextArea<'a> {
+        let mut textarea = TextArea::default();
+        self.configure(&mut textarea);
+        textarea
+    }
+
+    fn configure(&self, textarea: &mut TextArea) {
+        textarea.set_tab_length(self.tab_width);
+        textarea.set_hard_tab_indent(!self.insert_spaces);
+    }
+
+    fn hash_lines(lines: &[String]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        lines.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn init(&mut self) {
+        let textarea = self.new_textarea();
+        self.saved_hash = Self::hash_lines(textarea.lines());
+        self.textarea = Some(textarea);
+        self.word_count = Some(0);
+        self.title = None;
+        self.target = None;
+        self.modified = false;
+        self.read_only = false;
+        self.stashed = None;
+    }
+
+    /// Drops the text once it is saved for good.
+    pub fn close(&mut self) {
+        self.init();
+        self.textarea = None;
+    }
+
+    /// Shows `text` under `name` with only the keys that move the cursor
+    /// working. The note being written comes back with `close_read_only`.
+    pub fn open_read_only(&mut self, name: Option<String>, text: &str) {
+        if !self.read_only {
+            self.stashed = Some(Draft {
+                textarea: self.textarea.take(),
+                word_count: self.word_count,
+                title: self.title.take(),
+                modified: self.modified,
+                saved_hash: self.saved_hash,
+            });
+        }
+        let mut textarea = TextArea::from(text.lines());
+        self.configure(&mut textarea);
+        self.textarea = Some(textarea);
+        self.word_count = Some(self.word_count_live());
+        self.title = name;
+        self.modified = false;
+        self.read_only = true;
+    }
+
+    pub fn close_read_only(&mut self) {
+        if !self.read_only {
+            return;
+        }
+        self.read_only = false;
+        let draft = self.stashed.take().unwrap_or(Draft {
+            textarea: None,
+            word_count: Some(0),
+            title: None,
+            modified: false,
+            saved_hash: self.saved_hash,
+        });
+        self.textarea = draft.textarea;
+        self.word_count = draft.word_count;
+        self.title = draft.title;
+        self.modified = draft.modified;
+        self.saved_hash = draft.saved_hash;
+    }
+
+    /// The lines of the text area, if it is open.
+    pub fn get_lines(&self) -> Option<&[String]> {
+        self.textarea.as_ref().map(|textarea| textarea.lines())
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Whether the text area only moves the cursor or scrolls on `key`.
+    fn moves_only(key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::PageUp
+            | KeyCode::PageDown => true,
+            KeyCode::Char('f' | 'b' | 'p' | 'n') => ctrl || alt,
+            KeyCode::Char('a' | 'e') => ctrl && !alt,
+            KeyCode::Char('v') => ctrl || alt,
+            KeyCode::Char('<' | '>' | '[' | ']') => alt && !ctrl,
+            _ => false,
+        }
+    }
+
+    /// Passes a key to the text area, counting the words again when the key
+    /// changed the text. Read-only text only gets the keys that move.
+    pub fn input(&mut self, key: KeyEvent) {
+        if self.read_only && !Self::moves_only(&key) {
+            return;
+        }
+        let modified = self
+            .textarea
+            .as_mut()
+            .is_some_and(|textarea| textarea.input(key));
+        if modified {
+            self.word_count = Some(self.word_count_live());
+            self.modified = true;
+        }
+    }
+
+    pub fn word_count_live(&self) -> usize {
+        self.textarea.as_ref().map_or(0, |textarea| {
+            textarea
+                .lines()
+                .iter()
+                .map(|line| line.split_whitespace().count())
+                .sum()
+        })
+    }
+
+    /// The word count, counted again only after the text changed.
+    pub fn get_word_count(&self) -> usize {
+        self.word_count.unwrap_or_else(|| self.word_count_live())
+    }
+
+    /// Whether there is text that would be lost without saving.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn get_target(&self) -> Option<(&Path, bool)> {
+        self.target
+            .as_ref()
+            .map(|(path, encrypted)| (path.as_path(), *encrypted))
+    }
+
+    pub fn set_target(&mut self, path: PathBuf, encrypted: bool) {
+        self.target = Some((path, encrypted));
+    }
+
+    /// The bytes to save, encrypted with the session key if asked to. The
+    /// text stays in the editor, see `mark_saved`.
+    pub fn snapshot(&self, encrypt: bool) -> Result<Vec<u8>> {
+        let text = self.current_text()?.unwrap_or_default();
+        Ok(match encrypt {
+            true => self.key.encrypt(&text)?,
+            false => text.into_bytes(),
+        })
+    }
+
+    /// Clears the modified flag once a snapshot is written, and takes the
+    /// text as saved.
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+        if let Some(textarea) = &self.textarea {
+            self.saved_hash = Self::hash_lines(textarea.lines());
+        }
+    }
+
+    /// Whether the text differs from the one last saved, or from none for
+    /// a new note. Unlike `is_modified`, text changed back is not counted.
+    /// Lines are compared, so line endings do not count.
+    pub fn has_unsaved_changes(&self) -> bool {
+        match &self.textarea {
+            Some(textarea) if !self.read_only => {
+                Self::hash_lines(textarea.lines()) != self.saved_hash
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+    }
+
+    pub fn get_expand_variables(&self) -> bool {
+        self.expand_variables
+    }
+
+    /// Switches expanding of `$DATE`, `$USER` and `$FILENAME` on save.
+    pub fn toggle_expand_variables(&mut self) {
+        self.expand_variables = !self.expand_variables;
+    }
+
+    pub fn get_tab_width(&self) -> u8 {
+        self.tab_width
+    }
+
+    /// Sets the columns between tab stops, at least one.
+    pub fn set_tab_width(&mut self, width: u8) {
+        self.tab_width = width.max(1);
+        if let Some(mut textarea) = self.textarea.take() {
+            self.configure(&mut textarea);
+            self.textarea = Some(textarea);
+        }
+    }
+
+    pub fn get_insert_spaces(&self) -> bool {
+        self.insert_spaces
+    }
+
+    /// Makes Tab insert spaces up to the next tab stop, or a tab.
+    pub fn set_insert_spaces(&mut self, insert_spaces: bool) {
+        self.insert_spaces = insert_spaces;
+        if let Some(mut textarea) = self.textarea.take() {
+            self.configure(&mut textarea);
+            self.textarea = Some(textarea);
+        }
+    }
+
+    /// The lines broken at the last space before `column_width` columns.
+    /// Broken lines go on with their indentation, words wider than the
+    /// columns keep a line of their own.
+    pub fn hard_wrap(&self, column_width: usize) -> Vec<String> {
+        let Some(textarea) = self.textarea.as_ref() else {
+            return Vec::new();
+        };
+        let mut wrapped = Vec::new();
+        for line in textarea.lines() {
+            if line.width() <= column_width {
+                wrapped.push(line.clone());
+                continue;
+            }
+            let body = line.trim_start();
+            let indent = &line[..line.len() - body.len()];
+            let mut current = String::from(indent);
+            for word in body.split_whitespace() {
+                let fits = current.width() + 1 + word.width() <= column_width;
+                if current.len() == indent.len() {
+                    current.push_str(word);
+                } else if fits {
+                    current.push(' ');
+                    current.push_str(word);
+                } else {
+                    wrapped.push(std::mem::replace(
+                        &mut current,
+                        format!("{}{}", indent, word),
+                    ));
+                }
+            }
+            wrapped.push(current);
+        }
+
+        wrapped
+    }
+
+    /// Replaces the text with its lines wrapped by `hard_wrap`, returns
+    /// whether a line was broken.
+    pub fn apply_hard_wrap(&mut self, column_width: usize) -> bool {
+        let wrapped = self.hard_wrap(column_width);
+        let changed = self
+            .textarea
+            .as_ref()
+            .is_some_and(|textarea| textarea.lines() != wrapped.as_slice());
+        if changed {
+            let mut textarea = TextArea::from(wrapped);
+            self.configure(&mut textarea);
+            self.textarea = Some(textarea);
+            self.modified = true;
+        }
+
+        changed
+    }
+
+    /// Sets the formats of the inserted time and date, as checked by
+    /// `parse_time_format`.
+    pub fn set_time_formats(&mut self, timestamp: &str, date: &str) {
+        self.timestamp_format = String::from(timestamp);
+        self.date_format = String::from(date);
+    }
+
+    /// Inserts `time` at the cursor in the time zone it is in, or just its
+    /// date.
+    pub fn insert_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>, date_only: bool)
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let format = match date_only {
+            true => &self.date_format,
+            false => &self.timestamp_format,
+        };
+        let text = time.format(format).to_string();
+        if let Some(textarea) = self.textarea.as_mut() {
+            if textarea.insert_str(text) {
+                self.word_count = Some(self.word_count_live());
+                self.modified = true;
+            }
+        }
+    }
+
+    pub fn get_textarea_ref(&self) -> Option<&TextArea<'a>> {
+        self.textarea.as_ref()
+    }
+
+    /// The text area. Changes made through it are not tracked, so the words
+    /// are counted afresh until the editor changes the text again.
+    pub fn get_textarea_mut(&mut self) -> Option<&mut TextArea<'a>> {
+        self.word_count = None;
+        self.textarea.as_mut()
+    }
+
+    /// Checks the text with an external `aspell` process.
+    pub fn spell_check(&self) -> Result<Vec<SpellingError>> {
+        let lines = match &self.textarea {
+            Some(textarea) => textarea.lines().to_vec(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut child = Command::new("aspell")
+            .arg("-a")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => {
+                    io::Error::new(io::ErrorKind::NotFound, "aspell is not installed")
+                }
+                _ => err,
+            })?;
+        // '^' keeps aspell from reading lines as pipe commands.
+        let input: String = lines.iter().map(|line| format!("^{}\n", line)).collect();
+        let mut stdin = child.stdin.take();
+        // aspell answers while it reads, so the text is written from another
+        // thread: writing it all first would block on a full stdout pipe.
+        let writer = thread::spawn(move || match stdin.as_mut() {
+            Some(stdin) => stdin.write_all(input.as_bytes()),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_err| io::Error::other("The text could not be sent to aspell"))??;
+
+        Ok(Self::parse_aspell_output(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Underlines every occurrence of the misspelled words.
+    pub fn highlight_spelling_errors(&mut self, errors: &[SpellingError]) {
+        if let Some(textarea) = self.textarea.as_mut() {
+            let words: Vec<String> = errors.iter().map(|err| regex::escape(&err.word)).collect();
+            let pattern = if words.is_empty() {
+                String::new()
+            } else {
+                format!(r"\b({})\b", words.join("|"))
+            };
+            // The pattern is built from escaped words only.
+            let _ = textarea.set_search_pattern(pattern);
+            textarea.set_search_style(
+                Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::UNDERLINED),
+            );
+        }
+    }
+
+    /// Inserts the text of the file at `path` at the cursor, see
+    /// `insert_data`.
+    pub fn insert_file_contents(&mut self, path: &Path) -> io::Result<()> {
+        self.insert_data(fs::read(path)?)
+    }
+
+    /// Inserts file contents at the cursor, decrypted with the session key
+    /// if they are encrypted. Binary data is refused.
+    pub fn insert_data(&mut self, data: Vec<u8>) -> io::Result<()> {
+        let decoded = Viewer::decode(loader::entity_from_bytes(data), &self.key);
+        let text = match decoded.get_entity_ref() {
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => text,
+            _binary => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Only text or notes encrypted with the session key can be inserted",
+                ))
+            }
+        };
+        let mut textarea = match self.textarea.take() {
+            Some(textarea) => textarea,
+            None => self.new_textarea(),
+        };
+        // The text area takes no line breaks within the inserted strings.
+        for (id, line) in text.split('\n').enumerate() {
+            if id > 0 {
+                textarea.insert_newline();
+            }
+            textarea.insert_str(line.strip_suffix('\r').unwrap_or(line));
+        }
+        self.textarea = Some(textarea);
+        self.word_count = Some(self.word_count_live());
+        self.modified = true;
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<String> {
+        if let Some(text) = self.take_text()? {
+            return Ok(text);
+        }
+
+        Ok(String::new())
+    }
+
+    pub fn finish_encrypt(&mut self) -> Result<Vec<u8>> {
+        if let Some(text) = self.take_text()? {
+            return self.key.encrypt(&text);
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn parses_misses_and_guesses_per_line() {
+        let output = "@(#) International Ispell Version 3.1.20 (but really Aspell 0.60.8)\n\
+                      *\n\
+                      & helo 3 1: hello, halo, hell\n\
+                      \n\
+                      *\n\
+                      # qwzx 6\n\
+                      \n";
+        assert_eq!(
+            Editor::parse_aspell_output(output),
+            vec![
+                SpellingError {
+                    line: 0,
+                    column: 0,
+                    word: String::from("helo"),
+                    suggestions: vec![
+                        String::from("hello"),
+                        String::from("halo"),
+                        String::from("hell")
+                    ],
+                },
+                SpellingError {
+                    line: 1,
+                    column: 5,
+                    word: String::from("qwzx"),
+                    suggestions: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn inserted_notes_are_decrypted() {
+        let mut editor = Editor::new("secret");
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str("Quote: ");
+        editor
+            .insert_data(Editor::encrypt_string("kept private", "secret"))
+            .unwrap();
+        editor.insert_data(b" and plain\r\nlines".to_vec()).unwrap();
+        assert_eq!(
+            editor.get_textarea_ref().unwrap().lines(),
+            ["Quote: kept private and plain", "lines"]
+        );
+        assert_eq!(editor.get_word_count(), 6);
+
+        let err = editor
+            .insert_data(vec![0, 159, 146, 150, 0, 255])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            editor.finish().unwrap(),
+            "Quote: kept private and plain\nlines"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_wrapped_at_word_boundaries() {
+        let mut editor = Editor::new("secret");
+        editor.init();
+        editor
+            .get_textarea_mut()
+            .unwrap()
+            .insert_str("one two three four five six");
+        editor.get_textarea_mut().unwrap().insert_newline();
+        editor.get_textarea_mut().unwrap().insert_newline();
+        editor
+            .get_textarea_mut()
+            .unwrap()
+            .insert_str("  - an incomprehensibilities list");
+        editor.get_textarea_mut().unwrap().insert_newline();
+        editor.get_textarea_mut().unwrap().insert_str("short");
+        assert_eq!(
+            editor.hard_wrap(13),
+            [
+                "one two three",
+                "four five six",
+                "",
+                "  - an",
+                "  incomprehensibilities",
+                "  list",
+                "short",
+            ]
+        );
+
+        assert!(!editor.apply_hard_wrap(40));
+        assert!(!editor.is_modified());
+        assert!(editor.apply_hard_wrap(13));
+        assert!(editor.is_modified());
+        assert_eq!(
+            editor.get_textarea_ref().unwrap().lines()[1],
+            "four five six"
+        );
+    }
+
+    #[test]
+    fn time_is_inserted_in_its_own_time_zone() {
+        let mut editor = Editor::new("secret");
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str("Entry ");
+        let tokyo = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+        let time = tokyo.with_ymd_and_hms(2024, 3, 1, 8, 30, 5).unwrap();
+        editor.insert_time(time, false);
+        editor.insert_time(time, true);
+        assert_eq!(
+            editor.get_textarea_ref().unwrap().lines(),
+            ["Entry 2024-03-01T08:30:05+09:002024-03-01"]
+        );
+        assert!(editor.is_modified());
+
+        editor.set_time_formats("%d.%m.%Y %H:%M", "%A");
+        editor.init();
+        editor.insert_time(time, false);
+        editor.insert_time(time, true);
+        assert_eq!(
+            editor.get_textarea_ref().unwrap().lines(),
+            ["01.03.2024 08:30Friday"]
+        );
+    }
+
+    #[test]
+    fn unknown_time_specifiers_are_refused() {
+        assert_eq!(
+            parse_time_format(DEFAULT_TIMESTAMP_FORMAT),
+            Ok(String::from(DEFAULT_TIMESTAMP_FORMAT))
+        );
+        assert!(parse_time_format("%Y-%m-%d %Q").is_err());
+        assert!(parse_time_format("%").is_err());
+    }
+
+    #[test]
+    fn edits_are_modified_until_saved() {
+        let press = |editor: &mut Editor, code| {
+            editor.input(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        let mut editor = Editor::new("secret");
+        editor.init();
+        // Moving the cursor changes nothing.
+        press(&mut editor, KeyCode::Left);
+        assert!(!editor.is_modified());
+        press(&mut editor, KeyCode::Char('a'));
+        assert!(editor.is_modified());
+        editor.finish_encrypt().unwrap();
+        assert!(!editor.is_modified());
+
+        editor.init();
+        editor.insert_data(b"quoted".to_vec()).unwrap();
+        assert!(editor.is_modified());
+        editor.init();
+        assert!(!editor.is_modified());
+    }
+
+    #[test]
+    fn unsaved_changes_are_told_from_the_saved_text() {
+        let press = |editor: &mut Editor, code| {
+            editor.input(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        let mut editor = Editor::new("secret");
+        assert!(!editor.has_unsaved_changes());
+        editor.init();
+        assert!(!editor.has_unsaved_changes());
+        press(&mut editor, KeyCode::Char('a'));
+        assert!(editor.has_unsaved_changes());
+        // Typing the text back to how it was leaves nothing to save.
+        press(&mut editor, KeyCode::Backspace);
+        assert!(editor.is_modified());
+        assert!(!editor.has_unsaved_changes());
+
+        press(&mut editor, KeyCode::Char('a'));
+        editor.mark_saved();
+        assert!(!editor.has_unsaved_changes());
+        press(&mut editor, KeyCode::Enter);
+        assert!(editor.has_unsaved_changes());
+
+        // The note comes back with its state after a read-only view.
+        editor.open_read_only(Some(String::from("other.txt")), "other");
+        assert!(!editor.has_unsaved_changes());
+        editor.close_read_only();
+        assert!(editor.has_unsaved_changes());
+        press(&mut editor, KeyCode::Backspace);
+        assert!(!editor.has_unsaved_changes());
+    }
+
+    #[test]
+    fn read_only_text_only_moves_the_cursor() {
+        let mut editor = Editor::new("secret");
+        editor.init();
+        editor.input(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE));
+        editor.open_read_only(Some(String::from("note.txt")), "first line\nsecond");
+        let edits = [
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('X'), KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Char('d'), KeyModifiers::ALT),
+            KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+        ];
+        for key in [KeyCode::Down, KeyCode::End] {
+            editor.input(KeyEvent::new(key, KeyModifiers::NONE));
+        }
+        editor.input(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        for key in edits {
+            editor.input(key);
+        }
+        let textarea = editor.get_textarea_ref().unwrap();
+        assert_eq!(textarea.lines(), ["first line", "second"]);
+        assert_eq!(textarea.cursor(), (1, 6));
+        assert!(!editor.is_modified());
+        assert_eq!(editor.get_title(), Some("note.txt"));
+
+        // The note being written is back as it was.
+        editor.close_read_only();
+        assert!(!editor.is_read_only());
+        assert_eq!(editor.get_textarea_ref().unwrap().lines(), ["d"]);
+        assert!(editor.is_modified());
+        assert_eq!(editor.get_word_count(), 1);
+    }
+
+    #[test]
+    fn words_are_counted_after_edits() {
+        let press = |editor: &mut Editor, code| {
+            editor.input(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        let mut editor = Editor::new("secret");
+        editor.init();
+        for ch in "one  two\tthree".chars() {
+            press(&mut editor, KeyCode::Char(ch));
+        }
+        press(&mut editor, KeyCode::Enter);
+        press(&mut editor, KeyCode::Char('x'));
+        assert_eq!(editor.get_word_count(), 4);
+
+        press(&mut editor, KeyCode::Up);
+        assert_eq!(editor.get_word_count(), 4);
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.get_word_count(), 3);
+
+        // Changes made through the text area itself are counted too.
+        editor.get_textarea_mut().unwrap().insert_str(" four");
+        assert_eq!(editor.get_word_count(), 4);
+        press(&mut editor, KeyCode::Char(' '));
+        press(&mut editor, KeyCode::Char('5'));
+        assert_eq!(editor.get_word_count(), 5);
+    }
+
+    #[test]
+    fn variables_are_expanded_when_asked_to() {
+        let text = "$FILENAME by $USER on $DATE, $HOME and $DATES";
+        let mut editor = Editor::new("secret");
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str(text);
+        assert_eq!(editor.finish().unwrap(), text);
+
+        editor.init();
+        editor.toggle_expand_variables();
+        editor.set_title(Some(String::from("note.md")));
+        editor.get_textarea_mut().unwrap().insert_str(text);
+        let user = env::var("USER").unwrap_or_else(|_err| String::from("$USER"));
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        assert_eq!(
+            editor.finish().unwrap(),
+            format!("note.md by {} on {}, $HOME and $DATES", user, date)
+        );
+
+        // Without a title the variable is kept.
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str("$FILENAME");
+        assert_eq!(editor.finish().unwrap(), "$FILENAME");
+    }
+
+    #[test]
+    fn correct_text_has_no_errors() {
+        let output = "@(#) International Ispell Version 3.1.20\n*\n*\n\n";
+        assert!(Editor::parse_aspell_output(output).is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MystoreError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Cannot delete {}: not created in the current session", .0.display())]
+    NotDeletable(PathBuf),
+    #[error("Cannot {0} the {1} entity")]
+    NotAFile(&'static str, &'static str),
+    #[error("Cannot decrypt the file with the session key")]
+    DecryptFailed,
+    #[error("Invalid key: at least {0} bytes are expected")]
+    WrongKey(usize),
+    #[error("File {} already exists", .0.display())]
+    NameCollision(PathBuf),
+    #[error("{} is locked by another session", .0.display())]
+    Locked(PathBuf),
+    #[error("{} is opened read-only", .0.display())]
+    ReadOnly(PathBuf),
+    #[error("git: {0}")]
+    Git(String),
+    #[error("{} is outside of the root folder", .0.display())]
+    OutsideRoot(PathBuf),
+    #[error("Only {} of disk space is left", crate::sizes::format_size(*.0))]
+    LowSpace(u64),
+    #[error("The password does not match the session key")]
+    PasswordMismatch,
+    #[error("The keyring of the store cannot be unlocked with this password")]
+    KeyringLocked,
+}
+
+pub type Result<T> = std::result::Result<T, MystoreError>;
+
+impl From<git2::Error> for MystoreError {
+    fn from(err: git2::Error) -> Self {
+        MystoreError::Git(err.message().to_string())
+    }
+}
+
+impl MystoreError {
+    pub fn title(&self) -> &'static str {
+        match self {
+            MystoreError::Io(_) => "I/O Error",
+            MystoreError::NotDeletable(_)
+            | MystoreError::NotAFile(..)
+            | MystoreError::OutsideRoot(_) => "Not Allowed",
+            MystoreError::DecryptFailed
+            | MystoreError::WrongKey(_)
+            | MystoreError::PasswordMismatch
+            | MystoreError::KeyringLocked => "Key Error",
+            MystoreError::NameCollision(_) => "Name Collision",
+            MystoreError::Locked(_) => "Locked",
+            MystoreError::ReadOnly(_) => "Read-only",
+            MystoreError::Git(_) => "Git Error",
+            MystoreError::LowSpace(_) => "Low Disk Space",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_error_converts_into_io_variant() {
+        let err: MystoreError = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert!(
+            matches!(err, MystoreError::Io(ref inner) if inner.kind() == io::ErrorKind::NotFound)
+        );
+        assert_eq!(err.to_string(), "missing");
+    }
+
+    #[test]
+    fn question_mark_lifts_io_errors() {
+        fn open() -> Result<()> {
+            std::fs::read("/nonexistent/mystore/file")?;
+            Ok(())
+        }
+        assert!(matches!(open(), Err(MystoreError::Io(_))));
+    }
+
+    #[test]
+    fn display_formatting() {
+        assert_eq!(
+            MystoreError::NotDeletable(PathBuf::from("notes/a.txt")).to_string(),
+            "Cannot delete notes/a.txt: not created in the current session"
+        );
+        assert_eq!(
+            MystoreError::NotAFile("delete", "folder").to_string(),
+            "Cannot delete the folder entity"
+        );
+        assert_eq!(
+            MystoreError::WrongKey(5).to_string(),
+            "Invalid key: at least 5 bytes are expected"
+        );
+        assert_eq!(
+            MystoreError::NameCollision(PathBuf::from("a.txt")).to_string(),
+            "File a.txt already exists"
+        );
+        assert_eq!(
+            MystoreError::Locked(PathBuf::from("a.txt")).to_string(),
+            "a.txt is locked by another session"
+        );
+        assert_eq!(
+            MystoreError::ReadOnly(PathBuf::from("notes.zip")).to_string(),
+            "notes.zip is opened read-only"
+        );
+        assert_eq!(
+            MystoreError::Git(String::from("not a git repository")).to_string(),
+            "git: not a git repository"
+        );
+        assert_eq!(
+            MystoreError::OutsideRoot(PathBuf::from("/etc")).to_string(),
+            "/etc is outside of the root folder"
+        );
+        assert_eq!(
+            MystoreError::LowSpace(5 * 1024 * 1024).to_string(),
+            "Only 5.0 MiB of disk space is left"
+        );
+        assert_eq!(
+            MystoreError::PasswordMismatch.to_string(),
+            "The password does not match the session key"
+        );
+        assert_eq!(
+            MystoreError::KeyringLocked.to_string(),
+            "The keyring of the store cannot be unlocked with this password"
+        );
+    }
+
+    #[test]
+    fn titles_distinguish_filesystem_from_domain_errors() {
+        assert_eq!(
+            MystoreError::from(io::Error::other("x")).title(),
+            "I/O Error"
+        );
+        assert_eq!(MystoreError::DecryptFailed.title(), "Key Error");
+        assert_eq!(
+            MystoreError::NotAFile("delete", "action").title(),
+            "Not Allowed"
+        );
+        assert_eq!(
+            MystoreError::ReadOnly(PathBuf::from("notes.zip")).title(),
+            "Read-only"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::error::{MystoreError, Result};
+use git2::{Index, IndexEntry, IndexTime, Oid, Repository, Signature, StatusOptions};
+use std::path::{Path, PathBuf};
+
+/// Mode of a regular file in a git tree.
+const FILE_MODE: u32 = 0o100644;
+
+/// The path of `path` relative to the working tree of `repo`, with forward
+/// slashes. A deleted file is found through its folder.
+fn repo_path(repo: &Repository, path: &Path) -> Result<String> {
+    let workdir = repo
+        .workdir()
+        .ok_or(git2::Error::from_str("The repository has no working tree"))?
+        .canonicalize()?;
+    let folder = path.parent().unwrap_or(path).canonicalize()?;
+    let full = match path.file_name() {
+        Some(name) => folder.join(name),
+        None => folder,
+    };
+    let relative = full
+        .strip_prefix(&workdir)
+        .map_err(|_err| MystoreError::OutsideRoot(path.to_path_buf()))?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    Ok(parts.join("/"))
+}
+
+/// Commits `paths` alone on top of HEAD, as they are in the working tree,
+/// a missing file being removed. The tree is built from HEAD's in memory,
+/// so changes the user staged are not swept in. The index is then updated
+/// for the committed paths only, like `git commit --only` does. Returns
+/// `None` when the paths are already committed as they are.
+pub fn commit_paths(
+    repo: &Repository,
+    paths: &[&Path],
+    message: &str,
+    author: &Signature,
+) -> Result<Option<Oid>> {
+    // An unborn branch gets its first commit.
+    let parent = repo
+        .head()
+        .ok()
+        .map(|head| head.peel_to_commit())
+        .transpose()?;
+    let mut tree = Index::new()?;
+    if let Some(parent) = &parent {
+        tree.read_tree(&parent.tree()?)?;
+    }
+    let mut relative = Vec::new();
+    for path in paths {
+        let name = repo_path(repo, path)?;
+        if path.is_file() {
+            let data = std::fs::read(path)?;
+            let mode = tree
+                .get_path(Path::new(&name), 0)
+                .map_or(FILE_MODE, |entry| entry.mode);
+            tree.add(&IndexEntry {
+                ctime: IndexTime::new(0, 0),
+                mtime: IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode,
+                uid: 0,
+                gid: 0,
+                file_size: data.len() as u32,
+                id: repo.blob(&data)?,
+                flags: 0,
+                flags_extended: 0,
+                path: name.clone().into_bytes(),
+            })?;
+        } else if tree.get_path(Path::new(&name), 0).is_some() {
+            tree.remove_path(Path::new(&name))?;
+        }
+        relative.push(PathBuf::from(name));
+    }
+    let tree = repo.find_tree(tree.write_tree_to(repo)?)?;
+    if parent
+        .as_ref()
+        .is_some_and(|parent| parent.tree_id() == tree.id())
+    {
+        return Ok(None);
+    }
+
+    let committer = repo.signature()?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let oid = repo.commit(Some("HEAD"), author, &committer, message, &tree, &parents)?;
+    let mut index = repo.index()?;
+    for (path, name) in paths.iter().zip(&relative) {
+        match path.is_file() {
+            true => index.add_path(name)?,
+            false => index.remove_path(name)?,
+        }
+    }
+    index.write()?;
+
+    Ok(Some(oid))
+}
+
+/// Snapshots every change of the store with a git commit.
+pub struct GitSync {
+    root: PathBuf,
+}
+
+impl GitSync {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Commits `path` alone, returns false when nothing changed.
+    pub fn commit(&self, path: &Path, verb: &str) -> Result<bool> {
+        let repo = Repository::discover(&self.root)?;
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let message = format!("mystore: {} {}", verb, relative.to_string_lossy());
+        let author = repo.signature()?;
+
+        Ok(commit_paths(&repo, &[path], &message, &author)?.is_some())
+    }
+
+    /// Whether anything under the root differs from HEAD, untracked files
+    /// included.
+    pub fn is_dirty(&self) -> Result<bool> {
+        let repo = Repository::discover(&self.root)?;
+        let mut options = StatusOptions::new();
+        options.include_untracked(true);
+        let root = repo_path(&repo, &self.root)?;
+        if !root.is_empty() {
+            options.pathspec(&root);
+        }
+        let statuses = repo.statuses(Some(&mut options))?;
+
+        Ok(!statuses.is_empty())
+    }
+}
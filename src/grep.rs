@@ -0,0 +1,47 @@
+use crate::{
+    keyring::SessionKey,
+    loader::{FsLoader, Loader},
+    viewer::{Viewer, ViewerEntity},
+};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrepResult {
+    pub path: PathBuf,
+    /// Counts from one, like editors do.
+    pub line_number: usize,
+    pub line_text: String,
+}
+
+/// Finds the lines of `files` containing `query`, ignoring case. Encrypted
+/// files are searched once decrypted with `key`; binary and unreadable files
+/// are skipped.
+pub fn grep(files: &[PathBuf], query: &str, key: &SessionKey) -> Vec<GrepResult> {
+    let query = query.to_lowercase();
+    let mut results = Vec::new();
+    if query.is_empty() {
+        return results;
+    }
+    for path in files {
+        let entity = match FsLoader.load(path) {
+            Ok(entity) => entity,
+            Err(_err) => continue,
+        };
+        let decoded = Viewer::decode(entity, key);
+        let text = match decoded.get_entity_ref() {
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => text,
+            _ => continue,
+        };
+        for (id, line) in text.lines().enumerate() {
+            if line.to_lowercase().contains(&query) {
+                results.push(GrepResult {
+                    path: path.clone(),
+                    line_number: id + 1,
+                    line_text: String::from(line),
+                });
+            }
+        }
+    }
+
+    results
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// How many operations can be undone.
+pub const JOURNAL_SIZE: usize = 20;
+/// Larger deleted files are held in a temporary file rather than in memory.
+pub const HELD_IN_MEMORY: usize = 1 << 20;
+
+#[derive(Debug)]
+enum Held {
+    Memory(Vec<u8>),
+    /// An unnamed temporary file, removed by the system once dropped.
+    TempFile(File),
+}
+
+/// A deleted file, kept until the session ends or the journal drops it.
+#[derive(Debug)]
+pub struct HeldFile {
+    pub path: PathBuf,
+    /// The modification time of a local file, given back with the bytes.
+    pub modified: Option<SystemTime>,
+    /// Whether the file was created in the session, and so is again.
+    pub created: bool,
+    contents: Held,
+}
+
+impl HeldFile {
+    pub fn new(
+        path: PathBuf,
+        data: Vec<u8>,
+        modified: Option<SystemTime>,
+        created: bool,
+    ) -> io::Result<HeldFile> {
+        let contents = if data.len() > HELD_IN_MEMORY {
+            let mut file = tempfile::tempfile()?;
+            file.write_all(&data)?;
+            Held::TempFile(file)
+        } else {
+            Held::Memory(data)
+        };
+
+        Ok(HeldFile {
+            path,
+            modified,
+            created,
+            contents,
+        })
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        matches!(self.contents, Held::Memory(_))
+    }
+
+    pub fn read_contents(&mut self) -> io::Result<Vec<u8>> {
+        match &mut self.contents {
+            Held::Memory(data) => Ok(data.clone()),
+            Held::TempFile(file) => {
+                let mut data = Vec::new();
+                file.seek(SeekFrom::Start(0))?;
+                file.read_to_end(&mut data)?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// A change to the store that can be reversed.
+#[derive(Debug)]
+pub enum Operation {
+    /// Files deleted at once, in the order they were deleted.
+    Delete(Vec<HeldFile>),
+    /// Files renamed at once, as pairs of the old and the new path.
+    Rename(Vec<(PathBuf, PathBuf)>),
+}
+
+/// The last operations of the session, the latest last.
+#[derive(Debug, Default)]
+pub struct Journal {
+    operations: VecDeque<Operation>,
+}
+
+impl Journal {
+    /// Records `operation`, forgetting the oldest one once full. Operations
+    /// that changed nothing are left out.
+    pub fn push(&mut self, operation: Operation) {
+        let is_empty = match &operation {
+            Operation::Delete(files) => files.is_empty(),
+            Operation::Rename(renames) => renames.is_empty(),
+        };
+        if is_empty {
+            return;
+        }
+        self.operations.push_back(operation);
+        if self.operations.len() > JOURNAL_SIZE {
+            self.operations.pop_front();
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Operation> {
+        self.operations.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.operations.clear();
+    }
+}
@@ -0,0 +1,491 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const SECTION: &str = "keybindings";
+
+/// The modes keys can be bound in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppMode {
+    Manager,
+    Viewer,
+    Editor,
+}
+
+impl FromStr for AppMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "manager" => Ok(AppMode::Manager),
+            "viewer" => Ok(AppMode::Viewer),
+            "editor" => Ok(AppMode::Editor),
+            _ => Err(format!(
+                "unknown mode {:?}, expected manager, viewer or editor",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AppMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppMode::Manager => write!(f, "manager"),
+            AppMode::Viewer => write!(f, "viewer"),
+            AppMode::Editor => write!(f, "editor"),
+        }
+    }
+}
+
+const NONE: KeyModifiers = KeyModifiers::NONE;
+const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+
+/// What a bound key does, each action belongs to one mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ActionName {
+    Quit,
+    NextItem,
+    PrevItem,
+    NextPage,
+    PrevPage,
+    FirstItem,
+    LastItem,
+    OpenItem,
+    GoBack,
+    GoRoot,
+    GoTo,
+    SearchFiles,
+    OpenEditor,
+    NewBuffer,
+    DeleteItem,
+    MarkItem,
+    Undo,
+    FolderSize,
+    Checksum,
+    FindDuplicates,
+    CycleSort,
+    ToggleHidden,
+    NarrowManager,
+    WidenManager,
+    ToggleWrap,
+    RenameMarked,
+    FilterTags,
+    FilterExtension,
+    CopyPath,
+    LockItem,
+    ExportZip,
+    CloseViewer,
+    ScrollUp,
+    ScrollDown,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    ScrollTop,
+    ScrollBottom,
+    Search,
+    NextMatch,
+    PrevMatch,
+    Reload,
+    ViewInEditor,
+    CloseEditor,
+    Save,
+    SaveEncrypted,
+    SaveAndClose,
+    CheckSpelling,
+    InsertFile,
+    HardWrap,
+    InsertTime,
+}
+
+impl ActionName {
+    pub const ALL: [ActionName; 53] = [
+        ActionName::Quit,
+        ActionName::NextItem,
+        ActionName::PrevItem,
+        ActionName::NextPage,
+        ActionName::PrevPage,
+        ActionName::FirstItem,
+        ActionName::LastItem,
+        ActionName::OpenItem,
+        ActionName::GoBack,
+        ActionName::GoRoot,
+        ActionName::GoTo,
+        ActionName::SearchFiles,
+        ActionName::OpenEditor,
+        ActionName::NewBuffer,
+        ActionName::DeleteItem,
+        ActionName::MarkItem,
+        ActionName::Undo,
+        ActionName::FolderSize,
+        ActionName::Checksum,
+        ActionName::FindDuplicates,
+        ActionName::CycleSort,
+        ActionName::ToggleHidden,
+        ActionName::NarrowManager,
+        ActionName::WidenManager,
+        ActionName::ToggleWrap,
+        ActionName::RenameMarked,
+        ActionName::FilterTags,
+        ActionName::FilterExtension,
+        ActionName::CopyPath,
+        ActionName::LockItem,
+        ActionName::ExportZip,
+        ActionName::CloseViewer,
+        ActionName::ScrollUp,
+        ActionName::ScrollDown,
+        ActionName::PageDown,
+        ActionName::PageUp,
+        ActionName::HalfPageDown,
+        ActionName::HalfPageUp,
+        ActionName::ScrollTop,
+        ActionName::ScrollBottom,
+        ActionName::Search,
+        ActionName::NextMatch,
+        ActionName::PrevMatch,
+        ActionName::Reload,
+        ActionName::ViewInEditor,
+        ActionName::CloseEditor,
+        ActionName::Save,
+        ActionName::SaveEncrypted,
+        ActionName::SaveAndClose,
+        ActionName::CheckSpelling,
+        ActionName::InsertFile,
+        ActionName::HardWrap,
+        ActionName::InsertTime,
+    ];
+
+    /// The mode of the action, its name in the `[keybindings]` section and
+    /// the key it is handled on, which any key bound to it stands for.
+    fn spec(self) -> (AppMode, &'static str, KeyCode, KeyModifiers) {
+        match self {
+            ActionName::Quit => (AppMode::Manager, "quit", KeyCode::Esc, NONE),
+            ActionName::NextItem => (AppMode::Manager, "next_item", KeyCode::Down, NONE),
+            ActionName::PrevItem => (AppMode::Manager, "prev_item", KeyCode::Up, NONE),
+            ActionName::NextPage => (AppMode::Manager, "next_page", KeyCode::PageDown, NONE),
+            ActionName::PrevPage => (AppMode::Manager, "prev_page", KeyCode::PageUp, NONE),
+            ActionName::FirstItem => (AppMode::Manager, "first_item", KeyCode::Home, NONE),
+            ActionName::LastItem => (AppMode::Manager, "last_item", KeyCode::End, NONE),
+            ActionName::OpenItem => (AppMode::Manager, "open_item", KeyCode::Enter, NONE),
+            ActionName::GoBack => (AppMode::Manager, "go_back", KeyCode::Backspace, NONE),
+            ActionName::GoRoot => (AppMode::Manager, "go_root", KeyCode::Char('~'), NONE),
+            ActionName::GoTo => (AppMode::Manager, "go_to", KeyCode::Char('g'), NONE),
+            ActionName::SearchFiles => (AppMode::Manager, "search_files", KeyCode::Char('g'), CTRL),
+            ActionName::OpenEditor => (AppMode::Manager, "open_editor", KeyCode::Char('e'), NONE),
+            ActionName::NewBuffer => (AppMode::Manager, "new_buffer", KeyCode::Char('n'), NONE),
+            ActionName::DeleteItem => (AppMode::Manager, "delete_item", KeyCode::Char('d'), NONE),
+            ActionName::MarkItem => (AppMode::Manager, "mark_item", KeyCode::Char(' '), NONE),
+            ActionName::Undo => (AppMode::Manager, "undo", KeyCode::Char('u'), CTRL),
+            ActionName::FolderSize => (AppMode::Manager, "folder_size", KeyCode::Char('u'), NONE),
+            ActionName::Checksum => (AppMode::Manager, "checksum", KeyCode::Char('h'), NONE),
+            ActionName::FindDuplicates => (
+                AppMode::Manager,
+                "find_duplicates",
+                KeyCode::Char('f'),
+                CTRL,
+            ),
+            ActionName::CycleSort => (AppMode::Manager, "cycle_sort", KeyCode::Char('s'), NONE),
+            ActionName::ToggleHidden => {
+                (AppMode::Manager, "toggle_hidden", KeyCode::Char('.'), NONE)
+            }
+            ActionName::NarrowManager => {
+                (AppMode::Manager, "narrow_manager", KeyCode::Char('<'), NONE)
+            }
+            ActionName::WidenManager => {
+                (AppMode::Manager, "widen_manager", KeyCode::Char('>'), NONE)
+            }
+            ActionName::ToggleWrap => (AppMode::Manager, "toggle_wrap", KeyCode::Char('w'), NONE),
+            ActionName::RenameMarked => {
+                (AppMode::Manager, "rename_marked", KeyCode::Char('r'), CTRL)
+            }
+            ActionName::FilterTags => (AppMode::Manager, "filter_tags", KeyCode::Char('f'), NONE),
+            ActionName::FilterExtension => (
+                AppMode::Manager,
+                "filter_extension",
+                KeyCode::Char('x'),
+                NONE,
+            ),
+            ActionName::CopyPath => (AppMode::Manager, "copy_path", KeyCode::Char('y'), NONE),
+            ActionName::LockItem => (AppMode::Manager, "lock_item", KeyCode::Char('l'), NONE),
+            ActionName::ExportZip => (AppMode::Manager, "export_zip", KeyCode::Char('z'), NONE),
+            ActionName::CloseViewer => (AppMode::Viewer, "close_viewer", KeyCode::Esc, NONE),
+            ActionName::ScrollUp => (AppMode::Viewer, "scroll_up", KeyCode::Up, NONE),
+            ActionName::ScrollDown => (AppMode::Viewer, "scroll_down", KeyCode::Down, NONE),
+            ActionName::PageDown => (AppMode::Viewer, "page_down", KeyCode::Char(' '), NONE),
+            ActionName::PageUp => (AppMode::Viewer, "page_up", KeyCode::Char('b'), NONE),
+            ActionName::HalfPageDown => {
+                (AppMode::Viewer, "half_page_down", KeyCode::Char('d'), NONE)
+            }
+            ActionName::HalfPageUp => (AppMode::Viewer, "half_page_up", KeyCode::Char('u'), NONE),
+            ActionName::ScrollTop => (AppMode::Viewer, "scroll_top", KeyCode::Char('g'), NONE),
+            ActionName::ScrollBottom => {
+                (AppMode::Viewer, "scroll_bottom", KeyCode::Char('G'), SHIFT)
+            }
+            ActionName::Search => (AppMode::Viewer, "search", KeyCode::Char('/'), NONE),
+            ActionName::NextMatch => (AppMode::Viewer, "next_match", KeyCode::Char('n'), NONE),
+            ActionName::PrevMatch => (AppMode::Viewer, "prev_match", KeyCode::Char('N'), SHIFT),
+            ActionName::Reload => (AppMode::Viewer, "reload", KeyCode::Char('r'), NONE),
+            ActionName::ViewInEditor => {
+                (AppMode::Viewer, "view_in_editor", KeyCode::Char('v'), NONE)
+            }
+            ActionName::CloseEditor => (AppMode::Editor, "close_editor", KeyCode::Esc, NONE),
+            ActionName::Save => (AppMode::Editor, "save", KeyCode::Char('s'), CTRL),
+            ActionName::SaveEncrypted => {
+                (AppMode::Editor, "save_encrypted", KeyCode::Char('e'), CTRL)
+            }
+            ActionName::SaveAndClose => (
+                AppMode::Editor,
+                "save_and_close",
+                KeyCode::Char('s'),
+                CTRL.union(SHIFT),
+            ),
+            ActionName::CheckSpelling => {
+                (AppMode::Editor, "check_spelling", KeyCode::Char('k'), CTRL)
+            }
+            ActionName::InsertFile => (AppMode::Editor, "insert_file", KeyCode::Char('i'), CTRL),
+            ActionName::HardWrap => (AppMode::Editor, "hard_wrap", KeyCode::Char('w'), CTRL),
+            ActionName::InsertTime => (AppMode::Editor, "insert_time", KeyCode::Char('t'), CTRL),
+        }
+    }
+
+    pub fn mode(self) -> AppMode {
+        self.spec().0
+    }
+
+    /// The name in the `[keybindings]` section.
+    pub fn name(self) -> &'static str {
+        self.spec().1
+    }
+
+    /// The key the action is handled on.
+    pub fn default_key(self) -> (KeyCode, KeyModifiers) {
+        let (_mode, _name, code, modifiers) = self.spec();
+        (code, modifiers)
+    }
+}
+
+impl FromStr for ActionName {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ActionName::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == value)
+            .ok_or(format!("unknown action {:?}", value))
+    }
+}
+
+/// A key of a mode bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub mode: AppMode,
+    pub key: KeyCode,
+    pub modifiers: KeyModifiers,
+    pub action: ActionName,
+}
+
+/// Brings a key to the form bindings are looked up by: letters lowercase
+/// with Shift for capitals, and no Shift on other characters, which
+/// terminals report differently. Only Ctrl, Alt and Shift count.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    let modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+    match code {
+        KeyCode::Char(ch) if ch.is_uppercase() => (
+            KeyCode::Char(ch.to_lowercase().next().unwrap_or(ch)),
+            modifiers | KeyModifiers::SHIFT,
+        ),
+        KeyCode::Char(ch) if !ch.is_alphabetic() => (code, modifiers - KeyModifiers::SHIFT),
+        code => (code, modifiers),
+    }
+}
+
+/// Parses a key like `ctrl+shift+s`, `esc`, `pgdn`, `G` or `<`.
+pub fn parse_key(text: &str) -> Result<(KeyCode, KeyModifiers), String> {
+    let (prefix, name) = match text.strip_suffix("++") {
+        Some(prefix) => (Some(prefix), "+"),
+        None => match text.rsplit_once('+') {
+            Some((prefix, name)) if !name.is_empty() => (Some(prefix), name),
+            _ => (None, text),
+        },
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    for modifier in prefix.into_iter().flat_map(|prefix| prefix.split('+')) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier {:?} in {:?}", modifier, text)),
+        };
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match name.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            lower => match lower.strip_prefix('f').and_then(|id| id.parse().ok()) {
+                Some(id @ 1..=12) => KeyCode::F(id),
+                _ => return Err(format!("unknown key {:?}", name)),
+            },
+        },
+    };
+
+    Ok(normalize(code, modifiers))
+}
+
+/// The actions keys are bound to in each mode. Keys bound to nothing
+/// keep what they do by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: HashMap<(AppMode, KeyCode, KeyModifiers), ActionName>,
+}
+
+impl Default for KeyBindings {
+    /// Every action on the key it is handled on.
+    fn default() -> Self {
+        let bindings = ActionName::ALL
+            .iter()
+            .map(|action| {
+                let (code, modifiers) = action.default_key();
+                let (code, modifiers) = normalize(code, modifiers);
+                ((action.mode(), code, modifiers), *action)
+            })
+            .collect();
+
+        KeyBindings { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// `config.toml` in the mystore folder of the user's configuration.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("mystore").join("config.toml"))
+    }
+
+    pub fn bind(&mut self, binding: KeyBinding) {
+        let (code, modifiers) = normalize(binding.key, binding.modifiers);
+        self.bindings
+            .insert((binding.mode, code, modifiers), binding.action);
+    }
+
+    /// The action `key` is bound to in `mode`.
+    pub fn get(&self, mode: AppMode, key: &KeyEvent) -> Option<ActionName> {
+        let (code, modifiers) = normalize(key.code, key.modifiers);
+        self.bindings.get(&(mode, code, modifiers)).copied()
+    }
+
+    /// Reads the `"manager ctrl+d" = "delete_item"` entries of the
+    /// `[keybindings]` table of a TOML configuration on top of the defaults.
+    /// Entries that cannot be used are skipped and told about, other tables
+    /// are left alone. A configuration that is not valid TOML gives the
+    /// defaults.
+    pub fn parse(text: &str) -> (KeyBindings, Vec<String>) {
+        let mut bindings = KeyBindings::default();
+        let (entries, mut errors) = match Self::read_section(text) {
+            Ok(section) => section,
+            Err(err) => return (bindings, vec![err]),
+        };
+        let mut keys: Vec<&String> = entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            match Self::parse_binding(key, &entries[key]) {
+                Ok(binding) => bindings.bind(binding),
+                Err(err) => errors.push(format!("{:?}: {}", key, err)),
+            }
+        }
+
+        (bindings, errors)
+    }
+
+    /// The string entries of the `[keybindings]` table, along with what is
+    /// wrong with the other ones.
+    fn read_section(text: &str) -> Result<(HashMap<String, String>, Vec<String>), String> {
+        let config: toml::Table = text.parse().map_err(|err: toml::de::Error| {
+            let line = err
+                .span()
+                .map_or(0, |span| text[..span.start].matches('\n').count());
+            format!("line {}: {}", line + 1, err.message())
+        })?;
+        let Some(section) = config.get(SECTION) else {
+            return Ok((HashMap::new(), Vec::new()));
+        };
+        let section = section
+            .as_table()
+            .ok_or(format!("[{}] is not a table", SECTION))?;
+        let mut entries = HashMap::new();
+        let mut errors = Vec::new();
+        for (key, value) in section {
+            match value.as_str() {
+                Some(action) => {
+                    entries.insert(key.clone(), action.to_string());
+                }
+                None => errors.push(format!(
+                    "{:?}: expected a quoted action, not {}",
+                    key, value
+                )),
+            }
+        }
+
+        Ok((entries, errors))
+    }
+
+    fn parse_binding(key: &str, action: &str) -> Result<KeyBinding, String> {
+        let (mode, key) = key
+            .split_once(' ')
+            .ok_or(format!("expected a mode and a key in {:?}", key))?;
+        let mode: AppMode = mode.parse()?;
+        let (key, modifiers) = parse_key(key.trim())?;
+        let action: ActionName = action.parse()?;
+        if action.mode() != mode {
+            return Err(format!(
+                "{} is an action of the {}, not of the {}",
+                action.name(),
+                action.mode(),
+                mode
+            ));
+        }
+
+        Ok(KeyBinding {
+            mode,
+            key,
+            modifiers,
+            action,
+        })
+    }
+
+    /// Reads the bindings of the configuration at `path`, the defaults if
+    /// there is no such file. A file that cannot be read is told about
+    /// like a wrong entry.
+    pub fn load(path: &Path) -> (KeyBindings, Vec<String>) {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                (KeyBindings::default(), Vec::new())
+            }
+            Err(err) => (KeyBindings::default(), vec![err.to_string()]),
+        }
+    }
+}
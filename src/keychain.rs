@@ -0,0 +1,88 @@
+use crate::{error::Result, keyring::SessionKey};
+use std::{io, path::Path};
+
+/// Service the passwords of the stores are saved under in the keychain.
+pub const SERVICE: &str = "mystore";
+
+/// Where passwords are kept between sessions, by account.
+pub trait SecretStore {
+    fn get(&self, account: &str) -> io::Result<Option<String>>;
+    fn set(&self, account: &str, secret: &str) -> io::Result<()>;
+    /// Tells whether there was an entry to remove.
+    fn delete(&self, account: &str) -> io::Result<bool>;
+}
+
+/// The keychain of the platform: the Keychain on macOS, the Credential
+/// Manager on Windows and the kernel keyring on Linux.
+pub struct SystemKeychain;
+
+impl SystemKeychain {
+    fn entry(account: &str) -> io::Result<::keyring::Entry> {
+        ::keyring::Entry::new(SERVICE, account).map_err(Self::io_error)
+    }
+
+    fn io_error(err: ::keyring::Error) -> io::Error {
+        io::Error::other(err.to_string())
+    }
+}
+
+impl SecretStore for SystemKeychain {
+    fn get(&self, account: &str) -> io::Result<Option<String>> {
+        match Self::entry(account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(::keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(Self::io_error(err)),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &str) -> io::Result<()> {
+        Self::entry(account)?
+            .set_password(secret)
+            .map_err(Self::io_error)
+    }
+
+    fn delete(&self, account: &str) -> io::Result<bool> {
+        match Self::entry(account)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(::keyring::Error::NoEntry) => Ok(false),
+            Err(err) => Err(Self::io_error(err)),
+        }
+    }
+}
+
+/// The account of the store at `root`: its canonical path, so that every
+/// way of naming the folder finds the same entry.
+pub fn account_for(root: &Path) -> io::Result<String> {
+    let root = root.canonicalize()?;
+    root.to_str().map(String::from).ok_or(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Root folder path is not valid UTF-8",
+    ))
+}
+
+/// The saved password of the store at `root`, if it unlocks the store. A
+/// keychain out of reach, a stale entry or a store with nothing to check it
+/// against give none, to ask for it instead.
+pub fn load_password(store: &dyn SecretStore, root: &Path) -> Option<String> {
+    let account = account_for(root).ok()?;
+    let password = store.get(&account).ok()??;
+    SessionKey::verify(root, &password).ok()?;
+
+    Some(password)
+}
+
+/// Saves `password` for the store at `root` once it unlocks the store.
+/// The password itself is kept: files encrypted before the store had a
+/// keyring need it, not only the key derived from it.
+pub fn save_password(store: &dyn SecretStore, root: &Path, password: &str) -> Result<()> {
+    SessionKey::verify(root, password)?;
+    store.set(&account_for(root)?, password)?;
+
+    Ok(())
+}
+
+/// Removes the saved password of the store at `root`, tells whether there
+/// was one.
+pub fn clear_password(store: &dyn SecretStore, root: &Path) -> Result<bool> {
+    Ok(store.delete(&account_for(root)?)?)
+}
@@ -0,0 +1,352 @@
+use crate::{
+    batch::write_atomically,
+    editor::Editor,
+    error::{MystoreError, Result},
+    tags::INDEX_DIR,
+    viewer::Viewer,
+};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const KEYRING_FILE: &str = "keyring";
+const KEYRING_HEADER: &[u8] = b"mystore keyring v2\n";
+/// Starts every file encrypted with a data key of its own.
+pub(crate) const ENVELOPE_HEADER: &[u8] = b"mystore envelope v2\n";
+/// Bytes of the master key and of every data key.
+pub const KEY_BYTES: usize = 32;
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+/// A wrapped key is its nonce, the encrypted key and the tag.
+const WRAPPED_KEY_LEN: usize = NONCE_BYTES + KEY_BYTES + TAG_BYTES;
+const FINGERPRINT_LEN: usize = 8;
+/// Keeps the fingerprint and the ZIP password apart from any other hash.
+const FINGERPRINT_CONTEXT: &[u8] = b"mystore fingerprint\n";
+const EXPORT_CONTEXT: &[u8] = b"mystore zip export\n";
+/// Passwords shorter than this are refused.
+pub const MIN_PASSWORD_LEN: usize = 5;
+/// Most files `SessionKey::verify` reads looking for one encrypted with the
+/// password, and the largest it reads.
+const MAX_PROBED_FILES: usize = 256;
+const MAX_PROBED_LEN: u64 = 1 << 20;
+
+pub type RawKey = [u8; KEY_BYTES];
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn random_bytes<const N: usize>() -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    getrandom::getrandom(&mut buf).map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(buf)
+}
+
+/// The key the master key is wrapped with, by Argon2id over the password
+/// and the salt stored in the keyring.
+fn derive_key(password: &str, salt: &[u8]) -> io::Result<RawKey> {
+    let mut key = [0; KEY_BYTES];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(key)
+}
+
+/// Encrypts and authenticates `plaintext` with `key` under a fresh nonce,
+/// which leads the result.
+fn seal_with(key: &RawKey, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    let nonce = random_bytes::<NONCE_BYTES>()?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+/// The plaintext of `sealed`, if it was sealed with `key` and not altered.
+fn open_with(key: &RawKey, sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_BYTES + TAG_BYTES {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_BYTES);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .ok()
+}
+
+/// Encrypts `key` with `wrapping_key`.
+pub fn wrap_key(key: &RawKey, wrapping_key: &RawKey) -> io::Result<Vec<u8>> {
+    seal_with(wrapping_key, key)
+}
+
+/// The key in `wrapped`, if `wrapping_key` is the one it was wrapped with.
+pub fn unwrap_key(wrapped: &[u8], wrapping_key: &RawKey) -> Option<RawKey> {
+    open_with(wrapping_key, wrapped)?.try_into().ok()
+}
+
+/// The first hex digits of the SHA-256 of `master` after
+/// `mystore fingerprint` and a newline.
+pub fn fingerprint_of(master: &RawKey) -> String {
+    let digest = Sha256::digest([FINGERPRINT_CONTEXT, master].concat());
+    to_hex(&digest)[..FINGERPRINT_LEN].to_string()
+}
+
+/// Whether `bin` was encrypted with a data key wrapped in its header.
+pub fn is_sealed(bin: &[u8]) -> bool {
+    bin.starts_with(ENVELOPE_HEADER)
+}
+
+/// The master key of a store, kept in `.mystore/keyring` wrapped with the
+/// password.
+#[derive(Clone)]
+struct Keyring {
+    path: PathBuf,
+    master: RawKey,
+}
+
+impl Keyring {
+    /// The header, a fresh salt and the master key wrapped with the key
+    /// derived from `password` and the salt.
+    fn contents(&self, password: &str) -> io::Result<Vec<u8>> {
+        let salt = random_bytes::<SALT_BYTES>()?;
+        let wrapped = wrap_key(&self.master, &derive_key(password, &salt)?)?;
+        Ok([KEYRING_HEADER, &salt, &wrapped].concat())
+    }
+
+    fn unwrap_master(data: &[u8], password: &str) -> Result<RawKey> {
+        let rest = data
+            .strip_prefix(KEYRING_HEADER)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The keyring is damaged"))?;
+        if rest.len() < SALT_BYTES {
+            return Err(MystoreError::KeyringLocked);
+        }
+        let (salt, wrapped) = rest.split_at(SALT_BYTES);
+        unwrap_key(wrapped, &derive_key(password, salt)?).ok_or(MystoreError::KeyringLocked)
+    }
+
+    /// Writes the keyring unless it is there already. One written by another
+    /// session meanwhile is only fine if it holds the same master key.
+    fn store(&self, password: &str) -> Result<()> {
+        if self.path.exists() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.path)
+        {
+            Ok(mut file) => {
+                file.write_all(&self.contents(password)?)?;
+                file.sync_all()?;
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let master = Self::unwrap_master(&fs::read(&self.path)?, password)?;
+                if master != self.master {
+                    return Err(
+                        io::Error::other("The keyring was created by another session").into(),
+                    );
+                }
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Encrypts `text` with a fresh data key, wrapped with the master key
+    /// in the header.
+    fn seal(&self, text: &str) -> Result<Vec<u8>> {
+        let data_key = random_bytes::<KEY_BYTES>()?;
+        let wrapped = wrap_key(&data_key, &self.master)?;
+        let body = seal_with(&data_key, text.as_bytes())?;
+        Ok([ENVELOPE_HEADER, &wrapped, &body].concat())
+    }
+
+    fn open(&self, bin: &[u8]) -> Result<String> {
+        let rest = bin
+            .strip_prefix(ENVELOPE_HEADER)
+            .ok_or(MystoreError::DecryptFailed)?;
+        if rest.len() < WRAPPED_KEY_LEN {
+            return Err(MystoreError::DecryptFailed);
+        }
+        let (wrapped, body) = rest.split_at(WRAPPED_KEY_LEN);
+        let data_key = unwrap_key(wrapped, &self.master).ok_or(MystoreError::DecryptFailed)?;
+        let text = open_with(&data_key, body).ok_or(MystoreError::DecryptFailed)?;
+        String::from_utf8(text).map_err(|_err| MystoreError::DecryptFailed)
+    }
+}
+
+/// The key of a session: the password, and the master key of the store if
+/// it has a keyring. Files are encrypted with a data key of their own when
+/// there is a master key, and with the password directly otherwise. Both
+/// kinds are decrypted.
+#[derive(Clone)]
+pub struct SessionKey {
+    password: String,
+    keyring: Option<Keyring>,
+}
+
+impl From<&str> for SessionKey {
+    /// A key without a keyring, which encrypts with the password directly.
+    fn from(password: &str) -> Self {
+        SessionKey {
+            password: password.to_string(),
+            keyring: None,
+        }
+    }
+}
+
+impl SessionKey {
+    /// Unlocks the keyring of the store at `root` with `password`. A store
+    /// without one gets a new master key, saved with the first encryption.
+    pub fn open(root: &Path, password: &str) -> Result<SessionKey> {
+        if password.len() < MIN_PASSWORD_LEN {
+            return Err(MystoreError::WrongKey(MIN_PASSWORD_LEN));
+        }
+        let path = root.join(INDEX_DIR).join(KEYRING_FILE);
+        let master = match fs::read(&path) {
+            Ok(data) => Keyring::unwrap_master(&data, password)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => random_bytes::<KEY_BYTES>()?,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(SessionKey {
+            password: password.to_string(),
+            keyring: Some(Keyring { path, master }),
+        })
+    }
+
+    /// Checks that `password` unlocks the store at `root`: its keyring
+    /// unwraps with it or, when it has none, one of its files encrypted with
+    /// the password directly decrypts to text. Unlike `open`, a store with
+    /// nothing to check the password against refuses it.
+    pub fn verify(root: &Path, password: &str) -> Result<()> {
+        if password.len() < MIN_PASSWORD_LEN {
+            return Err(MystoreError::WrongKey(MIN_PASSWORD_LEN));
+        }
+        match fs::read(root.join(INDEX_DIR).join(KEYRING_FILE)) {
+            Ok(data) => return Keyring::unwrap_master(&data, password).map(|_master| ()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut dirs = vec![root.to_path_buf()];
+        let mut probed = 0;
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    if entry.file_name() != INDEX_DIR {
+                        dirs.push(entry.path());
+                    }
+                    continue;
+                }
+                let is_small = entry
+                    .metadata()
+                    .is_ok_and(|meta| meta.len() <= MAX_PROBED_LEN);
+                if !file_type.is_file() || !is_small || probed == MAX_PROBED_FILES {
+                    continue;
+                }
+                probed += 1;
+                // Notes in plain text are valid UTF-8, the encrypted ones
+                // almost never are.
+                let decrypts = fs::read(entry.path()).is_ok_and(|bin| {
+                    !is_sealed(&bin)
+                        && std::str::from_utf8(&bin).is_err()
+                        && Viewer::decrypt_binary(&bin, password)
+                            .is_ok_and(|text| Viewer::looks_like_text(&text))
+                });
+                if decrypts {
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Nothing in the store to check the password against",
+        )
+        .into())
+    }
+
+    pub fn get_password(&self) -> &str {
+        &self.password
+    }
+
+    /// A short name of the master key, to tell stores apart at a glance.
+    /// It is a hash of the random key, so it tells nothing of the password.
+    /// A session without a keyring has none.
+    pub fn fingerprint(&self) -> Option<String> {
+        Some(fingerprint_of(&self.keyring.as_ref()?.master))
+    }
+
+    /// The password of exported ZIP archives, the SHA-256 of the password
+    /// after `mystore zip export` and a newline in lowercase hex, so that it
+    /// can be shared without the session password or the keyring:
+    /// `printf 'mystore zip export\n%s' KEY | sha256sum`.
+    pub fn export_password(&self) -> String {
+        to_hex(&Sha256::digest(
+            [EXPORT_CONTEXT, self.password.as_bytes()].concat(),
+        ))
+    }
+
+    pub fn has_keyring(&self) -> bool {
+        self.keyring.is_some()
+    }
+
+    pub fn encrypt(&self, text: &str) -> Result<Vec<u8>> {
+        match &self.keyring {
+            Some(keyring) => {
+                keyring.store(&self.password)?;
+                keyring.seal(text)
+            }
+            None => Ok(Editor::encrypt_string(text, &self.password)),
+        }
+    }
+
+    pub fn decrypt(&self, bin: &[u8]) -> Result<String> {
+        match &self.keyring {
+            Some(keyring) if is_sealed(bin) => keyring.open(bin),
+            None if is_sealed(bin) => Err(MystoreError::DecryptFailed),
+            _ => Viewer::decrypt_binary(bin, &self.password),
+        }
+    }
+
+    /// Wraps the master key with `password` instead, no file is encrypted
+    /// again. Files encrypted with the old password directly still need it.
+    pub fn change_password(&mut self, password: &str) -> Result<()> {
+        if password.len() < MIN_PASSWORD_LEN {
+            return Err(MystoreError::WrongKey(MIN_PASSWORD_LEN));
+        }
+        let Some(keyring) = &self.keyring else {
+            return Err(
+                io::Error::new(io::ErrorKind::Unsupported, "The store has no keyring").into(),
+            );
+        };
+        if keyring.path.exists() {
+            write_atomically(&keyring.path, &keyring.contents(password)?)?;
+        } else {
+            keyring.store(password)?;
+        }
+        self.password = password.to_string();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+pub mod app;
+pub mod batch;
+pub mod bookmarks;
+pub mod checksum;
+pub mod clipboard;
+pub mod duplicates;
+pub mod editor;
+pub mod error;
+pub mod git;
+pub mod grep;
+pub mod journal;
+pub mod keybindings;
+pub mod keychain;
+pub mod keyring;
+pub mod loader;
+pub mod locks;
+pub mod manager;
+pub mod recovery;
+pub mod s3;
+pub mod session;
+pub mod sizes;
+pub mod stats;
+pub mod strength;
+pub mod tags;
+pub mod times;
+pub mod timings;
+pub mod tree_hash;
+pub mod ui;
+pub mod urls;
+pub mod viewer;
+pub mod whitespace;
+pub mod wrap;
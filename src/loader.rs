@@ -0,0 +1,109 @@
+use crate::{
+    error::Result,
+    keyring::SessionKey,
+    viewer::{Decoded, Viewer, ViewerEntity},
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+pub trait Loader: Send + Sync {
+    fn load(&self, path: &Path) -> Result<ViewerEntity>;
+}
+
+/// Text for valid UTF-8, binary, possibly encrypted, otherwise.
+pub fn entity_from_bytes(data: Vec<u8>) -> ViewerEntity {
+    match String::from_utf8(data) {
+        Ok(text) => ViewerEntity::Text(text),
+        Err(err) => ViewerEntity::Binary(err.into_bytes()),
+    }
+}
+
+pub struct FsLoader;
+
+impl Loader for FsLoader {
+    fn load(&self, path: &Path) -> Result<ViewerEntity> {
+        Ok(entity_from_bytes(std::fs::read(path)?))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadToken(u64);
+
+pub struct Loaded {
+    pub token: LoadToken,
+    pub name: Option<String>,
+    pub result: Result<Decoded>,
+}
+
+pub struct BackgroundLoader {
+    loader: Arc<dyn Loader>,
+    key: SessionKey,
+    sender: Sender<Loaded>,
+    receiver: Receiver<Loaded>,
+    next_token: u64,
+    pending: Option<LoadToken>,
+}
+
+impl BackgroundLoader {
+    pub fn new(loader: Arc<dyn Loader>, key: &SessionKey) -> BackgroundLoader {
+        let (sender, receiver) = mpsc::channel();
+        BackgroundLoader {
+            loader,
+            key: key.clone(),
+            sender,
+            receiver,
+            next_token: 0,
+            pending: None,
+        }
+    }
+
+    /// Reads and decrypts the file on a worker thread, superseding any pending load.
+    pub fn start(&mut self, path: PathBuf, name: Option<String>) -> LoadToken {
+        let token = LoadToken(self.next_token);
+        self.next_token += 1;
+        self.pending = Some(token);
+
+        let loader = Arc::clone(&self.loader);
+        let key = self.key.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = loader
+                .load(&path)
+                .map(|entity| Viewer::decode(entity, &key));
+            // The receiver only goes away with the session itself.
+            let _ = sender.send(Loaded {
+                token,
+                name,
+                result,
+            });
+        });
+
+        token
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Returns the result of the pending load once it is ready, dropping stale results.
+    pub fn poll(&mut self) -> Option<Loaded> {
+        while let Ok(loaded) = self.receiver.try_recv() {
+            if self.pending == Some(loaded.token) {
+                self.pending = None;
+                return Some(loaded);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,199 @@
+use crate::manager::{FileManager, LockCleanup};
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+/// The session holding a lock, written into the lock file as `pid@host`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub host: String,
+}
+
+impl fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.pid, self.host)
+    }
+}
+
+impl LockOwner {
+    /// This session.
+    pub fn current() -> LockOwner {
+        LockOwner {
+            pid: std::process::id(),
+            host: hostname(),
+        }
+    }
+
+    /// Reads the owner from the contents of a lock file. Empty locks, as
+    /// written by older versions, have none.
+    pub fn parse(data: &[u8]) -> Option<LockOwner> {
+        let text = std::str::from_utf8(data).ok()?.trim();
+        let (pid, host) = text.split_once('@')?;
+
+        Some(LockOwner {
+            pid: pid.parse().ok()?,
+            host: String::from(host),
+        })
+    }
+
+    /// Whether the owner still runs, `None` if that cannot be told, e.g.
+    /// for a session on another machine.
+    pub fn is_alive(&self) -> Option<bool> {
+        if self.host != hostname() {
+            return None;
+        }
+        is_running(self.pid)
+    }
+}
+
+/// Whether a lock with the contents `data` is left over from a session
+/// that is gone. Locks without an owner are never stale by this measure.
+pub fn is_stale(data: &[u8]) -> bool {
+    LockOwner::parse(data).is_some_and(|owner| owner.is_alive() == Some(false))
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut name = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length, which is passed on.
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return String::new();
+    }
+    let len = name
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> Option<bool> {
+    let pid = libc::pid_t::try_from(pid).ok().filter(|pid| *pid > 0)?;
+    // SAFETY: signal 0 is not sent, only the process is looked up.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return Some(true);
+    }
+    // A process of another user cannot be signalled but does exist.
+    Some(io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH))
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> Option<bool> {
+    None
+}
+
+/// Removes the stale locks under the local folders `roots`, see `is_stale`.
+/// Hidden folders like `.git` are not entered. Unreadable folders and locks
+/// that could not be removed are counted. Gives up once `cancelled` is set.
+pub fn sweep_locks(roots: &[PathBuf], cancelled: &AtomicBool) -> LockCleanup {
+    let mut cleanup = LockCleanup::default();
+    let mut dirs = roots.to_vec();
+    while let Some(dir) = dirs.pop() {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            cleanup.unreadable += 1;
+            continue;
+        };
+        let mut files = HashSet::new();
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() && !is_hidden(&entry.path()) => {
+                    dirs.push(entry.path());
+                }
+                Ok(file_type) if file_type.is_file() => {
+                    files.insert(entry.path());
+                }
+                _ => {}
+            }
+        }
+        for target in &files {
+            let lock = FileManager::lock_path(target);
+            if !files.contains(&lock) || !fs::read(&lock).is_ok_and(|data| is_stale(&data)) {
+                continue;
+            }
+            match fs::remove_file(&lock) {
+                Ok(()) => cleanup.removed += 1,
+                Err(_err) => cleanup.failed += 1,
+            }
+        }
+    }
+
+    cleanup
+}
+
+/// Sweeps stale locks on a worker thread, so that the first frame does
+/// not wait for a walk of the whole tree.
+pub struct BackgroundLockSweep {
+    receiver: Option<Receiver<LockCleanup>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Default for BackgroundLockSweep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundLockSweep {
+    pub fn new() -> BackgroundLockSweep {
+        BackgroundLockSweep {
+            receiver: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts sweeping under `roots`, cancelling the sweep running so far.
+    pub fn start(&mut self, roots: Vec<PathBuf>) {
+        self.cancel();
+
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.receiver = Some(receiver);
+        self.cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            let cleanup = sweep_locks(&roots, &cancelled);
+            // The receiver is dropped if the sweep was cancelled.
+            let _ = sender.send(cleanup);
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.receiver = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Returns what was removed once the sweep is over.
+    pub fn poll(&mut self) -> Option<LockCleanup> {
+        let cleanup = self.receiver.as_ref()?.try_recv().ok()?;
+        self.receiver = None;
+
+        Some(cleanup)
+    }
+}
+
+/// Whether the name of `path` starts with a dot, such folders are not
+/// swept for locks.
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
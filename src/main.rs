@@ -1,817 +1,517 @@
-use chrono::Utc;
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use crossterm::{
-    event::{read, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::{
-    cmp::Reverse,
-    fmt,
-    fs::File,
-    io::{self, Write},
-    path::Path,
-    path::PathBuf,
-    time::SystemTime,
+use mystore::{
+    app::{App, AppEvent},
+    bookmarks::{Bookmarks, ScrollPositions},
+    editor::{parse_time_format, DEFAULT_DATE_FORMAT, DEFAULT_TAB_WIDTH, DEFAULT_TIMESTAMP_FORMAT},
+    error::Result,
+    keybindings::KeyBindings,
+    keychain::{self, SystemKeychain},
+    keyring::SessionKey,
+    loader,
+    manager::{ActionPlacement, SshConfig},
+    recovery,
+    s3::S3Config,
+    session::SessionState,
+    stats::{self, TOP_FILES},
+    strength,
+    times::{parse_name_format, TimeFormats, DEFAULT_DISPLAY_FORMAT, DEFAULT_NAME_FORMAT},
+    ui,
+    viewer::Viewer,
 };
-use tui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
-    text::Text,
-    widgets::{self, Block, Borders, List, ListItem, ListState, Paragraph},
-    Frame, Terminal,
-};
-use tui_textarea::TextArea;
-
-#[derive(Clone, PartialEq)]
-pub enum Action {
-    Back,
-    Root,
-}
-
-#[derive(Clone, PartialEq)]
-pub enum ManagerEntity {
-    TextFile(PathBuf),
-    Folder(PathBuf),
-    Action(Action),
-}
-
-#[derive(Clone, PartialEq)]
-pub enum Respond {
-    Text(String),
-    Bin(Vec<u8>),
-    None,
-}
+use std::{fs, io, path::Path, process, time::Duration};
+use tui::{backend::CrosstermBackend, Terminal};
 
-pub struct FileManager {
-    root: PathBuf,
-    current: PathBuf,
-    entities: Vec<ManagerEntity>,
-    selected: Option<usize>,
-    created_entities: Vec<ManagerEntity>,
-}
-
-impl FileManager {
-    fn open_dir<T: AsRef<Path>>(dir: &T) -> Result<Vec<PathBuf>, io::Error> {
-        let items = std::fs::read_dir(dir)?;
-        let file_names: Vec<PathBuf> = items
-            .filter_map(|entry| {
-                let path = entry.ok()?.path();
-                Some(path)
-            })
-            .collect();
-
-        Ok(file_names)
-    }
-
-    fn create_entities(files: Vec<PathBuf>, is_root: bool) -> Vec<ManagerEntity> {
-        let mut folder_entities: Vec<ManagerEntity> = files
-            .iter()
-            .filter_map(|path| {
-                if path.is_dir() {
-                    Some(ManagerEntity::Folder(path.clone()))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        folder_entities.sort_by_cached_key(|entity| match entity {
-            ManagerEntity::TextFile(path) => Some(path.as_path().to_owned()),
-            ManagerEntity::Folder(path) => Some(path.as_path().to_owned()),
-            ManagerEntity::Action(_act) => None,
-        });
-
-        let mut file_entities: Vec<ManagerEntity> = files
-            .iter()
-            .filter_map(|path| {
-                if path.is_file() {
-                    Some(ManagerEntity::TextFile(path.clone()))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        file_entities.sort_by_cached_key(|entity| match entity {
-            ManagerEntity::TextFile(path) => Reverse(path.metadata().map_or(None, |meta| {
-                Some(meta.modified().map_or(SystemTime::UNIX_EPOCH, |st| st))
-            })),
-            ManagerEntity::Folder(path) => Reverse(path.metadata().map_or(None, |meta| {
-                Some(meta.modified().map_or(SystemTime::UNIX_EPOCH, |st| st))
-            })),
-            ManagerEntity::Action(_act) => Reverse(None),
-        });
-
-        let mut entities = folder_entities;
-        entities.extend(file_entities);
-
-        if !is_root {
-            entities.push(ManagerEntity::Action(Action::Back));
-            entities.push(ManagerEntity::Action(Action::Root));
+fn run_session(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    args: &Args,
+    key: &str,
+) -> Result<()> {
+    let roots: Vec<&str> = args.root.iter().map(String::as_str).collect();
+    // The scratch folder is removed when the session ends.
+    let s3 = args.s3_bucket.as_ref().map(|bucket| S3Config {
+        bucket: bucket.clone(),
+        prefix: args.s3_prefix.clone(),
+        region: args.s3_region.clone(),
+    });
+    let (mut app, _scratch) = if let Some(config) = args.ssh.clone() {
+        (App::from_ssh(config, &args.ssh_key, key)?, None)
+    } else if let Some(config) = s3 {
+        (App::from_s3(config, key)?, None)
+    } else if let Some(archive) = &args.zip {
+        (App::from_zip(archive, key)?, None)
+    } else if let Some(archive) = &args.tar {
+        (App::from_tar(archive, key)?, None)
+    } else if let Some(repo) = &args.git_repo {
+        (App::from_git_revision(repo, &args.revision, key)?, None)
+    } else if let Some(pattern) = &args.glob {
+        (App::new_from_glob(pattern, key)?, None)
+    } else if let Some(history) = &args.history {
+        (App::from_history(Path::new(history), key)?, None)
+    } else {
+        match roots.as_slice() {
+            [] => {
+                let (app, dir) = App::new_scratch(key)?;
+                (app, Some(dir))
+            }
+            [root] if args.incremental => (App::new_incremental(root, key)?, None),
+            [root] => (App::new(root, key)?, None),
+            roots => (App::new_multi_root(roots, key)?, None),
         }
-
-        entities
-    }
-
-    fn goto_dir(&mut self, dir: PathBuf) -> Result<(), io::Error> {
-        let is_root = dir == self.root;
-        let files = Self::open_dir(&dir)?;
-        self.entities = Self::create_entities(files, is_root);
-        self.selected = None;
-        self.current = dir;
-
-        Ok(())
-    }
-}
-
-impl FileManager {
-    pub fn new(root: &str) -> Result<Self, io::Error> {
-        let files = Self::open_dir(&root)?;
-
-        Ok(Self {
-            current: PathBuf::from(root),
-            root: PathBuf::from(root),
-            entities: Self::create_entities(files, true),
-            selected: Option::default(),
-            created_entities: Vec::new(),
-        })
-    }
-
-    pub fn get_root(&self) -> PathBuf {
-        self.root.clone()
-    }
-
-    pub fn get_current(&self) -> PathBuf {
-        self.current.clone()
-    }
-
-    pub fn get_entities_ref(&self) -> &Vec<ManagerEntity> {
-        &self.entities
-    }
-
-    pub fn get_selected_id(&self) -> Option<usize> {
-        self.selected.clone()
-    }
-
-    pub fn get_selected_entity(&self) -> Option<ManagerEntity> {
-        self.selected.map(|id| self.entities[id].clone())
-    }
-
-    pub fn get_selected_entity_name(&self) -> Option<String> {
-        self.selected.map_or(None, |id| match &self.entities[id] {
-            ManagerEntity::TextFile(path) => path.file_name().map_or(None, |name| {
-                name.to_owned().into_string().map_or(None, |str| Some(str))
-            }),
-            ManagerEntity::Folder(path) => path.file_name().map_or(None, |name| {
-                name.to_owned().into_string().map_or(None, |str| Some(str))
-            }),
-            ManagerEntity::Action(_act) => None,
-        })
-    }
-
-    pub fn next(&mut self) {
-        if !self.entities.is_empty() {
-            self.selected = match self.selected {
-                Some(value) => Some((value + 1) % self.entities.len()),
-                None => Some(0),
-            };
+    };
+    // An archive or revision has no lock files and cannot be changed
+    // anyway, a remote home folder or bucket is too large to walk at every
+    // start.
+    let is_readonly = args.zip.is_some() || args.tar.is_some() || args.git_repo.is_some();
+    if !is_readonly && args.ssh.is_none() && args.s3_bucket.is_none() {
+        // The root of a glob or history listing may be far above the files,
+        // e.g. `/`, so only plain roots are walked for stale locks.
+        if args.glob.is_none() && args.history.is_none() {
+            app.cleanup_locks();
         }
-    }
-
-    pub fn previous(&mut self) {
-        if !self.entities.is_empty() {
-            self.selected = match self.selected {
-                Some(0) => Some(self.entities.len() - 1),
-                Some(value) => Some(value - 1),
-                None => Some(self.entities.len() - 1),
-            };
+        // The editor text is saved to the root on a crash, and offered back
+        // at the next start.
+        let root = app.get_manager_ref().get_root().to_path_buf();
+        app.set_recovery_files(recovery::find_recovery_files(&root)?);
+        recovery::install_hook(root, app.get_key_ref().clone());
+    }
+    // Only a single local root comes back where it was left.
+    let session_root = match roots.as_slice() {
+        [root]
+            if !is_readonly
+                && args.ssh.is_none()
+                && args.s3_bucket.is_none()
+                && args.glob.is_none()
+                && args.history.is_none() =>
+        {
+            Some(Path::new(root).to_path_buf())
         }
-    }
-
-    pub fn select(&mut self, id: usize) -> bool {
-        if id < self.entities.len() {
-            self.selected = Some(id);
-            true
-        } else {
-            false
+        _ => None,
+    };
+    if let Some(path) = Bookmarks::default_path() {
+        app.set_bookmarks(Bookmarks::load(&path));
+    }
+    if let Some(path) = ScrollPositions::default_path() {
+        app.get_viewer_mut()
+            .set_scroll_positions(ScrollPositions::load(&path));
+    }
+    app.get_manager_mut().set_action_placement(args.actions)?;
+    app.get_manager_mut().set_wrap_navigation(!args.no_wrap);
+    app.get_manager_mut().set_parallel_read(!args.no_parallel);
+    app.get_manager_mut()
+        .set_time_formats(TimeFormats::new(&args.time_format, &args.name_format));
+    if let Some(path) = KeyBindings::default_path() {
+        // The terminal is already taken by the session, so problems with
+        // the configuration are shown on the message line.
+        let (keybindings, errors) = KeyBindings::load(&path);
+        if let Some(err) = errors.first() {
+            app.set_message(match errors.len() {
+                1 => format!("{}: {}", path.display(), err),
+                count => format!("{}: {} (and {} more)", path.display(), err, count - 1),
+            });
         }
+        app.set_keybindings(keybindings);
     }
-
-    pub fn refresh(&mut self) -> Result<(), io::Error> {
-        let selected = self.selected;
-        Self::goto_dir(self, self.current.clone())?;
-        selected.map(|id| Self::select(self, id));
-
-        Ok(())
-    }
-
-    pub fn create_file(
-        &mut self,
-        data: Vec<u8>,
-        file_name: Option<String>,
-    ) -> Result<(), io::Error> {
-        let file_name = file_name.map_or(Utc::now().to_rfc3339(), |name| name);
-        let file_path = self.current.join(file_name);
-        let mut file = File::create(file_path.clone())?;
-        file.write_all(&data)?;
-
-        self.created_entities
-            .push(ManagerEntity::TextFile(file_path));
-        self.refresh()?;
-
-        Ok(())
-    }
-
-    pub fn delete_selected(&mut self) -> Result<(), io::Error> {
-        self.selected
-            .map_or(Ok(()), |id| match &self.entities[id] {
-                ManagerEntity::TextFile(path) => self
-                    .created_entities
-                    .iter()
-                    .position(|elem| *elem == ManagerEntity::TextFile(path.clone()))
-                    .map_or(
-                        Err(io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            "Cannot delete the entity not created in the current session",
-                        )),
-                        |item| {
-                            std::fs::remove_file(path.clone())?;
-                            self.created_entities.remove(item);
-                            Ok(())
-                        },
-                    ),
-                ManagerEntity::Folder(_path) => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Cannot delete the folder entity",
-                )),
-                ManagerEntity::Action(_act) => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Cannot delete the action entity",
-                )),
-            })?;
-
-        self.refresh()?;
-
-        Ok(())
-    }
-
-    pub fn action(&mut self) -> Result<Respond, io::Error> {
-        self.selected
-            .map_or(Ok(Respond::None), |id| match &self.entities[id] {
-                ManagerEntity::TextFile(path) => {
-                    let text = std::fs::read_to_string(path);
-                    match text {
-                        Ok(text) => Ok(Respond::Text(text)),
-                        Err(_err) => Ok(Respond::Bin(std::fs::read(path)?)),
-                    }
-                }
-                ManagerEntity::Folder(path) => {
-                    Self::goto_dir(self, path.clone())?;
-                    Ok(Respond::None)
-                }
-                ManagerEntity::Action(act) => {
-                    match act {
-                        Action::Back => {
-                            let parent_path = self.current.parent().map(|path| PathBuf::from(path));
-                            match parent_path {
-                                Some(path) => Self::goto_dir(self, path)?,
-                                None => (),
-                            }
-                        }
-                        Action::Root => Self::goto_dir(self, self.root.clone())?,
-                    }
-                    Ok(Respond::None)
-                }
-            })
-    }
-}
-
-#[derive(Clone, PartialEq)]
-pub enum ViewerEntity {
-    Text(String),
-    DecryptedText(String),
-    Binary(Vec<u8>),
-}
-
-pub struct Viewer {
-    name: Option<String>,
-    entity: ViewerEntity,
-    scroll: u16,
-    key: String,
-}
-
-impl Viewer {
-    fn crypt_rm(c: i32, count: usize, key: &str) -> i32 {
-        let crypt: Vec<_> = key.bytes().collect();
-        if c < crypt[count] as i32 {
-            c - crypt[count] as i32 + 256
-        } else {
-            c - crypt[count] as i32
-        }
+    if args.git {
+        app.enable_git();
     }
-
-    fn decrypt_binary(bin: &Vec<u8>, key: &str) -> Result<String, std::string::FromUtf8Error> {
-        let mut text: Vec<u8> = Vec::new();
-        let mut count: usize = 0;
-        for byte in bin {
-            let ch = Self::crypt_rm(*byte as i32, count, key);
-            text.push(ch as u8);
-            count = (count + 1) % 5;
-        }
-
-        String::from_utf8(text)
+    app.set_reauth_interval(
+        args.prompt_key_every_n_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+    );
+    app.set_wheel_lines(args.wheel_lines);
+    app.get_editor_mut().set_tab_width(args.tab_width);
+    app.get_editor_mut().set_insert_spaces(args.expand_tabs);
+    app.get_editor_mut()
+        .set_time_formats(&args.timestamp_format, &args.date_format);
+    if let Some(state) = session_root.as_deref().and_then(SessionState::load) {
+        app.restore_session(&state)?;
     }
-}
 
-impl Viewer {
-    pub fn new(key: &str) -> Result<Viewer, io::Error> {
-        if key.len() < 5 {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid key"));
+    // Render loop.
+    loop {
+        // Rendering, with the text wrapped to the current viewer width.
+        let area = terminal.size()?;
+        match app.get_split() {
+            Some(split) => {
+                let (first, second) =
+                    ui::split_pane_sizes(area, app.get_manager_width(), split.layout);
+                app.get_viewer_mut().set_pane_size(first.0, first.1);
+                app.get_second_viewer_mut()
+                    .set_pane_size(second.0, second.1);
+            }
+            None => {
+                let (width, height) = ui::viewer_pane_size(area, app.get_manager_width());
+                app.get_viewer_mut().set_pane_size(width, height);
+            }
         }
-
-        Ok(Viewer {
-            name: None,
-            entity: ViewerEntity::Text(String::new()),
-            scroll: 0,
-            key: key.to_string(),
-        })
-    }
-
-    pub fn set_entity(&mut self, entity: ViewerEntity, name: Option<String>) {
-        self.name = name;
-        self.scroll = 0;
-        match entity {
-            ViewerEntity::Text(_) => self.entity = entity,
-            ViewerEntity::DecryptedText(_) => self.entity = entity,
-            ViewerEntity::Binary(bin) => {
-                // Try to decrypt binary:
-                let decrypted = Self::decrypt_binary(&bin, self.key.as_str());
-                match decrypted {
-                    Ok(text) => self.entity = ViewerEntity::DecryptedText(text),
-                    Err(_) => self.entity = ViewerEntity::Binary(bin),
+        app.set_pane_areas(ui::pane_areas(area, app.get_manager_width()));
+        let start = app.get_timings_ref().start();
+        terminal.draw(|f| ui::draw(f, &app))?;
+        app.get_timings_mut().record_draw(start);
+
+        // Handling input, waking up periodically for background results.
+        if poll(Duration::from_millis(100))? {
+            match read()? {
+                Event::Key(key) => {
+                    app.handle_event(AppEvent::Key(key));
+                    let editor = app.get_editor_ref();
+                    let encrypted = editor
+                        .get_target()
+                        .is_some_and(|(_path, encrypted)| encrypted);
+                    recovery::remember(
+                        editor.get_lines().filter(|_| !editor.is_read_only()),
+                        encrypted,
+                    );
                 }
+                Event::Mouse(mouse) => app.handle_event(AppEvent::Mouse(mouse)),
+                _ => (),
             }
         }
-    }
-
-    pub fn get_name(&self) -> Option<String> {
-        self.name.clone()
-    }
-
-    pub fn get_entity_ref(&self) -> &ViewerEntity {
-        &self.entity
-    }
+        app.handle_event(AppEvent::Tick);
 
-    pub fn get_scroll(&self) -> u16 {
-        self.scroll
-    }
-
-    pub fn scroll_up(&mut self, value: u16) {
-        self.scroll = self
-            .scroll
-            .checked_sub(value)
-            .map_or(self.scroll, |scroll| scroll)
+        if app.is_finished() {
+            break;
+        }
     }
-
-    pub fn scroll_down(&mut self, value: u16) {
-        self.scroll = self
-            .scroll
-            .checked_add(value)
-            .map_or(self.scroll, |scroll| scroll)
+    if let Some(root) = &session_root {
+        app.get_session_state().save(root)?;
     }
 
-    pub fn clear(&mut self) {
-        self.name = None;
-        self.entity = ViewerEntity::Text(String::new());
-        self.scroll = 0;
-    }
+    Ok(())
 }
 
-pub struct Editor<'a> {
-    textarea: Option<TextArea<'a>>,
-    key: String,
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Root directory, repeat to list several folders as one.
+    #[arg(long, required_unless_present_any = ["scratch", "glob", "history", "zip", "tar", "git_repo", "ssh", "s3_bucket"])]
+    root: Vec<String>,
+    /// Start an ephemeral session in a temporary folder.
+    #[arg(long, conflicts_with = "root")]
+    scratch: bool,
+    /// List the files matching a pattern, e.g. "notes/**/*.enc".
+    #[arg(long, conflicts_with_all = ["root", "scratch"])]
+    glob: Option<String>,
+    /// List the files recorded in a history file, one path per line, and
+    /// record the files created in the session there too.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["root", "scratch", "glob", "zip", "tar", "git_repo", "ssh", "s3_bucket"]
+    )]
+    history: Option<String>,
+    /// Browse the files of a ZIP archive, read-only.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["root", "scratch", "glob"])]
+    zip: Option<String>,
+    /// Browse the files of a tarball, optionally gzip-compressed, read-only.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["root", "scratch", "glob", "zip"]
+    )]
+    tar: Option<String>,
+    /// Browse the files a git repository tracks at a revision, read-only.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["root", "scratch", "glob", "zip", "tar"]
+    )]
+    git_repo: Option<String>,
+    /// Branch, tag or commit to browse with --git-repo.
+    #[arg(
+        long,
+        value_name = "REV",
+        default_value = "HEAD",
+        requires = "git_repo"
+    )]
+    revision: String,
+    /// Manage the notes in the home folder of a host over SFTP.
+    #[arg(
+        long,
+        value_name = "USER@HOST:PORT",
+        conflicts_with_all = ["root", "scratch", "glob", "zip", "tar", "git_repo"]
+    )]
+    ssh: Option<SshConfig>,
+    /// Private key to log in over SSH with, the SSH agent is used without it.
+    #[arg(long, value_name = "FILE", default_value = "", requires = "ssh")]
+    ssh_key: String,
+    /// Manage the notes kept in an S3 bucket, with the usual AWS credentials.
+    #[arg(
+        long,
+        value_name = "BUCKET",
+        conflicts_with_all = ["root", "scratch", "glob", "zip", "tar", "git_repo", "ssh"]
+    )]
+    s3_bucket: Option<String>,
+    /// Key prefix within the bucket to use as the root folder.
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "",
+        requires = "s3_bucket"
+    )]
+    s3_prefix: String,
+    /// AWS region of the bucket, taken from the AWS configuration without it.
+    #[arg(
+        long,
+        value_name = "REGION",
+        default_value = "",
+        requires = "s3_bucket"
+    )]
+    s3_region: String,
+    /// Commit every save and delete to the git repository holding the root.
+    #[arg(long)]
+    git: bool,
+    /// Where the Back and Root rows go: top, bottom or hidden.
+    #[arg(long, default_value_t = ActionPlacement::Bottom)]
+    actions: ActionPlacement,
+    /// Stop at the ends of the list instead of wrapping around.
+    #[arg(long)]
+    no_wrap: bool,
+    /// Read the modification times of listed files one after another.
+    #[arg(long)]
+    no_parallel: bool,
+    /// List the first page of a large folder at once, End loads the next.
+    #[arg(long)]
+    incremental: bool,
+    /// Ask for the password again every N minutes.
+    #[arg(
+        long,
+        alias = "reauth",
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..=u64::MAX / 60)
+    )]
+    prompt_key_every_n_minutes: Option<u64>,
+    /// Lines scrolled by one notch of the mouse wheel.
+    #[arg(long, value_name = "LINES", default_value_t = 3)]
+    wheel_lines: u16,
+    /// Columns between tab stops in the editor.
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        default_value_t = DEFAULT_TAB_WIDTH,
+        value_parser = clap::value_parser!(u8).range(1..=16)
+    )]
+    tab_width: u8,
+    /// Make Tab insert spaces up to the next tab stop instead of a tab.
+    #[arg(long)]
+    expand_tabs: bool,
+    /// How Ctrl + T in the editor inserts the local time, in strftime format.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = DEFAULT_TIMESTAMP_FORMAT,
+        value_parser = parse_time_format
+    )]
+    timestamp_format: String,
+    /// How Ctrl + Shift + T in the editor inserts the local date.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = DEFAULT_DATE_FORMAT,
+        value_parser = parse_time_format
+    )]
+    date_format: String,
+    /// How times are shown, in the local time zone, in strftime format.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = DEFAULT_DISPLAY_FORMAT,
+        value_parser = parse_time_format
+    )]
+    time_format: String,
+    /// How new files are named after the time they are saved at. The names
+    /// must be valid on every system, so no colons.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = DEFAULT_NAME_FORMAT,
+        value_parser = parse_name_format
+    )]
+    name_format: String,
+    /// Leave the mouse to the terminal, e.g. to select text.
+    #[arg(long)]
+    no_mouse: bool,
+    /// File to print, relative to the root.
+    #[arg(long, value_name = "FILE", requires_all = ["root", "print"])]
+    open: Option<String>,
+    /// Print the file given with --open in color and exit, e.g. for `less -R`.
+    #[arg(long, requires = "open")]
+    print: bool,
+    /// Take the password from the platform keychain, and save it there
+    /// once it unlocks the store.
+    #[arg(long, requires = "root")]
+    use_keyring: bool,
 }
 
-impl Editor<'_> {
-    fn crypt_add(c: i32, count: usize, key: &str) -> i32 {
-        let crypt: Vec<_> = key.bytes().collect();
-        (c + crypt[count] as i32) % 256
-    }
-
-    fn encrypt_string(str: &String, key: &str) -> Vec<u8> {
-        let mut encrypt_text: Vec<u8> = Vec::new();
-        let mut count: usize = 0;
-        for byte in str.as_bytes() {
-            let ch = Self::crypt_add(*byte as i32, count, key);
-            encrypt_text.push(ch as u8);
-            count = (count + 1) % 5;
-        }
-
-        encrypt_text
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the passwords saved in the platform keychain.
+    Keyring {
+        #[command(subcommand)]
+        action: KeyringAction,
+    },
+    /// Count the files of a store without a password.
+    Stats {
+        /// Root directory of the store.
+        #[arg(long)]
+        root: String,
+        #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+        format: StatsFormat,
+    },
 }
 
-impl<'a> Editor<'a> {
-    pub fn new(key: &str) -> Editor<'a> {
-        Editor {
-            textarea: None,
-            key: key.to_string(),
-        }
-    }
-
-    pub fn init(&mut self) {
-        self.textarea = Some(TextArea::default());
-    }
-
-    pub fn get_textarea_ref(&self) -> Option<&TextArea<'a>> {
-        self.textarea.as_ref()
-    }
-
-    pub fn get_textarea_mut(&mut self) -> Option<&mut TextArea<'a>> {
-        self.textarea.as_mut()
-    }
-
-    pub fn finish(&mut self) -> Result<String, io::Error> {
-        if let Some(textarea) = self.textarea.take() {
-            return Ok(textarea.into_lines().join("\n"));
-        }
-
-        Ok(String::new())
-    }
-
-    pub fn finish_encrypt(&mut self) -> Result<Vec<u8>, io::Error> {
-        if let Some(textarea) = self.textarea.take() {
-            let text = textarea.into_lines().join("\n");
-            let encrypted_text = Self::encrypt_string(&text, self.key.as_str());
-            return Ok(encrypted_text);
-        }
-
-        Ok(Vec::new())
-    }
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StatsFormat {
+    Text,
+    Json,
 }
 
-#[derive(Clone, PartialEq)]
-enum Mode {
-    Manager,
-    Viewer,
-    Editor,
-    Exit,
+#[derive(Subcommand, Debug)]
+enum KeyringAction {
+    /// Remove the saved password of a store.
+    Clear {
+        /// Root directory of the store.
+        #[arg(long)]
+        root: String,
+    },
 }
 
-impl fmt::Display for Mode {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Mode::Manager => {
-                let help_manager = vec![
-                    String::from("Esc: End the session"),
-                    String::from("Down: Select next item"),
-                    String::from("Up: Select previous item"),
-                    String::from("Enter: Action on the selected item"),
-                    String::from("E: Open the editor"),
-                    String::from("N: Create a new editor instance"),
-                    String::from("D: Delete the selected item"),
-                ];
-                write!(f, "Manager mode\n{}", help_manager.join("; "))
-            }
-            Mode::Viewer => {
-                let help_viewer = vec![
-                    String::from("Esc: Quit"),
-                    String::from("Down, Up: Scroll the viewer"),
-                ];
-                write!(f, "Viewer mode\n{}", help_viewer.join("; "))
-            }
-            Mode::Editor => {
-                let help_editor = vec![
-                    String::from("Esc: Quit"),
-                    String::from("Ctrl + S: Save the text file"),
-                    String::from("Ctrl + E: Encrypt and save the encrypted file"),
-                    String::from("Other: See TextArea help"),
-                ];
-                write!(f, "Editor mode\n{}", help_editor.join("; "))
-            }
-            Mode::Exit => write!(f, "End the session"),
-        }
+/// Whether the session starts a store: a single root folder, which is
+/// missing or empty.
+fn is_new_store(args: &Args) -> bool {
+    match args.root.as_slice() {
+        [root] => fs::read_dir(root).map_or(true, |mut entries| entries.next().is_none()),
+        _ => false,
     }
 }
 
-fn update(
-    key: KeyEvent,
-    mode: Mode,
-    manager: &mut FileManager,
-    viewer: &mut Viewer,
-    editor: &mut Editor,
-) -> Result<Mode, io::Error> {
-    match mode {
-        Mode::Manager => match key.code {
-            KeyCode::Esc => Ok(Mode::Exit),
-            KeyCode::Up => {
-                manager.previous();
-                Ok(Mode::Manager)
-            }
-            KeyCode::Down => {
-                manager.next();
-                Ok(Mode::Manager)
-            }
-            KeyCode::Enter => match manager.action()? {
-                Respond::Text(text) => {
-                    viewer.set_entity(ViewerEntity::Text(text), manager.get_selected_entity_name());
-                    Ok(Mode::Viewer)
-                }
-                Respond::Bin(bin) => {
-                    viewer.set_entity(
-                        ViewerEntity::Binary(bin),
-                        manager.get_selected_entity_name(),
-                    );
-                    Ok(Mode::Viewer)
-                }
-                Respond::None => Ok(Mode::Manager),
-            },
-            KeyCode::Char('e') | KeyCode::Char('E') => Ok(Mode::Editor),
-            KeyCode::Char('n') | KeyCode::Char('N') => {
-                editor.init();
-                Ok(Mode::Editor)
-            }
-            KeyCode::Char('d') | KeyCode::Char('D') => {
-                manager.delete_selected()?;
-                Ok(Mode::Manager)
-            }
-            _ => Ok(Mode::Manager),
-        },
-        Mode::Viewer => match key.code {
-            KeyCode::Up => {
-                viewer.scroll_up(1);
-                Ok(Mode::Viewer)
-            }
-            KeyCode::Down => {
-                viewer.scroll_down(1);
-                Ok(Mode::Viewer)
-            }
-            _ => {
-                viewer.clear();
-                Ok(Mode::Manager)
-            }
-        },
-        Mode::Editor => match key {
-            KeyEvent {
-                code: KeyCode::Esc,
-                modifiers: _,
-                kind: _,
-                state: _,
-            } => Ok(Mode::Manager),
-            KeyEvent {
-                code: KeyCode::Char('s') | KeyCode::Char('S'),
-                modifiers: KeyModifiers::CONTROL,
-                kind: _,
-                state: _,
-            } => {
-                let text = editor.finish()?;
-                manager.create_file(text.into_bytes(), None)?;
-                Ok(Mode::Manager)
-            }
-            KeyEvent {
-                code: KeyCode::Char('e') | KeyCode::Char('E'),
-                modifiers: KeyModifiers::CONTROL,
-                kind: _,
-                state: _,
-            } => {
-                let encrypted = editor.finish_encrypt()?;
-                manager.create_file(encrypted, None)?;
-                Ok(Mode::Manager)
-            }
-            _ => {
-                editor
-                    .get_textarea_mut()
-                    .map(|textarea: &mut TextArea<'_>| textarea.input(key));
-                Ok(Mode::Editor)
+/// Asks for the password and rates it, a weak one for a new store has to
+/// be confirmed. All of it goes to stderr, which keeps stdout for --print.
+fn prompt_password(args: &Args) -> String {
+    eprintln!("Type the session password");
+    let password = rpassword::read_password().expect("Password is expected");
+    let rating = strength::rate_password(&password);
+    eprintln!("{}", rating);
+    let new_store = is_new_store(args);
+    if let Some(warning) = rating.warning(new_store) {
+        eprintln!("{}", warning);
+        if new_store {
+            let mut answer = String::new();
+            io::stdin()
+                .read_line(&mut answer)
+                .expect("An answer is expected");
+            if !strength::is_confirmed(&answer) {
+                eprintln!("Aborted");
+                process::exit(1);
             }
-        },
-        Mode::Exit => Ok(Mode::Exit),
+        }
     }
-}
 
-fn draw_session_status<B: Backend>(frame: &mut Frame<B>, area: Rect) {
-    let paragraph = Paragraph::new(Utc::now().to_rfc2822()).block(
-        Block::default()
-            .border_style(
-                Style::default()
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .title("Session")
-            .borders(Borders::ALL),
-    );
-    frame.render_widget(paragraph, area)
-}
-
-fn draw_help<B: Backend>(frame: &mut Frame<B>, area: Rect, mode: &Mode) {
-    let paragraph = Paragraph::new(mode.to_string())
-        .block(Block::default().borders(Borders::ALL))
-        .wrap(widgets::Wrap { trim: false });
-    frame.render_widget(paragraph, area)
+    password
 }
 
-fn draw_error<B: Backend>(frame: &mut Frame<B>, area: Rect, err: &io::Error) {
-    let paragraph = Paragraph::new(err.to_string())
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Error")
-                .border_style(Style::default().add_modifier(Modifier::BOLD)),
-        )
-        .style(Style::default().fg(Color::Red))
-        .wrap(widgets::Wrap { trim: true });
-    frame.render_widget(paragraph, area)
-}
-
-fn draw_viewer<B: Backend>(frame: &mut Frame<B>, area: Rect, viewer: &Viewer) {
-    let entity = viewer.get_entity_ref();
-    let paragraph = match entity {
-        ViewerEntity::Text(text) => {
-            let text = Text::from(text.as_str());
-            let title = viewer
-                .get_name()
-                .map_or(String::from("Text File"), |name| name);
-            Paragraph::new(text)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_style(
-                            Style::default()
-                                .fg(Color::White)
-                                .add_modifier(Modifier::BOLD),
-                        )
-                        .title(title),
-                )
-                .wrap(widgets::Wrap { trim: true })
-                .scroll((viewer.get_scroll(), 0))
-        }
-        ViewerEntity::DecryptedText(text) => {
-            let text = Text::from(text.as_str());
-            let title = viewer
-                .get_name()
-                .map_or(String::from("Encrypted File"), |name| name);
-            Paragraph::new(text)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_style(
-                            Style::default()
-                                .fg(Color::Green)
-                                .add_modifier(Modifier::BOLD),
-                        )
-                        .title(title),
-                )
-                .wrap(widgets::Wrap { trim: true })
-                .scroll((viewer.get_scroll(), 0))
-        }
-        ViewerEntity::Binary(_bin) => {
-            let text = Text::from("Binary file");
-            let title = viewer
-                .get_name()
-                .map_or(String::from("Binary File"), |name| name);
-            Paragraph::new(text)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(title)
-                        .border_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
-                        .style(Style::default().fg(Color::Red)),
-                )
-                .wrap(widgets::Wrap { trim: true })
-        }
+/// The file given with --open as the viewer shows it, in ANSI colors.
+fn print_file(args: &Args, file: &str, password: &str) -> Result<String> {
+    let root = Path::new(args.root.first().map_or(".", String::as_str));
+    let key = match args.root.len() {
+        1 => SessionKey::open(root, password)?,
+        _ => SessionKey::from(password),
     };
-    frame.render_widget(paragraph, area)
-}
-
-fn draw_manager<B: Backend>(frame: &mut Frame<B>, area: Rect, manager: &FileManager) {
-    let list_data = manager.get_entities_ref();
-    let items: Vec<ListItem> = list_data
-        .iter()
-        .map(|entity| match entity {
-            ManagerEntity::TextFile(path) => {
-                ListItem::new(path.file_name().map_or("Unknown text file", |str| {
-                    str.to_str().map_or("Unknown text name", |name| name)
-                }))
-                .style(Style::default().fg(Color::White))
-            }
-            ManagerEntity::Folder(path) => {
-                ListItem::new(path.file_name().map_or("Unknown folder", |str| {
-                    str.to_str().map_or("Unknown folder name", |name| name)
-                }))
-                .style(Style::default().fg(Color::Blue))
-            }
-            ManagerEntity::Action(act) => match act {
-                Action::Back => ListItem::new("Back").style(Style::default().fg(Color::Blue)),
-                Action::Root => ListItem::new("Root").style(Style::default().fg(Color::Green)),
-            },
-        })
-        .collect();
-    let title = manager
-        .get_current()
-        .to_str()
-        .map_or(String::from("Folder"), |name| String::from(name));
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .title(title.as_str())
-                .borders(Borders::ALL)
-                .border_style(
-                    Style::default()
-                        .fg(Color::White)
-                        .add_modifier(Modifier::BOLD),
-                ),
-        )
-        .highlight_style(
-            Style::default()
-                .add_modifier(Modifier::BOLD)
-                .bg(Color::Yellow),
-        );
-    let mut state = ListState::default();
-    state.select(manager.get_selected_id());
-    frame.render_stateful_widget(list, area, &mut state);
-}
+    let mut viewer = Viewer::with_key(key)?;
+    viewer.set_entity(
+        loader::entity_from_bytes(fs::read(root.join(file))?),
+        Some(file.to_string()),
+    );
 
-fn draw_editor<B: Backend>(frame: &mut Frame<B>, area: Rect, editor: &Editor) {
-    editor.get_textarea_ref().map(|textarea| {
-        let widget = textarea.widget();
-        frame.render_widget(widget, area);
-    });
+    Ok(viewer.to_ansi_colored_string())
 }
 
-fn run_session(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    root: &str,
-    key: &str,
-) -> Result<(), io::Error> {
-    let mut manager = FileManager::new(root)?;
-    let mut viewer = Viewer::new(key)?;
-    let mut editor = Editor::new(key);
-    let mut mode = Mode::Manager;
-    let mut status: Result<(), io::Error> = Ok(());
-
-    // Render loop.
-    loop {
-        // Rendering.
-        terminal.draw(|f: &mut Frame<'_, CrosstermBackend<io::Stdout>>| {
-            let vertical_chunks = Layout::default()
-                .direction(tui::layout::Direction::Vertical)
-                .constraints([
-                    Constraint::Percentage(10),
-                    Constraint::Percentage(80),
-                    Constraint::Percentage(10),
-                ])
-                .split(f.size());
-            let horizontal_chunks = Layout::default()
-                .direction(tui::layout::Direction::Horizontal)
-                .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
-                .split(vertical_chunks[1]);
-
-            draw_session_status(f, vertical_chunks[0]);
-            draw_manager(f, horizontal_chunks[0], &manager);
-            if mode == Mode::Editor {
-                draw_editor(f, horizontal_chunks[1], &editor);
-            } else {
-                draw_viewer(f, horizontal_chunks[1], &viewer);
+fn run_command(command: &Command) {
+    match command {
+        Command::Keyring {
+            action: KeyringAction::Clear { root },
+        } => match keychain::clear_password(&SystemKeychain, Path::new(root)) {
+            Ok(true) => println!("The saved password of {} was removed", root),
+            Ok(false) => println!("No password of {} is saved", root),
+            Err(error) => {
+                println!("Error {:?} ocurred while clearing the keychain", error);
+                process::exit(1);
             }
-            if let Err(err) = &status {
-                draw_error(f, vertical_chunks[2], &err);
-            } else {
-                draw_help(f, vertical_chunks[2], &mode);
-            }
-        })?;
-
-        // Handling input.
-        if let Event::Key(key) = read()? {
-            match update(key, mode.clone(), &mut manager, &mut viewer, &mut editor) {
-                Ok(new_mode) => {
-                    status = Ok(());
-                    mode = new_mode;
+        },
+        Command::Stats { root, format } => {
+            let stats = match stats::collect_stats(Path::new(root), TOP_FILES) {
+                Ok(stats) => stats,
+                Err(error) => {
+                    eprintln!("Error {:?} ocurred while walking {}", error, root);
+                    process::exit(1);
                 }
-                Err(err) => status = Err(err),
+            };
+            match format {
+                StatsFormat::Text => println!("{}", stats.to_text(&TimeFormats::default())),
+                StatsFormat::Json => println!("{:#}", stats.to_json()),
+            }
+            if stats.unreadable > 0 {
+                eprintln!(
+                    "Warning: {} entries could not be read and were skipped",
+                    stats.unreadable
+                );
             }
-        }
-
-        if mode == Mode::Exit {
-            break Ok(());
         }
     }
 }
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Root directory.
-    #[arg(long)]
-    root: String,
-}
-
 fn main() {
     // Parse CLI arguments.
     let args = Args::parse();
+    if let Some(command) = &args.command {
+        run_command(command);
+        return;
+    }
 
-    // Password.
-    println!("Type the session password");
-    let password = rpassword::read_password().expect("Password is expected");
+    // Password, taken from the keychain if it is saved there and still
+    // unlocks the store.
+    let keychain_root = match (args.use_keyring, args.root.as_slice()) {
+        (true, [root]) => Some(Path::new(root)),
+        _ => None,
+    };
+    let saved = keychain_root.and_then(|root| keychain::load_password(&SystemKeychain, root));
+    let password = match saved {
+        Some(password) => {
+            eprintln!("Using the password saved in the keychain");
+            password
+        }
+        None => {
+            let password = prompt_password(&args);
+            if let Some(root) = keychain_root {
+                if let Err(error) = keychain::save_password(&SystemKeychain, root, &password) {
+                    eprintln!("The password was not saved in the keychain: {}", error);
+                }
+            }
+            password
+        }
+    };
+
+    // Print the file instead of a session.
+    if let Some(file) = args.open.as_deref().filter(|_| args.print) {
+        match print_file(&args, file, &password) {
+            Ok(text) => print!("{}", text),
+            Err(error) => {
+                eprintln!("Error {:?} ocurred while printing {}", error, file);
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
     // Initialize terminal for the session.
     let stdout = io::stdout();
@@ -819,11 +519,24 @@ fn main() {
     let mut terminal = Terminal::new(backend).expect("Cannot create a terminal");
     enable_raw_mode().expect("Cannot enable raw mode");
     execute!(terminal.backend_mut(), EnterAlternateScreen).expect("Cannot enable alternate screen");
+    // Lets supporting terminals tell Ctrl + M from Enter, it is fine to go without.
+    let _ = execute!(
+        terminal.backend_mut(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+    );
+    if !args.no_mouse {
+        execute!(terminal.backend_mut(), EnableMouseCapture).expect("Cannot capture the mouse");
+    }
 
     // Session.
-    let result = run_session(&mut terminal, args.root.as_str(), password.as_str());
+    let result = run_session(&mut terminal, &args, password.as_str());
 
     // Shutdown the session.
+    let _ = execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags);
+    // The terminal's own text selection works again only without the capture.
+    if !args.no_mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture).expect("Cannot release the mouse");
+    }
     disable_raw_mode().expect("Cannot disable raw mode");
     execute!(terminal.backend_mut(), LeaveAlternateScreen)
         .expect("Cannot disable alternate screen");
@@ -0,0 +1,2924 @@
+use crate::{
+    editor::Editor,
+    error::{MystoreError, Result},
+    git,
+    journal::HeldFile,
+    keyring::SessionKey,
+    locks::{self, LockOwner},
+    s3::{S3Config, S3Store},
+    sizes::format_size,
+    tags::INDEX_DIR,
+    times::TimeFormats,
+    tree_hash::{self, BackgroundHasher},
+};
+use chrono::{DateTime, Datelike, Days, Local, Utc};
+use flate2::read::GzDecoder;
+use git2::{ObjectType, Oid, Repository, Signature, Time, TreeWalkMode, TreeWalkResult};
+use rayon::prelude::*;
+use regex::Regex;
+use ssh2::{CheckResult, KnownHostFileKind, OpenFlags, OpenType, Session, Sftp};
+use std::net::TcpStream;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    ffi::OsString,
+    fmt,
+    fs::{File, OpenOptions, ReadDir},
+    io::{self, Cursor, Read, Seek, Write},
+    mem,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant, SystemTime},
+};
+use tempfile::TempDir;
+use zip::{write::SimpleFileOptions, AesMode, CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Back,
+    Root,
+    /// An action added with `FileManager::register_action`, by its name.
+    Custom(String),
+}
+
+/// Runs a custom action on the manager, see `FileManager::register_action`.
+pub type ActionHandler = Box<dyn Fn(&FileManager) -> io::Result<Respond>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ManagerEntity {
+    TextFile(PathBuf),
+    Folder(PathBuf),
+    Action(Action),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Respond {
+    File(PathBuf),
+    /// A file read from an archive or a remote host, with its listed path.
+    Data(PathBuf, Vec<u8>),
+    None,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    /// Recently modified files first.
+    #[default]
+    Modified,
+    /// Largest files first.
+    Size,
+    /// Files by name in natural order.
+    Name,
+}
+
+impl SortOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Modified => SortOrder::Size,
+            SortOrder::Size => SortOrder::Name,
+            SortOrder::Name => SortOrder::Modified,
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortOrder::Modified => write!(f, "modification time"),
+            SortOrder::Size => write!(f, "size"),
+            SortOrder::Name => write!(f, "name"),
+        }
+    }
+}
+
+/// Where the Back and Root rows go in a listing below the root.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ActionPlacement {
+    Top,
+    #[default]
+    Bottom,
+    /// No rows, Back and Root are left to their keys.
+    Hidden,
+}
+
+impl FromStr for ActionPlacement {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "top" => Ok(ActionPlacement::Top),
+            "bottom" => Ok(ActionPlacement::Bottom),
+            "hidden" => Ok(ActionPlacement::Hidden),
+            _ => Err(format!(
+                "unknown placement {:?}, expected top, bottom or hidden",
+                value
+            )),
+        }
+    }
+}
+
+/// Where to reach a host over SSH, parsed from `user@host:port`. The
+/// port defaults to 22.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SshConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+}
+
+impl FromStr for SshConfig {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (user, address) = value
+            .split_once('@')
+            .ok_or(format!("expected user@host:port, got {:?}", value))?;
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_err| format!("invalid port {:?}", port))?,
+            ),
+            None => (address, 22),
+        };
+        if user.is_empty() || host.is_empty() {
+            return Err(format!("expected user@host:port, got {:?}", value));
+        }
+
+        Ok(SshConfig {
+            host: String::from(host),
+            port,
+            user: String::from(user),
+        })
+    }
+}
+
+impl fmt::Display for ActionPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionPlacement::Top => write!(f, "top"),
+            ActionPlacement::Bottom => write!(f, "bottom"),
+            ActionPlacement::Hidden => write!(f, "hidden"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictResolution {
+    Skip,
+    Overwrite,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LockCleanup {
+    pub removed: usize,
+    /// Locks kept because their session still runs or may still run.
+    pub held: usize,
+    /// Locks that could not be removed.
+    pub failed: usize,
+    /// Folders that could not be read, their locks are left in place.
+    pub unreadable: usize,
+}
+
+impl fmt::Display for LockCleanup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Removed {} lock files", self.removed)?;
+        if self.held > 0 {
+            write!(f, ", kept {} in use", self.held)?;
+        }
+        if self.failed > 0 {
+            write!(f, ", failed to remove {}", self.failed)?;
+        }
+        if self.unreadable > 0 {
+            write!(f, ", skipped {} unreadable folders", self.unreadable)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The entries of one page of a folder, kept while the folder's
+/// modification time is unchanged.
+struct CachedPage {
+    skip: usize,
+    take: usize,
+    entries: Vec<PathBuf>,
+    total: usize,
+    modified: SystemTime,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeResult {
+    pub copied: usize,
+    pub skipped_existing: usize,
+}
+
+pub struct FileManager {
+    root: PathBuf,
+    /// All roots of the session, the first one is `root`.
+    roots: Vec<PathBuf>,
+    current: PathBuf,
+    entities: Vec<ManagerEntity>,
+    selected: Option<usize>,
+    created_entities: Vec<ManagerEntity>,
+    session_start: DateTime<Utc>,
+    locked: HashSet<PathBuf>,
+    session_locks: Vec<PathBuf>,
+    templates_dir: Option<PathBuf>,
+    filter: Option<(String, Vec<PathBuf>)>,
+    selected_set: HashSet<PathBuf>,
+    page: usize,
+    page_size: usize,
+    page_count: usize,
+    sort_order: SortOrder,
+    action_placement: ActionPlacement,
+    wrap_navigation: bool,
+    /// Whether entries named with a leading dot are listed.
+    show_hidden: bool,
+    /// Whether listings are timed, see `take_listing_time`.
+    time_listings: bool,
+    listing_time: Option<Duration>,
+    /// Whether the modification times of a listing are read on all cores.
+    parallel_read: bool,
+    /// Whether folders are read a page at a time, see `new_incremental`.
+    incremental: bool,
+    /// The rest of the folder read incrementally, `None` once all is read.
+    pending_iter: Option<ReadDir>,
+    /// Entries of the folder read incrementally so far.
+    loaded: Vec<PathBuf>,
+    sizes: HashMap<PathBuf, u64>,
+    /// Modification times of the listed files, read when sorted by them.
+    modified: HashMap<PathBuf, SystemTime>,
+    time_formats: TimeFormats,
+    /// The hash of the current folder tree, computed on a worker thread
+    /// after every refresh and compared with the one before.
+    last_hash: Option<String>,
+    tree_changed: Option<bool>,
+    /// Entries left out of the last hash because they could not be read.
+    hash_unreadable: usize,
+    hasher: BackgroundHasher,
+    extensions: Option<Vec<String>>,
+    dir_cache: HashMap<PathBuf, CachedPage>,
+    dir_cache_order: VecDeque<PathBuf>,
+    /// Where the files are kept, every file operation goes through it.
+    backend: Backend,
+    custom_actions: Vec<(String, ActionHandler)>,
+    /// The key of the session, the ZIP export password is derived from it.
+    key: Option<SessionKey>,
+    /// What the last `cleanup_locks` did besides removing locks.
+    last_cleanup: LockCleanup,
+    /// The renames done by the last `rename_batch`, until taken for the
+    /// journal.
+    applied_renames: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Where the files of the store are kept.
+enum Backend {
+    Local,
+    /// A ZIP archive, the root is then the archive itself.
+    Zip(ZipArchive<File>),
+    /// A tarball, read into memory whole. Folders end with `/` and have no
+    /// data.
+    Tar(Vec<(String, Vec<u8>)>),
+    /// A git revision, the root is then the repository. Holds the path and
+    /// blob of every tracked file.
+    GitTree(Repository, Vec<(String, Oid)>),
+    Sftp(Sftp),
+    S3(S3Store),
+}
+
+const LOCK_SUFFIX: &str = ".lock";
+const PAGE_SIZE: usize = 1000;
+const DIR_CACHE_CAPACITY: usize = 16;
+/// Longest file name most filesystems accept, in bytes.
+const MAX_NAME_LEN: usize = 255;
+/// Most a tarball may unpack to, it is held in memory whole.
+pub const MAX_TAR_SIZE: u64 = 256 * 1024 * 1024;
+/// Buckets of `FileManager::age_histogram`, from the newest files.
+pub const AGE_BUCKETS: [&str; 4] = ["today", "this week", "this month", "older"];
+
+impl FileManager {
+    fn open_dir<T: AsRef<Path>>(dir: &T) -> io::Result<Vec<PathBuf>> {
+        let items = std::fs::read_dir(dir)?;
+        let file_names: Vec<PathBuf> = items
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                Some(path)
+            })
+            .collect();
+
+        Ok(file_names)
+    }
+
+    /// Reads `take` entries of `dir` after the first `skip` in directory
+    /// order, along with the total number of entries. Only those entries
+    /// are kept, however large the folder.
+    fn open_dir_paginated<T: AsRef<Path>>(
+        dir: &T,
+        skip: usize,
+        take: usize,
+    ) -> io::Result<(Vec<PathBuf>, usize)> {
+        let mut total = 0;
+        let mut file_names = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            if total >= skip && total - skip < take {
+                if let Ok(entry) = entry {
+                    file_names.push(entry.path());
+                }
+            }
+            total += 1;
+        }
+
+        Ok((file_names, total))
+    }
+
+    /// Reads a page of `dir` like `open_dir_paginated`, reusing the last
+    /// read of the same page while the folder's modification time is
+    /// unchanged. The least recently used folder is evicted once the cache
+    /// is full.
+    fn open_dir_cached(
+        &mut self,
+        dir: &Path,
+        skip: usize,
+        take: usize,
+    ) -> Result<(Vec<PathBuf>, usize)> {
+        let modified = dir.metadata()?.modified()?;
+        self.dir_cache_order.retain(|cached| cached != dir);
+        self.dir_cache_order.push_back(dir.to_path_buf());
+        if let Some(cached) = self.dir_cache.get(dir) {
+            if (cached.skip, cached.take, cached.modified) == (skip, take, modified) {
+                return Ok((cached.entries.clone(), cached.total));
+            }
+        }
+
+        let (entries, total) = Self::open_dir_paginated(&dir, skip, take)?;
+        let page = CachedPage {
+            skip,
+            take,
+            entries: entries.clone(),
+            total,
+            modified,
+        };
+        self.dir_cache.insert(dir.to_path_buf(), page);
+        if self.dir_cache_order.len() > DIR_CACHE_CAPACITY {
+            if let Some(evicted) = self.dir_cache_order.pop_front() {
+                self.dir_cache.remove(&evicted);
+            }
+        }
+
+        Ok((entries, total))
+    }
+
+    /// Drops the cached entries of the folder holding `path`, once the
+    /// manager itself changed it. The modification time alone may miss a
+    /// change made within its resolution.
+    fn forget_parent(&mut self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            self.dir_cache.remove(parent);
+            self.dir_cache_order.retain(|cached| cached != parent);
+        }
+    }
+
+    /// Reads file sizes once per listing. Files without readable metadata
+    /// are left out.
+    fn read_sizes(files: &[PathBuf]) -> HashMap<PathBuf, u64> {
+        files
+            .iter()
+            .filter_map(|path| {
+                let meta = path.metadata().ok()?;
+                Some((path.clone(), meta.len()))
+            })
+            .collect()
+    }
+
+    /// Sorts paths into folders and files, anything else is left out.
+    fn split_folders(paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        paths
+            .into_iter()
+            .filter(|path| path.is_dir() || path.is_file())
+            .partition(|path| path.is_dir())
+    }
+
+    fn create_entities(
+        folders: Vec<PathBuf>,
+        files: Vec<PathBuf>,
+        actions: Vec<ManagerEntity>,
+        order: SortOrder,
+        placement: ActionPlacement,
+        sizes: &HashMap<PathBuf, u64>,
+        parallel: bool,
+    ) -> (Vec<ManagerEntity>, HashMap<PathBuf, SystemTime>) {
+        let mut folder_entities: Vec<ManagerEntity> =
+            folders.into_iter().map(ManagerEntity::Folder).collect();
+        folder_entities.sort_by(Self::compare_names);
+
+        let mut file_entities: Vec<ManagerEntity> =
+            files.into_iter().map(ManagerEntity::TextFile).collect();
+        let mut modified = HashMap::new();
+        match order {
+            // Restored or checked out files often share a modification time,
+            // ties are broken by name so that they keep their places.
+            SortOrder::Modified => {
+                let with_time = |entity: ManagerEntity| match &entity {
+                    ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => {
+                        let modified = path.metadata().map_or(None, |meta| {
+                            Some(meta.modified().map_or(SystemTime::UNIX_EPOCH, |st| st))
+                        });
+                        (Reverse(modified), entity)
+                    }
+                    ManagerEntity::Action(_act) => (Reverse(None), entity),
+                };
+                let mut timed: Vec<(Reverse<Option<SystemTime>>, ManagerEntity)> = match parallel {
+                    true => file_entities.into_par_iter().map(with_time).collect(),
+                    false => file_entities.into_iter().map(with_time).collect(),
+                };
+                timed.sort_by(|(a_time, a), (b_time, b)| {
+                    a_time.cmp(b_time).then_with(|| Self::compare_names(a, b))
+                });
+                // The times read for sorting are kept to be shown.
+                modified = timed
+                    .iter()
+                    .filter_map(|(Reverse(time), entity)| match entity {
+                        ManagerEntity::TextFile(path) => Some((path.clone(), (*time)?)),
+                        _entity => None,
+                    })
+                    .collect();
+                file_entities = timed.into_iter().map(|(_time, entity)| entity).collect();
+            }
+            // Ties are broken by name, and unknown sizes go last.
+            SortOrder::Size => file_entities.sort_by(|a, b| {
+                let size = |entity: &ManagerEntity| match entity {
+                    ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => {
+                        sizes.get(path).copied()
+                    }
+                    ManagerEntity::Action(_act) => None,
+                };
+                size(b)
+                    .cmp(&size(a))
+                    .then_with(|| Self::compare_names(a, b))
+            }),
+            SortOrder::Name => file_entities.sort_by(Self::compare_names),
+        }
+
+        let mut entities = Vec::new();
+        if placement == ActionPlacement::Top {
+            entities.extend(actions.iter().cloned());
+        }
+        entities.extend(folder_entities);
+        entities.extend(file_entities);
+        if placement == ActionPlacement::Bottom {
+            entities.extend(actions);
+        }
+
+        (entities, modified)
+    }
+
+    /// The action rows of a listing: Back and Root below the root, then the
+    /// custom actions in the order they were registered.
+    fn action_rows(&self, is_root: bool) -> Vec<ManagerEntity> {
+        let navigation = match is_root {
+            true => Vec::new(),
+            false => vec![Action::Back, Action::Root],
+        };
+        let custom = self
+            .custom_actions
+            .iter()
+            .map(|(name, _handler)| Action::Custom(name.clone()));
+        navigation
+            .into_iter()
+            .chain(custom)
+            .map(ManagerEntity::Action)
+            .collect()
+    }
+
+    /// Orders entities by file name in natural order, ignoring case. Names
+    /// equal after lowercasing, like `README` and `readme`, fall back to a
+    /// case-sensitive comparison, then to the full path, so that equal names
+    /// from different folders keep a stable order.
+    fn compare_names(a: &ManagerEntity, b: &ManagerEntity) -> Ordering {
+        let path = |entity: &ManagerEntity| match entity {
+            ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => Some(path.clone()),
+            ManagerEntity::Action(_act) => None,
+        };
+        let (a, b) = (path(a), path(b));
+        let name = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let (name_a, name_b) = (name(&a), name(&b));
+        Self::natural_cmp(&name_a.to_lowercase(), &name_b.to_lowercase())
+            .then_with(|| Self::natural_cmp(&name_a, &name_b))
+            .then_with(|| a.cmp(&b))
+    }
+
+    /// Resolves `.` and `..` without touching the file system. Links are
+    /// not followed, `resolve_within_root` checks where they lead.
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normal = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => (),
+                Component::ParentDir => {
+                    normal.pop();
+                }
+                component => normal.push(component),
+            }
+        }
+        normal
+    }
+
+    fn expand_home(input: &str) -> PathBuf {
+        match (input.strip_prefix('~'), env::var_os("HOME")) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                PathBuf::from(home).join(rest.trim_start_matches('/'))
+            }
+            _ => PathBuf::from(input),
+        }
+    }
+
+    /// The sidecar whose presence marks `path` as locked by a session.
+    pub fn lock_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().map_or(OsString::new(), OsString::from);
+        name.push(LOCK_SUFFIX);
+        path.with_file_name(name)
+    }
+
+    /// Splits the lock sidecars off `files`, giving the other files and
+    /// those of them that are locked.
+    fn split_locks(files: Vec<PathBuf>) -> (Vec<PathBuf>, HashSet<PathBuf>) {
+        let names: HashSet<&PathBuf> = files.iter().collect();
+        let locked: HashSet<PathBuf> = files
+            .iter()
+            .filter(|path| names.contains(&Self::lock_path(path)))
+            .cloned()
+            .collect();
+        let sidecars: HashSet<PathBuf> = locked.iter().map(|path| Self::lock_path(path)).collect();
+        let files = files
+            .into_iter()
+            .filter(|path| !sidecars.contains(path))
+            .collect();
+
+        (files, locked)
+    }
+
+    fn default_templates_dir() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("mystore").join("templates"))
+    }
+
+    fn fill_template(data: Vec<u8>) -> Vec<u8> {
+        match String::from_utf8(data) {
+            Ok(text) => {
+                let now = Utc::now();
+                text.replace("{{DATE}}", &now.format("%Y-%m-%d").to_string())
+                    .replace("{{TIME}}", &now.format("%H:%M:%S").to_string())
+                    .into_bytes()
+            }
+            Err(err) => err.into_bytes(),
+        }
+    }
+
+    fn goto_dir(&mut self, dir: PathBuf) -> Result<()> {
+        self.page = 0;
+        self.extensions = None;
+        self.list_dir(dir)?;
+        // The hash of the tree as visited is the baseline that refreshes
+        // compare against.
+        self.hasher.cancel();
+        self.last_hash = None;
+        self.tree_changed = None;
+        self.hash_unreadable = 0;
+        self.start_tree_hash();
+
+        Ok(())
+    }
+
+    fn list_dir(&mut self, dir: PathBuf) -> Result<()> {
+        let start = self.time_listings.then(Instant::now);
+        let result = self.read_listing(dir);
+        if let Some(start) = start {
+            self.listing_time = Some(start.elapsed());
+        }
+        result
+    }
+
+    fn read_listing(&mut self, dir: PathBuf) -> Result<()> {
+        if self.is_readonly() {
+            self.list_archive_dir(dir);
+            return Ok(());
+        }
+        if self.is_remote() {
+            let entries = self.read_entries(&dir)?;
+            self.list_remote_dir(dir, entries);
+            return Ok(());
+        }
+        let is_root = self.is_root(&dir);
+        // Any of several roots lists the entries of all of them.
+        let dir = if is_root { self.root.clone() } else { dir };
+        if self.incremental {
+            let files = self.read_incrementally(&dir)?;
+            self.list_entries(dir, files, is_root);
+            return Ok(());
+        }
+        // Pages follow the directory order, only the page is sorted
+        // afterwards. With several roots they run across one after another.
+        let skip = self.page * self.page_size;
+        let mut files = Vec::new();
+        let mut total = 0;
+        for source in self.get_listed_dirs(&dir) {
+            let (page, count) = self.open_dir_cached(
+                &source,
+                skip.saturating_sub(total),
+                self.page_size - files.len(),
+            )?;
+            files.extend(page);
+            total += count;
+        }
+        self.page_count = total.div_ceil(self.page_size).max(1);
+        if self.page >= self.page_count {
+            self.page = self.page_count - 1;
+            return self.read_listing(dir);
+        }
+        self.list_entries(dir, files, is_root);
+
+        Ok(())
+    }
+
+    /// Reads the first page of `dir`, or as many entries as were loaded
+    /// when it is listed again, and keeps the rest of it for later.
+    fn read_incrementally(&mut self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let count = match dir == self.current {
+            true => self.loaded.len().max(self.page_size),
+            false => self.page_size,
+        };
+        let mut items = std::fs::read_dir(dir)?;
+        self.loaded = Self::read_batch(&mut items, count);
+        self.pending_iter = (self.loaded.len() == count).then_some(items);
+
+        Ok(self.loaded.clone())
+    }
+
+    fn read_batch(items: &mut ReadDir, count: usize) -> Vec<PathBuf> {
+        items
+            .take(count)
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect()
+    }
+
+    /// Lists `files` read from `dir` as its entities.
+    fn list_entries(&mut self, dir: PathBuf, files: Vec<PathBuf>, is_root: bool) {
+        let (mut files, locked) = Self::split_locks(files);
+        self.retain_extensions(&mut files);
+        self.retain_visible(&mut files);
+        self.sizes = match self.sort_order {
+            SortOrder::Size => Self::read_sizes(&files),
+            SortOrder::Modified | SortOrder::Name => HashMap::new(),
+        };
+        let (folders, files) = Self::split_folders(files);
+        (self.entities, self.modified) = Self::create_entities(
+            folders,
+            files,
+            self.action_rows(is_root),
+            self.sort_order,
+            self.action_placement,
+            &self.sizes,
+            self.parallel_read,
+        );
+        self.locked = locked;
+        self.set_selected(None);
+        self.current = dir;
+        self.filter = None;
+    }
+
+    /// Lists a remote folder from its entries, all of it on one page.
+    fn list_remote_dir(&mut self, dir: PathBuf, entries: Vec<(PathBuf, bool, u64)>) {
+        let paths = entries.iter().map(|(path, ..)| path.clone()).collect();
+        let (mut paths, locked) = Self::split_locks(paths);
+        self.retain_visible(&mut paths);
+        let kinds: HashMap<PathBuf, (bool, u64)> = entries
+            .into_iter()
+            .map(|(path, is_dir, size)| (path, (is_dir, size)))
+            .collect();
+        let (folders, mut files): (Vec<PathBuf>, Vec<PathBuf>) = paths
+            .into_iter()
+            .partition(|path| kinds.get(path).is_some_and(|(is_dir, _size)| *is_dir));
+        if let Some(extensions) = &self.extensions {
+            files.retain(|path| Self::has_extension(path, extensions));
+        }
+        self.page_count = 1;
+        self.sizes = files
+            .iter()
+            .filter_map(|path| Some((path.clone(), kinds.get(path)?.1)))
+            .collect();
+        (self.entities, self.modified) = Self::create_entities(
+            folders,
+            files,
+            self.action_rows(self.is_root(&dir)),
+            self.sort_order,
+            self.action_placement,
+            &self.sizes,
+            self.parallel_read,
+        );
+        self.locked = locked;
+        self.set_selected(None);
+        self.current = dir;
+        self.filter = None;
+    }
+
+    /// The entries of `dir`, local or remote, with whether each is a
+    /// folder and its size. Symbolic links are not followed.
+    fn read_entries(&self, dir: &Path) -> Result<Vec<(PathBuf, bool, u64)>> {
+        match &self.backend {
+            Backend::Sftp(sftp) => Ok(sftp
+                .readdir(dir)
+                .map_err(io::Error::from)?
+                .into_iter()
+                .filter(|(_path, stat)| stat.is_dir() || stat.is_file())
+                .map(|(path, stat)| (path, stat.is_dir(), stat.size.unwrap_or_default()))
+                .collect()),
+            Backend::S3(s3) => {
+                let listing = s3.list(&self.key_of(s3, dir)?)?;
+                let name_of = |key: &str| dir.join(key.rsplit('/').next().unwrap_or(key));
+                let folders = listing.folders.iter().map(|key| (name_of(key), true, 0));
+                let files = listing
+                    .files
+                    .iter()
+                    .map(|(key, size)| (name_of(key), false, *size));
+                Ok(folders.chain(files).collect())
+            }
+            _ => Self::open_dir(&dir)?
+                .into_iter()
+                .map(|path| {
+                    let meta = path.symlink_metadata()?;
+                    Ok((path, meta.is_dir(), meta.len()))
+                })
+                .collect(),
+        }
+    }
+
+    /// The S3 key of `path`, which must be below the root.
+    fn key_of(&self, s3: &S3Store, path: &Path) -> io::Result<String> {
+        s3.key_of(&self.root, path).ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is outside of the bucket prefix", path.display()),
+        ))
+    }
+
+    /// Whether `path` is a folder and whether it is a file, neither when
+    /// there is nothing at `path`. Other failures, like a refused access or
+    /// a lost connection, are errors.
+    fn kind_of(&self, path: &Path) -> io::Result<(bool, bool)> {
+        let found = match &self.backend {
+            Backend::Sftp(sftp) => sftp
+                .stat(path)
+                .map(|stat| (stat.is_dir(), stat.is_file()))
+                .map_err(io::Error::from),
+            Backend::S3(s3) => {
+                let key = self.key_of(s3, path)?;
+                match s3.is_folder(&key)? {
+                    true => Ok((true, false)),
+                    false => Ok((false, s3.is_file(&key)?)),
+                }
+            }
+            _ => path.metadata().map(|meta| (meta.is_dir(), meta.is_file())),
+        };
+        match found {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok((false, false)),
+            found => found,
+        }
+    }
+
+    /// Whether there is a file at `path`, or a folder unless it is in S3,
+    /// which only has objects. Errors are those of `kind_of`.
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        let found = match &self.backend {
+            Backend::Sftp(sftp) => sftp.stat(path).map(|_stat| ()).map_err(io::Error::from),
+            Backend::S3(s3) => return s3.is_file(&self.key_of(s3, path)?),
+            _ => return path.try_exists(),
+        };
+        match found {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match &self.backend {
+            Backend::Sftp(sftp) => {
+                let mut data = Vec::new();
+                sftp.open(path)?.read_to_end(&mut data)?;
+                Ok(data)
+            }
+            Backend::S3(s3) => s3.get(&self.key_of(s3, path)?),
+            _ => std::fs::read(path),
+        }
+    }
+
+    /// Writes `data` to `path`, replacing the file if there is one.
+    fn write_file(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.forget_parent(path);
+        self.store_file(path, data)
+    }
+
+    /// `write_file` without dropping the cached listing of the folder.
+    fn store_file(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        match &self.backend {
+            Backend::Sftp(sftp) => sftp.create(path)?.write_all(data),
+            Backend::S3(s3) => s3.put(&self.key_of(s3, path)?, data, false),
+            _ => std::fs::write(path, data),
+        }
+    }
+
+    /// Creates a file that must not exist yet with `data`, for a lock.
+    fn create_new(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.forget_parent(path);
+        match &self.backend {
+            Backend::S3(s3) => s3.put(&self.key_of(s3, path)?, data, true),
+            Backend::Sftp(sftp) => sftp
+                .open_mode(
+                    path,
+                    OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::EXCLUSIVE,
+                    0o644,
+                    OpenType::File,
+                )
+                .map_err(io::Error::from)?
+                .write_all(data),
+            _ => OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)?
+                .write_all(data),
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> io::Result<()> {
+        self.forget_parent(path);
+        match &self.backend {
+            Backend::Sftp(sftp) => Ok(sftp.unlink(path)?),
+            Backend::S3(s3) => s3.delete(&self.key_of(s3, path)?),
+            _ => std::fs::remove_file(path),
+        }
+    }
+
+    fn rename_file(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        self.forget_parent(from);
+        self.forget_parent(to);
+        match &self.backend {
+            Backend::Sftp(sftp) => Ok(sftp.rename(from, to, None)?),
+            Backend::S3(s3) => s3.rename(&self.key_of(s3, from)?, &self.key_of(s3, to)?),
+            _ => std::fs::rename(from, to),
+        }
+    }
+
+    /// Logs in with the SSH agent, or with the private key file `key`
+    /// unless it is empty, once the host key is found in known_hosts.
+    fn connect_sftp(config: &SshConfig, key: &str) -> io::Result<Sftp> {
+        let mut session = Session::new()?;
+        session.set_tcp_stream(TcpStream::connect((config.host.as_str(), config.port))?);
+        session.handshake()?;
+
+        let (host_key, _key_type) = session.host_key().ok_or(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "The host sent no host key",
+        ))?;
+        let mut known_hosts = session.known_hosts()?;
+        if let Some(home) = env::var_os("HOME") {
+            let path = PathBuf::from(home).join(".ssh").join("known_hosts");
+            // A missing file only means that no host is known.
+            let _ = known_hosts.read_file(&path, KnownHostFileKind::OpenSSH);
+        }
+        match known_hosts.check_port(&config.host, config.port, host_key) {
+            CheckResult::Match => (),
+            CheckResult::Mismatch => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("The host key of {} has changed", config.host),
+                ))
+            }
+            CheckResult::NotFound | CheckResult::Failure => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} is not in known_hosts", config.host),
+                ))
+            }
+        }
+
+        if key.is_empty() {
+            session.userauth_agent(&config.user)?;
+        } else {
+            session.userauth_pubkey_file(&config.user, None, Path::new(key), None)?;
+        }
+        Ok(session.sftp()?)
+    }
+
+    /// Lists a folder of the archive, all of it on one page.
+    fn list_archive_dir(&mut self, dir: PathBuf) {
+        let (folders, files) = self.read_archive_dir(&dir);
+        self.page_count = 1;
+        self.sizes = HashMap::new();
+        (self.entities, self.modified) = Self::create_entities(
+            folders,
+            files,
+            self.action_rows(self.is_root(&dir)),
+            self.sort_order,
+            self.action_placement,
+            &self.sizes,
+            self.parallel_read,
+        );
+        self.locked = HashSet::new();
+        self.set_selected(None);
+        self.current = dir;
+        self.filter = None;
+    }
+
+    /// The folders and files of the archive or revision directly inside
+    /// `dir`, folders included when they are only implied by the paths of
+    /// their files. Entries that would leave the root, like `../name`, are
+    /// skipped.
+    fn read_archive_dir(&self, dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let Ok(inside) = dir.strip_prefix(&self.root) else {
+            return (Vec::new(), Vec::new());
+        };
+        let names: Vec<&str> = match &self.backend {
+            Backend::Zip(archive) => archive.file_names().collect(),
+            Backend::Tar(entries) => entries.iter().map(|(name, _data)| name.as_str()).collect(),
+            Backend::GitTree(_repo, tracked) => {
+                tracked.iter().map(|(name, _blob)| name.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+        let mut folders = Vec::new();
+        let mut files = Vec::new();
+        for name in names {
+            let entry = Path::new(name);
+            if !entry
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                continue;
+            }
+            let Ok(relative) = entry.strip_prefix(inside) else {
+                continue;
+            };
+            let mut components = relative.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            if components.next().is_some() || name.ends_with('/') {
+                folders.push(dir.join(first));
+            } else {
+                files.push(dir.join(first));
+            }
+        }
+        folders.sort();
+        folders.dedup();
+
+        (folders, files)
+    }
+
+    /// Reads a file of the archive or revision by its listed path, from
+    /// memory for a tarball and from the object store for a revision.
+    fn read_archive_entry(&mut self, path: &Path) -> Result<Vec<u8>> {
+        let name = path
+            .strip_prefix(&self.root)
+            .map_err(|_err| MystoreError::OutsideRoot(path.to_path_buf()))?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        match &mut self.backend {
+            Backend::GitTree(repo, tracked) => {
+                let blob = tracked
+                    .iter()
+                    .find(|(tracked, _blob)| *tracked == name)
+                    .map(|(_name, blob)| *blob)
+                    .ok_or(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is not tracked", name),
+                    ))?;
+                Ok(repo.find_blob(blob)?.content().to_vec())
+            }
+            Backend::Tar(entries) => entries
+                .iter()
+                .find(|(entry, _data)| *entry == name)
+                .map(|(_name, data)| data.clone())
+                .ok_or(MystoreError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not in the archive", name),
+                ))),
+            Backend::Zip(archive) => {
+                let mut data = Vec::new();
+                archive
+                    .by_name(&name)
+                    .map_err(io::Error::from)?
+                    .read_to_end(&mut data)?;
+                Ok(data)
+            }
+            _ => Err(MystoreError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No archive is open",
+            ))),
+        }
+    }
+
+    /// Archives and git revisions are browsed read-only, every change is
+    /// refused.
+    fn check_writable(&self) -> Result<()> {
+        match self.is_readonly() {
+            true => Err(MystoreError::ReadOnly(self.root.clone())),
+            false => Ok(()),
+        }
+    }
+
+    fn list_filter(&mut self, files: &[PathBuf]) {
+        let mut files: Vec<PathBuf> = files
+            .iter()
+            .filter(|path| path.is_file() || path.is_dir())
+            .cloned()
+            .collect();
+        self.retain_extensions(&mut files);
+        self.locked = files
+            .iter()
+            .filter(|path| Self::lock_path(path).exists())
+            .cloned()
+            .collect();
+        self.sizes = match self.sort_order {
+            SortOrder::Size => Self::read_sizes(&files),
+            SortOrder::Modified | SortOrder::Name => HashMap::new(),
+        };
+        // Back and Root both leave the filtered listing.
+        let (folders, files) = Self::split_folders(files);
+        (self.entities, self.modified) = Self::create_entities(
+            folders,
+            files,
+            self.action_rows(false),
+            self.sort_order,
+            self.action_placement,
+            &self.sizes,
+            self.parallel_read,
+        );
+        self.set_selected(None);
+    }
+
+    /// The longest path all of `paths` start with, none for no paths.
+    fn common_prefix(paths: &[PathBuf]) -> Option<PathBuf> {
+        let (first, rest) = paths.split_first()?;
+        let mut prefix: Vec<Component> = first.components().collect();
+        for path in rest {
+            let common = prefix
+                .iter()
+                .zip(path.components())
+                .take_while(|(a, b)| **a == *b)
+                .count();
+            prefix.truncate(common);
+        }
+
+        Some(prefix.iter().collect())
+    }
+
+    fn is_root(&self, dir: &Path) -> bool {
+        self.roots.iter().any(|root| root == dir)
+    }
+
+    /// The folders whose entries make up the listing of `dir`.
+    fn get_listed_dirs(&self, dir: &Path) -> Vec<PathBuf> {
+        if self.is_root(dir) {
+            self.roots.clone()
+        } else {
+            vec![dir.to_path_buf()]
+        }
+    }
+
+    /// The only place the selection is changed: it is kept either `None` or
+    /// a valid index into the listed entities.
+    fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected.filter(|id| *id < self.entities.len());
+    }
+
+    /// Drops the files outside of the extension filter, folders stay.
+    fn retain_extensions(&self, files: &mut Vec<PathBuf>) {
+        if let Some(extensions) = &self.extensions {
+            files.retain(|path| !path.is_file() || Self::has_extension(path, extensions));
+        }
+    }
+
+    /// Drops the entries named with a leading dot unless they are shown.
+    fn retain_visible(&self, paths: &mut Vec<PathBuf>) {
+        if !self.show_hidden {
+            paths.retain(|path| {
+                !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            });
+        }
+    }
+}
+
+impl FileManager {
+    pub fn new(root: &str) -> Result<Self> {
+        let mut manager = Self::with_root(root);
+        manager.goto_dir(PathBuf::from(root))?;
+
+        Ok(manager)
+    }
+
+    /// Reads the folders a page at a time, so that a large one is listed
+    /// without waiting for all of it. The next page is added to the listing
+    /// with `load_more_entities`.
+    pub fn new_incremental(root: &str) -> Result<Self> {
+        let mut manager = Self::with_root(root);
+        manager.incremental = true;
+        manager.goto_dir(PathBuf::from(root))?;
+
+        Ok(manager)
+    }
+
+    /// Manages the files in the home folder of a remote host over SFTP.
+    /// `key` is the private key file to log in with, the SSH agent is
+    /// asked if it is empty. The host must be in `~/.ssh/known_hosts`.
+    pub fn new_from_ssh(config: SshConfig, key: &str) -> Result<Self> {
+        let sftp = Self::connect_sftp(&config, key)?;
+        let home = sftp.realpath(Path::new(".")).map_err(io::Error::from)?;
+        let root = home.to_str().ok_or(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Remote folder path is not valid UTF-8",
+        ))?;
+        let mut manager = Self::with_root(root);
+        manager.backend = Backend::Sftp(sftp);
+        manager.goto_dir(home)?;
+
+        Ok(manager)
+    }
+
+    /// Manages the objects under a key prefix of an S3 bucket as if the
+    /// prefix were the root folder, with `/` separating folders. The root
+    /// is shown as `s3://bucket/prefix`.
+    pub fn new_from_s3(config: S3Config) -> Result<Self> {
+        let s3 = S3Store::connect(&config)?;
+        let root = s3.get_root();
+        let mut manager = Self::with_root(&root);
+        manager.backend = Backend::S3(s3);
+        manager.goto_dir(PathBuf::from(root))?;
+
+        Ok(manager)
+    }
+
+    /// Browses the files of a ZIP archive without extracting it. Nothing
+    /// can be created, renamed, deleted or locked.
+    pub fn new_readonly_from_zip(path: &str) -> Result<Self> {
+        let archive = ZipArchive::new(File::open(path)?).map_err(io::Error::from)?;
+        let mut manager = Self::with_root(path);
+        manager.backend = Backend::Zip(archive);
+        manager.goto_dir(PathBuf::from(path))?;
+
+        Ok(manager)
+    }
+
+    /// Browses the files of a tarball, gzip-compressed or not, read-only like
+    /// a ZIP archive. The archive is unpacked into memory, so it is refused
+    /// once its files add up to more than `MAX_TAR_SIZE`.
+    pub fn new_readonly_from_tar(path: &str) -> Result<Self> {
+        let entries = Self::read_tar(Path::new(path))?;
+        let mut manager = Self::with_root(path);
+        manager.backend = Backend::Tar(entries);
+        manager.goto_dir(PathBuf::from(path))?;
+
+        Ok(manager)
+    }
+
+    /// The folders and regular files of a tarball with their data, links
+    /// and other special entries are left out. Leading `./` is dropped from
+    /// the names.
+    fn read_tar(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut file = File::open(path)?;
+        let mut magic = [0; 2];
+        let is_gzip = file.read(&mut magic)? == 2 && magic == [0x1f, 0x8b];
+        file.rewind()?;
+        let reader: Box<dyn Read> = match is_gzip {
+            true => Box::new(GzDecoder::new(file)),
+            false => Box::new(file),
+        };
+        let mut archive = tar::Archive::new(reader);
+        let mut entries = Vec::new();
+        let mut total: u64 = 0;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let kind = entry.header().entry_type();
+            if !kind.is_file() && !kind.is_dir() {
+                continue;
+            }
+            let name = entry
+                .path()?
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if name.is_empty() {
+                continue;
+            }
+            if kind.is_dir() {
+                entries.push((format!("{}/", name), Vec::new()));
+                continue;
+            }
+            total = total.saturating_add(entry.size());
+            if total > MAX_TAR_SIZE {
+                return Err(MystoreError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} unpacks to more than {}",
+                        path.display(),
+                        format_size(MAX_TAR_SIZE)
+                    ),
+                )));
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            entries.push((name, data));
+        }
+
+        Ok(entries)
+    }
+
+    /// Browses the files git tracks at HEAD, see `new_from_git_revision`.
+    pub fn new_from_git_repo(repo_path: &str) -> Result<Self> {
+        Self::new_from_git_revision(repo_path, "HEAD")
+    }
+
+    /// Browses the files git tracks at `revision`, a branch, tag, commit or
+    /// anything else `git rev-parse` takes. Untracked files are not listed
+    /// and files are read from the object store, not the working tree.
+    /// Nothing can be changed.
+    pub fn new_from_git_revision(repo_path: &str, revision: &str) -> Result<Self> {
+        let repo = Repository::open(repo_path)?;
+        let mut tracked = Vec::new();
+        {
+            let tree = repo.revparse_single(revision)?.peel_to_tree()?;
+            tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                // Symbolic links are blobs too, but not of a regular file mode.
+                let is_file = matches!(entry.filemode(), 0o100644 | 0o100755);
+                if let (Some(ObjectType::Blob), true, Some(name)) =
+                    (entry.kind(), is_file, entry.name())
+                {
+                    tracked.push((format!("{}{}", dir, name), entry.id()));
+                }
+                TreeWalkResult::Ok
+            })?;
+        }
+        let mut manager = Self::with_root(repo_path);
+        manager.backend = Backend::GitTree(repo, tracked);
+        manager.goto_dir(PathBuf::from(repo_path))?;
+
+        Ok(manager)
+    }
+
+    fn with_root(root: &str) -> Self {
+        Self {
+            current: PathBuf::from(root),
+            root: PathBuf::from(root),
+            roots: vec![PathBuf::from(root)],
+            entities: Vec::new(),
+            selected: Option::default(),
+            created_entities: Vec::new(),
+            session_start: Utc::now(),
+            locked: HashSet::new(),
+            session_locks: Vec::new(),
+            templates_dir: Self::default_templates_dir(),
+            filter: None,
+            selected_set: HashSet::new(),
+            page: 0,
+            page_size: PAGE_SIZE,
+            page_count: 1,
+            sort_order: SortOrder::default(),
+            action_placement: ActionPlacement::default(),
+            wrap_navigation: true,
+            show_hidden: true,
+            time_listings: false,
+            listing_time: None,
+            parallel_read: true,
+            incremental: false,
+            pending_iter: None,
+            loaded: Vec::new(),
+            sizes: HashMap::new(),
+            modified: HashMap::new(),
+            time_formats: TimeFormats::default(),
+            last_hash: None,
+            tree_changed: None,
+            hash_unreadable: 0,
+            hasher: BackgroundHasher::new(),
+            extensions: None,
+            dir_cache: HashMap::new(),
+            dir_cache_order: VecDeque::new(),
+            backend: Backend::Local,
+            custom_actions: Vec::new(),
+            key: None,
+            last_cleanup: LockCleanup::default(),
+            applied_renames: Vec::new(),
+        }
+    }
+
+    /// Starts in a new temporary folder, removed once the caller drops it.
+    pub fn new_scratch() -> Result<(Self, TempDir)> {
+        let dir = TempDir::new()?;
+        let root = dir.path().to_str().ok_or(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Temporary folder path is not valid UTF-8",
+        ))?;
+        let manager = Self::new(root)?;
+
+        Ok((manager, dir))
+    }
+
+    /// Lists several folders side by side as if they were one root. The
+    /// first one is the root that typed paths and templates refer to.
+    pub fn new_multi_root(roots: &[&str]) -> Result<Self> {
+        let (first, _rest) = roots.split_first().ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "At least one root folder is expected",
+        ))?;
+        let mut manager = Self::with_root(first);
+        manager.roots = roots.iter().map(PathBuf::from).collect();
+        manager.goto_dir(PathBuf::from(first))?;
+
+        Ok(manager)
+    }
+
+    /// Lists the files and folders matching a glob pattern, rooted at the
+    /// deepest folder holding all of them.
+    pub fn new_from_glob(pattern: &str) -> Result<Self> {
+        let paths = glob::glob(pattern)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        // Unreadable folders are skipped, like in a shell.
+        let matches: Vec<PathBuf> = paths.filter_map(|path| path.ok()).collect();
+        let root = Self::deepest_folder(&matches).ok_or(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Nothing matches {}", pattern),
+        ))?;
+        let mut manager = Self::new(Self::path_str(&root)?)?;
+        manager.set_filter(String::from(pattern), matches);
+
+        Ok(manager)
+    }
+
+    /// Lists the files and folders of a history file, one path per line,
+    /// rooted at the deepest folder holding all of them. Paths that no
+    /// longer exist are left out, so the list of files created in earlier
+    /// sessions survives their removal.
+    pub fn new_from_clipboard_history(history: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for line in std::fs::read_to_string(history)?.lines() {
+            let path = PathBuf::from(line.trim());
+            if !line.trim().is_empty() && path.exists() && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        let root = Self::deepest_folder(&paths).ok_or(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No path in {} exists", history.display()),
+        ))?;
+        let mut manager = Self::new(Self::path_str(&root)?)?;
+        manager.set_filter(String::from("history"), paths);
+
+        Ok(manager)
+    }
+
+    /// The deepest folder holding all of `paths`, none for no paths.
+    fn deepest_folder(paths: &[PathBuf]) -> Option<PathBuf> {
+        let mut root = Self::common_prefix(paths)?;
+        if !root.is_dir() {
+            root = root.parent().map_or(PathBuf::new(), PathBuf::from);
+        }
+        if root.as_os_str().is_empty() {
+            root = PathBuf::from(".");
+        }
+
+        Some(root)
+    }
+
+    fn path_str(path: &Path) -> io::Result<&str> {
+        path.to_str().ok_or(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Folder path is not valid UTF-8",
+        ))
+    }
+
+    /// Starts over in another root folder.
+    pub fn set_root(&mut self, root: &str) -> Result<()> {
+        self.clear_cache();
+        self.selected_set.clear();
+        let root = PathBuf::from(root);
+        self.root = root.clone();
+        self.roots = vec![root.clone()];
+        self.goto_dir(root)
+    }
+
+    pub fn get_roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn is_multi_root(&self) -> bool {
+        self.roots.len() > 1
+    }
+
+    /// The root holding `path`, the innermost one if roots are nested.
+    pub fn get_source_root(&self, path: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.dir_cache.clear();
+        self.dir_cache_order.clear();
+    }
+
+    /// The folders whose entries are cached, least recently used first.
+    pub fn get_cached_dirs(&self) -> Vec<&Path> {
+        self.dir_cache_order.iter().map(PathBuf::as_path).collect()
+    }
+
+    /// The files created, copied in or renamed in this session.
+    pub fn get_created_files(&self) -> Vec<&Path> {
+        self.created_entities
+            .iter()
+            .filter_map(|entity| match entity {
+                ManagerEntity::TextFile(path) => Some(path.as_path()),
+                ManagerEntity::Folder(_path) => None,
+                ManagerEntity::Action(_act) => None,
+            })
+            .collect()
+    }
+
+    pub fn get_session_start(&self) -> DateTime<Utc> {
+        self.session_start
+    }
+
+    /// Commits the files created in this session on top of HEAD in the git
+    /// repository holding the root, see `git::commit_paths`. The commit is
+    /// authored at the start of the session, by the user git is configured
+    /// with.
+    pub fn commit_changes(&self, message: &str) -> Result<Oid> {
+        let files = self.get_created_files();
+        if files.is_empty() {
+            return Err(MystoreError::Git(String::from(
+                "No files were created in this session",
+            )));
+        }
+        let repo = Repository::discover(&self.root)?;
+        let committer = repo.signature()?;
+        let author = Signature::new(
+            committer.name().unwrap_or_default(),
+            committer.email().unwrap_or_default(),
+            &Time::new(self.session_start.timestamp(), 0),
+        )?;
+
+        git::commit_paths(&repo, &files, message, &author)?.ok_or(MystoreError::Git(String::from(
+            "The files of this session are already committed",
+        )))
+    }
+
+    pub fn is_remote(&self) -> bool {
+        matches!(self.backend, Backend::Sftp(_) | Backend::S3(_))
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        matches!(
+            self.backend,
+            Backend::Zip(_) | Backend::Tar(_) | Backend::GitTree(..)
+        )
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn get_current(&self) -> &Path {
+        &self.current
+    }
+
+    /// Lists only `files`, wherever they are, until the next folder change.
+    pub fn set_filter(&mut self, label: String, files: Vec<PathBuf>) {
+        self.list_filter(&files);
+        self.filter = Some((label, files));
+    }
+
+    pub fn get_filter_label(&self) -> Option<&str> {
+        self.filter.as_ref().map(|(label, _files)| label.as_str())
+    }
+
+    pub fn set_page_size(&mut self, page_size: usize) -> Result<()> {
+        self.page_size = page_size.max(1);
+        self.page = 0;
+        self.loaded.clear();
+        self.refresh()
+    }
+
+    /// Shows only files with one of the comma separated extensions, like
+    /// `md, .txt`, until the next folder change. No extensions show all.
+    pub fn set_extension_filter(&mut self, extensions: &str) -> Result<()> {
+        let extensions: Vec<String> = extensions
+            .split(',')
+            .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .collect();
+        self.extensions = if extensions.is_empty() {
+            None
+        } else {
+            Some(extensions)
+        };
+        self.refresh()
+    }
+
+    pub fn get_extension_filter(&self) -> Option<&[String]> {
+        self.extensions.as_deref()
+    }
+
+    /// Whether the extension of `path` is one of the lowercase `extensions`,
+    /// ignoring case.
+    pub fn has_extension(path: &Path, extensions: &[String]) -> bool {
+        path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| extensions.contains(&extension))
+    }
+
+    /// The current page and the number of pages, counting from one.
+    pub fn get_page(&self) -> (usize, usize) {
+        (self.page + 1, self.page_count)
+    }
+
+    pub fn next_page(&mut self) -> Result<()> {
+        if self.page + 1 < self.page_count {
+            self.page += 1;
+            self.refresh()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn prev_page(&mut self) -> Result<()> {
+        if self.page > 0 {
+            self.page -= 1;
+            self.refresh()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_sort_order(&mut self, order: SortOrder) -> Result<()> {
+        self.sort_order = order;
+        self.refresh()
+    }
+
+    pub fn get_sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    pub fn set_action_placement(&mut self, placement: ActionPlacement) -> Result<()> {
+        self.action_placement = placement;
+        self.refresh()
+    }
+
+    pub fn get_action_placement(&self) -> ActionPlacement {
+        self.action_placement
+    }
+
+    /// The size of a listed file, known only while sorting by size.
+    pub fn get_file_size(&self, path: &Path) -> Option<u64> {
+        self.sizes.get(path).copied()
+    }
+
+    /// The modification time of a listed file, known while sorted by it.
+    pub fn get_modified(&self, path: &Path) -> Option<SystemTime> {
+        self.modified.get(path).copied()
+    }
+
+    pub fn get_time_formats(&self) -> &TimeFormats {
+        &self.time_formats
+    }
+
+    pub fn set_key(&mut self, key: SessionKey) {
+        self.key = Some(key);
+    }
+
+    pub fn set_time_formats(&mut self, time_formats: TimeFormats) {
+        self.time_formats = time_formats;
+    }
+
+    /// Hashes the names and contents of every file under the current
+    /// folder with SHA-256 into a single Merkle root as hex. Entries that
+    /// cannot be read are left out. The walk runs on the calling thread,
+    /// refreshes hash on a worker thread instead.
+    pub fn hash_tree(&self) -> io::Result<String> {
+        self.check_hashable()?;
+        let dirs = self.get_listed_dirs(&self.current);
+        Ok(tree_hash::hash_dirs(&dirs, &AtomicBool::new(false)).digest)
+    }
+
+    fn check_hashable(&self) -> io::Result<()> {
+        if let Backend::GitTree(..) = self.backend {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Git revisions are not hashed",
+            ));
+        }
+        if self.is_remote() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Remote folders are not hashed",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Hashes the tree of the current folder in the background, if it can
+    /// be hashed.
+    fn start_tree_hash(&mut self) {
+        if self.check_hashable().is_ok() {
+            let dirs = self.get_listed_dirs(&self.current);
+            self.hasher.start(self.current.clone(), dirs);
+        }
+    }
+
+    /// Takes the hash of the current folder once the worker has it,
+    /// returns whether it arrived. The hash taken on a visit is the
+    /// baseline, later ones tell whether the tree changed since.
+    pub fn poll_tree_hash(&mut self) -> bool {
+        let Some((dir, hash)) = self.hasher.poll() else {
+            return false;
+        };
+        if dir != self.current {
+            return false;
+        }
+        self.tree_changed = self.last_hash.as_ref().map(|last| *last != hash.digest);
+        self.last_hash = Some(hash.digest);
+        self.hash_unreadable = hash.unreadable;
+
+        true
+    }
+
+    /// Whether the tree of the current folder is being hashed.
+    pub fn is_hashing(&self) -> bool {
+        self.hasher.get_pending().is_some()
+    }
+
+    /// Entries left out of the last hash because they could not be read.
+    pub fn get_hash_unreadable(&self) -> usize {
+        self.hash_unreadable
+    }
+
+    /// Names and sizes of the listed files, the largest first. Sizes read
+    /// for sorting are reused, files without readable metadata are left out.
+    pub fn size_histogram(&self) -> Vec<(String, u64)> {
+        let mut histogram: Vec<(String, u64)> = self
+            .entities
+            .iter()
+            .filter_map(|entity| match entity {
+                ManagerEntity::TextFile(path) => {
+                    let size = match self.sizes.get(path) {
+                        Some(size) => *size,
+                        None => path.metadata().ok()?.len(),
+                    };
+                    let name = path.file_name()?.to_string_lossy().into_owned();
+                    Some((name, size))
+                }
+                _ => None,
+            })
+            .collect();
+        histogram.sort_by(|(name, size), (other_name, other_size)| {
+            other_size.cmp(size).then_with(|| name.cmp(other_name))
+        });
+
+        histogram
+    }
+
+    /// Counts the listed files by when they were modified, see `AGE_BUCKETS`.
+    pub fn age_histogram(&self) -> Vec<(String, usize)> {
+        self.age_histogram_at(Local::now())
+    }
+
+    /// Counts the listed files modified on the day of `now`, earlier in its
+    /// week from Monday, earlier in its month and before. Files without a
+    /// readable modification time count as older.
+    pub fn age_histogram_at(&self, now: DateTime<Local>) -> Vec<(String, usize)> {
+        let today = now.date_naive();
+        let week_start = today - Days::new(today.weekday().num_days_from_monday().into());
+        let month_start = today.with_day(1).unwrap_or(today);
+        let mut counts = [0; AGE_BUCKETS.len()];
+        for entity in &self.entities {
+            let ManagerEntity::TextFile(path) = entity else {
+                continue;
+            };
+            let modified = path
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .map(|time| DateTime::<Local>::from(time).date_naive());
+            let bucket = match modified {
+                Some(date) if date >= today => 0,
+                Some(date) if date >= week_start => 1,
+                Some(date) if date >= month_start => 2,
+                _ => 3,
+            };
+            counts[bucket] += 1;
+        }
+
+        AGE_BUCKETS
+            .iter()
+            .zip(counts)
+            .map(|(name, count)| (String::from(*name), count))
+            .collect()
+    }
+
+    pub fn get_last_hash(&self) -> Option<&str> {
+        self.last_hash.as_deref()
+    }
+
+    /// Whether the last refresh found the current folder modified, if known.
+    pub fn is_tree_changed(&self) -> Option<bool> {
+        self.tree_changed
+    }
+
+    pub fn get_entities_ref(&self) -> &Vec<ManagerEntity> {
+        &self.entities
+    }
+
+    /// The folders and files of the folder holding the current one, sorted
+    /// and filtered like the listing but without action rows, while the
+    /// current folder stays listed. A root has no parent in the store and
+    /// gives none. Only local folders are read.
+    pub fn get_parent_entities(&self) -> io::Result<Vec<ManagerEntity>> {
+        if self.is_remote() || self.is_readonly() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Only local folders are read",
+            ));
+        }
+        let Some(parent) = self
+            .current
+            .parent()
+            .filter(|_| !self.is_root(&self.current))
+        else {
+            return Ok(Vec::new());
+        };
+        let (mut files, _locked) = Self::split_locks(Self::open_dir(&parent)?);
+        self.retain_extensions(&mut files);
+        self.retain_visible(&mut files);
+        let sizes = match self.sort_order {
+            SortOrder::Size => Self::read_sizes(&files),
+            SortOrder::Modified | SortOrder::Name => HashMap::new(),
+        };
+        let (folders, files) = Self::split_folders(files);
+        let (entities, _modified) = Self::create_entities(
+            folders,
+            files,
+            Vec::new(),
+            self.sort_order,
+            self.action_placement,
+            &sizes,
+            self.parallel_read,
+        );
+
+        Ok(entities)
+    }
+
+    pub fn get_selected_id(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn get_selected_entity(&self) -> Option<&ManagerEntity> {
+        self.selected.and_then(|id| self.entities.get(id))
+    }
+
+    pub fn get_selected_entity_name(&self) -> Option<&str> {
+        self.get_selected_entity().and_then(|entity| match entity {
+            ManagerEntity::TextFile(path) => path.file_name().and_then(|name| name.to_str()),
+            ManagerEntity::Folder(path) => path.file_name().and_then(|name| name.to_str()),
+            ManagerEntity::Action(_act) => None,
+        })
+    }
+
+    pub fn set_templates_dir(&mut self, dir: PathBuf) {
+        self.templates_dir = Some(dir);
+    }
+
+    /// Formats the path of the selected entity, relative to the root if asked.
+    pub fn get_selected_path(&self, relative: bool) -> Option<String> {
+        match self.get_selected_entity()? {
+            ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => {
+                Some(Self::format_path(path, &self.root, relative))
+            }
+            ManagerEntity::Action(_act) => None,
+        }
+    }
+
+    /// Resolves a path typed by the user, relative to the root or absolute,
+    /// with `~` and `..` resolved first. The result must stay within the root
+    /// and is joined to the root as given, like the listed paths.
+    pub fn resolve_path(&self, input: &str) -> Result<PathBuf> {
+        self.resolve_within_root(&Self::expand_home(input.trim()))
+    }
+
+    /// Resolves the name of a file to create, typed relative to the current
+    /// folder, like `resolve_path`.
+    pub fn resolve_in_current(&self, input: &str) -> Result<PathBuf> {
+        let current = self
+            .current
+            .strip_prefix(&self.root)
+            .unwrap_or(Path::new(""));
+        self.resolve_within_root(&current.join(Self::expand_home(input.trim())))
+    }
+
+    fn resolve_within_root(&self, path: &Path) -> Result<PathBuf> {
+        let root = Self::normalize(&std::path::absolute(&self.root)?);
+        let path = Self::normalize(&root.join(path));
+        let resolved = match path.strip_prefix(&root) {
+            Ok(relative) => self.root.join(relative),
+            Err(_err) => return Err(MystoreError::OutsideRoot(path)),
+        };
+        if self.is_remote() || self.is_readonly() {
+            return Ok(resolved);
+        }
+        // `..` was resolved before any link was followed, so a link inside
+        // the root may still lead out of it. What exists of the path is
+        // followed to where it really is.
+        let existing = resolved.ancestors().find(|ancestor| ancestor.exists());
+        let real = existing.map(Path::canonicalize).transpose()?;
+        let real_root = self.root.canonicalize()?;
+        match real {
+            Some(real) if !real.starts_with(&real_root) => Err(MystoreError::OutsideRoot(resolved)),
+            _ => Ok(resolved),
+        }
+    }
+
+    /// Opens the folder at `input`, or the parent folder of a file with the
+    /// file selected.
+    pub fn go_to(&mut self, input: &str) -> Result<()> {
+        let path = self.resolve_path(input)?;
+        let (is_dir, is_file) = self.kind_of(&path)?;
+        if is_dir {
+            self.goto_dir(path)
+        } else if is_file {
+            self.reveal(&path)
+        } else {
+            Err(MystoreError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            )))
+        }
+    }
+
+    /// Opens the folder holding the file at `path`, with the file selected.
+    pub fn reveal(&mut self, path: &Path) -> Result<()> {
+        if self.get_source_root(path).is_none() {
+            return Err(MystoreError::OutsideRoot(path.to_path_buf()));
+        }
+        let parent = path.parent().map_or(self.root.clone(), PathBuf::from);
+        self.goto_dir(parent)?;
+        // A large folder is listed a page at a time, the file may be on any.
+        let entity = ManagerEntity::TextFile(path.to_path_buf());
+        loop {
+            if let Some(position) = self.entities.iter().position(|elem| *elem == entity) {
+                self.set_selected(Some(position));
+                return Ok(());
+            }
+            if self.incremental {
+                if !self.load_more_entities()? {
+                    break;
+                }
+            } else if self.page + 1 < self.page_count {
+                self.page += 1;
+                self.list_dir(self.current.clone())?;
+            } else {
+                break;
+            }
+        }
+        // The file is not listed, e.g. hidden, the first page is shown.
+        if self.page > 0 {
+            self.page = 0;
+            self.list_dir(self.current.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Completes the last segment of `input` with the longest prefix shared
+    /// by the matching entries, adding `/` after a single folder. Input that
+    /// does not resolve within the root is returned as is.
+    pub fn complete_path(&self, input: &str) -> String {
+        let (dir, partial) = match input.rfind('/') {
+            Some(id) => input.split_at(id + 1),
+            None => ("", input),
+        };
+        let names: Vec<(String, bool)> = match self.resolve_path(dir) {
+            Ok(dir) => self
+                .read_entries(&dir)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(path, is_dir, _size)| {
+                    let name = path.file_name()?.to_str()?.to_owned();
+                    name.starts_with(partial).then_some((name, is_dir))
+                })
+                .collect(),
+            Err(_err) => Vec::new(),
+        };
+        match names.as_slice() {
+            [] => String::from(input),
+            [(name, true)] => format!("{}{}/", dir, name),
+            [(name, false)] => format!("{}{}", dir, name),
+            [(first, _), rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, (name, _)| {
+                    let len = common
+                        .char_indices()
+                        .zip(name.chars())
+                        .find(|((_id, a), b)| a != b)
+                        .map_or(common.len().min(name.len()), |((id, _a), _b)| id);
+                    &common[..len]
+                });
+                format!("{}{}", dir, common)
+            }
+        }
+    }
+
+    /// Formats `path` lossily, either absolute or relative to `root`.
+    pub fn format_path(path: &Path, root: &Path, relative: bool) -> String {
+        let formatted = if relative {
+            path.strip_prefix(root)
+                .map_or(path.to_path_buf(), PathBuf::from)
+        } else {
+            std::path::absolute(path).unwrap_or(path.to_path_buf())
+        };
+        formatted.to_string_lossy().into_owned()
+    }
+
+    /// Turns user input into a single file name: surrounding whitespace is
+    /// dropped, separators and null bytes become `_` and the name is cut
+    /// to 255 bytes. Fails if nothing usable is left.
+    pub fn sanitize_filename(name: &str) -> Result<String> {
+        let mut sanitized: String = name
+            .trim()
+            .chars()
+            .map(|ch| match ch {
+                '/' | '\\' | '\0' => '_',
+                ch => ch,
+            })
+            .collect();
+        if sanitized.len() > MAX_NAME_LEN {
+            let mut end = MAX_NAME_LEN;
+            while !sanitized.is_char_boundary(end) {
+                end -= 1;
+            }
+            sanitized.truncate(end);
+            sanitized.truncate(sanitized.trim_end().len());
+        }
+        if sanitized.is_empty() || sanitized == "." || sanitized == ".." {
+            return Err(MystoreError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid file name: {:?}", name),
+            )));
+        }
+
+        Ok(sanitized)
+    }
+
+    /// Compares names in natural order, so that `note-2` sorts before
+    /// `note-10`.
+    ///
+    /// Runs of ASCII digits compare by value; with equal values, the run
+    /// with fewer leading zeros comes first (`1` before `01`). Everything
+    /// else compares char by char and case-sensitively, which includes
+    /// non-ASCII digits, since their numeric value is not always meaningful
+    /// in a name.
+    pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+        fn split_digits(text: &str) -> (&str, &str) {
+            let end = text
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(text.len());
+            text.split_at(end)
+        }
+
+        let (mut a, mut b) = (a, b);
+        loop {
+            match (a.chars().next(), b.chars().next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                    let (number_a, rest_a) = split_digits(a);
+                    let (number_b, rest_b) = split_digits(b);
+                    let value_a = number_a.trim_start_matches('0');
+                    let value_b = number_b.trim_start_matches('0');
+                    // Comparing lengths first avoids parsing into a bounded integer.
+                    let order = value_a
+                        .len()
+                        .cmp(&value_b.len())
+                        .then_with(|| value_a.cmp(value_b))
+                        .then_with(|| number_a.len().cmp(&number_b.len()));
+                    if order != Ordering::Equal {
+                        return order;
+                    }
+                    (a, b) = (rest_a, rest_b);
+                }
+                (Some(x), Some(y)) => {
+                    if x != y {
+                        return x.cmp(&y);
+                    }
+                    (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+                }
+            }
+        }
+    }
+
+    /// Lists the template names, an absent templates folder has none.
+    pub fn get_templates(&self) -> Result<Vec<String>> {
+        let dir = match &self.templates_dir {
+            Some(dir) if dir.is_dir() => dir,
+            _ => return Ok(Vec::new()),
+        };
+        let mut templates: Vec<String> = Self::open_dir(dir)?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .filter_map(|path| path.file_name()?.to_owned().into_string().ok())
+            .collect();
+        templates.sort_by(|a, b| Self::natural_cmp(a, b));
+
+        Ok(templates)
+    }
+
+    /// Marks or unmarks the selected file for batch operations.
+    pub fn toggle_mark(&mut self) {
+        if let Some(ManagerEntity::TextFile(path)) = self.get_selected_entity().cloned() {
+            if !self.selected_set.remove(&path) {
+                self.selected_set.insert(path);
+            }
+        }
+    }
+
+    pub fn is_marked(&self, path: &Path) -> bool {
+        self.selected_set.contains(path)
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.selected_set.clear();
+    }
+
+    pub fn get_marked_ref(&self) -> &HashSet<PathBuf> {
+        &self.selected_set
+    }
+
+    /// The marked files in path order.
+    pub fn get_marked_sorted(&self) -> Vec<PathBuf> {
+        let mut marked: Vec<PathBuf> = self.selected_set.iter().cloned().collect();
+        marked.sort();
+        marked
+    }
+
+    pub fn is_locked(&self, path: &Path) -> bool {
+        self.locked.contains(path)
+    }
+
+    /// Whether `path` is locked by another session, not by this one.
+    pub fn is_locked_elsewhere(&self, path: &Path) -> bool {
+        self.is_locked(path) && !self.session_locks.iter().any(|own| own == path)
+    }
+
+    /// Whether moving past either end of the list continues at the other.
+    pub fn set_wrap_navigation(&mut self, wrap: bool) {
+        self.wrap_navigation = wrap;
+    }
+
+    pub fn get_wrap_navigation(&self) -> bool {
+        self.wrap_navigation
+    }
+
+    /// Whether files and folders named with a leading dot are listed.
+    pub fn set_show_hidden(&mut self, show: bool) -> Result<()> {
+        self.show_hidden = show;
+        self.refresh()
+    }
+
+    pub fn get_show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+
+    /// Whether folder listings are timed, for the debug overlay.
+    pub fn set_time_listings(&mut self, time: bool) {
+        self.time_listings = time;
+        self.listing_time = None;
+    }
+
+    /// How long the latest listing took, once, if listings are timed.
+    pub fn take_listing_time(&mut self) -> Option<Duration> {
+        self.listing_time.take()
+    }
+
+    /// Whether the modification times files are sorted by are read in
+    /// parallel, which is faster for large folders. The order is the same.
+    pub fn set_parallel_read(&mut self, parallel: bool) {
+        self.parallel_read = parallel;
+    }
+
+    pub fn get_parallel_read(&self) -> bool {
+        self.parallel_read
+    }
+
+    pub fn next(&mut self) {
+        if !self.entities.is_empty() {
+            let last = self.entities.len() - 1;
+            self.set_selected(match self.selected {
+                Some(value) if value >= last && !self.wrap_navigation => Some(last),
+                Some(value) if value >= last => Some(0),
+                Some(value) => Some(value + 1),
+                None => Some(0),
+            });
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.entities.is_empty() {
+            let last = self.entities.len() - 1;
+            self.set_selected(match self.selected {
+                Some(0) if !self.wrap_navigation => Some(0),
+                Some(0) => Some(last),
+                Some(value) => Some((value - 1).min(last)),
+                None => Some(last),
+            });
+        }
+    }
+
+    /// Whether the folder read incrementally has entries left to load.
+    pub fn has_pending_entities(&self) -> bool {
+        self.pending_iter.is_some()
+    }
+
+    /// Reads the next page of the folder read incrementally into the
+    /// listing, keeping the selection. Tells whether any entry was added.
+    pub fn load_more_entities(&mut self) -> Result<bool> {
+        let Some(items) = self.pending_iter.as_mut() else {
+            return Ok(false);
+        };
+        let batch = Self::read_batch(items, self.page_size);
+        if batch.len() < self.page_size {
+            self.pending_iter = None;
+        }
+        if batch.is_empty() {
+            return Ok(false);
+        }
+        self.loaded.extend(batch);
+        let selected_entity = self.get_selected_entity().cloned();
+        let dir = self.current.clone();
+        let is_root = self.is_root(&dir);
+        self.list_entries(dir, self.loaded.clone(), is_root);
+        let position = selected_entity
+            .and_then(|entity| self.entities.iter().position(|elem| *elem == entity));
+        self.set_selected(position);
+
+        Ok(true)
+    }
+
+    pub fn first(&mut self) {
+        self.set_selected(Some(0));
+    }
+
+    pub fn last(&mut self) {
+        self.set_selected(self.entities.len().checked_sub(1));
+    }
+
+    /// Turns to the next page, or past the last one wraps around to the
+    /// first page or stops at the last entry.
+    pub fn page_down(&mut self) -> Result<()> {
+        if self.page + 1 < self.page_count {
+            self.next_page()
+        } else if self.wrap_navigation && self.page_count > 1 {
+            self.page = 0;
+            self.refresh()?;
+            self.first();
+            Ok(())
+        } else {
+            self.last();
+            Ok(())
+        }
+    }
+
+    /// Turns to the previous page, or before the first one wraps around to
+    /// the last page or stops at the first entry.
+    pub fn page_up(&mut self) -> Result<()> {
+        if self.page > 0 {
+            self.prev_page()
+        } else if self.wrap_navigation && self.page_count > 1 {
+            self.page = self.page_count - 1;
+            self.refresh()?;
+            self.last();
+            Ok(())
+        } else {
+            self.first();
+            Ok(())
+        }
+    }
+
+    pub fn select(&mut self, id: usize) -> bool {
+        self.set_selected(Some(id));
+        self.selected.is_some()
+    }
+
+    pub fn refresh(&mut self) -> Result<()> {
+        let selected = self.selected;
+        let selected_entity = self.get_selected_entity().cloned();
+        match self.filter.clone() {
+            Some((_label, files)) => self.list_filter(&files),
+            None => self.list_dir(self.current.clone())?,
+        }
+
+        // Follow the selected entity to its new position. Once it is gone,
+        // e.g. deleted, the entity that took its place is selected, or the
+        // new last one if the old last was removed.
+        let position = selected_entity
+            .and_then(|entity| self.entities.iter().position(|elem| *elem == entity));
+        let last = self.entities.len().checked_sub(1);
+        self.set_selected(position.or(selected.and_then(|id| last.map(|last| id.min(last)))));
+
+        // Restarting a baseline still being taken would hash the refreshed
+        // tree instead, and the change would go unseen.
+        let is_baseline = self.last_hash.is_none() && self.hasher.get_pending().is_some();
+        if !is_baseline {
+            self.start_tree_hash();
+        }
+
+        Ok(())
+    }
+
+    pub fn create_file(&mut self, data: Vec<u8>, file_name: Option<String>) -> Result<PathBuf> {
+        self.check_writable()?;
+        let file_name = match file_name {
+            Some(name) => Self::sanitize_filename(&name)?,
+            None => self.time_formats.file_name(&Local::now()),
+        };
+        let file_path = self.current.join(file_name);
+        self.write_new_file(&file_path, &data)?;
+
+        self.created_entities
+            .push(ManagerEntity::TextFile(file_path.clone()));
+        self.refresh()?;
+
+        Ok(file_path)
+    }
+
+    /// Writes `data` to a file that must not exist yet, an existing one is
+    /// a name collision and is left alone.
+    pub fn write_new_file(&mut self, path: &Path, data: &[u8]) -> Result<()> {
+        self.forget_parent(path);
+        self.write_new(path, data)
+    }
+
+    /// `write_new_file` without dropping the cached listing of the folder.
+    fn write_new(&self, path: &Path, data: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        if self.is_remote() {
+            if self.exists(path)? {
+                return Err(MystoreError::NameCollision(path.to_path_buf()));
+            }
+            self.store_file(path, data)?;
+        } else {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::AlreadyExists => MystoreError::NameCollision(path.to_path_buf()),
+                    _ => MystoreError::Io(err),
+                })?;
+            file.write_all(data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the files created in this session into a ZIP archive at
+    /// `path`, named by their paths under their root. With `encrypt` every
+    /// entry is AES-256 encrypted with `SessionKey::export_password`. The
+    /// archive is built in memory and written like any other file of the
+    /// store, an existing file is left alone. The listing is not refreshed.
+    pub fn export_as_zip(&self, path: &Path, encrypt: bool) -> Result<()> {
+        self.check_writable()?;
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let password = match (encrypt, &self.key) {
+            (true, Some(key)) => Some(key.export_password()),
+            (true, None) => {
+                return Err(MystoreError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No session key to encrypt the archive with",
+                )))
+            }
+            (false, _key) => None,
+        };
+        let options = match &password {
+            Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+            None => options,
+        };
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for file in self.get_created_files() {
+            let root = self.get_source_root(file).unwrap_or(Path::new(""));
+            let name = file
+                .strip_prefix(root)
+                .unwrap_or(file)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            zip.start_file(name, options).map_err(io::Error::from)?;
+            zip.write_all(&self.read_file(file)?)?;
+        }
+        let data = zip.finish().map_err(io::Error::from)?.into_inner();
+        self.write_new(path, &data)
+    }
+
+    /// Replaces the contents of a file, e.g. of a note saved again.
+    pub fn overwrite_file(&mut self, path: &Path, data: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        self.write_file(path, data)?;
+        self.refresh()
+    }
+
+    /// Creates a file from a template, replacing `{{DATE}}` and `{{TIME}}`.
+    pub fn create_file_from_template(
+        &mut self,
+        template_name: &str,
+        file_name: Option<String>,
+    ) -> Result<PathBuf> {
+        let dir = self.templates_dir.clone().ok_or(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No templates folder is configured",
+        ))?;
+        let data = std::fs::read(dir.join(template_name))?;
+        self.create_file(Self::fill_template(data), file_name)
+    }
+
+    /// Copies the files of `source`, not its subfolders, into the current
+    /// folder. Lock sidecars and the `.mystore` folder are left behind, and
+    /// locked files are not overwritten. Only files that were not there
+    /// before join the files of the session.
+    pub fn merge_dirs(
+        &mut self,
+        source: &Path,
+        resolution: ConflictResolution,
+    ) -> Result<MergeResult> {
+        self.check_writable()?;
+        if !self.is_remote() && source.canonicalize()? == self.current.canonicalize()? {
+            return Err(MystoreError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot merge a folder into itself",
+            )));
+        }
+        let mut result = MergeResult::default();
+        let mut copies = Vec::new();
+        for path in Self::open_dir(&source)? {
+            let Some(name) = path
+                .file_name()
+                .filter(|name| path.is_file() && *name != INDEX_DIR)
+            else {
+                continue;
+            };
+            let target = self.current.join(name);
+            if self.is_sidecar(source, &target)? {
+                continue;
+            }
+            let existed = self.exists(&target)?;
+            if existed {
+                if resolution == ConflictResolution::Skip {
+                    result.skipped_existing += 1;
+                    continue;
+                }
+                if self.exists(&Self::lock_path(&target))? {
+                    return Err(MystoreError::Locked(target));
+                }
+            }
+            copies.push((path, target, existed));
+        }
+
+        for (path, target, existed) in copies {
+            if self.is_remote() {
+                self.write_file(&target, &std::fs::read(&path)?)?;
+            } else {
+                self.forget_parent(&target);
+                std::fs::copy(&path, &target)?;
+            }
+            result.copied += 1;
+
+            let created = ManagerEntity::TextFile(target);
+            if !existed && !self.created_entities.contains(&created) {
+                self.created_entities.push(created);
+            }
+        }
+        self.refresh()?;
+
+        Ok(result)
+    }
+
+    /// Whether `target`, merged in from `source`, would be the lock sidecar
+    /// of a file in either folder.
+    fn is_sidecar(&self, source: &Path, target: &Path) -> io::Result<bool> {
+        let Some(locked) = target
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(LOCK_SUFFIX))
+            .filter(|locked| !locked.is_empty())
+        else {
+            return Ok(false);
+        };
+        Ok(source.join(locked).is_file() || self.exists(&self.current.join(locked))?)
+    }
+
+    /// Lists the new paths of the marked files whose names `pattern` changes.
+    /// A new name must be a plain file name in the same folder, one with a
+    /// path separator, `..` or surrounding blanks is refused.
+    pub fn preview_rename(
+        &self,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        let mut renames = Vec::new();
+        for path in self.get_marked_sorted() {
+            let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+                continue;
+            };
+            let renamed = regex.replace_all(&name, replacement);
+            if renamed == name {
+                continue;
+            }
+            let invalid = || {
+                MystoreError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid file name: {:?}", renamed),
+                ))
+            };
+            if Self::sanitize_filename(&renamed).map_err(|_err| invalid())? != renamed {
+                return Err(invalid());
+            }
+            let to = path.with_file_name(renamed.as_ref());
+            if to.parent() != path.parent() {
+                return Err(invalid());
+            }
+            renames.push((path, to));
+        }
+
+        Ok(renames)
+    }
+
+    /// Renames the marked files with a regex substitution on their names,
+    /// giving the number of files renamed. The renames done are kept for
+    /// `take_applied_renames`, also when a later one fails, so that they
+    /// can still be undone.
+    pub fn rename_batch(&mut self, pattern: &str, replacement: &str) -> Result<usize> {
+        self.applied_renames.clear();
+        self.check_writable()?;
+        let renames = self.preview_rename(pattern, replacement)?;
+        // Check everything first so that a batch is not left half renamed.
+        for (id, (from, to)) in renames.iter().enumerate() {
+            if self.exists(&Self::lock_path(from))? {
+                return Err(MystoreError::Locked(from.clone()));
+            }
+            let is_taken = renames[..id].iter().any(|(_from, other)| other == to);
+            if is_taken || self.exists(to)? {
+                return Err(MystoreError::NameCollision(to.clone()));
+            }
+        }
+        let mut result = Ok(());
+        for (from, to) in renames {
+            if let Err(err) = self.rename_file(&from, &to) {
+                result = Err(err.into());
+                break;
+            }
+            let created = ManagerEntity::TextFile(from.clone());
+            if let Some(id) = self
+                .created_entities
+                .iter()
+                .position(|elem| *elem == created)
+            {
+                self.created_entities[id] = ManagerEntity::TextFile(to.clone());
+            }
+            self.applied_renames.push((from, to));
+        }
+        if result.is_ok() {
+            self.selected_set.clear();
+        }
+        self.refresh()?;
+
+        result.map(|()| self.applied_renames.len())
+    }
+
+    /// The renames done by the last `rename_batch`, as pairs of the old and
+    /// the new path, to be recorded in the journal.
+    pub fn take_applied_renames(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        mem::take(&mut self.applied_renames)
+    }
+
+    /// Deletes the selected file if it was created in the session, giving
+    /// it back held for `restore_files`.
+    pub fn delete_selected(&mut self) -> Result<Option<HeldFile>> {
+        self.check_writable()?;
+        let held = self
+            .get_selected_entity()
+            .cloned()
+            .map(|entity| match &entity {
+                ManagerEntity::TextFile(path) => self
+                    .created_entities
+                    .iter()
+                    .position(|elem| *elem == ManagerEntity::TextFile(path.clone()))
+                    .map_or(Err(MystoreError::NotDeletable(path.clone())), |item| {
+                        // Like `delete_copy`, a locked file is left alone.
+                        if self.exists(&Self::lock_path(path))? {
+                            return Err(MystoreError::Locked(path.clone()));
+                        }
+                        let held = self.hold_file(path)?;
+                        self.remove_file(path)?;
+                        self.created_entities.remove(item);
+                        Ok(held)
+                    }),
+                ManagerEntity::Folder(_path) => Err(MystoreError::NotAFile("delete", "folder")),
+                ManagerEntity::Action(_act) => Err(MystoreError::NotAFile("delete", "action")),
+            })
+            .transpose()?;
+
+        self.refresh()?;
+
+        Ok(held)
+    }
+
+    /// Deletes a copy of a file known to have duplicates, giving it back
+    /// held for `restore_files`. Unlike `delete_selected` it is not limited
+    /// to files created in the session, the caller keeps at least one copy
+    /// and asks first.
+    pub fn delete_copy(&mut self, path: &Path) -> Result<HeldFile> {
+        self.check_writable()?;
+        if self.exists(&Self::lock_path(path))? {
+            return Err(MystoreError::Locked(path.to_path_buf()));
+        }
+        let held = self.hold_file(path)?;
+        self.remove_file(path)?;
+        self.created_entities
+            .retain(|elem| *elem != ManagerEntity::TextFile(path.to_path_buf()));
+        self.refresh()?;
+
+        Ok(held)
+    }
+
+    /// Reads the file at `path` to be given back by `restore_files` once
+    /// deleted.
+    pub fn hold_file(&self, path: &Path) -> Result<HeldFile> {
+        let modified = match self.is_remote() {
+            true => None,
+            false => path.metadata()?.modified().ok(),
+        };
+        let created = self
+            .created_entities
+            .contains(&ManagerEntity::TextFile(path.to_path_buf()));
+
+        Ok(HeldFile::new(
+            path.to_path_buf(),
+            self.read_file(path)?,
+            modified,
+            created,
+        )?)
+    }
+
+    /// Writes deleted files back with their bytes, and their modification
+    /// times when local. Nothing is written if any of the paths is taken.
+    /// Restored files are taken out of `files`, so that after a failure it
+    /// only holds the ones still deleted.
+    pub fn restore_files(&mut self, files: &mut Vec<HeldFile>) -> Result<()> {
+        self.check_writable()?;
+        for file in files.iter() {
+            if self.exists(&file.path)? {
+                return Err(MystoreError::NameCollision(file.path.clone()));
+            }
+        }
+        let result = self.write_held(files);
+        self.refresh()?;
+
+        result
+    }
+
+    fn write_held(&mut self, files: &mut Vec<HeldFile>) -> Result<()> {
+        while let Some(file) = files.last_mut() {
+            let data = file.read_contents()?;
+            if self.is_remote() {
+                self.write_file(&file.path, &data)?;
+            } else {
+                self.forget_parent(&file.path);
+                let mut restored = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&file.path)?;
+                restored.write_all(&data)?;
+                if let Some(modified) = file.modified {
+                    restored.set_modified(modified)?;
+                }
+            }
+            let created = ManagerEntity::TextFile(file.path.clone());
+            if file.created && !self.created_entities.contains(&created) {
+                self.created_entities.push(created);
+            }
+            files.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Gives the files renamed as `renames` their old names back. Nothing
+    /// is renamed if a new name is gone or an old one is taken. Undone
+    /// renames are taken out of `renames`, like in `restore_files`.
+    pub fn undo_renames(&mut self, renames: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+        self.check_writable()?;
+        for (from, to) in renames.iter() {
+            if !self.exists(to)? {
+                return Err(MystoreError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} does not exist", to.display()),
+                )));
+            }
+            if self.exists(from)? {
+                return Err(MystoreError::NameCollision(from.clone()));
+            }
+        }
+        let mut result = Ok(());
+        while let Some((from, to)) = renames.last() {
+            if let Err(err) = self.rename_file(to, from) {
+                result = Err(err.into());
+                break;
+            }
+            let renamed = ManagerEntity::TextFile(to.clone());
+            if let Some(id) = self
+                .created_entities
+                .iter()
+                .position(|elem| *elem == renamed)
+            {
+                self.created_entities[id] = ManagerEntity::TextFile(from.clone());
+            }
+            renames.pop();
+        }
+        self.refresh()?;
+
+        result
+    }
+
+    pub fn lock_file(&mut self) -> Result<()> {
+        self.check_writable()?;
+        match self.get_selected_entity().cloned() {
+            Some(ManagerEntity::TextFile(path)) => {
+                let lock = Self::lock_path(&path);
+                let owner = LockOwner::current().to_string();
+                self.create_new(&lock, owner.as_bytes())
+                    .map_err(|err| match err.kind() {
+                        io::ErrorKind::AlreadyExists => MystoreError::Locked(path.clone()),
+                        // SFTP servers do not tell why an exclusive create failed.
+                        // If the lock cannot be looked up either, the failed
+                        // create is what is reported.
+                        _ if self.is_remote() && self.exists(&lock).unwrap_or(false) => {
+                            MystoreError::Locked(path.clone())
+                        }
+                        _ => MystoreError::Io(err),
+                    })?;
+                self.session_locks.push(path);
+            }
+            Some(ManagerEntity::Folder(_path)) => {
+                return Err(MystoreError::NotAFile("lock", "folder"))
+            }
+            Some(ManagerEntity::Action(_act)) => {
+                return Err(MystoreError::NotAFile("lock", "action"))
+            }
+            None => return Ok(()),
+        }
+        self.refresh()
+    }
+
+    pub fn unlock_file(&mut self) -> Result<()> {
+        self.check_writable()?;
+        if let Some(ManagerEntity::TextFile(path)) = self.get_selected_entity().cloned() {
+            let item = self
+                .session_locks
+                .iter()
+                .position(|locked| *locked == path)
+                .ok_or(MystoreError::Locked(path.clone()))?;
+            self.remove_file(&Self::lock_path(&path))?;
+            self.session_locks.remove(item);
+            self.refresh()?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the lock sidecars left under the root, e.g. after a crash:
+    /// those of sessions that are gone and the empty ones of older versions,
+    /// which tell no session. Hidden folders are not entered. Returns the
+    /// number of locks removed, the locks kept, the folders that cannot be
+    /// read and the locks that cannot be removed are counted in
+    /// `get_last_cleanup`.
+    pub fn cleanup_locks(&mut self) -> Result<usize> {
+        self.check_writable()?;
+        let mut cleanup = LockCleanup::default();
+        let mut dirs = self.roots.clone();
+        while let Some(dir) = dirs.pop() {
+            let entries = match self.read_entries(&dir) {
+                Ok(entries) => entries,
+                Err(_err) => {
+                    cleanup.unreadable += 1;
+                    continue;
+                }
+            };
+            let files = entries.iter().map(|(path, ..)| path.clone()).collect();
+            let (_files, locked) = Self::split_locks(files);
+            for (path, is_dir, _size) in &entries {
+                if *is_dir && !locks::is_hidden(path) {
+                    dirs.push(path.clone());
+                }
+            }
+            for target in locked {
+                let lock = Self::lock_path(&target);
+                let is_empty = entries
+                    .iter()
+                    .any(|(path, _is_dir, size)| *path == lock && *size == 0);
+                let is_stale = is_empty
+                    || self
+                        .read_file(&lock)
+                        .is_ok_and(|data| locks::is_stale(&data));
+                if !is_stale {
+                    cleanup.held += 1;
+                    continue;
+                }
+                match self.remove_file(&lock) {
+                    Ok(()) => cleanup.removed += 1,
+                    Err(_err) => cleanup.failed += 1,
+                }
+            }
+        }
+        let removed = cleanup.removed;
+        self.last_cleanup = cleanup;
+        self.refresh()?;
+
+        Ok(removed)
+    }
+
+    pub fn get_last_cleanup(&self) -> &LockCleanup {
+        &self.last_cleanup
+    }
+
+    /// Leaves a filtered listing, otherwise opens the parent folder unless
+    /// the root is listed already.
+    pub fn go_back(&mut self) -> Result<()> {
+        if self.filter.is_some() {
+            return self.goto_dir(self.current.clone());
+        }
+        if !self.is_root(&self.current) {
+            if let Some(path) = self.current.parent().map(PathBuf::from) {
+                self.goto_dir(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn go_root(&mut self) -> Result<()> {
+        self.goto_dir(self.root.clone())
+    }
+
+    /// Adds an action row named `name` to every listing, which runs
+    /// `handler` on Enter. One registered again under the same name is
+    /// replaced, in its place.
+    pub fn register_action(&mut self, name: &str, handler: ActionHandler) -> Result<()> {
+        match self
+            .custom_actions
+            .iter_mut()
+            .find(|(known, _handler)| known == name)
+        {
+            Some((_name, known)) => *known = handler,
+            None => self.custom_actions.push((String::from(name), handler)),
+        }
+        self.refresh()
+    }
+
+    /// Whether the text of `editor` differs from the file it is saved to,
+    /// or until then from the file its title names in the current folder.
+    /// Text without a file counts unless empty. Lines are compared, so line
+    /// endings and a final newline do not count. Encrypted files and text
+    /// saved with its variables expanded are left to the editor's own
+    /// modified flag. The file is read, so this is for when the editor is
+    /// shown or saved rather than for every key.
+    pub fn has_unsaved_changes(&self, editor: &Editor) -> bool {
+        let Some(lines) = editor.get_lines() else {
+            return false;
+        };
+        if editor.is_read_only() {
+            return false;
+        }
+        let is_empty = lines.iter().all(String::is_empty);
+        let path = match (editor.get_target(), editor.get_title()) {
+            (Some((_path, true)), _title) => return editor.is_modified(),
+            _target if editor.get_expand_variables() => return editor.is_modified(),
+            (Some((path, false)), _title) => path.to_path_buf(),
+            (None, Some(title)) => self.current.join(title),
+            (None, None) => return !is_empty,
+        };
+        match self.read_file(&path) {
+            Ok(saved) if saved.is_empty() => !is_empty,
+            Ok(saved) => !String::from_utf8_lossy(&saved)
+                .lines()
+                .eq(lines.iter().map(String::as_str)),
+            Err(_err) => !is_empty,
+        }
+    }
+
+    pub fn action(&mut self) -> Result<Respond> {
+        self.get_selected_entity()
+            .cloned()
+            .map_or(Ok(Respond::None), |entity| match &entity {
+                ManagerEntity::TextFile(path) if self.is_readonly() => {
+                    Ok(Respond::Data(path.clone(), self.read_archive_entry(path)?))
+                }
+                ManagerEntity::TextFile(path) if self.is_remote() => {
+                    Ok(Respond::Data(path.clone(), self.read_file(path)?))
+                }
+                ManagerEntity::TextFile(path) => Ok(Respond::File(path.clone())),
+                ManagerEntity::Folder(path) => {
+                    Self::goto_dir(self, path.clone())?;
+                    Ok(Respond::None)
+                }
+                ManagerEntity::Action(Action::Custom(name)) => {
+                    let (_name, handler) = self
+                        .custom_actions
+                        .iter()
+                        .find(|(known, _handler)| known == name)
+                        .ok_or(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("No action {:?} is registered", name),
+                        ))?;
+                    Ok(handler(self)?)
+                }
+                ManagerEntity::Action(act) => {
+                    match act {
+                        Action::Back => self.go_back()?,
+                        Action::Root => self.go_root()?,
+                        Action::Custom(_name) => (),
+                    }
+                    Ok(Respond::None)
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_names_are_rejected() {
+        assert!(FileManager::sanitize_filename("").is_err());
+        assert!(FileManager::sanitize_filename("   \t ").is_err());
+    }
+
+    #[test]
+    fn dots_are_kept_unless_they_are_the_whole_name() {
+        assert_eq!(
+            FileManager::sanitize_filename(" a.b.txt ").unwrap(),
+            "a.b.txt"
+        );
+        assert_eq!(
+            FileManager::sanitize_filename(".hidden").unwrap(),
+            ".hidden"
+        );
+        assert!(FileManager::sanitize_filename(".").is_err());
+        assert!(FileManager::sanitize_filename(" .. ").is_err());
+        assert_eq!(FileManager::sanitize_filename("../up").unwrap(), ".._up");
+    }
+
+    #[test]
+    fn separators_and_null_bytes_are_replaced() {
+        assert_eq!(
+            FileManager::sanitize_filename("a/b\\c\0d").unwrap(),
+            "a_b_c_d"
+        );
+    }
+
+    #[test]
+    fn long_names_are_cut_at_a_char_boundary() {
+        assert_eq!(
+            FileManager::sanitize_filename(&"x".repeat(300)).unwrap(),
+            "x".repeat(MAX_NAME_LEN)
+        );
+        // A two byte char would straddle the limit.
+        let name = format!("{}é", "x".repeat(MAX_NAME_LEN - 1));
+        assert_eq!(
+            FileManager::sanitize_filename(&name).unwrap(),
+            "x".repeat(MAX_NAME_LEN - 1)
+        );
+    }
+}
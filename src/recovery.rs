@@ -0,0 +1,123 @@
+use crate::{error::Result, keyring::SessionKey};
+use chrono::{DateTime, Local};
+use std::{
+    fs, io, panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, TryLockError},
+};
+
+/// Starts the name of every recovery file, followed by the time of the crash.
+pub const RECOVERY_PREFIX: &str = "mystore_recovery_";
+const RECOVERY_SUFFIX: &str = ".txt";
+/// Ends the name of the recovery file of an encrypted note.
+const ENCRYPTED_SUFFIX: &str = ".enc";
+
+/// The lines of the editor as last seen by the render loop, and whether
+/// they belong to an encrypted note.
+struct Remembered {
+    lines: Vec<String>,
+    encrypted: bool,
+}
+
+/// What the panic hook saves.
+static EDITOR_LINES: Mutex<Option<Remembered>> = Mutex::new(None);
+
+/// Keeps the lines of the editor, or none once it is closed, for a crash.
+/// The lines are only copied when they differ from the kept ones.
+pub fn remember(lines: Option<&[String]>, encrypted: bool) {
+    let mut remembered = EDITOR_LINES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match (lines, remembered.as_mut()) {
+        (Some(lines), Some(kept)) if kept.lines == lines => kept.encrypted = encrypted,
+        (Some(lines), _kept) => {
+            *remembered = Some(Remembered {
+                lines: lines.to_vec(),
+                encrypted,
+            })
+        }
+        (None, _kept) => *remembered = None,
+    }
+}
+
+/// Writes `lines` to a recovery file under `root` named after `now`,
+/// encrypted with `key` if given.
+pub fn write_recovery(
+    root: &Path,
+    lines: &[String],
+    key: Option<&SessionKey>,
+    now: DateTime<Local>,
+) -> Result<PathBuf> {
+    let text = lines.join("\n");
+    let (suffix, data) = match key {
+        Some(key) => (ENCRYPTED_SUFFIX, key.encrypt(&text)?),
+        None => (RECOVERY_SUFFIX, text.into_bytes()),
+    };
+    let name = format!(
+        "{}{}{}",
+        RECOVERY_PREFIX,
+        now.format("%Y%m%d-%H%M%S"),
+        suffix
+    );
+    let path = root.join(name);
+    fs::write(&path, data)?;
+
+    Ok(path)
+}
+
+/// Writes the remembered lines to a recovery file if any has text, those
+/// of an encrypted note encrypted with `key`. A panic while they are being
+/// remembered leaves them alone rather than waiting.
+pub fn save_remembered(
+    root: &Path,
+    key: &SessionKey,
+    now: DateTime<Local>,
+) -> Option<Result<PathBuf>> {
+    let remembered = match EDITOR_LINES.try_lock() {
+        Ok(remembered) => remembered,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return None,
+    };
+    let remembered = remembered.as_ref()?;
+    if remembered.lines.iter().all(|line| line.is_empty()) {
+        return None;
+    }
+    let key = remembered.encrypted.then_some(key);
+
+    Some(write_recovery(root, &remembered.lines, key, now))
+}
+
+/// Saves the remembered lines under `root` on a panic, before the usual
+/// panic message.
+pub fn install_hook(root: PathBuf, key: SessionKey) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match save_remembered(&root, &key, Local::now()) {
+            Some(Ok(path)) => eprintln!("The editor text was saved to {}", path.display()),
+            Some(Err(err)) => eprintln!("The editor text could not be saved: {}", err),
+            None => (),
+        }
+        previous(info);
+    }));
+}
+
+/// Recovery files left under `root` by earlier crashes, the latest first.
+pub fn find_recovery_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        name.starts_with(RECOVERY_PREFIX)
+                            && (name.ends_with(RECOVERY_SUFFIX) || name.ends_with(ENCRYPTED_SUFFIX))
+                    })
+        })
+        .collect();
+    files.sort_by(|path, other| other.cmp(path));
+
+    Ok(files)
+}
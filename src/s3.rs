@@ -0,0 +1,283 @@
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::{
+    config::http::HttpResponse,
+    error::{DisplayErrorContext, SdkError},
+    primitives::ByteStream,
+    Client,
+};
+use std::{io, path::Path};
+use tokio::runtime::Runtime;
+
+/// Where the notes are kept in S3.
+#[derive(Clone, Debug, PartialEq)]
+pub struct S3Config {
+    pub bucket: String,
+    /// The key prefix listed as the root, without a trailing `/`.
+    pub prefix: String,
+    /// Taken from the AWS environment and profile if empty.
+    pub region: String,
+}
+
+/// The objects under a prefix, one level deep as with `/` as the delimiter.
+#[derive(Debug, Default, PartialEq)]
+pub struct S3Listing {
+    /// Key prefixes that act as folders, without their trailing `/`.
+    pub folders: Vec<String>,
+    pub files: Vec<(String, u64)>,
+}
+
+/// A blocking S3 client for a single bucket. Paths below `root` map to the
+/// keys below the prefix of the bucket.
+pub struct S3Store {
+    runtime: Runtime,
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+fn to_io<E>(err: SdkError<E, HttpResponse>) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let kind = match err
+        .raw_response()
+        .map(|response| response.status().as_u16())
+    {
+        Some(404) => io::ErrorKind::NotFound,
+        Some(403) => io::ErrorKind::PermissionDenied,
+        Some(412) => io::ErrorKind::AlreadyExists,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, DisplayErrorContext(&err).to_string())
+}
+
+/// Percent-encodes a key for the `x-amz-copy-source` header.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+impl S3Store {
+    /// Loads the credentials the AWS way: environment, profile, then the
+    /// instance metadata.
+    pub fn connect(config: &S3Config) -> io::Result<S3Store> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if !config.region.is_empty() {
+            loader = loader.region(Region::new(config.region.clone()));
+        }
+        let client = Client::new(&runtime.block_on(loader.load()));
+
+        Ok(S3Store {
+            runtime,
+            client,
+            bucket: config.bucket.clone(),
+            prefix: config.prefix.trim_matches('/').to_owned(),
+        })
+    }
+
+    /// The path the prefix is listed as, `s3://bucket/prefix`.
+    pub fn get_root(&self) -> String {
+        match self.prefix.as_str() {
+            "" => format!("s3://{}", self.bucket),
+            prefix => format!("s3://{}/{}", self.bucket, prefix),
+        }
+    }
+
+    /// The key of a path below the root, `None` for paths outside of it.
+    pub fn key_of(&self, root: &Path, path: &Path) -> Option<String> {
+        let inside = path.strip_prefix(root).ok()?;
+        let names = inside
+            .iter()
+            .map(|name| name.to_str())
+            .collect::<Option<Vec<&str>>>()?;
+        let mut parts: Vec<&str> = Some(self.prefix.as_str())
+            .filter(|prefix| !prefix.is_empty())
+            .into_iter()
+            .collect();
+        parts.extend(names);
+
+        Some(parts.join("/"))
+    }
+
+    /// Lists what is right below `key` as a folder, following every page.
+    pub fn list(&self, key: &str) -> io::Result<S3Listing> {
+        let prefix = match key {
+            "" => String::new(),
+            key => format!("{}/", key),
+        };
+        let mut listing = S3Listing::default();
+        let mut token = None;
+        loop {
+            let output = self
+                .runtime
+                .block_on(
+                    self.client
+                        .list_objects_v2()
+                        .bucket(&self.bucket)
+                        .prefix(&prefix)
+                        .delimiter("/")
+                        .set_continuation_token(token)
+                        .send(),
+                )
+                .map_err(to_io)?;
+            listing.folders.extend(
+                output
+                    .common_prefixes()
+                    .iter()
+                    .filter_map(|folder| folder.prefix()?.strip_suffix('/'))
+                    .map(String::from),
+            );
+            listing
+                .files
+                .extend(output.contents().iter().filter_map(|object| {
+                    let key = object.key()?;
+                    // Folders created in the console are empty `name/` objects.
+                    (!key.ends_with('/'))
+                        .then(|| (key.to_owned(), object.size().unwrap_or_default() as u64))
+                }));
+            token = output.next_continuation_token().map(String::from);
+            if token.is_none() {
+                break;
+            }
+        }
+
+        Ok(listing)
+    }
+
+    /// Whether objects are kept below `key` as a folder.
+    pub fn is_folder(&self, key: &str) -> io::Result<bool> {
+        let prefix = match key {
+            "" => return Ok(true),
+            key => format!("{}/", key),
+        };
+        self.runtime
+            .block_on(
+                self.client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .prefix(prefix)
+                    .max_keys(1)
+                    .send(),
+            )
+            .map(|output| output.key_count().unwrap_or_default() > 0)
+            .map_err(to_io)
+    }
+
+    /// Whether there is an object at `key`. Only a missing object gives
+    /// false, a refused request or a failed connection is an error.
+    pub fn is_file(&self, key: &str) -> io::Result<bool> {
+        let found = self.runtime.block_on(
+            self.client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send(),
+        );
+        match found.map_err(to_io) {
+            Ok(_output) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.runtime.block_on(async {
+            let output = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(to_io)?;
+            let data = output.body.collect().await.map_err(io::Error::other)?;
+            Ok(data.to_vec())
+        })
+    }
+
+    /// Stores `data` at `key`, replacing the object unless `only_new` is
+    /// set. An existing object is then an `AlreadyExists` error.
+    pub fn put(&self, key: &str, data: &[u8], only_new: bool) -> io::Result<()> {
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(data.to_vec()));
+        let request = match only_new {
+            true => request.if_none_match("*"),
+            false => request,
+        };
+        self.runtime
+            .block_on(request.send())
+            .map(|_output| ())
+            .map_err(to_io)
+    }
+
+    pub fn delete(&self, key: &str) -> io::Result<()> {
+        self.runtime
+            .block_on(
+                self.client
+                    .delete_object()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .send(),
+            )
+            .map(|_output| ())
+            .map_err(to_io)
+    }
+
+    /// S3 cannot rename, the object is copied and the original deleted.
+    pub fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.runtime
+            .block_on(
+                self.client
+                    .copy_object()
+                    .bucket(&self.bucket)
+                    .copy_source(format!("{}/{}", self.bucket, encode_key(from)))
+                    .key(to)
+                    .send(),
+            )
+            .map_err(to_io)?;
+        self.delete(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn paths_below_the_root_map_to_keys() {
+        let config = S3Config {
+            bucket: String::from("notes"),
+            prefix: String::from("/work/"),
+            region: String::from("eu-west-1"),
+        };
+        let s3 = S3Store::connect(&config).unwrap();
+        let root = PathBuf::from(s3.get_root());
+        assert_eq!(root, PathBuf::from("s3://notes/work"));
+        assert_eq!(s3.key_of(&root, &root), Some(String::from("work")));
+        assert_eq!(
+            s3.key_of(&root, &root.join("2024").join("a.txt")),
+            Some(String::from("work/2024/a.txt"))
+        );
+        assert_eq!(s3.key_of(&root, Path::new("s3://notes/home")), None);
+    }
+
+    #[test]
+    fn keys_are_encoded_for_copying() {
+        assert_eq!(encode_key("notes/a b+c.txt"), "notes/a%20b%2Bc.txt");
+        assert_eq!(encode_key("ü"), "%C3%BC");
+    }
+}
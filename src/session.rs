@@ -0,0 +1,116 @@
+use crate::{app::DEFAULT_MANAGER_WIDTH, error::Result, manager::SortOrder, tags::INDEX_DIR};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+const SESSION_FILE: &str = "session.json";
+/// Files of another version are ignored rather than half understood.
+const SESSION_VERSION: u64 = 1;
+
+/// Where the last session left the manager, saved under
+/// `.mystore/session.json` on a clean exit and restored at the next start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionState {
+    /// The listed folder, relative to the root.
+    pub dir: PathBuf,
+    /// The file name of the selected entry.
+    pub selected: Option<String>,
+    pub sort_order: SortOrder,
+    pub show_hidden: bool,
+    /// The share of the screen width the manager takes, in percent.
+    pub manager_width: u16,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        SessionState {
+            dir: PathBuf::new(),
+            selected: None,
+            sort_order: SortOrder::default(),
+            show_hidden: true,
+            manager_width: DEFAULT_MANAGER_WIDTH,
+        }
+    }
+}
+
+fn sort_order_name(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Modified => "modified",
+        SortOrder::Size => "size",
+        SortOrder::Name => "name",
+    }
+}
+
+fn parse_sort_order(name: &str) -> Option<SortOrder> {
+    match name {
+        "modified" => Some(SortOrder::Modified),
+        "size" => Some(SortOrder::Size),
+        "name" => Some(SortOrder::Name),
+        _ => None,
+    }
+}
+
+impl SessionState {
+    fn path(root: &Path) -> PathBuf {
+        root.join(INDEX_DIR).join(SESSION_FILE)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let dir: Vec<String> = self
+            .dir
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        json!({
+            "version": SESSION_VERSION,
+            "dir": dir.join("/"),
+            "selected": self.selected,
+            "sort_order": sort_order_name(self.sort_order),
+            "show_hidden": self.show_hidden,
+            "manager_width": self.manager_width,
+        })
+    }
+
+    /// Reads a state saved by `to_json`, `None` for anything else,
+    /// including folders that would lead out of the root.
+    pub fn from_json(text: &str) -> Option<SessionState> {
+        let value: Value = serde_json::from_str(text).ok()?;
+        if value.get("version")?.as_u64()? != SESSION_VERSION {
+            return None;
+        }
+        let dir = PathBuf::from(value.get("dir")?.as_str()?);
+        if !dir
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+        let selected = match value.get("selected")? {
+            Value::Null => None,
+            name => Some(String::from(name.as_str()?)),
+        };
+
+        Some(SessionState {
+            dir,
+            selected,
+            sort_order: parse_sort_order(value.get("sort_order")?.as_str()?)?,
+            show_hidden: value.get("show_hidden")?.as_bool()?,
+            manager_width: u16::try_from(value.get("manager_width")?.as_u64()?).ok()?,
+        })
+    }
+
+    /// The state saved for the store at `root`, `None` if there is none
+    /// or it cannot be read.
+    pub fn load(root: &Path) -> Option<SessionState> {
+        Self::from_json(&fs::read_to_string(Self::path(root)).ok()?)
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        fs::create_dir_all(root.join(INDEX_DIR))?;
+        fs::write(Self::path(root), format!("{:#}\n", self.to_json()))?;
+
+        Ok(())
+    }
+}
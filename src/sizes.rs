@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FolderSize {
+    pub bytes: u64,
+    pub files: usize,
+    /// Entries that could not be read, e.g. for lack of permissions.
+    pub unreadable: usize,
+}
+
+/// Formats a byte count with binary units and one decimal, e.g. `4.2 MiB`.
+/// Counts below a KiB are shown in whole bytes.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{} {}", bytes, UNITS[0]);
+    }
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    // Values that round up to 1024.0 belong to the next unit.
+    while (size * 10.0).round() >= 10240.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Formats a count with thousands separators, e.g. `12,345`.
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut text = String::new();
+    for (id, digit) in digits.chars().enumerate() {
+        if id > 0 && (digits.len() - id).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+/// Space left for the user on the filesystem holding `path`, if the
+/// platform tells.
+pub fn available_space(path: &Path) -> Option<u64> {
+    fs2::available_space(path).ok()
+}
+
+/// Sums the sizes of the files under `path`, see `folder_size`. Fails only
+/// if `path` itself cannot be read, entries below it that cannot be read
+/// are left out.
+pub fn calculate_dir_size(path: &Path) -> io::Result<u64> {
+    fs::read_dir(path)?;
+    let size = folder_size(path, &AtomicBool::new(false)).unwrap_or_default();
+
+    Ok(size.bytes)
+}
+
+/// Sums the sizes of the files under `dir`, gives up once `cancelled` is set.
+/// Entries that cannot be read are counted, not summed. Symbolic links are
+/// not followed.
+pub fn folder_size(dir: &Path, cancelled: &AtomicBool) -> Option<FolderSize> {
+    let mut size = FolderSize::default();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_err) => {
+                size.unreadable += 1;
+                continue;
+            }
+        };
+        for entry in entries {
+            let meta = entry.and_then(|entry| entry.metadata().map(|meta| (entry.path(), meta)));
+            match meta {
+                Ok((path, meta)) if meta.is_dir() => dirs.push(path),
+                Ok((_path, meta)) if meta.is_file() => {
+                    size.bytes += meta.len();
+                    size.files += 1;
+                }
+                Ok(_symlink) => (),
+                Err(_err) => size.unreadable += 1,
+            }
+        }
+    }
+
+    Some(size)
+}
+
+/// Computes folder sizes on a worker thread and keeps them for the session.
+pub struct BackgroundSizer {
+    sender: Sender<(PathBuf, FolderSize)>,
+    receiver: Receiver<(PathBuf, FolderSize)>,
+    pending: Option<(PathBuf, Arc<AtomicBool>)>,
+    sizes: HashMap<PathBuf, FolderSize>,
+}
+
+impl Default for BackgroundSizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundSizer {
+    pub fn new() -> BackgroundSizer {
+        let (sender, receiver) = mpsc::channel();
+        BackgroundSizer {
+            sender,
+            receiver,
+            pending: None,
+            sizes: HashMap::new(),
+        }
+    }
+
+    /// Starts sizing `dir` unless it is known already, cancelling any other one.
+    pub fn start(&mut self, dir: PathBuf) {
+        if self.sizes.contains_key(&dir) || self.get_pending() == Some(&dir) {
+            return;
+        }
+        self.cancel();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending = Some((dir.clone(), Arc::clone(&cancelled)));
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            if let Some(size) = folder_size(&dir, &cancelled) {
+                // The receiver only goes away with the session itself.
+                let _ = sender.send((dir, size));
+            }
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some((_dir, cancelled)) = self.pending.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_pending(&self) -> Option<&Path> {
+        self.pending.as_ref().map(|(dir, _cancelled)| dir.as_path())
+    }
+
+    pub fn get_size(&self, dir: &Path) -> Option<&FolderSize> {
+        self.sizes.get(dir)
+    }
+
+    /// Stores a finished size, returns the folder it belongs to.
+    pub fn poll(&mut self) -> Option<PathBuf> {
+        while let Ok((dir, size)) = self.receiver.try_recv() {
+            if self.get_pending() == Some(&dir) {
+                self.pending = None;
+                self.sizes.insert(dir.clone(), size);
+                return Some(dir);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    keyring::{self, ENVELOPE_HEADER},
+    sizes::{format_count, format_size},
+    tags::INDEX_DIR,
+    times::TimeFormats,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How many of the largest and of the newest files are kept.
+pub const TOP_FILES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileStat {
+    /// The path relative to the root.
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub modified: SystemTime,
+}
+
+/// What a store holds, from `collect_stats`. Files count as encrypted when
+/// they start with the header of a data key, files encrypted with the
+/// password directly have none and count as plain.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StoreStats {
+    pub files: usize,
+    pub folders: usize,
+    pub bytes: u64,
+    pub encrypted_files: usize,
+    pub encrypted_bytes: u64,
+    pub plain_files: usize,
+    pub plain_bytes: u64,
+    /// The largest files, largest first.
+    pub largest: Vec<FileStat>,
+    /// The most recently modified files, newest first.
+    pub newest: Vec<FileStat>,
+    /// Entries that could not be read, e.g. for lack of permissions.
+    pub unreadable: usize,
+}
+
+/// Keeps the `limit` greatest items pushed, without holding the rest.
+struct Top<T: Ord> {
+    heap: BinaryHeap<Reverse<T>>,
+    limit: usize,
+}
+
+impl<T: Ord> Top<T> {
+    fn new(limit: usize) -> Self {
+        Top {
+            heap: BinaryHeap::new(),
+            limit,
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        self.heap.push(Reverse(item));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    /// The items kept, the greatest first.
+    fn into_sorted(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(item)| item)
+            .collect()
+    }
+}
+
+fn is_sealed_file(path: &Path) -> io::Result<bool> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(ENVELOPE_HEADER.len() as u64)
+        .read_to_end(&mut header)?;
+    Ok(keyring::is_sealed(&header))
+}
+
+/// Walks the tree under `root` one folder at a time, keeping only the
+/// `top` largest and newest files besides the counts. Symbolic links are
+/// not followed and the `.mystore` folder is left out. Entries that cannot
+/// be read are counted and skipped, only an unreadable root fails.
+pub fn collect_stats(root: &Path, top: usize) -> io::Result<StoreStats> {
+    let mut stats = StoreStats::default();
+    let mut largest = Top::new(top);
+    let mut newest = Top::new(top);
+    // Only an unreadable root fails.
+    fs::read_dir(root)?;
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_err) => {
+                stats.unreadable += 1;
+                continue;
+            }
+        };
+        for entry in entries {
+            let meta = entry.and_then(|entry| entry.metadata().map(|meta| (entry.path(), meta)));
+            let (path, meta) = match meta {
+                Ok((path, meta)) if meta.is_dir() => {
+                    if dir != root || path.file_name() != Some(INDEX_DIR.as_ref()) {
+                        stats.folders += 1;
+                        dirs.push(path);
+                    }
+                    continue;
+                }
+                Ok((path, meta)) if meta.is_file() => (path, meta),
+                Ok(_symlink) => continue,
+                Err(_err) => {
+                    stats.unreadable += 1;
+                    continue;
+                }
+            };
+            let Ok(sealed) = is_sealed_file(&path) else {
+                stats.unreadable += 1;
+                continue;
+            };
+            let bytes = meta.len();
+            stats.files += 1;
+            stats.bytes += bytes;
+            if sealed {
+                stats.encrypted_files += 1;
+                stats.encrypted_bytes += bytes;
+            } else {
+                stats.plain_files += 1;
+                stats.plain_bytes += bytes;
+            }
+            let file = FileStat {
+                path: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+                bytes,
+                modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            };
+            largest.push((file.bytes, Reverse(file.path.clone()), file.clone()));
+            newest.push((file.modified, Reverse(file.path.clone()), file));
+        }
+    }
+    // Ties are broken by path, the first in order comes first.
+    stats.largest = largest
+        .into_sorted()
+        .into_iter()
+        .map(|(_bytes, _path, file)| file)
+        .collect();
+    stats.newest = newest
+        .into_sorted()
+        .into_iter()
+        .map(|(_modified, _path, file)| file)
+        .collect();
+
+    Ok(stats)
+}
+
+impl StoreStats {
+    /// A report for people, times shown with `formats`.
+    pub fn to_text(&self, formats: &TimeFormats) -> String {
+        let mut lines = vec![
+            format!(
+                "Files: {} ({})",
+                format_count(self.files as u64),
+                format_size(self.bytes)
+            ),
+            format!("Folders: {}", format_count(self.folders as u64)),
+            format!(
+                "Encrypted: {} files, {}",
+                format_count(self.encrypted_files as u64),
+                format_size(self.encrypted_bytes)
+            ),
+            format!(
+                "Plain: {} files, {}",
+                format_count(self.plain_files as u64),
+                format_size(self.plain_bytes)
+            ),
+        ];
+        if !self.largest.is_empty() {
+            lines.push(String::from("Largest files:"));
+            for file in &self.largest {
+                lines.push(format!(
+                    "  {:>10}  {}",
+                    format_size(file.bytes),
+                    file.path.display()
+                ));
+            }
+        }
+        if !self.newest.is_empty() {
+            lines.push(String::from("Recently modified files:"));
+            for file in &self.newest {
+                lines.push(format!(
+                    "  {}  {}",
+                    formats.display(&DateTime::<Utc>::from(file.modified)),
+                    file.path.display()
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// A report for scripts, times in RFC 3339 and UTC.
+    pub fn to_json(&self) -> serde_json::Value {
+        let file = |file: &FileStat| {
+            json!({
+                "path": file.path.to_string_lossy(),
+                "bytes": file.bytes,
+                "modified": DateTime::<Utc>::from(file.modified)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            })
+        };
+        json!({
+            "files": self.files,
+            "folders": self.folders,
+            "bytes": self.bytes,
+            "encrypted": {"files": self.encrypted_files, "bytes": self.encrypted_bytes},
+            "plain": {"files": self.plain_files, "bytes": self.plain_bytes},
+            "largest": self.largest.iter().map(file).collect::<Vec<_>>(),
+            "newest": self.newest.iter().map(file).collect::<Vec<_>>(),
+            "unreadable": self.unreadable,
+        })
+    }
+}
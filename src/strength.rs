@@ -0,0 +1,152 @@
+use std::fmt;
+
+/// Passwords guessed in fewer tries than this are weak: a new store asks to
+/// confirm them, an existing one warns.
+pub const MIN_GUESSES: f64 = 1e8;
+
+/// Passwords tried first, whatever their length.
+const COMMON_PASSWORDS: [&str; 20] = [
+    "123456",
+    "password",
+    "12345678",
+    "qwerty",
+    "123456789",
+    "12345",
+    "111111",
+    "1234567",
+    "iloveyou",
+    "abc123",
+    "123123",
+    "admin",
+    "letmein",
+    "welcome",
+    "monkey",
+    "dragon",
+    "passw0rd",
+    "secret",
+    "sunshine",
+    "trustno1",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Strength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strength::VeryWeak => write!(f, "very weak"),
+            Strength::Weak => write!(f, "weak"),
+            Strength::Fair => write!(f, "fair"),
+            Strength::Strong => write!(f, "strong"),
+            Strength::VeryStrong => write!(f, "very strong"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub strength: Strength,
+    /// Tries it would take to guess the password.
+    pub guesses: f64,
+}
+
+impl Rating {
+    pub fn is_weak(&self) -> bool {
+        self.guesses < MIN_GUESSES
+    }
+
+    /// What to tell before the session starts about a weak password, which
+    /// asks for a confirmation for a `new_store`.
+    pub fn warning(&self, new_store: bool) -> Option<String> {
+        match (self.is_weak(), new_store) {
+            (false, _) => None,
+            (true, true) => Some(format!(
+                "Weak password ({}) — type YES to proceed",
+                self.strength
+            )),
+            (true, false) => Some(format!(
+                "Warning: the {} password of this store is easy to guess",
+                self.strength
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let guesses = if self.guesses < 1e6 {
+            format!("{:.0}", self.guesses)
+        } else {
+            format!("{:.1e}", self.guesses)
+        };
+        write!(
+            f,
+            "Password strength: {}, guessed in about {} tries",
+            self.strength, guesses
+        )
+    }
+}
+
+/// Characters to pick each one of the password from, judging by the kinds
+/// of characters it has.
+fn pool_size(password: &str) -> f64 {
+    let has = |test: fn(&char) -> bool| password.chars().any(|ch| test(&ch));
+    let pools = [
+        (has(char::is_ascii_lowercase), 26.0),
+        (has(char::is_ascii_uppercase), 26.0),
+        (has(char::is_ascii_digit), 10.0),
+        (has(|ch| ch.is_ascii() && !ch.is_ascii_alphanumeric()), 33.0),
+        (has(|ch| !ch.is_ascii()), 100.0),
+    ];
+    pools
+        .iter()
+        .filter(|(present, _size)| *present)
+        .map(|(_present, size)| size)
+        .sum()
+}
+
+/// Estimates the tries a password takes to guess. Each character counts as
+/// picked from the pool of its kinds, except that repeated characters and
+/// runs like `abcd` or `4321` add a bit each. Common passwords take a few.
+pub fn rate_password(password: &str) -> Rating {
+    let lowered = password.to_lowercase();
+    let guesses = match COMMON_PASSWORDS
+        .iter()
+        .position(|common| *common == lowered)
+    {
+        Some(rank) => (rank + 1) as f64,
+        None => {
+            let bits_per_char = pool_size(password).max(1.0).log2();
+            let mut bits = 0.0;
+            let mut previous: Option<char> = None;
+            for ch in password.chars() {
+                bits += match previous {
+                    Some(prev) if (ch as i64 - prev as i64).abs() <= 1 => 1.0,
+                    _ => bits_per_char,
+                };
+                previous = Some(ch);
+            }
+            2f64.powf(bits)
+        }
+    };
+    let strength = match guesses {
+        guesses if guesses < 1e3 => Strength::VeryWeak,
+        guesses if guesses < 1e6 => Strength::Weak,
+        guesses if guesses < MIN_GUESSES => Strength::Fair,
+        guesses if guesses < 1e12 => Strength::Strong,
+        _ => Strength::VeryStrong,
+    };
+
+    Rating { strength, guesses }
+}
+
+/// Whether the answer to the confirmation of a weak password is yes.
+pub fn is_confirmed(answer: &str) -> bool {
+    answer.trim() == "YES"
+}
@@ -0,0 +1,425 @@
+use crate::{
+    error::Result,
+    keyring::SessionKey,
+    loader::{FsLoader, Loader},
+    viewer::{Viewer, ViewerEntity},
+};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub(crate) const INDEX_DIR: &str = ".mystore";
+const INDEX_FILE: &str = "tags";
+/// Indexes of another version are dropped and built again.
+const INDEX_VERSION: u64 = 2;
+const TAGS_PREFIX: &str = "tags:";
+/// Only the first lines of a note are searched for the tags line.
+const TAGS_LINES: usize = 5;
+/// Larger files are not read for tags.
+const MAX_TAGGED_SIZE: u64 = 1 << 20;
+
+/// Finds the `tags: work, ideas` line among the first lines of a note.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let line = text
+        .lines()
+        .take(TAGS_LINES)
+        .find_map(|line| line.trim().strip_prefix(TAGS_PREFIX));
+    let mut tags: Vec<String> = Vec::new();
+    for tag in line.unwrap_or_default().split(',').map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|known| known == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
+
+/// Replaces the tags line of a note, or puts a new one first.
+pub fn set_tags(text: &str, tags: &[String]) -> String {
+    let tags_line = format!("{} {}", TAGS_PREFIX, tags.join(", "));
+    let position = text
+        .lines()
+        .take(TAGS_LINES)
+        .position(|line| line.trim().starts_with(TAGS_PREFIX));
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    match (position, tags.is_empty()) {
+        (Some(id), true) => {
+            lines.remove(id);
+        }
+        (Some(id), false) => lines[id] = &tags_line,
+        (None, true) => (),
+        (None, false) => lines.insert(0, &tags_line),
+    }
+
+    lines.join("\n")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TagData {
+    Plain(Vec<String>),
+    /// Tags of an encrypted note, encrypted with the session key.
+    Encrypted(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct IndexEntry {
+    modified: u128,
+    tags: TagData,
+}
+
+/// Tags of all notes under the root, cached in `.mystore/tags` and
+/// refreshed for the notes whose modification time changed.
+pub struct TagIndex {
+    root: PathBuf,
+    key: SessionKey,
+    entries: BTreeMap<PathBuf, IndexEntry>,
+}
+
+impl TagIndex {
+    fn index_path(&self) -> PathBuf {
+        self.root.join(INDEX_DIR).join(INDEX_FILE)
+    }
+
+    fn modified(path: &Path) -> Result<u128> {
+        let modified = path.metadata()?.modified()?;
+        Ok(modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos()))
+    }
+
+    fn read_tags(&self, path: &Path) -> Result<TagData> {
+        if path.metadata()?.len() > MAX_TAGGED_SIZE {
+            return Ok(TagData::Plain(Vec::new()));
+        }
+        let decoded = Viewer::decode(FsLoader.load(path)?, &self.key);
+        Ok(match decoded.get_entity_ref() {
+            ViewerEntity::Text(text) => TagData::Plain(parse_tags(text)),
+            ViewerEntity::DecryptedText(text) => {
+                TagData::Encrypted(self.key.encrypt(&parse_tags(text).join(","))?)
+            }
+            ViewerEntity::Binary(_)
+            | ViewerEntity::Table(_)
+            | ViewerEntity::Json(_)
+            | ViewerEntity::JsonLines(_) => TagData::Plain(Vec::new()),
+        })
+    }
+
+    fn entry_tags(&self, entry: &IndexEntry) -> Vec<String> {
+        match &entry.tags {
+            TagData::Plain(tags) => tags.clone(),
+            TagData::Encrypted(bin) => self.key.decrypt(bin).map_or(Vec::new(), |text| {
+                parse_tags(&format!("{}{}", TAGS_PREFIX, text))
+            }),
+        }
+    }
+
+    /// Reads the JSON index, where every entry has the relative `path`, the
+    /// `modified` time in nanoseconds as a string, and either the plain
+    /// `tags` or the `encrypted` ones in hex. Malformed entries are skipped.
+    fn parse_index(text: &str) -> BTreeMap<PathBuf, IndexEntry> {
+        let Ok(index) = serde_json::from_str::<serde_json::Value>(text) else {
+            return BTreeMap::new();
+        };
+        if index["version"] != INDEX_VERSION {
+            return BTreeMap::new();
+        }
+        index["entries"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = PathBuf::from(entry["path"].as_str()?);
+                let modified = entry["modified"].as_str()?.parse().ok()?;
+                let tags = match (entry.get("tags"), entry.get("encrypted")) {
+                    (Some(tags), None) => TagData::Plain(
+                        tags.as_array()?
+                            .iter()
+                            .map(|tag| tag.as_str().map(String::from))
+                            .collect::<Option<_>>()?,
+                    ),
+                    (None, Some(bin)) => TagData::Encrypted(decode_hex(bin.as_str()?)?),
+                    _ => return None,
+                };
+                Some((path, IndexEntry { modified, tags }))
+            })
+            .collect()
+    }
+}
+
+impl TagIndex {
+    /// Opens the cached index of `root`, a missing or unreadable one is empty.
+    pub fn open(root: &Path, key: &SessionKey) -> Self {
+        let mut index = Self {
+            root: root.to_path_buf(),
+            key: key.clone(),
+            entries: BTreeMap::new(),
+        };
+        if let Ok(text) = fs::read_to_string(index.index_path()) {
+            index.entries = Self::parse_index(&text);
+        }
+
+        index
+    }
+
+    /// Rereads the notes changed since the last refresh, returns if any did.
+    /// Folders and notes that cannot be read are left out, only an
+    /// unreadable root is an error.
+    pub fn refresh(&mut self) -> Result<bool> {
+        let mut changed = false;
+        let mut seen = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_err) if dir != self.root => continue,
+                Err(err) => return Err(err.into()),
+            };
+            for entry in entries {
+                let Ok(entry) = entry else {
+                    continue;
+                };
+                let path = entry.path();
+                if path.is_symlink() || path.file_name() == Some(INDEX_DIR.as_ref()) {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let relative = path
+                    .strip_prefix(&self.root)
+                    .map_or(path.clone(), PathBuf::from);
+                let Ok(modified) = Self::modified(&path) else {
+                    continue;
+                };
+                let is_fresh = self
+                    .entries
+                    .get(&relative)
+                    .is_some_and(|entry| entry.modified == modified);
+                if !is_fresh {
+                    let Ok(tags) = self.read_tags(&path) else {
+                        continue;
+                    };
+                    self.entries
+                        .insert(relative.clone(), IndexEntry { modified, tags });
+                    changed = true;
+                }
+                seen.push(relative);
+            }
+        }
+        let count = self.entries.len();
+        self.entries.retain(|path, _entry| seen.contains(path));
+
+        Ok(changed || count != self.entries.len())
+    }
+
+    /// Writes the index as JSON. Notes whose path is not UTF-8 are left
+    /// out and read again at the next refresh.
+    pub fn save(&self) -> Result<()> {
+        let entries: Vec<serde_json::Value> = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| {
+                let path = path.to_str()?;
+                let modified = entry.modified.to_string();
+                Some(match &entry.tags {
+                    TagData::Plain(tags) => {
+                        json!({"path": path, "modified": modified, "tags": tags})
+                    }
+                    TagData::Encrypted(bin) => {
+                        json!({"path": path, "modified": modified, "encrypted": encode_hex(bin)})
+                    }
+                })
+            })
+            .collect();
+        let index = json!({"version": INDEX_VERSION, "entries": entries});
+        fs::create_dir_all(self.root.join(INDEX_DIR))?;
+        fs::write(self.index_path(), index.to_string())?;
+
+        Ok(())
+    }
+
+    /// Lists all known tags in order.
+    pub fn get_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .entries
+            .values()
+            .flat_map(|entry| self.entry_tags(entry))
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Lists the notes carrying `tag` anywhere under the root.
+    pub fn get_files_with(&self, tag: &str) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .filter(|(_path, entry)| self.entry_tags(entry).iter().any(|known| known == tag))
+            .map(|(path, _entry)| self.root.join(path))
+            .collect()
+    }
+}
+
+fn encode_hex(bin: &[u8]) -> String {
+    bin.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|id| u8::from_str_radix(text.get(id..id + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const KEY: &str = "secret";
+
+    fn key() -> SessionKey {
+        SessionKey::from(KEY)
+    }
+
+    fn encrypted(text: &str) -> Vec<u8> {
+        key().encrypt(text).unwrap()
+    }
+
+    #[test]
+    fn tags_line_is_parsed_from_the_first_lines() {
+        assert_eq!(
+            parse_tags("# Title\ntags: work, ideas,, work \nbody"),
+            vec![String::from("work"), String::from("ideas")]
+        );
+        assert!(parse_tags("1\n2\n3\n4\n5\ntags: late").is_empty());
+        assert!(parse_tags("no tags here").is_empty());
+    }
+
+    #[test]
+    fn tags_line_is_replaced_added_or_removed() {
+        let tags = vec![String::from("a"), String::from("b")];
+        assert_eq!(set_tags("tags: old\nbody", &tags), "tags: a, b\nbody");
+        assert_eq!(set_tags("body\n", &tags), "tags: a, b\nbody\n");
+        assert_eq!(set_tags("# T\ntags: old\nbody", &[]), "# T\nbody");
+        assert_eq!(set_tags("body", &[]), "body");
+    }
+
+    #[test]
+    fn index_round_trips_through_its_file() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.txt"), "tags: work\n").unwrap();
+        fs::write(
+            dir.path().join("sub").join("b.txt"),
+            encrypted("tags: ideas\n"),
+        )
+        .unwrap();
+
+        let mut index = TagIndex::open(dir.path(), &key());
+        assert!(index.refresh().unwrap());
+        index.save().unwrap();
+
+        let reopened = TagIndex::open(dir.path(), &key());
+        assert_eq!(reopened.entries, index.entries);
+        assert_eq!(
+            reopened.get_tags(),
+            vec![String::from("ideas"), String::from("work")]
+        );
+    }
+
+    #[test]
+    fn index_keeps_tabs_and_newlines_of_names_and_tags() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a\tb\nc.txt"), "tags: x\ty, z").unwrap();
+        fs::create_dir(dir.path().join(INDEX_DIR)).unwrap();
+        fs::write(
+            dir.path().join(INDEX_DIR).join(INDEX_FILE),
+            "mystore-tags 1\n0\tp\told\tgone.txt\n",
+        )
+        .unwrap();
+
+        // The index of the former format is not read.
+        let mut index = TagIndex::open(dir.path(), &key());
+        assert!(index.entries.is_empty());
+        index.refresh().unwrap();
+        index.save().unwrap();
+
+        let reopened = TagIndex::open(dir.path(), &key());
+        assert_eq!(reopened.entries, index.entries);
+        assert_eq!(
+            reopened.get_files_with("x\ty"),
+            vec![dir.path().join("a\tb\nc.txt")]
+        );
+        assert_eq!(
+            reopened.get_tags(),
+            vec![String::from("x\ty"), String::from("z")]
+        );
+    }
+
+    #[test]
+    fn encrypted_tags_are_not_stored_in_plain_text() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("b.txt"), encrypted("tags: classified\n")).unwrap();
+
+        let mut index = TagIndex::open(dir.path(), &key());
+        index.refresh().unwrap();
+        index.save().unwrap();
+        let stored = fs::read_to_string(dir.path().join(INDEX_DIR).join(INDEX_FILE)).unwrap();
+        assert!(!stored.contains("classified"));
+        assert_eq!(index.get_tags(), vec![String::from("classified")]);
+
+        // Another key cannot read them back.
+        let foreign = TagIndex::open(dir.path(), &SessionKey::from("other-key"));
+        assert!(!foreign.get_tags().contains(&String::from("classified")));
+    }
+
+    #[test]
+    fn files_are_filtered_by_tag_across_folders() {
+        let dir = TempDir::new().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        fs::write(dir.path().join("a.txt"), "tags: work, ideas").unwrap();
+        fs::write(sub.join("b.txt"), "tags: work").unwrap();
+        fs::write(sub.join("c.txt"), "untagged").unwrap();
+
+        let mut index = TagIndex::open(dir.path(), &key());
+        index.refresh().unwrap();
+        assert_eq!(
+            index.get_files_with("work"),
+            vec![dir.path().join("a.txt"), sub.join("b.txt")]
+        );
+        assert_eq!(
+            index.get_files_with("ideas"),
+            vec![dir.path().join("a.txt")]
+        );
+        assert!(index.get_files_with("missing").is_empty());
+    }
+
+    #[test]
+    fn refresh_rereads_only_changed_notes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "tags: old").unwrap();
+
+        let mut index = TagIndex::open(dir.path(), &key());
+        assert!(index.refresh().unwrap());
+        assert!(!index.refresh().unwrap());
+
+        fs::write(&path, "tags: new").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert!(index.refresh().unwrap());
+        assert_eq!(index.get_tags(), vec![String::from("new")]);
+
+        fs::remove_file(&path).unwrap();
+        assert!(index.refresh().unwrap());
+        assert!(index.get_tags().is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::editor::parse_time_format;
+use chrono::{DateTime, Local, TimeZone};
+
+/// How times are shown, in the local time zone.
+pub const DEFAULT_DISPLAY_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// How new files are named after the time they are saved at.
+pub const DEFAULT_NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+/// Characters Windows refuses in file names, `/` is refused everywhere.
+const ILLEGAL_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// The formats of the times shown to the user and of the names new files
+/// get by default, both in strftime syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeFormats {
+    display: String,
+    file_name: String,
+}
+
+impl Default for TimeFormats {
+    fn default() -> Self {
+        TimeFormats::new(DEFAULT_DISPLAY_FORMAT, DEFAULT_NAME_FORMAT)
+    }
+}
+
+impl TimeFormats {
+    /// Takes formats checked with `parse_time_format` and `parse_name_format`.
+    pub fn new(display: &str, file_name: &str) -> Self {
+        TimeFormats {
+            display: String::from(display),
+            file_name: String::from(file_name),
+        }
+    }
+
+    pub fn display<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String {
+        format_local(time, &self.display)
+    }
+
+    pub fn file_name<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String {
+        format_local(time, &self.file_name)
+    }
+}
+
+/// Formats `time` in the local time zone, whatever zone it is kept in.
+pub fn format_local<Tz: TimeZone>(time: &DateTime<Tz>, format: &str) -> String {
+    time.with_timezone(&Local).format(format).to_string()
+}
+
+/// Whether `name` is a file name every common filesystem takes.
+pub fn is_portable_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|ch| ch.is_control() || ILLEGAL_NAME_CHARS.contains(&ch))
+}
+
+/// Checks a strftime format for file names, which must only give names
+/// valid on Windows as well, so no `%T` or `%+` with their colons.
+pub fn parse_name_format(format: &str) -> std::result::Result<String, String> {
+    let format = parse_time_format(format)?;
+    let sample = format_local(&Local::now(), &format);
+    if !is_portable_name(&sample) {
+        return Err(format!(
+            "time format {:?} gives file names like {:?}, which are not valid on every system",
+            format, sample
+        ));
+    }
+
+    Ok(format)
+}
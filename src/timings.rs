@@ -0,0 +1,109 @@
+use chrono::{DateTime, Local};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How many events the trace keeps.
+pub const TRACE_SIZE: usize = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEvent {
+    pub at: DateTime<Local>,
+    pub label: String,
+    pub duration: Option<Duration>,
+}
+
+/// How long drawing, handling events and listing folders took lately, for
+/// the debug overlay. While disabled no timer is started and no label is
+/// formatted, so the instrumented calls cost a branch.
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    enabled: bool,
+    last_draw: Option<Duration>,
+    last_update: Option<Duration>,
+    last_listing: Option<Duration>,
+    /// The latest events, the latest last.
+    trace: VecDeque<TraceEvent>,
+}
+
+/// Shows a duration in milliseconds, or a dash for none.
+pub fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.1} ms", duration.as_secs_f64() * 1000.0),
+        None => String::from("-"),
+    }
+}
+
+impl Timings {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts or stops collecting, what was collected is dropped on stop.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            *self = Timings::default();
+        }
+        self.enabled = enabled;
+    }
+
+    /// A timer to hand to one of the `record_*` methods, none while disabled.
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    pub fn record_draw(&mut self, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.last_draw = Some(start.elapsed());
+        }
+    }
+
+    /// Records the handling of an event and traces it under `label`.
+    pub fn record_update(&mut self, start: Option<Instant>, label: impl FnOnce() -> String) {
+        if let Some(start) = start {
+            let duration = start.elapsed();
+            self.last_update = Some(duration);
+            self.trace(label(), Some(duration));
+        }
+    }
+
+    /// Records a folder listing timed elsewhere and traces it.
+    pub fn record_listing(&mut self, duration: Duration, label: impl FnOnce() -> String) {
+        if self.enabled {
+            self.last_listing = Some(duration);
+            self.trace(label(), Some(duration));
+        }
+    }
+
+    /// Adds an event to the trace, dropping the oldest once full.
+    pub fn trace(&mut self, label: String, duration: Option<Duration>) {
+        if !self.enabled {
+            return;
+        }
+        self.trace.push_back(TraceEvent {
+            at: Local::now(),
+            label,
+            duration,
+        });
+        if self.trace.len() > TRACE_SIZE {
+            self.trace.pop_front();
+        }
+    }
+
+    pub fn get_last_draw(&self) -> Option<Duration> {
+        self.last_draw
+    }
+
+    pub fn get_last_update(&self) -> Option<Duration> {
+        self.last_update
+    }
+
+    pub fn get_last_listing(&self) -> Option<Duration> {
+        self.last_listing
+    }
+
+    pub fn get_trace(&self) -> &VecDeque<TraceEvent> {
+        &self.trace
+    }
+}
@@ -0,0 +1,185 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+/// The hash of a folder tree and the entries left out of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeHash {
+    /// The root hash as hex.
+    pub digest: String,
+    /// Entries that could not be read, e.g. for lack of permissions.
+    pub unreadable: usize,
+}
+
+/// Hashes a file from its name and contents, or a folder from its name and
+/// the hashes of its children in name order. Symbolic links are skipped,
+/// children that cannot be read are counted in `unreadable`. Gives up once
+/// `cancelled` is set.
+fn hash_node(path: &Path, unreadable: &mut usize, cancelled: &AtomicBool) -> Option<[u8; 32]> {
+    let Ok(meta) = path.symlink_metadata() else {
+        *unreadable += 1;
+        return None;
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(
+        path.file_name()
+            .map_or(&[][..], |name| name.as_encoded_bytes()),
+    );
+    hasher.update([0]);
+    if meta.is_file() {
+        hasher.update(b"file");
+        if hash_contents(path, &mut hasher, cancelled).is_err() {
+            *unreadable += 1;
+            return None;
+        }
+    } else if meta.is_dir() {
+        hasher.update(b"dir");
+        let Ok(entries) = fs::read_dir(path) else {
+            *unreadable += 1;
+            return None;
+        };
+        let mut children = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => children.push(entry.path()),
+                Err(_err) => *unreadable += 1,
+            }
+        }
+        children.sort();
+        for child in children {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(digest) = hash_node(&child, unreadable, cancelled) {
+                hasher.update(digest);
+            }
+        }
+    } else {
+        return None;
+    }
+
+    Some(hasher.finalize().into())
+}
+
+/// Streams the contents of the file at `path` into `hasher` a block at a
+/// time, so that large files are never held in memory. Stops early once
+/// `cancelled` is set.
+fn hash_contents(path: &Path, hasher: &mut Sha256, cancelled: &AtomicBool) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    while !cancelled.load(Ordering::Relaxed) {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Hashes the trees of `dirs` one after another into a single root hash.
+/// The walk stops early once `cancelled` is set, its hash is then of no use.
+pub fn hash_dirs(dirs: &[PathBuf], cancelled: &AtomicBool) -> TreeHash {
+    let mut unreadable = 0;
+    let digest = match dirs {
+        [dir] => hash_node(dir, &mut unreadable, cancelled).unwrap_or_default(),
+        dirs => {
+            let mut hasher = Sha256::new();
+            for dir in dirs {
+                hasher.update(hash_node(dir, &mut unreadable, cancelled).unwrap_or_default());
+            }
+            hasher.finalize().into()
+        }
+    };
+
+    TreeHash {
+        digest: digest.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        unreadable,
+    }
+}
+
+/// Hashes folder trees on a worker thread, one at a time.
+pub struct BackgroundHasher {
+    sender: Sender<(usize, TreeHash)>,
+    receiver: Receiver<(usize, TreeHash)>,
+    /// The folder being hashed, with the number of the walk so that the
+    /// result of a cancelled one is told apart.
+    pending: Option<(usize, PathBuf, Arc<AtomicBool>)>,
+    walks: usize,
+}
+
+impl Default for BackgroundHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundHasher {
+    pub fn new() -> BackgroundHasher {
+        let (sender, receiver) = mpsc::channel();
+        BackgroundHasher {
+            sender,
+            receiver,
+            pending: None,
+            walks: 0,
+        }
+    }
+
+    /// Starts hashing `dirs` under the name `dir`, cancelling the hash
+    /// being computed, which may be out of date by now.
+    pub fn start(&mut self, dir: PathBuf, dirs: Vec<PathBuf>) {
+        self.cancel();
+
+        self.walks += 1;
+        let walk = self.walks;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending = Some((walk, dir, Arc::clone(&cancelled)));
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let hash = hash_dirs(&dirs, &cancelled);
+            if !cancelled.load(Ordering::Relaxed) {
+                // The receiver only goes away with the manager itself.
+                let _ = sender.send((walk, hash));
+            }
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some((_walk, _dir, cancelled)) = self.pending.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_pending(&self) -> Option<&Path> {
+        self.pending
+            .as_ref()
+            .map(|(_walk, dir, _cancelled)| dir.as_path())
+    }
+
+    /// The finished hash of the folder being hashed, if it has arrived.
+    pub fn poll(&mut self) -> Option<(PathBuf, TreeHash)> {
+        while let Ok((walk, hash)) = self.receiver.try_recv() {
+            if self
+                .pending
+                .as_ref()
+                .is_some_and(|(pending, _dir, _cancelled)| *pending == walk)
+            {
+                let (_walk, dir, _cancelled) = self.pending.take()?;
+                return Some((dir, hash));
+            }
+        }
+
+        None
+    }
+}
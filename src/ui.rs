@@ -0,0 +1,1801 @@
+use crate::{
+    app::{App, Confirm, Mode, Prompt, SplitLayout},
+    batch::BatchOp,
+    checksum::Checksum,
+    error::MystoreError,
+    manager::{Action, FileManager, ManagerEntity, SortOrder},
+    sizes::{format_count, format_size, FolderSize},
+    timings::format_duration,
+    viewer::{LogStats, Viewer, ViewerEntity},
+    whitespace::reveal_whitespace,
+};
+use chrono::{DateTime, Local, Utc};
+use std::path::Path;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{
+        self, BarChart, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table,
+    },
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Width of the file size column shown while sorting by size.
+const SIZE_COLUMN: usize = 10;
+/// Narrowest names the modification time column leaves room for.
+const MIN_NAME_WIDTH: usize = 16;
+
+fn describe_size(size: &FolderSize) -> String {
+    let mut text = format!(
+        "{} in {} files",
+        format_size(size.bytes),
+        format_count(size.files as u64)
+    );
+    if size.unreadable > 0 {
+        text.push_str(&format!(", {} unreadable", size.unreadable));
+    }
+    text
+}
+
+fn draw_session_status<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    app: &App,
+    now: DateTime<Local>,
+) {
+    let formats = app.get_manager_ref().get_time_formats();
+    let mut status = vec![Span::raw(formats.display(&now))];
+    if let Some(ManagerEntity::Folder(path)) = app.get_manager_ref().get_selected_entity() {
+        let sizer = app.get_sizer_ref();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(size) = sizer.get_size(path) {
+            status.push(Span::raw(format!(" | {}: {}", name, describe_size(size))));
+        } else if sizer.get_pending() == Some(path) {
+            status.push(Span::raw(format!(" | {}: sizing…", name)));
+        }
+    }
+    if let Some((done, total)) = app.get_batch_ref().get_progress() {
+        let verb = match app.get_batch_ref().get_op() {
+            BatchOp::Encrypt => "encrypting",
+            BatchOp::Decrypt => "decrypting",
+        };
+        status.push(Span::raw(format!(" | {} {}/{}…", verb, done, total)));
+    }
+    if let Some(free) = app.get_free_space() {
+        status.push(Span::raw(format!(" | {} free", format_size(free))));
+    }
+    if let Some(oid) = app.get_last_commit() {
+        let hash = oid.to_string();
+        status.push(Span::raw(format!(" | committed {}", &hash[..7])));
+    }
+    if app.is_scratch() {
+        status.push(Span::styled(
+            " [scratch session]",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    match app.is_git_dirty() {
+        Some(true) => status.push(Span::styled(
+            " git: dirty",
+            Style::default().fg(Color::Yellow),
+        )),
+        Some(false) => status.push(Span::styled(
+            " git: clean",
+            Style::default().fg(Color::Green),
+        )),
+        None => (),
+    }
+    match app.get_manager_ref().is_tree_changed() {
+        Some(true) => status.push(Span::styled(
+            " ⚠ Directory modified",
+            Style::default().fg(Color::Yellow),
+        )),
+        Some(false) => status.push(Span::styled(
+            " ✓ No changes",
+            Style::default().fg(Color::Green),
+        )),
+        None => (),
+    }
+    match app.get_manager_ref().get_hash_unreadable() {
+        0 => (),
+        unreadable => status.push(Span::styled(
+            format!(" ({} unreadable)", unreadable),
+            Style::default().fg(Color::Yellow),
+        )),
+    }
+    if app.is_clipboard_watched() {
+        status.push(Span::styled(
+            " [clipboard watch]",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if app.is_recording() {
+        status.push(Span::raw(" "));
+        status.push(Span::styled(
+            "● REC",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    let paragraph = Paragraph::new(Spans::from(status)).block(
+        Block::default()
+            .border_style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .title(match app.get_key_fingerprint() {
+                Some(fingerprint) => format!("Session | key {}", fingerprint),
+                None => String::from("Session"),
+            })
+            .borders(Borders::ALL),
+    );
+    frame.render_widget(paragraph, area)
+}
+
+/// Draws rows from `scroll` on, each column as wide as its widest cell.
+fn draw_table<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    title: &str,
+    rows: &[Vec<String>],
+    scroll: u16,
+) {
+    let mut widths: Vec<u16> = Vec::new();
+    for row in rows {
+        for (column, cell) in row.iter().enumerate() {
+            let width = u16::try_from(cell.chars().count()).unwrap_or(u16::MAX);
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    let widths: Vec<Constraint> = widths.into_iter().map(Constraint::Length).collect();
+    let rows = rows
+        .iter()
+        .skip(usize::from(scroll))
+        .map(|row| Row::new(row.iter().map(String::as_str)));
+    let table = Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .title(title),
+        )
+        .widths(&widths);
+    frame.render_widget(table, area)
+}
+
+/// One summary per line, the selected one highlighted and long ones cut
+/// to the pane width.
+fn draw_json_lines<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    title: &str,
+    records: &[serde_json::Value],
+    viewer: &Viewer,
+) {
+    let width = usize::from(area.width.saturating_sub(2));
+    let lines: Vec<Spans> = records
+        .iter()
+        .enumerate()
+        .skip(usize::from(viewer.get_scroll()))
+        .map(|(id, record)| {
+            let summary = Viewer::summarize_record(record);
+            let summary = if summary.chars().count() > width {
+                let cut: String = summary.chars().take(width.saturating_sub(1)).collect();
+                format!("{}…", cut)
+            } else {
+                summary
+            };
+            let style = if id == viewer.get_selected_record() {
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Spans::from(Span::styled(summary, style))
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .title(title),
+    );
+    frame.render_widget(paragraph, area)
+}
+
+/// Most of `area`, leaving a margin for what the popup is drawn over.
+fn popup_area(area: Rect) -> Rect {
+    Rect::new(
+        area.x + 2.min(area.width / 4),
+        area.y + 1.min(area.height / 4),
+        area.width.saturating_sub(4.min(area.width / 2)),
+        area.height.saturating_sub(2.min(area.height / 2)),
+    )
+}
+
+/// The selected JSON line pretty-printed over the viewer.
+fn draw_json_record<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let viewer = app.get_viewer_ref();
+    let Some(record) = viewer.get_record_ref() else {
+        return;
+    };
+    let popup = popup_area(area);
+    let title = format!("Line {}", viewer.get_selected_record() + 1);
+    let paragraph = Paragraph::new(serde_json::to_string_pretty(record).unwrap_or_default())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .scroll((app.get_record_scroll(), 0));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(paragraph, popup)
+}
+
+/// Lists the marks of the shown file with the line each one shows on top.
+fn draw_marks<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let viewer = app.get_viewer_ref();
+    let marks = viewer.get_marks();
+    let lines: Vec<String> = match marks.is_empty() {
+        true => vec![String::from("No marks yet")],
+        false => marks
+            .iter()
+            .map(|(letter, scroll)| {
+                let line = viewer.get_layout_ref().get_line_at(usize::from(*scroll)) + 1;
+                format!("'{}  line {}", letter, line)
+            })
+            .collect(),
+    };
+    let width = (lines.iter().map(String::len).max().unwrap_or_default() + 4) as u16;
+    let height = lines.len() as u16 + 2;
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width + 1),
+        area.y + 1,
+        width.min(area.width),
+        height.min(area.height.saturating_sub(1)),
+    );
+    let paragraph = Paragraph::new(lines.join("\n")).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Marks")
+            .border_style(Style::default().add_modifier(Modifier::BOLD)),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(paragraph, popup)
+}
+
+/// Timings of the latest draw, event and listing, and the latest events
+/// as far as they fit, over the right of `area`.
+fn draw_debug_overlay<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let timings = app.get_timings_ref();
+    let entities = app.get_manager_ref().get_entities_ref();
+    let mut lines = vec![
+        format!("Draw      {}", format_duration(timings.get_last_draw())),
+        format!("Update    {}", format_duration(timings.get_last_update())),
+        format!("Listing   {}", format_duration(timings.get_last_listing())),
+        format!(
+            "Entities  {} (capacity {})",
+            entities.len(),
+            entities.capacity()
+        ),
+        String::from("Events:"),
+    ];
+    let room = usize::from(area.height.saturating_sub(2)).saturating_sub(lines.len());
+    let trace = timings.get_trace();
+    lines.extend(
+        trace
+            .iter()
+            .skip(trace.len().saturating_sub(room))
+            .map(|event| {
+                format!(
+                    "{} {:>8}  {}",
+                    event.at.format("%H:%M:%S%.3f"),
+                    format_duration(event.duration),
+                    event.label
+                )
+            }),
+    );
+    let width = (area.width / 2).max(40.min(area.width));
+    let popup = Rect::new(area.x + area.width - width, area.y, width, area.height);
+    let paragraph = Paragraph::new(lines.join("\n")).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Debug")
+            .border_style(Style::default().add_modifier(Modifier::BOLD)),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(paragraph, popup)
+}
+
+/// One line with the mode, the number of listed files, the cursor and word
+/// count of the editor, whether the open text is encrypted and how long the
+/// session has lasted at `now`.
+pub fn mode_line(app: &App, now: DateTime<Local>) -> String {
+    let mode = app.get_mode();
+    let files = app
+        .get_manager_ref()
+        .get_entities_ref()
+        .iter()
+        .filter(|entity| matches!(entity, ManagerEntity::TextFile(_path)))
+        .count();
+    let mut parts = vec![
+        String::from(mode.name()),
+        match files {
+            1 => String::from("1 file"),
+            files => format!("{} files", files),
+        },
+    ];
+    let editor = app.get_editor_ref();
+    let in_editor = mode.is_editing();
+    let encrypted = match editor.get_textarea_ref() {
+        Some(textarea) if in_editor => {
+            let (row, column) = textarea.cursor();
+            parts.push(format!("Ln {}, Col {}", row + 1, column + 1));
+            Some(
+                editor
+                    .get_target()
+                    .is_some_and(|(_path, encrypted)| encrypted),
+            )
+        }
+        _ => app.get_viewer_ref().get_name().map(|_name| {
+            matches!(
+                app.get_viewer_ref().get_entity_ref(),
+                ViewerEntity::DecryptedText(_text)
+            )
+        }),
+    };
+    match encrypted {
+        Some(true) => parts.push(String::from("[ENC]")),
+        Some(false) => parts.push(String::from("[PLAIN]")),
+        None => (),
+    }
+    if in_editor {
+        parts.push(format!("{} words", editor.get_word_count()));
+    }
+    let session_start = app.get_manager_ref().get_session_start();
+    let seconds = (now.with_timezone(&Utc) - session_start)
+        .num_seconds()
+        .max(0);
+    parts.push(format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    ));
+    parts.join(" | ")
+}
+
+fn draw_mode_line<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App, now: DateTime<Local>) {
+    let paragraph = Paragraph::new(mode_line(app, now))
+        .style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_widget(paragraph, area)
+}
+
+fn draw_help<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App, title: &str) {
+    let paragraph = Paragraph::new(app.get_mode().to_string())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .wrap(widgets::Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area)
+}
+
+/// A box holding `lines` rows of text across the bottom of `area`, with
+/// the margin of `popup_area`, for the error, prompt, question or message
+/// over the panes.
+fn notice_area(area: Rect, lines: u16) -> Rect {
+    let popup = popup_area(area);
+    let height = (lines + 2).min(popup.height);
+    Rect::new(
+        popup.x,
+        popup.y + popup.height - height,
+        popup.width,
+        height,
+    )
+}
+
+fn draw_message<B: Backend>(frame: &mut Frame<B>, area: Rect, message: &str) {
+    let paragraph = Paragraph::new(message)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Info")
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .style(Style::default().fg(Color::Green))
+        .wrap(widgets::Wrap { trim: true });
+    frame.render_widget(paragraph, area)
+}
+
+fn draw_prompt<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App, prompt: &Prompt) {
+    let paragraph = Paragraph::new(format!("{}_", app.get_input()))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(prompt.to_string())
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .wrap(widgets::Wrap { trim: false });
+    frame.render_widget(paragraph, area)
+}
+
+fn draw_error<B: Backend>(frame: &mut Frame<B>, area: Rect, err: &MystoreError) {
+    let color = match err {
+        MystoreError::Io(_) => Color::Red,
+        MystoreError::NotDeletable(_)
+        | MystoreError::NotAFile(..)
+        | MystoreError::OutsideRoot(_) => Color::Yellow,
+        MystoreError::DecryptFailed
+        | MystoreError::WrongKey(_)
+        | MystoreError::PasswordMismatch
+        | MystoreError::KeyringLocked => Color::Magenta,
+        MystoreError::NameCollision(_) | MystoreError::Locked(_) => Color::Yellow,
+        MystoreError::LowSpace(_) => Color::Yellow,
+        MystoreError::ReadOnly(_) => Color::Cyan,
+        MystoreError::Git(_) => Color::Red,
+    };
+    let paragraph = Paragraph::new(err.to_string())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(err.title())
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .style(Style::default().fg(color))
+        .wrap(widgets::Wrap { trim: true });
+    frame.render_widget(paragraph, area)
+}
+
+/// Shows a checksum over the middle of `area`, the digest on a line of its own.
+fn draw_checksum<B: Backend>(frame: &mut Frame<B>, area: Rect, checksum: &Checksum) {
+    let height = 6.min(area.height);
+    let popup = Rect::new(
+        area.x,
+        area.y + (area.height - height) / 2,
+        area.width,
+        height,
+    );
+    let name = checksum.path.file_name().unwrap_or_default();
+    let text = format!(
+        "{} ({})\n{}\nY: Copy it to the clipboard; Other: Close",
+        name.to_string_lossy(),
+        format_size(checksum.size),
+        checksum.digest
+    );
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("SHA-256")
+                .border_style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .wrap(widgets::Wrap { trim: false });
+    frame.render_widget(Clear, popup);
+    frame.render_widget(paragraph, popup)
+}
+
+/// Adds the bookmark count and the auto-refresh interval to a viewer title.
+fn decorate_title(viewer: &Viewer, title: String) -> String {
+    let title = match viewer.get_line_bookmarks().len() {
+        0 => title,
+        count => format!("{} [▸ {}]", title, count),
+    };
+    let marks = viewer.get_marks();
+    let title = match marks.is_empty() {
+        true => title,
+        false => format!("{} ['{}]", title, marks.keys().collect::<String>()),
+    };
+    let title = match viewer.get_auto_refresh() {
+        Some(interval) => format!("{} [auto-refresh {}s]", title, interval.as_secs()),
+        None => title,
+    };
+    let title = if viewer.get_show_whitespace() {
+        format!("{} [whitespace]", title)
+    } else {
+        title
+    };
+    let title = if viewer.is_just_reloaded() {
+        format!("{} [reloaded]", title)
+    } else {
+        title
+    };
+    match viewer.get_scroll_percent() {
+        Some(percent) => format!("{} {}%", title, percent),
+        None => title,
+    }
+}
+
+/// The title followed by the count of log lines at each level, errors in
+/// red and warnings in yellow.
+fn log_title(title: String, stats: LogStats) -> Spans<'static> {
+    Spans::from(vec![
+        Span::raw(format!("{} | ", title)),
+        Span::styled(
+            format!("E:{}", stats.errors),
+            Style::default().fg(Color::Red),
+        ),
+        Span::raw(" "),
+        Span::styled(
+            format!("W:{}", stats.warnings),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::raw(format!(" I:{} D:{}", stats.infos, stats.debugs)),
+    ])
+}
+
+fn draw_viewer<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    draw_viewer_pane(frame, area, app.get_viewer_ref(), "")
+}
+
+/// The halves of the right-hand pane the two viewers of a split view get.
+fn split_areas(area: Rect, layout: SplitLayout) -> (Rect, Rect) {
+    let direction = match layout {
+        SplitLayout::Stacked => tui::layout::Direction::Vertical,
+        SplitLayout::SideBySide => tui::layout::Direction::Horizontal,
+    };
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    (chunks[0], chunks[1])
+}
+
+fn draw_split<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let Some(split) = app.get_split() else {
+        return draw_viewer(frame, area, app);
+    };
+    let (first, second) = split_areas(area, split.layout);
+    let prefix = |focused: bool| match (focused, split.scroll_lock) {
+        (true, true) => "▶ [locked] ",
+        (true, false) => "▶ ",
+        (false, true) => "[locked] ",
+        (false, false) => "",
+    };
+    draw_viewer_pane(
+        frame,
+        first,
+        app.get_viewer_ref(),
+        prefix(!split.second_focused),
+    );
+    draw_viewer_pane(
+        frame,
+        second,
+        app.get_second_viewer_ref(),
+        prefix(split.second_focused),
+    );
+}
+
+/// Draws a viewer with `prefix` in front of its title.
+fn draw_viewer_pane<B: Backend>(frame: &mut Frame<B>, area: Rect, viewer: &Viewer, prefix: &str) {
+    let title_of = |title: String| format!("{}{}", prefix, decorate_title(viewer, title));
+    if viewer.is_loading() {
+        let title = format!("{}{}", prefix, viewer.get_name().unwrap_or_default());
+        let paragraph = Paragraph::new("Loading…").block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray))
+                .title(title),
+        );
+        frame.render_widget(paragraph, area);
+        return;
+    }
+
+    let entity = viewer.get_entity_ref();
+    let paragraph = match entity {
+        ViewerEntity::Text(text) => {
+            let text = match viewer.get_show_whitespace() {
+                true => reveal_whitespace(text),
+                false => Text::from(text.as_str()),
+            };
+            let title = viewer.get_name().unwrap_or("Text File");
+            let title = match viewer.get_encoding() {
+                Some(encoding) => format!("{} [{}]", title, encoding.name()),
+                None => String::from(title),
+            };
+            let title = match viewer.get_log_stats() {
+                Some(stats) => log_title(title_of(title), stats),
+                None => Spans::from(title_of(title)),
+            };
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(
+                            Style::default()
+                                .fg(Color::White)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .title(title),
+                )
+                // Leading whitespace is kept while whitespace is shown.
+                .wrap(widgets::Wrap {
+                    trim: !viewer.get_show_whitespace(),
+                })
+                .scroll((viewer.get_scroll(), 0))
+        }
+        ViewerEntity::DecryptedText(text) => {
+            let text = match viewer.get_show_whitespace() {
+                true => reveal_whitespace(text),
+                false => Text::from(text.as_str()),
+            };
+            let title = title_of(String::from(viewer.get_name().unwrap_or("Encrypted File")));
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(
+                            Style::default()
+                                .fg(Color::Green)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .title(title),
+                )
+                .wrap(widgets::Wrap {
+                    trim: !viewer.get_show_whitespace(),
+                })
+                .scroll((viewer.get_scroll(), 0))
+        }
+        ViewerEntity::Table(rows) => {
+            let title = title_of(String::from(viewer.get_name().unwrap_or("Table")));
+            return draw_table(frame, area, &title, rows, viewer.get_scroll());
+        }
+        ViewerEntity::Json(value) => {
+            let text = serde_json::to_string_pretty(value).unwrap_or_default();
+            let title = title_of(String::from(viewer.get_name().unwrap_or("JSON")));
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(
+                            Style::default()
+                                .fg(Color::White)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .title(title),
+                )
+                .scroll((viewer.get_scroll(), 0))
+        }
+        ViewerEntity::JsonLines(records) => {
+            let title = title_of(String::from(viewer.get_name().unwrap_or("JSON")));
+            return draw_json_lines(frame, area, &title, records, viewer);
+        }
+        ViewerEntity::Binary(_bin) => {
+            let text = Text::from("Binary file");
+            let title = title_of(String::from(viewer.get_name().unwrap_or("Binary File")));
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(title)
+                        .border_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                        .style(Style::default().fg(Color::Red)),
+                )
+                .wrap(widgets::Wrap { trim: true })
+        }
+    };
+    frame.render_widget(paragraph, area)
+}
+
+/// Prefixes an entry with the name of its root when several are listed.
+fn source_label(manager: &FileManager, path: &Path, name: String) -> String {
+    if !manager.is_multi_root() {
+        return name;
+    }
+    match manager
+        .get_source_root(path)
+        .and_then(|root| root.file_name())
+    {
+        Some(root) => format!("[{}] {}", root.to_string_lossy(), name),
+        None => name,
+    }
+}
+
+fn draw_manager<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let manager = app.get_manager_ref();
+    let list_data = manager.get_entities_ref();
+    let items: Vec<ListItem> = list_data
+        .iter()
+        .map(|entity| match entity {
+            ManagerEntity::TextFile(path) => {
+                let name = path.file_name().map_or("Unknown text file", |str| {
+                    str.to_str().map_or("Unknown text name", |name| name)
+                });
+                let name = if manager.is_locked(path) {
+                    format!("{} 🔒", name)
+                } else {
+                    String::from(name)
+                };
+                let name = source_label(manager, path, name);
+                let name = if manager.is_marked(path) {
+                    format!("* {}", name)
+                } else {
+                    name
+                };
+                let name = match manager.get_sort_order() {
+                    SortOrder::Size => {
+                        let size = manager
+                            .get_file_size(path)
+                            .map_or(String::from("?"), format_size);
+                        // Long names are cut so that the column stays aligned.
+                        let width = usize::from(area.width).saturating_sub(SIZE_COLUMN + 3);
+                        let name: String = name.chars().take(width).collect();
+                        format!("{:<width$} {:>SIZE_COLUMN$}", name, size)
+                    }
+                    SortOrder::Modified => match manager.get_modified(path) {
+                        Some(modified) => {
+                            let modified = manager
+                                .get_time_formats()
+                                .display(&DateTime::<Local>::from(modified));
+                            // Only a pane wide enough for the names gets the column.
+                            let width =
+                                usize::from(area.width).saturating_sub(modified.width() + 3);
+                            match width >= MIN_NAME_WIDTH {
+                                true => {
+                                    let name: String = name.chars().take(width).collect();
+                                    format!("{:<width$} {}", name, modified)
+                                }
+                                false => name,
+                            }
+                        }
+                        None => name,
+                    },
+                    SortOrder::Name => name,
+                };
+                ListItem::new(name).style(Style::default().fg(Color::White))
+            }
+            ManagerEntity::Folder(path) => {
+                let name = path.file_name().map_or("Unknown folder", |str| {
+                    str.to_str().map_or("Unknown folder name", |name| name)
+                });
+                let name = match app.get_sizer_ref().get_size(path) {
+                    Some(size) => format!("{} ({})", name, format_size(size.bytes)),
+                    None => String::from(name),
+                };
+                let name = source_label(manager, path, name);
+                ListItem::new(name).style(Style::default().fg(Color::Blue))
+            }
+            ManagerEntity::Action(act) => match act {
+                Action::Back => ListItem::new("Back").style(Style::default().fg(Color::Blue)),
+                Action::Root => ListItem::new("Root").style(Style::default().fg(Color::Green)),
+                Action::Custom(name) => {
+                    ListItem::new(name.as_str()).style(Style::default().fg(Color::Magenta))
+                }
+            },
+        })
+        .collect();
+    let title = match manager.get_filter_label() {
+        Some(label) => format!("#{}", label),
+        None => String::from(manager.get_current().to_str().unwrap_or("Folder")),
+    };
+    let title = match manager.get_extension_filter() {
+        Some(extensions) => format!("[.{}] {}", extensions.join(" ."), title),
+        None => title,
+    };
+    let title = match manager.get_page() {
+        (_page, 1) => title,
+        (page, count) => format!("Page {}/{} {}", page, count, title),
+    };
+    let title = if manager.is_readonly() {
+        format!("{} [read-only]", title)
+    } else {
+        title
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Yellow),
+        );
+    let mut state = ListState::default();
+    state.select(manager.get_selected_id());
+    frame.render_stateful_widget(list, area, &mut state);
+    draw_recovery_banner(frame, area, app);
+}
+
+/// Tells over the top border of the manager about texts recovered from a
+/// crash, until they are all opened.
+fn draw_recovery_banner<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let Some(latest) = app.get_recovery_files().first() else {
+        return;
+    };
+    let name = latest.file_name().unwrap_or_default().to_string_lossy();
+    let banner = match app.get_recovery_files().len() {
+        1 => format!(" R: open {} recovered from a crash ", name),
+        count => format!(
+            " R: open {} recovered from a crash, {} more ",
+            name,
+            count - 1
+        ),
+    };
+    let banner_area = Rect {
+        x: area.x + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.min(1),
+        ..area
+    };
+    let paragraph = Paragraph::new(banner).style(
+        Style::default()
+            .fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_widget(paragraph, banner_area);
+}
+
+fn draw_editor<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let editor = app.get_editor_ref();
+    if let Some(textarea) = editor.get_textarea_ref() {
+        let mut label = match (editor.is_read_only(), editor.get_target()) {
+            (true, _target) => format!("Read-only: {}", editor.get_title().unwrap_or_default()),
+            (false, Some((path, encrypted))) => {
+                let root = app.get_manager_ref().get_root();
+                let path = path.strip_prefix(root).unwrap_or(path);
+                match encrypted {
+                    true => format!("Editing: {} [encrypted]", path.display()),
+                    false => format!("Editing: {}", path.display()),
+                }
+            }
+            (false, None) => String::from("New note"),
+        };
+        if app.has_unsaved_changes() {
+            label.push_str(" [modified]");
+        }
+        // The cursor position is on the mode line.
+        let mut title = format!(" | Words: {}", editor.get_word_count());
+        match editor.get_insert_spaces() {
+            true => title.push_str(&format!(" | Spaces: {}", editor.get_tab_width())),
+            false => title.push_str(&format!(" | Tab width: {}", editor.get_tab_width())),
+        }
+        if editor.get_expand_variables() {
+            title.push_str(" | $vars");
+        }
+        // A long path gives way from its front, so that the counts and the
+        // indentation stay in view.
+        let room = usize::from(area.width.saturating_sub(2)).saturating_sub(title.width());
+        if label.width() > room {
+            let kept: Vec<char> = label.chars().rev().take(room.saturating_sub(1)).collect();
+            label = kept.into_iter().rev().collect();
+            if room > 0 {
+                label.insert(0, '…');
+            }
+        }
+        title.insert_str(0, &label);
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        frame.render_widget(textarea.widget(), inner);
+    }
+}
+
+fn draw_rename<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let chunks = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)])
+        .split(area);
+    let mut textarea = app.get_rename_ref().clone();
+    textarea.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Pattern and replacement"),
+    );
+    frame.render_widget(textarea.widget(), chunks[0]);
+
+    let (pattern, replacement) = app.get_rename_pattern();
+    let items: Vec<ListItem> = match app.get_manager_ref().preview_rename(pattern, replacement) {
+        Ok(renames) => renames
+            .iter()
+            .map(|(from, to)| {
+                let name = |path: &std::path::Path| {
+                    path.file_name()
+                        .map_or(String::new(), |name| name.to_string_lossy().into_owned())
+                };
+                ListItem::new(format!("{} → {}", name(from), name(to)))
+            })
+            .collect(),
+        Err(err) => vec![ListItem::new(err.to_string()).style(Style::default().fg(Color::Red))],
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Preview")
+            .border_style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+    );
+    frame.render_widget(list, chunks[1]);
+}
+
+fn draw_commit<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let chunks = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)])
+        .split(area);
+    let mut textarea = app.get_commit_ref().clone();
+    textarea.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Commit message"),
+    );
+    frame.render_widget(textarea.widget(), chunks[0]);
+
+    let manager = app.get_manager_ref();
+    let items: Vec<ListItem> = manager
+        .get_created_files()
+        .iter()
+        .map(|path| {
+            ListItem::new(FileManager::format_path(
+                path,
+                manager.get_source_root(path).unwrap_or(manager.get_root()),
+                true,
+            ))
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Files to commit")
+            .border_style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+    );
+    frame.render_widget(list, chunks[1]);
+}
+
+fn draw_grep<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let chunks = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+    let mut textarea = app.get_grep_ref().clone();
+    textarea.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Search the listed files"),
+    );
+    frame.render_widget(textarea.widget(), chunks[0]);
+
+    let results = app.get_grep_results_ref();
+    let items: Vec<ListItem> = results
+        .iter()
+        .map(|result| {
+            let name = result
+                .path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+            ListItem::new(format!(
+                "{}:{}: {}",
+                name, result.line_number, result.line_text
+            ))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} matches", format_count(results.len() as u64)))
+                .border_style(
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Yellow),
+        );
+    let mut state = ListState::default();
+    if !results.is_empty() {
+        state.select(Some(app.get_grep_selected()));
+    }
+    frame.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+fn draw_choices<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App, title: &str) {
+    let items: Vec<ListItem> = app
+        .get_choices_ref()
+        .iter()
+        .map(|name| ListItem::new(name.as_str()).style(Style::default().fg(Color::White)))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Yellow),
+        );
+    let mut state = ListState::default();
+    state.select(Some(app.get_choice_selected()));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Lists every group of identical files under a header with its size.
+fn draw_duplicates<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let manager = app.get_manager_ref();
+    let marked = app.get_duplicate_marked_ref();
+    let (selected_group, selected_copy) = app.get_duplicate_selected();
+    let mut items = Vec::new();
+    let mut selected = 0;
+    for (group_id, group) in app.get_duplicates_ref().iter().enumerate() {
+        let size = group
+            .first()
+            .and_then(|path| path.metadata().ok())
+            .map_or(0, |meta| meta.len());
+        items.push(
+            ListItem::new(format!("{} copies of {}", group.len(), format_size(size)))
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        );
+        for (copy_id, path) in group.iter().enumerate() {
+            if (group_id, copy_id) == (selected_group, selected_copy) {
+                selected = items.len();
+            }
+            let relative = manager
+                .get_source_root(path)
+                .and_then(|root| path.strip_prefix(root).ok())
+                .unwrap_or(path);
+            let mark = if marked.contains(path) { "[x]" } else { "[ ]" };
+            items.push(ListItem::new(format!("  {} {}", mark, relative.display())));
+        }
+    }
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Files with the same contents")
+                .border_style(
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Yellow),
+        );
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// One bar per listed file, the largest one spanning the room left by the
+/// names and sizes, above the total of them all.
+fn draw_size_chart<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("File sizes")
+        .border_style(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chart = app.get_size_chart_ref();
+    let labels: Vec<String> = chart
+        .iter()
+        .map(|(_name, size)| format_size(*size))
+        .collect();
+    let name_width = chart
+        .iter()
+        .map(|(name, _size)| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .min(inner.width as usize / 3);
+    let size_width = labels.iter().map(String::len).max().unwrap_or_default();
+    let bar_width = (inner.width as usize).saturating_sub(name_width + size_width + 2);
+    let largest = chart.first().map_or(1, |(_name, size)| (*size).max(1));
+    let bars: Vec<Spans> = chart
+        .iter()
+        .zip(&labels)
+        .map(|((name, size), label)| {
+            let length = (*size as u128 * bar_width as u128 / largest as u128) as usize;
+            let name: String = name.chars().take(name_width).collect();
+            Spans::from(vec![
+                Span::raw(format!("{:<width$} ", name, width = name_width)),
+                Span::styled("█".repeat(length), Style::default().fg(Color::Cyan)),
+                Span::raw(format!(
+                    "{} {:>width$}",
+                    " ".repeat(bar_width - length),
+                    label,
+                    width = size_width
+                )),
+            ])
+        })
+        .collect();
+    let total: u64 = chart.iter().map(|(_name, size)| size).sum();
+
+    let chunks = Layout::default()
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    frame.render_widget(Paragraph::new(bars), chunks[0]);
+    frame.render_widget(
+        Paragraph::new(format!(
+            "Total: {} in {} files",
+            format_size(total),
+            format_count(chart.len() as u64)
+        ))
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+        chunks[1],
+    );
+}
+
+/// One bar per age bucket, labelled with its name and file count.
+fn draw_age_chart<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let chart = app.get_age_chart_ref();
+    let labels: Vec<String> = chart
+        .iter()
+        .map(|(name, count)| format!("{} ({})", name, count))
+        .collect();
+    let data: Vec<(&str, u64)> = labels
+        .iter()
+        .zip(chart)
+        .map(|(label, (_name, count))| (label.as_str(), *count as u64))
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("File ages")
+        .border_style(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+    // The bars share the width, each followed by a gap of one column.
+    let bars = data.len().max(1) as u16;
+    let bar_width = (block.inner(area).width / bars).saturating_sub(1).max(1);
+    let bar_chart = BarChart::default()
+        .block(block)
+        .data(&data)
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+        .label_style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(bar_chart, area);
+}
+
+/// Hides the files while the session waits for the password.
+fn draw_reauth<B: Backend>(frame: &mut Frame<B>, area: Rect, app: &App) {
+    let paragraph = Paragraph::new(format!(
+        "Password: {}_",
+        "*".repeat(app.get_password().chars().count())
+    ))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Session locked")
+            .border_style(Style::default().add_modifier(Modifier::BOLD)),
+    );
+    frame.render_widget(paragraph, area)
+}
+
+/// Splits the screen into the status and panes rows above the one-row mode
+/// line at the bottom, and the middle row into the manager, `manager_width`
+/// percent wide, and the viewer.
+fn split_screen(area: Rect, manager_width: u16) -> (Vec<Rect>, Vec<Rect>) {
+    let vertical_chunks = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(10),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(area);
+    let horizontal_chunks = Layout::default()
+        .direction(tui::layout::Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(manager_width),
+            Constraint::Percentage(100 - manager_width),
+        ])
+        .split(vertical_chunks[1]);
+
+    (vertical_chunks, horizontal_chunks)
+}
+
+/// The manager pane and the right-hand pane on a screen of the size of `area`.
+pub fn pane_areas(area: Rect, manager_width: u16) -> (Rect, Rect) {
+    let (_vertical_chunks, horizontal_chunks) = split_screen(area, manager_width);
+    (horizontal_chunks[0], horizontal_chunks[1])
+}
+
+/// Width and height of the text inside the borders of the two viewers of a
+/// split view on a screen of the size of `area`.
+pub fn split_pane_sizes(
+    area: Rect,
+    manager_width: u16,
+    layout: SplitLayout,
+) -> ((u16, u16), (u16, u16)) {
+    let (_vertical_chunks, horizontal_chunks) = split_screen(area, manager_width);
+    let (first, second) = split_areas(horizontal_chunks[1], layout);
+    let inner = |area: Rect| {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        (inner.width, inner.height)
+    };
+
+    (inner(first), inner(second))
+}
+
+/// Width and height of the text inside the viewer's borders on a screen
+/// of the size of `area`.
+pub fn viewer_pane_size(area: Rect, manager_width: u16) -> (u16, u16) {
+    let (_vertical_chunks, horizontal_chunks) = split_screen(area, manager_width);
+    let inner = Block::default()
+        .borders(Borders::ALL)
+        .inner(horizontal_chunks[1]);
+    (inner.width, inner.height)
+}
+
+pub fn draw<B: Backend>(frame: &mut Frame<B>, app: &App) {
+    let (vertical_chunks, horizontal_chunks) = split_screen(frame.size(), app.get_manager_width());
+    let now = Local::now();
+
+    draw_session_status(frame, vertical_chunks[0], app, now);
+    if !matches!(app.get_mode(), Mode::Reauth(_)) {
+        draw_manager(frame, horizontal_chunks[0], app);
+    }
+    match app.get_mode() {
+        Mode::Reauth(_back) => draw_reauth(frame, vertical_chunks[1], app),
+        Mode::Editor | Mode::Prompt(Prompt::HardWrap | Prompt::SaveNote { .. }) => {
+            draw_editor(frame, horizontal_chunks[1], app)
+        }
+        Mode::Rename => draw_rename(frame, horizontal_chunks[1], app),
+        Mode::Commit => draw_commit(frame, horizontal_chunks[1], app),
+        Mode::Grep => draw_grep(frame, horizontal_chunks[1], app),
+        Mode::Duplicates | Mode::Confirm(Confirm::DeleteDuplicates) => {
+            draw_duplicates(frame, horizontal_chunks[1], app)
+        }
+        Mode::SizeChart => draw_size_chart(frame, horizontal_chunks[1], app),
+        Mode::AgeChart => draw_age_chart(frame, horizontal_chunks[1], app),
+        Mode::Templates => draw_choices(frame, horizontal_chunks[1], app, "Templates"),
+        Mode::Tags => draw_choices(frame, horizontal_chunks[1], app, "Tags"),
+        Mode::Split => draw_split(frame, horizontal_chunks[1], app),
+        Mode::InsertFile => {
+            let popup = popup_area(horizontal_chunks[1]);
+            draw_editor(frame, horizontal_chunks[1], app);
+            frame.render_widget(Clear, popup);
+            draw_manager(frame, popup, app);
+        }
+        _ => draw_viewer(frame, horizontal_chunks[1], app),
+    }
+    if let Mode::Confirm(Confirm::CopyChecksum(checksum)) = app.get_mode() {
+        draw_checksum(frame, horizontal_chunks[1], checksum);
+    }
+    if app.get_mode() == &Mode::JsonRecord {
+        draw_json_record(frame, horizontal_chunks[1], app);
+    }
+    if app.get_mode() == &Mode::UrlList {
+        let popup = popup_area(horizontal_chunks[1]);
+        frame.render_widget(Clear, popup);
+        draw_choices(frame, popup, app, "URLs");
+    }
+    if let Mode::Mark(_mark) = app.get_mode() {
+        draw_marks(frame, horizontal_chunks[1], app);
+    }
+    if app.get_timings_ref().is_enabled() {
+        draw_debug_overlay(frame, vertical_chunks[1], app);
+    }
+    if app.is_help_shown() {
+        draw_help(frame, popup_area(vertical_chunks[1]), app, "Help (F1)");
+    }
+    let notice = notice_area(vertical_chunks[1], 1);
+    if let Some(err) = app.get_status() {
+        frame.render_widget(Clear, notice);
+        draw_error(frame, notice, err);
+    } else if let Mode::Prompt(prompt) = app.get_mode() {
+        frame.render_widget(Clear, notice);
+        draw_prompt(frame, notice, app, prompt);
+    } else if let Mode::Confirm(_) | Mode::Mark(_) = app.get_mode() {
+        let notice = notice_area(vertical_chunks[1], 2);
+        draw_help(frame, notice, app, app.get_mode().name());
+    } else if let Some(message) = app.get_message() {
+        frame.render_widget(Clear, notice);
+        draw_message(frame, notice, message);
+    }
+    draw_mode_line(frame, vertical_chunks[2], app, now);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppEvent;
+    use crate::editor::Editor;
+    use crate::times::TimeFormats;
+    use chrono::TimeZone;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::{
+        fs, thread,
+        time::{Duration, SystemTime},
+    };
+    use tempfile::TempDir;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    const KEY: &str = "secret";
+
+    fn render<F>(width: u16, height: u16, draw: F) -> Buffer
+    where
+        F: FnOnce(&mut Frame<TestBackend>, Rect),
+    {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, f.size())).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    fn open(app: &mut App, position: usize) {
+        for _ in 0..=position {
+            press(app, KeyCode::Down);
+        }
+        press(app, KeyCode::Enter);
+        for _ in 0..400 {
+            app.handle_event(AppEvent::Tick);
+            if !app.get_viewer_ref().is_loading() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("the file was not loaded in time");
+    }
+
+    fn app_with(files: &[(&str, &[u8])]) -> (TempDir, App<'static>) {
+        let dir = TempDir::new().unwrap();
+        for (name, data) in files {
+            fs::write(dir.path().join(name), data).unwrap();
+        }
+        let app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+        (dir, app)
+    }
+
+    #[test]
+    fn session_status_shows_the_given_time() {
+        let (_dir, app) = app_with(&[]);
+        let now = Local.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap();
+        let buffer = render(40, 3, |f, area| draw_session_status(f, area, &app, now));
+        let fingerprint = app.get_key_fingerprint().unwrap();
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                format!("┌Session | key {}────────────────┐", fingerprint),
+                String::from("│2023-05-01 12:30:00                   │"),
+                String::from("└──────────────────────────────────────┘"),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_status_honors_the_display_format() {
+        let (_dir, mut app) = app_with(&[]);
+        app.get_manager_mut()
+            .set_time_formats(TimeFormats::new("%d.%m.%Y %H:%M", "%Y"));
+        let now = Local.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap();
+        let buffer = render(40, 3, |f, area| draw_session_status(f, area, &app, now));
+        assert_eq!(lines(&buffer)[1], format!("│{:38}│", "01.05.2023 12:30"));
+    }
+
+    #[test]
+    fn session_status_marks_recording() {
+        let (_dir, mut app) = app_with(&[]);
+        app.handle_event(AppEvent::Key(KeyEvent::new(
+            KeyCode::Char('q'),
+            KeyModifiers::CONTROL,
+        )));
+        let now = Local.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap();
+        let buffer = render(40, 3, |f, area| draw_session_status(f, area, &app, now));
+        assert_eq!(
+            lines(&buffer)[1],
+            "│2023-05-01 12:30:00 ● REC             │"
+        );
+        assert_eq!(buffer.get(21, 1).fg, Color::Red);
+    }
+
+    #[test]
+    fn session_status_marks_scratch_sessions() {
+        let (app, _dir) = App::new_scratch(KEY).unwrap();
+        let now = Local.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap();
+        let buffer = render(60, 3, |f, area| draw_session_status(f, area, &app, now));
+        assert_eq!(
+            lines(&buffer)[1],
+            "│2023-05-01 12:30:00 [scratch session]                     │"
+        );
+    }
+
+    #[test]
+    fn session_status_reports_directory_changes() {
+        let hash = |app: &mut App| {
+            app.get_manager_mut().refresh().unwrap();
+            while !app.get_manager_mut().poll_tree_hash() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+        };
+        let (dir, mut app) = app_with(&[("note.txt", b"one")]);
+        hash(&mut app);
+        fs::write(dir.path().join("note.txt"), "two more").unwrap();
+        hash(&mut app);
+        let now = Local.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap();
+        let buffer = render(60, 3, |f, area| draw_session_status(f, area, &app, now));
+        assert_eq!(
+            lines(&buffer)[1],
+            "│2023-05-01 12:30:00 ⚠ Directory modified                  │"
+        );
+        assert_eq!(buffer.get(21, 1).fg, Color::Yellow);
+
+        hash(&mut app);
+        let buffer = render(60, 3, |f, area| draw_session_status(f, area, &app, now));
+        assert_eq!(
+            lines(&buffer)[1],
+            "│2023-05-01 12:30:00 ✓ No changes                          │"
+        );
+    }
+
+    #[test]
+    fn manager_lists_folders_files_and_actions() {
+        let dir = TempDir::new().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir_all(sub.join("inner")).unwrap();
+        let modified = Local.with_ymd_and_hms(2024, 5, 1, 13, 2, 45).unwrap();
+        fs::File::create(sub.join("note.txt"))
+            .unwrap()
+            .set_modified(modified.into())
+            .unwrap();
+        let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+
+        let buffer = render(60, 7, |f, area| draw_manager(f, area, &app));
+        let title = sub.to_str().unwrap();
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                format!("┌{}{}┐", title, "─".repeat(58 - title.chars().count())),
+                format!("│{:58}│", "inner"),
+                format!("│{:39}{}│", "note.txt", "2024-05-01 13:02:45"),
+                format!("│{:58}│", "Back"),
+                format!("│{:58}│", "Root"),
+                format!("│{:58}│", ""),
+                format!("└{}┘", "─".repeat(58)),
+            ]
+        );
+        assert_eq!(buffer.get(1, 1).fg, Color::Blue);
+        assert_eq!(buffer.get(1, 2).bg, Color::Yellow);
+        assert_eq!(buffer.get(1, 4).fg, Color::Green);
+    }
+
+    #[test]
+    fn viewer_scrolls_text() {
+        let (_dir, mut app) = app_with(&[("note.txt", b"one\ntwo\nthree")]);
+        open(&mut app, 0);
+        press(&mut app, KeyCode::Down);
+
+        let buffer = render(20, 5, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌note.txt──────────┐",
+                "│two               │",
+                "│three             │",
+                "│                  │",
+                "└──────────────────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn viewer_scrolls_tables_by_row() {
+        let (_dir, mut app) = app_with(&[("data.csv", b"name,size\nlonger,1\nb,22")]);
+        open(&mut app, 0);
+
+        let buffer = render(20, 5, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌data.csv──────────┐",
+                "│name   size       │",
+                "│longer 1          │",
+                "│b      22         │",
+                "└──────────────────┘",
+            ]
+        );
+
+        press(&mut app, KeyCode::Down);
+        let buffer = render(20, 5, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(lines(&buffer)[1], "│longer 1          │");
+    }
+
+    #[test]
+    fn viewer_pretty_prints_json_in_order() {
+        let (_dir, mut app) = app_with(&[("data.json", b"{\"z\":[1],\"a\":true}")]);
+        open(&mut app, 0);
+
+        let buffer = render(20, 7, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌data.json─────────┐",
+                "│{                 │",
+                "│  \"z\": [          │",
+                "│    1             │",
+                "│  ],              │",
+                "│  \"a\": true       │",
+                "└──────────────────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn editor_title_counts_words() {
+        let (_dir, mut app) = app_with(&[]);
+        press(&mut app, KeyCode::Char('n'));
+        let buffer = render(40, 3, |f, area| draw_editor(f, area, &app));
+        assert_eq!(
+            lines(&buffer)[0],
+            "┌New note | Words: 0 | Tab width: 4────┐"
+        );
+
+        for ch in "two words".chars() {
+            press(&mut app, KeyCode::Char(ch));
+        }
+        let buffer = render(50, 3, |f, area| draw_editor(f, area, &app));
+        assert_eq!(
+            lines(&buffer)[0],
+            "┌New note [modified] | Words: 2 | Tab width: 4───┐"
+        );
+        assert_eq!(
+            lines(&buffer)[1],
+            "│two words                                       │"
+        );
+
+        // A long path is cut from the front, the counts stay.
+        let path = app
+            .get_manager_ref()
+            .get_root()
+            .join("journal")
+            .join("2024")
+            .join("a-rather-long-name.md");
+        app.get_editor_mut().set_target(path, true);
+        let buffer = render(60, 3, |f, area| draw_editor(f, area, &app));
+        assert_eq!(
+            lines(&buffer)[0],
+            "┌…-name.md [encrypted] [modified] | Words: 2 | Tab width: 4┐"
+        );
+
+        app.get_editor_mut().set_insert_spaces(true);
+        let buffer = render(60, 3, |f, area| draw_editor(f, area, &app));
+        assert!(lines(&buffer)[0].contains("| Spaces: 4┐"));
+    }
+
+    #[test]
+    fn mode_line_follows_the_mode_and_the_open_text() {
+        let mut editor = Editor::new(KEY);
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str("hidden");
+        let encrypted = editor.finish_encrypt().unwrap();
+        let (_dir, mut app) = app_with(&[("note.txt", b"plain"), ("secret.bin", &encrypted)]);
+        let start = app
+            .get_manager_ref()
+            .get_session_start()
+            .with_timezone(&Local);
+        assert_eq!(mode_line(&app, start), "MANAGER | 2 files | 00:00:00");
+
+        open(&mut app, 1);
+        let later = start + chrono::Duration::seconds(3725);
+        assert_eq!(
+            mode_line(&app, later),
+            "VIEWER | 2 files | [ENC] | 01:02:05"
+        );
+
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('n'));
+        for ch in "two words".chars() {
+            press(&mut app, KeyCode::Char(ch));
+        }
+        assert_eq!(
+            mode_line(&app, start),
+            "EDITOR | 2 files | Ln 1, Col 10 | [PLAIN] | 2 words | 00:00:00"
+        );
+        let buffer = render(40, 1, |f, area| draw_mode_line(f, area, &app, start));
+        assert_eq!(
+            lines(&buffer),
+            vec!["EDITOR | 2 files | Ln 1, Col 10 | [PLAIN"]
+        );
+    }
+
+    #[test]
+    fn viewer_shows_decrypted_text() {
+        let mut editor = Editor::new(KEY);
+        editor.init();
+        editor.get_textarea_mut().unwrap().insert_str("hidden");
+        let encrypted = editor.finish_encrypt().unwrap();
+        let (_dir, mut app) = app_with(&[("secret.bin", &encrypted)]);
+        open(&mut app, 0);
+
+        let buffer = render(20, 3, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌secret.bin────────┐",
+                "│hidden            │",
+                "└──────────────────┘",
+            ]
+        );
+        assert_eq!(buffer.get(0, 0).fg, Color::Green);
+    }
+
+    #[test]
+    fn viewer_title_counts_log_levels() {
+        let log = "INFO up\nWARN slow\nERROR down\nerror again\n";
+        let (_dir, mut app) = app_with(&[("app.log", log.as_bytes())]);
+        open(&mut app, 0);
+
+        let buffer = render(40, 6, |f, area| draw_viewer(f, area, &app));
+        let title = &lines(&buffer)[0];
+        assert!(title.starts_with("┌app.log | E:2 W:1 I:1 D:0"), "{}", title);
+        let column = |symbol| title.chars().position(|c| c == symbol).unwrap() as u16;
+        assert_eq!(buffer.get(column('E'), 0).fg, Color::Red);
+        assert_eq!(buffer.get(column('W'), 0).fg, Color::Yellow);
+        assert_eq!(buffer.get(column('I'), 0).fg, Color::White);
+    }
+
+    #[test]
+    fn size_chart_scales_bars_to_the_largest_file() {
+        let (_dir, mut app) = app_with(&[
+            ("big.txt", &[b'x'; 400]),
+            ("small.txt", &[b'x'; 100]),
+            ("empty", b""),
+        ]);
+        app.handle_event(AppEvent::Key(KeyEvent::new(
+            KeyCode::Char('S'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        )));
+        assert_eq!(app.get_mode(), &Mode::SizeChart);
+
+        let buffer = render(32, 6, |f, area| draw_size_chart(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌File sizes────────────────────┐",
+                "│big.txt   ██████████████ 400 B│",
+                "│small.txt ███            100 B│",
+                "│empty                      0 B│",
+                "│Total: 500 B in 3 files       │",
+                "└──────────────────────────────┘",
+            ]
+        );
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.get_mode(), &Mode::Manager);
+    }
+
+    #[test]
+    fn age_chart_labels_bars_with_their_counts() {
+        let (dir, mut app) = app_with(&[("new.txt", b"a"), ("fresh.txt", b"b"), ("old.txt", b"c")]);
+        let old = fs::File::options()
+            .write(true)
+            .open(dir.path().join("old.txt"))
+            .unwrap();
+        old.set_modified(SystemTime::now() - Duration::from_secs(400 * 24 * 60 * 60))
+            .unwrap();
+        app.handle_event(AppEvent::Key(KeyEvent::new(
+            KeyCode::Char('A'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        )));
+        assert_eq!(app.get_mode(), &Mode::AgeChart);
+
+        let buffer = render(64, 8, |f, area| draw_age_chart(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌File ages─────────────────────────────────────────────────────┐",
+                "│██████████████                                                │",
+                "│██████████████                                                │",
+                "│██████████████                               ▄▄▄▄▄▄▄▄▄▄▄▄▄▄   │",
+                "│██████████████                               ██████████████   │",
+                "│██████2███████                               ██████1███████   │",
+                "│today (2)      this week (0)  this month (0) older (1)        │",
+                "└──────────────────────────────────────────────────────────────┘",
+            ]
+        );
+        press(&mut app, KeyCode::Char('q'));
+        assert_eq!(app.get_mode(), &Mode::Manager);
+    }
+
+    #[test]
+    fn viewer_marks_binary_files() {
+        let (_dir, mut app) = app_with(&[("blob", &[0x00, 0xff, 0x01, 0xfe])]);
+        open(&mut app, 0);
+
+        let buffer = render(20, 3, |f, area| draw_viewer(f, area, &app));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌blob──────────────┐",
+                "│Binary file       │",
+                "└──────────────────┘",
+            ]
+        );
+        assert_eq!(buffer.get(1, 1).fg, Color::Red);
+    }
+
+    #[test]
+    fn error_uses_title_and_color() {
+        let err = MystoreError::NotAFile("delete", "folder");
+        let buffer = render(40, 3, |f, area| draw_error(f, area, &err));
+        assert_eq!(
+            lines(&buffer),
+            vec![
+                "┌Not Allowed───────────────────────────┐",
+                "│Cannot delete the folder entity       │",
+                "└──────────────────────────────────────┘",
+            ]
+        );
+        assert_eq!(buffer.get(1, 1).fg, Color::Yellow);
+    }
+
+    #[test]
+    fn notices_sit_over_the_bottom_of_the_panes() {
+        let area = Rect::new(0, 3, 80, 20);
+        assert_eq!(notice_area(area, 1), Rect::new(2, 19, 76, 3));
+        assert_eq!(notice_area(area, 2), Rect::new(2, 18, 76, 4));
+        assert_eq!(
+            notice_area(Rect::new(0, 0, 10, 2), 1),
+            Rect::new(2, 0, 6, 1)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::Result;
+use regex::Regex;
+use std::{
+    io,
+    process::{Command, Stdio},
+};
+
+pub trait UrlOpener {
+    fn open(&self, url: &str) -> Result<()>;
+}
+
+/// Opens URLs in the default browser of the desktop.
+pub struct SystemOpener;
+
+impl SystemOpener {
+    /// The tool that hands a URL to the desktop on this platform.
+    fn command() -> &'static str {
+        if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        }
+    }
+}
+
+impl UrlOpener for SystemOpener {
+    /// Starts the tool without waiting for the browser.
+    fn open(&self, url: &str) -> Result<()> {
+        Command::new(Self::command())
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+}
+
+/// The `http` and `https` URLs in `text`, each once in the order they come.
+/// Punctuation that ends a sentence or closes brackets is not part of them.
+pub fn find_urls(text: &str) -> Result<Vec<String>> {
+    let regex = Regex::new(r#"https?://[^\s<>"'`]+"#)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+    let mut urls: Vec<String> = Vec::new();
+    for found in regex.find_iter(text) {
+        let url = found
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}']);
+        let has_host = url
+            .split_once("://")
+            .is_some_and(|(_, rest)| !rest.is_empty());
+        if has_host && !urls.iter().any(|known| known == url) {
+            urls.push(String::from(url));
+        }
+    }
+
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_found_without_trailing_punctuation() {
+        let text = "See https://example.com/a?b=1, (or http://example.org/x).\n\
+                    Again: https://example.com/a?b=1. Not ftp://example.net or https://";
+        assert_eq!(
+            find_urls(text).unwrap(),
+            ["https://example.com/a?b=1", "http://example.org/x"]
+        );
+        assert_eq!(
+            find_urls("<a href=\"https://example.com\">").unwrap(),
+            ["https://example.com"]
+        );
+        assert!(find_urls("no links").unwrap().is_empty());
+    }
+}
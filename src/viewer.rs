@@ -0,0 +1,1132 @@
+use crate::{
+    bookmarks::ScrollPositions,
+    error::{MystoreError, Result},
+    keyring::{self, SessionKey, MIN_PASSWORD_LEN},
+    tags, whitespace,
+    wrap::WrapLayout,
+};
+use chardetng::EncodingDetector;
+use chrono::Utc;
+use encoding_rs::{
+    Encoding, ISO_8859_2, KOI8_R, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252,
+};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const ANSI_WHITE: &str = "\x1b[37m";
+const ANSI_CYAN: &str = "\x1b[36m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// How often a file is reloaded once auto-refresh is turned on.
+pub const AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the title tells that a file was reloaded on request.
+pub const RELOADED_NOTICE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViewerEntity {
+    Text(String),
+    DecryptedText(String),
+    Binary(Vec<u8>),
+    Table(Vec<Vec<String>>),
+    Json(serde_json::Value),
+    /// One JSON value per line, as in `.jsonl` and `.ndjson` files.
+    JsonLines(Vec<serde_json::Value>),
+}
+
+/// How many lines of a log are at each level, by the first level word in
+/// the line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LogStats {
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub debugs: usize,
+}
+
+impl LogStats {
+    /// Counts the lines with `ERROR`, `WARN` or `WARNING`, `INFO` and
+    /// `DEBUG` in any case, none if no line has a level.
+    pub fn scan(text: &str) -> Option<LogStats> {
+        let regex = Regex::new(r"(?i)\b(error|warn|warning|info|debug)\b").ok()?;
+        let mut stats = LogStats::default();
+        for line in text.lines() {
+            let Some(found) = regex.find(line) else {
+                continue;
+            };
+            match found.as_str().to_ascii_lowercase().as_str() {
+                "error" => stats.errors += 1,
+                "warn" | "warning" => stats.warnings += 1,
+                "info" => stats.infos += 1,
+                _ => stats.debugs += 1,
+            }
+        }
+
+        (stats != LogStats::default()).then_some(stats)
+    }
+}
+
+pub struct Decoded {
+    entity: ViewerEntity,
+    encoding: Option<&'static Encoding>,
+    /// The bytes text in another encoding than UTF-8 was decoded from.
+    raw: Option<Vec<u8>>,
+}
+
+impl Decoded {
+    pub fn get_entity_ref(&self) -> &ViewerEntity {
+        &self.entity
+    }
+}
+
+pub struct Viewer {
+    name: Option<String>,
+    encoding: Option<&'static Encoding>,
+    source: ViewerEntity,
+    entity: ViewerEntity,
+    scroll: u16,
+    key: SessionKey,
+    strip_ansi: bool,
+    structured: bool,
+    show_whitespace: bool,
+    loading: bool,
+    /// The line, counted from zero, to show on top once loaded.
+    pending_line: Option<usize>,
+    /// Width and height of the text area, zero while unknown.
+    pane: (u16, u16),
+    layout: WrapLayout,
+    /// The JSON line Enter expands, counted from zero.
+    selected_record: usize,
+    auto_refresh: Option<Duration>,
+    last_refresh: Instant,
+    reload_requested: bool,
+    reloaded: Option<Instant>,
+    line_bookmarks: Vec<usize>,
+    /// The scroll marked under each letter, per file name. Kept for the
+    /// session only.
+    marks: HashMap<String, BTreeMap<char, u16>>,
+    scroll_positions: ScrollPositions,
+    search: Option<Regex>,
+    /// The line of the last match shown and the scroll it was shown at.
+    last_match: Option<(usize, u16)>,
+    raw: Option<Vec<u8>>,
+    /// Set for plain text with log levels in it.
+    log_stats: Option<LogStats>,
+}
+
+/// The encodings `Viewer::cycle_encoding` goes through, in order.
+pub fn cycled_encodings() -> [&'static Encoding; 8] {
+    [
+        UTF_8,
+        WINDOWS_1252,
+        ISO_8859_2,
+        WINDOWS_1251,
+        KOI8_R,
+        SHIFT_JIS,
+        UTF_16LE,
+        UTF_16BE,
+    ]
+}
+
+impl Viewer {
+    fn crypt_rm(c: i32, count: usize, key: &str) -> i32 {
+        let crypt: Vec<_> = key.bytes().collect();
+        if c < crypt[count] as i32 {
+            c - crypt[count] as i32 + 256
+        } else {
+            c - crypt[count] as i32
+        }
+    }
+
+    pub(crate) fn decrypt_binary(bin: &[u8], key: &str) -> Result<String> {
+        let mut text: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        for byte in bin {
+            let ch = Self::crypt_rm(*byte as i32, count, key);
+            text.push(ch as u8);
+            count = (count + 1) % 5;
+        }
+
+        String::from_utf8(text).map_err(|_err| MystoreError::DecryptFailed)
+    }
+
+    /// Whether `text` has no control characters but line breaks, tabs and
+    /// form feeds, unlike almost any binary data.
+    pub(crate) fn looks_like_text(text: &str) -> bool {
+        text.chars()
+            .all(|ch| !ch.is_control() || matches!(ch, '\n' | '\r' | '\t' | '\x0c'))
+    }
+
+    fn decode_legacy_text(bin: &[u8]) -> Option<(String, &'static Encoding)> {
+        let (encoding, confident) = Self::detect_encoding(bin);
+        if encoding == UTF_8 || !confident {
+            return None;
+        }
+
+        // Single-byte encodings map almost any byte sequence to characters,
+        // so control characters are what tells real binary data apart.
+        let (text, _, malformed) = encoding.decode(bin);
+        if malformed || !Self::looks_like_text(&text) {
+            return None;
+        }
+
+        Some((text.into_owned(), encoding))
+    }
+
+    /// Picks UTF-16 for text without a byte order mark if more than half
+    /// of the code units have a zero high byte, as Latin text has.
+    fn guess_utf16(bin: &[u8]) -> Option<&'static Encoding> {
+        if bin.len() < 2 || !bin.len().is_multiple_of(2) {
+            return None;
+        }
+        let units = bin.len() / 2;
+        let zeros_at = |offset: usize| {
+            bin.iter()
+                .skip(offset)
+                .step_by(2)
+                .filter(|byte| **byte == 0)
+                .count()
+        };
+        match (zeros_at(1) * 2 > units, zeros_at(0) * 2 > units) {
+            (true, false) => Some(UTF_16LE),
+            (false, true) => Some(UTF_16BE),
+            _ => None,
+        }
+    }
+
+    /// Decodes UTF-16 told by its byte order mark or by its zero bytes.
+    fn decode_utf16(bin: &[u8]) -> Option<(String, &'static Encoding)> {
+        let (encoding, bom) = match Encoding::for_bom(bin) {
+            Some((encoding, bom)) if encoding != UTF_8 => (encoding, bom),
+            Some(_utf8) => return None,
+            None => (Self::guess_utf16(bin)?, 0),
+        };
+        let (text, malformed) = encoding.decode_without_bom_handling(&bin[bom..]);
+        if malformed || !Self::looks_like_text(&text) {
+            return None;
+        }
+
+        Some((text.into_owned(), encoding))
+    }
+
+    fn strip_ansi_escapes(entity: ViewerEntity) -> ViewerEntity {
+        match entity {
+            ViewerEntity::Text(text) => ViewerEntity::Text(strip_ansi_escapes::strip_str(text)),
+            ViewerEntity::DecryptedText(text) => {
+                ViewerEntity::DecryptedText(strip_ansi_escapes::strip_str(text))
+            }
+            ViewerEntity::Binary(_)
+            | ViewerEntity::Table(_)
+            | ViewerEntity::Json(_)
+            | ViewerEntity::JsonLines(_) => entity,
+        }
+    }
+
+    /// CSV files, and text whose first line has commas, are shown as tables.
+    /// A leading `tags:` line does not count, since tags are comma separated.
+    fn is_tabular(name: Option<&str>, text: &str) -> bool {
+        let is_csv = name.is_some_and(|name| name.to_lowercase().ends_with(".csv"));
+        let first_line = text.lines().next().unwrap_or_default();
+        is_csv || (first_line.contains(',') && tags::parse_tags(first_line).is_empty())
+    }
+
+    /// JSON files, and text that starts like an object or an array, are
+    /// shown as JSON when they parse. Other text is never taken for a bare
+    /// JSON number or string.
+    fn parse_json(name: Option<&str>, text: &str) -> Option<serde_json::Value> {
+        let is_json = name.is_some_and(|name| name.to_lowercase().ends_with(".json"))
+            || text.trim_start().starts_with(['{', '[']);
+        if is_json {
+            serde_json::from_str(text).ok()
+        } else {
+            None
+        }
+    }
+
+    /// `.jsonl` and `.ndjson` files parse line by line, skipping empty
+    /// lines. A line that does not parse leaves the whole file as text.
+    fn parse_json_lines(name: Option<&str>, text: &str) -> Option<Vec<serde_json::Value>> {
+        let name = name?.to_lowercase();
+        if !name.ends_with(".jsonl") && !name.ends_with(".ndjson") {
+            return None;
+        }
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    fn parse_structured(name: Option<&str>, text: String) -> ViewerEntity {
+        if let Some(records) = Self::parse_json_lines(name, &text) {
+            return ViewerEntity::JsonLines(records);
+        }
+        if let Some(value) = Self::parse_json(name, &text) {
+            return ViewerEntity::Json(value);
+        }
+        if Self::is_tabular(name, &text) {
+            if let Some(rows) = Self::parse_table(&text) {
+                return ViewerEntity::Table(rows);
+            }
+        }
+
+        ViewerEntity::Text(text)
+    }
+
+    fn parse_table(text: &str) -> Option<Vec<Vec<String>>> {
+        let rows = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes())
+            .records()
+            .map(|record| Some(record.ok()?.iter().map(String::from).collect()))
+            .collect::<Option<Vec<Vec<String>>>>()?;
+        if rows.is_empty() {
+            None
+        } else {
+            Some(rows)
+        }
+    }
+
+    fn markdown_table(rows: &[Vec<String>]) -> String {
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+        let format_row = |row: &[String]| {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    row.get(column)
+                        .map_or(String::new(), |cell| cell.replace('|', "\\|"))
+                })
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+        let mut markdown = format_row(&rows[0]);
+        markdown.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        for row in &rows[1..] {
+            markdown.push_str(&format_row(row));
+        }
+        markdown
+    }
+}
+
+impl Viewer {
+    pub fn new(key: &str) -> Result<Viewer> {
+        Self::with_key(SessionKey::from(key))
+    }
+
+    pub fn with_key(key: SessionKey) -> Result<Viewer> {
+        if key.get_password().len() < MIN_PASSWORD_LEN {
+            return Err(MystoreError::WrongKey(MIN_PASSWORD_LEN));
+        }
+
+        Ok(Viewer {
+            name: None,
+            encoding: None,
+            source: ViewerEntity::Text(String::new()),
+            entity: ViewerEntity::Text(String::new()),
+            scroll: 0,
+            key,
+            strip_ansi: true,
+            structured: true,
+            show_whitespace: false,
+            loading: false,
+            pending_line: None,
+            pane: (0, 0),
+            layout: WrapLayout::default(),
+            selected_record: 0,
+            auto_refresh: None,
+            last_refresh: Instant::now(),
+            reload_requested: false,
+            reloaded: None,
+            line_bookmarks: Vec::new(),
+            marks: HashMap::new(),
+            scroll_positions: ScrollPositions::default(),
+            search: None,
+            last_match: None,
+            raw: None,
+            log_stats: None,
+        })
+    }
+
+    /// Guesses the encoding of `bytes` with chardetng, along with whether
+    /// the guess is confident.
+    pub fn detect_encoding(bytes: &[u8]) -> (&'static Encoding, bool) {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess_assess(None, true)
+    }
+
+    /// Decrypts or decodes a binary entity; text entities pass through as
+    /// is. Files sealed with a data key are told by their header, then
+    /// UTF-16 is recognized. Text that decrypts into readable
+    /// text is taken as encrypted before guessing a single-byte encoding,
+    /// as those map almost any bytes to characters; decrypted text with
+    /// control characters only comes after such a guess.
+    pub fn decode(entity: ViewerEntity, key: &SessionKey) -> Decoded {
+        let ViewerEntity::Binary(bin) = entity else {
+            return Decoded {
+                entity,
+                encoding: None,
+                raw: None,
+            };
+        };
+        let decoded_text = |(text, encoding): (String, &'static Encoding), bin: Vec<u8>| Decoded {
+            entity: ViewerEntity::Text(text),
+            encoding: Some(encoding),
+            raw: Some(bin),
+        };
+        let decrypted_text = |text: String| Decoded {
+            entity: ViewerEntity::DecryptedText(text),
+            encoding: None,
+            raw: None,
+        };
+        if keyring::is_sealed(&bin) {
+            return match key.decrypt(&bin) {
+                Ok(text) => decrypted_text(text),
+                Err(_err) => Decoded {
+                    entity: ViewerEntity::Binary(bin),
+                    encoding: None,
+                    raw: None,
+                },
+            };
+        }
+        if let Some(decoded) = Self::decode_utf16(&bin) {
+            return decoded_text(decoded, bin);
+        }
+        match key.decrypt(&bin) {
+            Ok(text) if Self::looks_like_text(&text) => decrypted_text(text),
+            decrypted => match (Self::decode_legacy_text(&bin), decrypted) {
+                (Some(decoded), _decrypted) => decoded_text(decoded, bin),
+                (None, Ok(text)) => decrypted_text(text),
+                (None, Err(_err)) => Decoded {
+                    entity: ViewerEntity::Binary(bin),
+                    encoding: None,
+                    raw: None,
+                },
+            },
+        }
+    }
+
+    pub fn set_decoded(&mut self, decoded: Decoded, name: Option<String>) {
+        self.name = name;
+        self.encoding = decoded.encoding;
+        self.raw = decoded.raw;
+        self.loading = false;
+        self.source = decoded.entity.clone();
+        let entity = if self.strip_ansi {
+            Self::strip_ansi_escapes(decoded.entity)
+        } else {
+            decoded.entity
+        };
+        self.entity = match entity {
+            ViewerEntity::Text(text) if self.structured => {
+                Self::parse_structured(self.name.as_deref(), text)
+            }
+            entity => entity,
+        };
+        self.log_stats = match &self.entity {
+            ViewerEntity::Text(text) => LogStats::scan(text),
+            _entity => None,
+        };
+        self.layout = Self::layout(&self.entity, self.pane.0, self.show_whitespace);
+        self.clamp_marks();
+        let scroll = match self.pending_line.take() {
+            Some(line) => Self::to_scroll(self.layout.get_line_start(line)),
+            None => self
+                .name
+                .as_deref()
+                .and_then(|name| self.scroll_positions.get(name))
+                .unwrap_or_default(),
+        };
+        self.scroll = scroll.min(self.get_max_scroll());
+        self.selected_record = usize::from(self.scroll);
+        self.reloaded = self.reload_requested.then(Instant::now);
+        self.reload_requested = false;
+    }
+
+    pub fn set_entity(&mut self, entity: ViewerEntity, name: Option<String>) {
+        let decoded = Self::decode(entity, &self.key);
+        self.set_decoded(decoded, name);
+    }
+
+    pub fn set_loading(&mut self, name: Option<String>) {
+        self.clear();
+        self.name = name;
+        self.loading = true;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn get_strip_ansi(&self) -> bool {
+        self.strip_ansi
+    }
+
+    pub fn toggle_strip_ansi(&mut self) {
+        self.strip_ansi = !self.strip_ansi;
+        let scroll = self.scroll;
+        let decoded = Decoded {
+            entity: self.source.clone(),
+            encoding: self.encoding,
+            raw: self.raw.clone(),
+        };
+        self.set_decoded(decoded, self.name.clone());
+        self.scroll = scroll.min(self.get_max_scroll());
+    }
+
+    pub fn get_structured(&self) -> bool {
+        self.structured
+    }
+
+    /// Switches tables and JSON between their structured view and raw text.
+    pub fn toggle_structured(&mut self) {
+        self.structured = !self.structured;
+        let scroll = self.scroll;
+        let decoded = Decoded {
+            entity: self.source.clone(),
+            encoding: self.encoding,
+            raw: self.raw.clone(),
+        };
+        self.set_decoded(decoded, self.name.clone());
+        self.scroll = scroll.min(self.get_max_scroll());
+    }
+
+    /// Decodes plain text again with the encoding after the current one in
+    /// `cycled_encodings`, for files whose encoding was guessed wrong.
+    /// Returns the encoding, or `None` if the text is not plain text.
+    pub fn cycle_encoding(&mut self) -> Option<&'static Encoding> {
+        let bytes = match (&self.raw, &self.source) {
+            (Some(raw), _source) => raw.clone(),
+            (None, ViewerEntity::Text(text)) => text.clone().into_bytes(),
+            (None, _source) => return None,
+        };
+        let encodings = cycled_encodings();
+        let current = self.encoding.unwrap_or(UTF_8);
+        let next = encodings
+            .iter()
+            .position(|encoding| *encoding == current)
+            .map_or(encodings[0], |id| encodings[(id + 1) % encodings.len()]);
+        let (text, _malformed) = next.decode_with_bom_removal(&bytes);
+        let scroll = self.scroll;
+        let decoded = Decoded {
+            entity: ViewerEntity::Text(text.into_owned()),
+            encoding: Some(next),
+            raw: Some(bytes),
+        };
+        self.set_decoded(decoded, self.name.clone());
+        self.scroll = scroll.min(self.get_max_scroll());
+
+        Some(next)
+    }
+
+    pub fn get_show_whitespace(&self) -> bool {
+        self.show_whitespace
+    }
+
+    /// Switches text between showing tabs, trailing spaces and control
+    /// characters and showing it as is, keeping the top line in place.
+    pub fn toggle_show_whitespace(&mut self) {
+        self.show_whitespace = !self.show_whitespace;
+        let line = self.get_top_line();
+        self.layout = Self::layout(&self.entity, self.pane.0, self.show_whitespace);
+        self.scroll = Self::to_scroll(self.layout.get_line_start(line)).min(self.get_max_scroll());
+    }
+
+    pub fn get_auto_refresh(&self) -> Option<Duration> {
+        self.auto_refresh
+    }
+
+    pub fn set_auto_refresh(&mut self, interval: Option<Duration>) {
+        self.auto_refresh = interval;
+        self.last_refresh = Instant::now();
+    }
+
+    /// Turns reloading of the shown file on, with the default interval, or off.
+    pub fn toggle_auto_refresh(&mut self) {
+        let interval = match self.auto_refresh {
+            Some(_interval) => None,
+            None => Some(AUTO_REFRESH_INTERVAL),
+        };
+        self.set_auto_refresh(interval);
+    }
+
+    /// Whether the shown file is to be reloaded now.
+    pub fn is_refresh_due(&self) -> bool {
+        self.auto_refresh
+            .is_some_and(|interval| !self.loading && self.last_refresh.elapsed() >= interval)
+    }
+
+    /// Starts the interval over and keeps the scroll for the reloaded file.
+    pub fn start_refresh(&mut self) {
+        self.last_refresh = Instant::now();
+        self.pending_line = Some(self.get_top_line());
+    }
+
+    /// Like `start_refresh`, for a reload the user asked for.
+    pub fn start_reload(&mut self) {
+        self.start_refresh();
+        self.reload_requested = true;
+    }
+
+    /// Whether the shown file was reloaded on request a moment ago.
+    pub fn is_just_reloaded(&self) -> bool {
+        self.reloaded
+            .is_some_and(|reloaded| reloaded.elapsed() < RELOADED_NOTICE)
+    }
+
+    fn to_scroll(row: usize) -> u16 {
+        u16::try_from(row).unwrap_or(u16::MAX)
+    }
+
+    /// Lays the entity out as `draw_viewer` shows it, wrapped into `width`
+    /// columns if known.
+    fn layout(entity: &ViewerEntity, width: u16, show_whitespace: bool) -> WrapLayout {
+        match entity {
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text)
+                if width > 0 && show_whitespace =>
+            {
+                let revealed = whitespace::reveal_whitespace(text);
+                WrapLayout::wrapped(&whitespace::to_plain(&revealed), width, false)
+            }
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) if width > 0 => {
+                WrapLayout::wrapped(text, width, true)
+            }
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => {
+                WrapLayout::unwrapped(text.lines().count())
+            }
+            ViewerEntity::Json(value) => WrapLayout::unwrapped(
+                serde_json::to_string_pretty(value)
+                    .unwrap_or_default()
+                    .lines()
+                    .count(),
+            ),
+            ViewerEntity::Table(rows) => WrapLayout::unwrapped(rows.len()),
+            ViewerEntity::JsonLines(records) => WrapLayout::unwrapped(records.len()),
+            ViewerEntity::Binary(_bin) => WrapLayout::unwrapped(1),
+        }
+    }
+
+    /// Tells the size of the text area, scrolling stops at the last page
+    /// once it is known. A new width wraps the text again, keeping the top
+    /// line in place.
+    pub fn set_pane_size(&mut self, width: u16, height: u16) {
+        if width != self.pane.0 {
+            let line = self.get_top_line();
+            self.layout = Self::layout(&self.entity, width, self.show_whitespace);
+            self.scroll = Self::to_scroll(self.layout.get_line_start(line));
+        }
+        self.pane = (width, height);
+        self.scroll = self.scroll.min(self.get_max_scroll());
+    }
+
+    pub fn get_layout_ref(&self) -> &WrapLayout {
+        &self.layout
+    }
+
+    /// The lowest scroll that still fills the pane, unlimited while the
+    /// pane height is unknown.
+    fn get_max_scroll(&self) -> u16 {
+        match self.pane.1 {
+            0 => u16::MAX,
+            height => Self::to_scroll(self.layout.get_rows().saturating_sub(usize::from(height))),
+        }
+    }
+
+    /// The logical line, counted from zero, on the top display row.
+    fn get_top_line(&self) -> usize {
+        self.layout.get_line_at(usize::from(self.scroll))
+    }
+
+    /// How much of the text is above the bottom of the pane, in percent,
+    /// if it does not fit into the pane.
+    pub fn get_scroll_percent(&self) -> Option<usize> {
+        let (rows, height) = (self.layout.get_rows(), usize::from(self.pane.1));
+        if height == 0 || rows <= height {
+            return None;
+        }
+        Some((usize::from(self.scroll) + height).min(rows) * 100 / rows)
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_encoding(&self) -> Option<&'static Encoding> {
+        self.encoding
+    }
+
+    pub fn get_entity_ref(&self) -> &ViewerEntity {
+        &self.entity
+    }
+
+    /// The shown entity before stripping of ANSI escape codes.
+    pub fn get_source_ref(&self) -> &ViewerEntity {
+        &self.source
+    }
+
+    pub fn get_log_stats(&self) -> Option<LogStats> {
+        self.log_stats
+    }
+
+    pub fn get_scroll(&self) -> u16 {
+        self.scroll
+    }
+
+    /// A one-line summary of a JSON line: its `id` or `name` field if it
+    /// has one, the compact JSON otherwise.
+    pub fn summarize_record(record: &serde_json::Value) -> String {
+        let field = ["id", "name"]
+            .iter()
+            .find_map(|key| Some((key, record.as_object()?.get(*key)?)));
+        match field {
+            Some((key, serde_json::Value::String(value))) => format!("{}: {}", key, value),
+            Some((key, value)) => format!("{}: {}", key, value),
+            None => record.to_string(),
+        }
+    }
+
+    pub fn get_selected_record(&self) -> usize {
+        self.selected_record
+    }
+
+    /// The selected JSON line, if JSON lines are shown.
+    pub fn get_record_ref(&self) -> Option<&serde_json::Value> {
+        match &self.entity {
+            ViewerEntity::JsonLines(records) => records.get(self.selected_record),
+            _ => None,
+        }
+    }
+
+    /// Selects a JSON line, the last one past the end, and scrolls it into view.
+    pub fn select_record(&mut self, id: usize) {
+        let ViewerEntity::JsonLines(records) = &self.entity else {
+            return;
+        };
+        self.selected_record = id.min(records.len().saturating_sub(1));
+        let height = usize::from(self.pane.1).max(1);
+        let scroll = usize::from(self.scroll);
+        if self.selected_record < scroll {
+            self.scroll = Self::to_scroll(self.selected_record);
+        } else if self.selected_record >= scroll + height {
+            self.scroll = Self::to_scroll(self.selected_record + 1 - height);
+        }
+    }
+
+    pub fn scroll_up(&mut self, value: u16) {
+        self.scroll = self
+            .scroll
+            .checked_sub(value)
+            .map_or(self.scroll, |scroll| scroll)
+            .min(self.get_max_scroll())
+    }
+
+    /// Scrolls to a line counted from one, once loaded if still loading.
+    pub fn goto_line(&mut self, line_number: usize) {
+        let line = line_number.saturating_sub(1);
+        if self.loading {
+            self.pending_line = Some(line);
+        } else {
+            self.scroll =
+                Self::to_scroll(self.layout.get_line_start(line)).min(self.get_max_scroll());
+        }
+    }
+
+    /// Rows the pane shows at once, one while its height is unknown.
+    pub fn get_page_height(&self) -> u16 {
+        self.pane.1.max(1)
+    }
+
+    /// Scrolls to the top, selecting the first JSON line if they are shown.
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+        self.select_record(0);
+    }
+
+    /// Scrolls to the last page, selecting the last JSON line if they are
+    /// shown.
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll = self.get_max_scroll();
+        self.select_record(usize::MAX);
+    }
+
+    /// The text a search looks through, one entry per shown line.
+    fn get_searched_lines(&self) -> Vec<String> {
+        match &self.entity {
+            ViewerEntity::Text(text) | ViewerEntity::DecryptedText(text) => {
+                text.lines().map(String::from).collect()
+            }
+            ViewerEntity::Json(value) => serde_json::to_string_pretty(value)
+                .unwrap_or_default()
+                .lines()
+                .map(String::from)
+                .collect(),
+            ViewerEntity::Table(rows) => rows.iter().map(|row| row.join(" ")).collect(),
+            ViewerEntity::JsonLines(records) => {
+                records.iter().map(Self::summarize_record).collect()
+            }
+            ViewerEntity::Binary(_bin) => Vec::new(),
+        }
+    }
+
+    /// Shows the first line matching the search from `from` on, or the last
+    /// one before it unless `forward`. Returns whether there is one.
+    fn show_match(&mut self, from: usize, forward: bool) -> bool {
+        let Some(regex) = &self.search else {
+            return false;
+        };
+        let lines = self.get_searched_lines();
+        let found = if forward {
+            (from..lines.len()).find(|line| regex.is_match(&lines[*line]))
+        } else {
+            (0..from.min(lines.len()))
+                .rev()
+                .find(|line| regex.is_match(&lines[*line]))
+        };
+        let Some(line) = found else {
+            return false;
+        };
+        match self.entity {
+            ViewerEntity::JsonLines(_) => self.select_record(line),
+            _ => self.goto_line(line + 1),
+        }
+        self.last_match = Some((line, self.scroll));
+
+        true
+    }
+
+    /// Searches for the regex `pattern` from the top line on, `n` and `N`
+    /// in `less` fashion then go on with `search_next`. Returns whether
+    /// anything matched.
+    pub fn search(&mut self, pattern: &str) -> Result<bool> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        self.search = Some(regex);
+        self.last_match = None;
+
+        Ok(self.show_match(self.get_top_line(), true))
+    }
+
+    /// Shows the next match below the last one, or the previous one above
+    /// it, counting from the top line once scrolled elsewhere. Returns
+    /// whether there is one.
+    pub fn search_next(&mut self, forward: bool) -> bool {
+        let from = match self.last_match {
+            Some((line, scroll)) if scroll == self.scroll => line,
+            _ => self.get_top_line(),
+        };
+        match forward {
+            true => self.show_match(from + 1, true),
+            false => self.show_match(from, false),
+        }
+    }
+
+    pub fn get_search(&self) -> Option<&str> {
+        self.search.as_ref().map(Regex::as_str)
+    }
+
+    /// Bookmarks the top shown line, or removes its bookmark. Returns the
+    /// line number and whether it is bookmarked now.
+    pub fn toggle_bookmark(&mut self) -> (usize, bool) {
+        let line = self.get_top_line() + 1;
+        match self.line_bookmarks.binary_search(&line) {
+            Ok(id) => {
+                self.line_bookmarks.remove(id);
+                (line, false)
+            }
+            Err(id) => {
+                self.line_bookmarks.insert(id, line);
+                (line, true)
+            }
+        }
+    }
+
+    /// Scrolls to the first bookmark below the top shown line, if any.
+    pub fn next_bookmark(&mut self) {
+        let line = self.get_top_line() + 1;
+        if let Some(next) = self.line_bookmarks.iter().find(|&&mark| mark > line) {
+            self.goto_line(*next);
+        }
+    }
+
+    /// Scrolls to the last bookmark above the top shown line, if any.
+    pub fn previous_bookmark(&mut self) {
+        let line = self.get_top_line() + 1;
+        if let Some(previous) = self.line_bookmarks.iter().rev().find(|&&mark| mark < line) {
+            self.goto_line(*previous);
+        }
+    }
+
+    pub fn get_line_bookmarks(&self) -> &[usize] {
+        &self.line_bookmarks
+    }
+
+    pub fn set_line_bookmarks(&mut self, mut lines: Vec<usize>) {
+        lines.sort_unstable();
+        lines.dedup();
+        self.line_bookmarks = lines;
+    }
+
+    /// Marks the current scroll under `letter` for the shown file. Returns
+    /// false if `letter` is not an ASCII letter or nothing is shown.
+    pub fn set_mark(&mut self, letter: char) -> bool {
+        let Some(name) = self
+            .name
+            .clone()
+            .filter(|_name| letter.is_ascii_alphabetic())
+        else {
+            return false;
+        };
+        self.marks
+            .entry(name)
+            .or_default()
+            .insert(letter, self.scroll);
+        true
+    }
+
+    /// Scrolls back to the position marked under `letter`, returns false if
+    /// the shown file has no such mark.
+    pub fn jump_to_mark(&mut self, letter: char) -> bool {
+        match self.get_marks().get(&letter) {
+            Some(scroll) => {
+                self.scroll = (*scroll).min(self.get_max_scroll());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The marks of the shown file by letter, empty if it has none.
+    pub fn get_marks(&self) -> BTreeMap<char, u16> {
+        self.name
+            .as_ref()
+            .and_then(|name| self.marks.get(name))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Keeps the marks of the shown file within the text after it was
+    /// reloaded shorter.
+    fn clamp_marks(&mut self) {
+        let max_scroll = self.get_max_scroll();
+        if let Some(marks) = self.name.as_ref().and_then(|name| self.marks.get_mut(name)) {
+            marks
+                .values_mut()
+                .for_each(|scroll| *scroll = (*scroll).min(max_scroll));
+        }
+    }
+
+    /// Uses `positions` to restore and remember where each file was left.
+    pub fn set_scroll_positions(&mut self, positions: ScrollPositions) {
+        self.scroll_positions = positions;
+    }
+
+    pub fn get_scroll_positions_ref(&self) -> &ScrollPositions {
+        &self.scroll_positions
+    }
+
+    pub fn scroll_down(&mut self, value: u16) {
+        self.scroll = self
+            .scroll
+            .checked_add(value)
+            .map_or(self.scroll, |scroll| scroll)
+            .min(self.get_max_scroll())
+    }
+
+    /// The shown entity with ANSI colors, for a terminal or `less -R`:
+    /// plain text in white, decrypted text in cyan and a hex dump of binary
+    /// data in green. Every line is colored on its own and reset at its end.
+    pub fn to_ansi_colored_string(&self) -> String {
+        let (color, text) = match &self.entity {
+            ViewerEntity::Text(text) => (ANSI_WHITE, text.clone()),
+            ViewerEntity::DecryptedText(text) => (ANSI_CYAN, text.clone()),
+            ViewerEntity::Table(rows) => (
+                ANSI_WHITE,
+                rows.iter()
+                    .map(|row| row.join("\t"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ViewerEntity::Json(value) => (
+                ANSI_WHITE,
+                serde_json::to_string_pretty(value).unwrap_or_default(),
+            ),
+            ViewerEntity::JsonLines(records) => (
+                ANSI_WHITE,
+                records
+                    .iter()
+                    .map(serde_json::Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ViewerEntity::Binary(bin) => (ANSI_GREEN, Self::hex_dump(bin)),
+        };
+        text.lines()
+            .map(|line| format!("{}{}{}\n", color, line, ANSI_RESET))
+            .collect()
+    }
+
+    /// Sixteen bytes a line in hex, after the offset of the first one.
+    fn hex_dump(bin: &[u8]) -> String {
+        bin.chunks(16)
+            .enumerate()
+            .map(|(id, chunk)| {
+                let bytes: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("{:08x}  {}", id * 16, bytes.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// `value` as a double-quoted YAML scalar.
+    fn yaml_quoted(value: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in value.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", u32::from(c))),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Writes the shown text to a new markdown file at `path`, see
+    /// `to_markdown`.
+    pub fn export_as_markdown(&self, path: &Path) -> Result<()> {
+        let markdown = self.to_markdown()?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::AlreadyExists => MystoreError::NameCollision(path.to_path_buf()),
+                _ => MystoreError::Io(err),
+            })?;
+        file.write_all(markdown.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// The shown text as markdown. Decrypted text is given as it is shown,
+    /// unencrypted.
+    pub fn to_markdown(&self) -> Result<String> {
+        Ok(match &self.entity {
+            ViewerEntity::Text(text) => format!(
+                "---\nfilename: {}\nexported: {}\nwords: {}\n---\n{}",
+                Self::yaml_quoted(self.name.as_deref().unwrap_or_default()),
+                Utc::now().to_rfc3339(),
+                text.split_whitespace().count(),
+                text
+            ),
+            ViewerEntity::DecryptedText(text) => format!("<!-- decrypted -->\n{}", text),
+            ViewerEntity::Table(rows) => Self::markdown_table(rows),
+            ViewerEntity::Json(value) => format!(
+                "```json\n{}\n```\n",
+                serde_json::to_string_pretty(value).unwrap_or_default()
+            ),
+            ViewerEntity::JsonLines(records) => format!(
+                "```json\n{}\n```\n",
+                records
+                    .iter()
+                    .map(serde_json::Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            ViewerEntity::Binary(_bin) => return Err(MystoreError::NotAFile("export", "binary")),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        if let Some(name) = self.name.as_deref().filter(|_name| !self.loading) {
+            self.scroll_positions.set(name, self.scroll);
+            // Starting a file from the top again is no reason to fail.
+            let _ = self.scroll_positions.save();
+        }
+        self.name = None;
+        self.encoding = None;
+        self.raw = None;
+        self.source = ViewerEntity::Text(String::new());
+        self.entity = ViewerEntity::Text(String::new());
+        self.scroll = 0;
+        self.loading = false;
+        self.pending_line = None;
+        self.layout = WrapLayout::default();
+        self.selected_record = 0;
+        self.reload_requested = false;
+        self.reloaded = None;
+        self.line_bookmarks.clear();
+        self.last_match = None;
+        self.log_stats = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::Editor;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    /// Keys of at least five bytes, the length the cipher cycles through.
+    fn key() -> impl Strategy<Value = String> {
+        "\\PC{5,12}"
+    }
+
+    proptest! {
+        #[test]
+        fn decryption_restores_encrypted_text(text in any::<String>(), key in key()) {
+            let bin = Editor::encrypt_string(&text, &key);
+            prop_assert_eq!(Viewer::decrypt_binary(&bin, &key).unwrap(), text);
+        }
+
+        #[test]
+        fn another_key_does_not_restore_the_text(
+            text in "\\PC{5,40}",
+            key in key(),
+            other in key(),
+        ) {
+            prop_assume!(key.as_bytes()[..5] != other.as_bytes()[..5]);
+            let bin = Editor::encrypt_string(&text, &key);
+            if let Ok(decrypted) = Viewer::decrypt_binary(&bin, &other) {
+                prop_assert_ne!(decrypted, text);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_text_stays_empty() {
+        let bin = Editor::encrypt_string("", "secret");
+        assert!(bin.is_empty());
+        assert_eq!(Viewer::decrypt_binary(&bin, "secret").unwrap(), "");
+    }
+
+    #[test]
+    fn every_byte_value_is_decrypted() {
+        // Each ASCII character, NUL included, at each position of a key
+        // that mixes one and multi-byte characters wraps around every value.
+        let text: String = (0..5).flat_map(|_| (0..128u8).map(char::from)).collect();
+        let key = "a0é€";
+        let bin = Editor::encrypt_string(&text, key);
+        assert_eq!(bin.iter().collect::<HashSet<_>>().len(), 256);
+        assert_eq!(Viewer::decrypt_binary(&bin, key).unwrap(), text);
+    }
+
+    #[test]
+    fn multi_byte_text_round_trips_with_a_non_ascii_key() {
+        let text = "Ключ → 鍵 🔑\n\u{0}\u{7f}\u{ff}";
+        let key = "пароль";
+        let bin = Editor::encrypt_string(text, key);
+        assert_ne!(bin, text.as_bytes());
+        assert_eq!(Viewer::decrypt_binary(&bin, key).unwrap(), text);
+    }
+}
@@ -0,0 +1,126 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+};
+
+/// Characters without a width that cannot be told apart from nothing.
+const INVISIBLE: [char; 5] = ['\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{feff}'];
+
+/// What a character is shown as with whitespace revealed, `None` if it is
+/// shown as is.
+fn marker(c: char) -> Option<String> {
+    match c {
+        '\t' => Some(String::from("→")),
+        '\u{7f}' => Some(String::from("^?")),
+        c if c.is_ascii_control() => Some(format!("^{}", char::from(c as u8 ^ 0x40))),
+        c if c.is_control() || INVISIBLE.contains(&c) => Some(format!("<U+{:04X}>", u32::from(c))),
+        _ => None,
+    }
+}
+
+fn reveal_line(line: &str, marked: Style) -> Spans<'static> {
+    let (content, cr) = match line.strip_suffix('\r') {
+        Some(content) => (content, true),
+        None => (line, false),
+    };
+    let body = content.trim_end_matches(' ');
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    for c in body.chars() {
+        match marker(c) {
+            Some(marker) => {
+                if !plain.is_empty() {
+                    spans.push(Span::raw(std::mem::take(&mut plain)));
+                }
+                spans.push(Span::styled(marker, marked));
+            }
+            None => plain.push(c),
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    let trailing = content.len() - body.len();
+    if trailing > 0 {
+        spans.push(Span::styled("·".repeat(trailing), marked));
+    }
+    if cr {
+        spans.push(Span::styled("^M", marked));
+    }
+
+    Spans::from(spans)
+}
+
+/// Shows tabs as `→`, trailing spaces as `·` and control characters as
+/// `^X` escapes, all of them dimmed. Lines end at `\n` alone, so the CR of
+/// a CRLF ending is shown as `^M`.
+pub fn reveal_whitespace(text: &str) -> Text<'static> {
+    if text.is_empty() {
+        return Text::default();
+    }
+    let marked = Style::default().add_modifier(Modifier::DIM);
+    let lines = text.strip_suffix('\n').unwrap_or(text).split('\n');
+
+    Text::from(
+        lines
+            .map(|line| reveal_line(line, marked))
+            .collect::<Vec<Spans>>(),
+    )
+}
+
+/// The characters of `text` without their styles, a line per line.
+pub fn to_plain(text: &Text) -> String {
+    text.lines
+        .iter()
+        .map(|spans| {
+            spans
+                .0
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revealed(text: &str) -> String {
+        to_plain(&reveal_whitespace(text))
+    }
+
+    #[test]
+    fn tabs_and_trailing_spaces_are_marked() {
+        assert_eq!(revealed("\tindented  \nnext"), "→indented··\nnext");
+        // Only spaces at the end of a line are trailing.
+        assert_eq!(revealed("a b\t \n"), "a b→·");
+        assert_eq!(revealed("   \n\n"), "···\n");
+    }
+
+    #[test]
+    fn markers_are_dimmed() {
+        let text = reveal_whitespace("\ta ");
+        let spans = &text.lines[0].0;
+        assert_eq!(spans.len(), 3);
+        assert!(spans[0].style.add_modifier.contains(Modifier::DIM));
+        assert_eq!(spans[1].style, Style::default());
+        assert!(spans[2].style.add_modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn carriage_returns_are_escaped() {
+        assert_eq!(revealed("crlf  \r\nlf\n"), "crlf··^M\nlf");
+        assert_eq!(revealed("over\rwrite"), "over^Mwrite");
+    }
+
+    #[test]
+    fn control_characters_and_bom_are_escaped() {
+        assert_eq!(revealed("\u{feff}title"), "<U+FEFF>title");
+        assert_eq!(revealed("\u{1b}[1m\u{0}\u{7f}"), "^[[1m^@^?");
+        assert_eq!(revealed("next\u{85}line"), "next<U+0085>line");
+        assert_eq!(revealed("é"), "é");
+        assert_eq!(revealed(""), "");
+    }
+}
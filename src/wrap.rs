@@ -0,0 +1,142 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const NBSP: &str = "\u{00a0}";
+
+/// How the logical lines of a text fall onto display rows in a pane, as
+/// drawn by a `Paragraph`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WrapLayout {
+    /// The first display row of every logical line.
+    line_starts: Vec<usize>,
+    rows: usize,
+}
+
+impl WrapLayout {
+    /// One display row per logical line, as for text that is not wrapped.
+    pub fn unwrapped(lines: usize) -> WrapLayout {
+        WrapLayout {
+            line_starts: (0..lines).collect(),
+            rows: lines,
+        }
+    }
+
+    /// Wraps `text` into `width` columns the way `Paragraph` does with
+    /// `Wrap { trim }`: on word boundaries, breaking words longer than the
+    /// width and dropping characters wider than it.
+    pub fn wrapped(text: &str, width: u16, trim: bool) -> WrapLayout {
+        let mut symbols = text
+            .lines()
+            .flat_map(|line| line.graphemes(true).chain(Some("\n")));
+        let mut wrapper = WordWrapper {
+            max_width: width,
+            trim,
+            current_line: Vec::new(),
+            next_line: Vec::new(),
+        };
+        let mut layout = WrapLayout::default();
+        let mut line_start = true;
+        while let Some(ends_line) = wrapper.next_row(&mut symbols) {
+            if line_start {
+                layout.line_starts.push(layout.rows);
+            }
+            layout.rows += 1;
+            line_start = ends_line;
+        }
+
+        layout
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_lines(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The first display row of a logical line counted from zero, the row
+    /// after the text for lines past its end.
+    pub fn get_line_start(&self, line: usize) -> usize {
+        self.line_starts.get(line).copied().unwrap_or(self.rows)
+    }
+
+    /// The logical line, counted from zero, shown on a display row.
+    pub fn get_line_at(&self, row: usize) -> usize {
+        self.line_starts
+            .partition_point(|start| *start <= row)
+            .saturating_sub(1)
+    }
+}
+
+/// Follows `tui`'s word wrapper, keeping the symbols instead of their styles.
+struct WordWrapper<'a> {
+    max_width: u16,
+    trim: bool,
+    current_line: Vec<&'a str>,
+    next_line: Vec<&'a str>,
+}
+
+impl<'a> WordWrapper<'a> {
+    fn width(symbols: &[&str]) -> u16 {
+        symbols.iter().map(|symbol| symbol.width() as u16).sum()
+    }
+
+    /// Composes the next display row, returns whether it ends a logical line.
+    fn next_row(&mut self, symbols: &mut impl Iterator<Item = &'a str>) -> Option<bool> {
+        if self.max_width == 0 {
+            return None;
+        }
+        std::mem::swap(&mut self.current_line, &mut self.next_line);
+        self.next_line.clear();
+
+        let mut current_width = Self::width(&self.current_line);
+        let mut symbols_to_last_word_end = 0;
+        let mut prev_whitespace = false;
+        let mut symbols_exhausted = true;
+        let mut ends_line = false;
+        for symbol in symbols {
+            symbols_exhausted = false;
+            let symbol_whitespace = symbol.chars().all(char::is_whitespace) && symbol != NBSP;
+            if symbol.width() as u16 > self.max_width
+                || self.trim && symbol_whitespace && symbol != "\n" && current_width == 0
+            {
+                continue;
+            }
+            if symbol == "\n" {
+                if prev_whitespace {
+                    self.current_line.truncate(symbols_to_last_word_end);
+                }
+                ends_line = true;
+                break;
+            }
+            if symbol_whitespace && !prev_whitespace {
+                symbols_to_last_word_end = self.current_line.len();
+            }
+            self.current_line.push(symbol);
+            current_width += symbol.width() as u16;
+            if current_width > self.max_width {
+                let truncate_at = if symbols_to_last_word_end != 0 {
+                    symbols_to_last_word_end
+                } else {
+                    self.current_line.len() - 1
+                };
+                let remainder = &self.current_line[truncate_at..];
+                if let Some(nonwhite) = remainder
+                    .iter()
+                    .position(|symbol| !symbol.chars().all(char::is_whitespace))
+                {
+                    self.next_line.extend_from_slice(&remainder[nonwhite..]);
+                }
+                self.current_line.truncate(truncate_at);
+                break;
+            }
+            prev_whitespace = symbol_whitespace;
+        }
+        if symbols_exhausted && self.current_line.is_empty() {
+            None
+        } else {
+            Some(ends_line)
+        }
+    }
+}
@@ -0,0 +1,2261 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use mystore::{
+    app::{App, AppEvent, Confirm, Mark, Mode, Prompt, SplitLayout},
+    bookmarks::Bookmarks,
+    clipboard::Clipboard,
+    error::{MystoreError, Result},
+    keyring,
+    loader::{FsLoader, Loader},
+    locks::LockOwner,
+    manager::{ActionPlacement, ManagerEntity},
+    times::{TimeFormats, DEFAULT_DISPLAY_FORMAT},
+    ui,
+    urls::UrlOpener,
+    viewer::ViewerEntity,
+};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tempfile::TempDir;
+use tui::{backend::TestBackend, layout::Rect, Terminal};
+
+const KEY: &str = "secret";
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+}
+
+fn press_ctrl(app: &mut App, c: char) {
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Char(c),
+        KeyModifiers::CONTROL,
+    )));
+}
+
+fn type_text(app: &mut App, text: &str) {
+    text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+}
+
+fn select(app: &mut App, entity: &ManagerEntity) {
+    for _ in 0..=app.get_manager_ref().get_entities_ref().len() {
+        if app.get_manager_ref().get_selected_entity() == Some(entity) {
+            return;
+        }
+        press(app, KeyCode::Down);
+    }
+    panic!("{:?} is not listed", entity);
+}
+
+/// Saves the note in the editor under the suggested name and closes it.
+fn save_and_close(app: &mut App, encrypt: bool) {
+    let key = if encrypt { 'E' } else { 'S' };
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Char(key),
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    )));
+    press(app, KeyCode::Enter);
+}
+
+fn wait_for_load(app: &mut App) {
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if !app.get_viewer_ref().is_loading() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("the file was not loaded in time");
+}
+
+/// Loads through the filesystem once the test releases the path.
+#[derive(Default)]
+struct GatedLoader {
+    gates: Mutex<HashMap<PathBuf, Receiver<()>>>,
+}
+
+impl GatedLoader {
+    fn gate(&self, path: PathBuf) -> Sender<()> {
+        let (sender, receiver) = mpsc::channel();
+        self.gates.lock().unwrap().insert(path, receiver);
+        sender
+    }
+}
+
+impl Loader for GatedLoader {
+    fn load(&self, path: &Path) -> Result<ViewerEntity> {
+        let gate = self.gates.lock().unwrap().remove(path);
+        if let Some(gate) = gate {
+            gate.recv().unwrap();
+        }
+        FsLoader.load(path)
+    }
+}
+
+/// Keeps the copied texts instead of touching the system clipboard.
+#[derive(Clone, Default)]
+struct RecordingClipboard {
+    copied: Arc<Mutex<Vec<String>>>,
+}
+
+impl Clipboard for RecordingClipboard {
+    fn copy(&self, text: &str) -> Result<()> {
+        self.copied.lock().unwrap().push(String::from(text));
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<String> {
+        Ok(self
+            .copied
+            .lock()
+            .unwrap()
+            .last()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[derive(Clone, Default)]
+struct RecordingOpener {
+    opened: Arc<Mutex<Vec<String>>>,
+}
+
+impl UrlOpener for RecordingOpener {
+    fn open(&self, url: &str) -> Result<()> {
+        self.opened.lock().unwrap().push(String::from(url));
+        Ok(())
+    }
+}
+
+fn files_in(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+fn render(app: &App) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.get(x, y).symbol.as_str())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn navigate_open_and_close_a_file() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(sub.join("note.txt"), "hello").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::Folder(sub.clone()));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_manager_ref().get_current(), sub);
+
+    select(&mut app, &ManagerEntity::TextFile(sub.join("note.txt")));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("hello"))
+    );
+    assert_eq!(app.get_viewer_ref().get_name(), Some("note.txt"));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    press(&mut app, KeyCode::Esc);
+    assert!(app.is_finished());
+}
+
+#[test]
+fn edit_save_and_delete() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    type_text(&mut app, "draft");
+    save_and_close(&mut app, false);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    let names = files_in(dir.path());
+    assert_eq!(names.len(), 1);
+    let path = dir.path().join(&names[0]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "draft");
+
+    select(&mut app, &ManagerEntity::TextFile(path.clone()));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(app.get_status().is_none());
+    assert!(!path.exists());
+}
+
+#[test]
+fn notes_are_saved_without_leaving_the_editor() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    let rename = |app: &mut App, name: &str| {
+        for _ in 0..app.get_input().len() {
+            press(app, KeyCode::Backspace);
+        }
+        type_text(app, name);
+        press(app, KeyCode::Enter);
+    };
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "first");
+    press_ctrl(&mut app, 's');
+    assert_eq!(
+        app.get_mode(),
+        &Mode::Prompt(Prompt::SaveNote {
+            encrypt: false,
+            exit: false
+        })
+    );
+    rename(&mut app, "draft.txt");
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(app.get_message(), Some("Saved draft.txt"));
+    assert!(!app.get_editor_ref().is_modified());
+    let path = dir.path().join("draft.txt");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+    type_text(&mut app, " and more");
+    assert!(render(&app)
+        .iter()
+        .any(|row| row.contains("Editing: draft.txt [modified]")));
+    press_ctrl(&mut app, 's');
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first and more");
+    assert_eq!(files_in(dir.path()), ["draft.txt"]);
+    type_text(&mut app, "!");
+    save_and_close(&mut app, false);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first and more!");
+    assert_eq!(files_in(dir.path()), ["draft.txt"]);
+
+    // Encrypted notes stay encrypted whichever key saves them again.
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "secret");
+    press_ctrl(&mut app, 'e');
+    rename(&mut app, "secret.bin");
+    assert!(render(&app)
+        .iter()
+        .any(|row| row.contains("Editing: secret.bin [encrypted]")));
+    type_text(&mut app, "s");
+    press_ctrl(&mut app, 's');
+    let encrypted = fs::read(dir.path().join("secret.bin")).unwrap();
+    assert!(keyring::is_sealed(&encrypted));
+    press(&mut app, KeyCode::Esc);
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("secret.bin")),
+    );
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::DecryptedText(String::from("secrets"))
+    );
+}
+
+#[test]
+fn encrypted_save_is_decrypted_in_the_viewer() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "top secret");
+    save_and_close(&mut app, true);
+
+    // The first encryption saves the keyring of the store next to the note.
+    let names = files_in(dir.path());
+    assert_eq!(names[0], ".mystore");
+    assert!(dir.path().join(".mystore").join("keyring").is_file());
+    let path = dir.path().join(&names[1]);
+    assert!(keyring::is_sealed(&fs::read(&path).unwrap()));
+
+    select(&mut app, &ManagerEntity::TextFile(path));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::DecryptedText(String::from("top secret"))
+    );
+
+    // Exporting the decrypted text asks first.
+    press_ctrl(&mut app, 'm');
+    assert_eq!(app.get_mode(), &Mode::Confirm(Confirm::ExportDecrypted));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    press_ctrl(&mut app, 'm');
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::ExportMarkdown));
+    type_text(&mut app, "plain.md");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("plain.md")).unwrap(),
+        "<!-- decrypted -->\ntop secret"
+    );
+
+    // The keyring now only opens with the password it was saved with.
+    drop(app);
+    assert!(matches!(
+        App::new(dir.path().to_str().unwrap(), "another password"),
+        Err(MystoreError::KeyringLocked)
+    ));
+}
+
+#[test]
+fn errors_are_reported_and_cleared() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::Folder(sub));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::NotAFile("delete", "folder"))
+    ));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    press(&mut app, KeyCode::Up);
+    assert!(app.get_status().is_none());
+}
+
+#[test]
+fn frames_show_listing_viewer_and_errors() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("folder")).unwrap();
+    fs::write(dir.path().join("note.txt"), "first line\nsecond line").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    let frame = render(&app);
+    assert!(frame.iter().any(|line| line.contains("│folder")));
+    assert!(frame.iter().any(|line| line.contains("│note.txt")));
+    assert!(frame
+        .iter()
+        .any(|line| line.starts_with("MANAGER | 1 file | ")));
+
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("note.txt")),
+    );
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    let frame = render(&app);
+    assert!(frame.iter().any(|line| line.contains("second line")));
+    assert!(frame
+        .iter()
+        .any(|line| line.starts_with("VIEWER | 1 file | [PLAIN] | ")));
+
+    press(&mut app, KeyCode::Esc);
+    select(&mut app, &ManagerEntity::Folder(dir.path().join("folder")));
+    press(&mut app, KeyCode::Char('d'));
+    let frame = render(&app);
+    assert!(frame
+        .iter()
+        .any(|line| line.contains("Cannot delete the folder entity")));
+}
+
+#[test]
+fn help_and_questions_are_shown_over_the_panes() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("note.txt"), "text").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    assert!(!render(&app).iter().any(|line| line.contains("Help (F1)")));
+
+    press(&mut app, KeyCode::F(1));
+    assert!(app.is_help_shown());
+    let frame = render(&app);
+    assert!(frame.iter().any(|line| line.contains("Help (F1)")));
+    assert!(frame.iter().any(|line| line.contains("Manager mode")));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    press(&mut app, KeyCode::F(1));
+    assert!(!render(&app).iter().any(|line| line.contains("Help (F1)")));
+
+    press_ctrl(&mut app, 'l');
+    assert_eq!(app.get_mode(), &Mode::Confirm(Confirm::CleanupLocks));
+    let frame = render(&app);
+    assert!(frame.iter().any(|line| line.contains("CONFIRM")));
+    assert!(frame
+        .iter()
+        .any(|line| line.contains("Remove stale lock files under the root?")));
+    assert!(frame.iter().any(|line| line.contains("Y: Yes; Other: No")));
+}
+
+#[test]
+fn loading_placeholder_is_shown_until_the_file_arrives() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("slow.txt");
+    fs::write(&path, "finally").unwrap();
+    let loader = Arc::new(GatedLoader::default());
+    let release = loader.gate(path.clone());
+
+    let mut app = App::with_loader(dir.path().to_str().unwrap(), KEY, loader).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path));
+    press(&mut app, KeyCode::Enter);
+    app.handle_event(AppEvent::Tick);
+    assert!(app.get_viewer_ref().is_loading());
+    assert!(render(&app).iter().any(|line| line.contains("Loading…")));
+
+    release.send(()).unwrap();
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("finally"))
+    );
+}
+
+#[test]
+fn cancelled_load_is_ignored_when_it_completes() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("slow.txt");
+    fs::write(&path, "too late").unwrap();
+    let loader = Arc::new(GatedLoader::default());
+    let release = loader.gate(path.clone());
+
+    let mut app = App::with_loader(dir.path().to_str().unwrap(), KEY, loader).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path));
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    release.send(()).unwrap();
+    for _ in 0..20 {
+        app.handle_event(AppEvent::Tick);
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(!app.get_viewer_ref().is_loading());
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::new())
+    );
+}
+
+#[test]
+fn superseded_load_does_not_overwrite_the_newer_one() {
+    let dir = TempDir::new().unwrap();
+    let old = dir.path().join("old.txt");
+    let new = dir.path().join("new.txt");
+    fs::write(&old, "old").unwrap();
+    fs::write(&new, "new").unwrap();
+    let loader = Arc::new(GatedLoader::default());
+    let release_old = loader.gate(old.clone());
+    let release_new = loader.gate(new.clone());
+
+    let mut app = App::with_loader(dir.path().to_str().unwrap(), KEY, loader).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(old));
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Esc);
+    select(&mut app, &ManagerEntity::TextFile(new));
+    press(&mut app, KeyCode::Enter);
+
+    release_new.send(()).unwrap();
+    wait_for_load(&mut app);
+    release_old.send(()).unwrap();
+    for _ in 0..20 {
+        app.handle_event(AppEvent::Tick);
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("new"))
+    );
+}
+
+#[test]
+fn failed_load_reports_the_error() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("gone.txt");
+    fs::write(&path, "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path.clone()));
+    fs::remove_file(&path).unwrap();
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(matches!(app.get_status(), Some(MystoreError::Io(_))));
+}
+
+#[test]
+fn cleanup_locks_asks_for_confirmation() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("a.txt.lock"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'l');
+    assert!(matches!(app.get_mode(), Mode::Confirm(_)));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(dir.path().join("a.txt.lock").exists());
+
+    press_ctrl(&mut app, 'l');
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_message(), Some("Removed 1 lock files"));
+    assert!(!dir.path().join("a.txt.lock").exists());
+}
+
+#[test]
+fn file_locked_by_another_session_opens_with_a_warning() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "text").unwrap();
+    fs::write(
+        dir.path().join("a.txt.lock"),
+        LockOwner::current().to_string(),
+    )
+    .unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path.clone()));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert!(matches!(app.get_status(), Some(MystoreError::Locked(locked)) if *locked == path));
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("text"))
+    );
+}
+
+#[test]
+fn startup_sweep_removes_locks_of_ended_sessions_in_the_background() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "").unwrap();
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    let dead = LockOwner {
+        pid: child.id(),
+        ..LockOwner::current()
+    };
+    fs::write(dir.path().join("a.txt.lock"), dead.to_string()).unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    assert!(app.get_manager_ref().is_locked(&path));
+    app.cleanup_locks();
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if !app.is_sweeping_locks() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(!app.is_sweeping_locks());
+    assert!(!dir.path().join("a.txt.lock").exists());
+    assert!(!app.get_manager_ref().is_locked(&path));
+    assert_eq!(app.get_message(), None);
+}
+
+#[test]
+fn leaving_unsaved_text_asks_for_confirmation() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "draft");
+    assert!(app.has_unsaved_changes());
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Confirm(Confirm::LeaveEditor));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(files_in(dir.path()).is_empty());
+}
+
+#[test]
+fn unsaved_changes_are_checked_against_the_file_as_the_editor_is_shown() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "draft");
+    press_ctrl(&mut app, 's');
+    type_text(&mut app, "-x");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert!(!app.has_unsaved_changes());
+    let [name] = files_in(dir.path()).try_into().unwrap();
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    // The file changed while the editor was hidden.
+    fs::write(dir.path().join(name), "changed").unwrap();
+    press(&mut app, KeyCode::Char('e'));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert!(app.has_unsaved_changes());
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Confirm(Confirm::LeaveEditor));
+}
+
+/// Names new notes down to the microsecond, the default format only tells
+/// seconds apart.
+fn with_distinct_names(app: &mut App) {
+    app.get_manager_mut().set_time_formats(TimeFormats::new(
+        DEFAULT_DISPLAY_FORMAT,
+        "%Y-%m-%d_%H-%M-%S-%6f",
+    ));
+}
+
+#[test]
+fn recorded_macro_is_replayed() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    with_distinct_names(&mut app);
+
+    press_ctrl(&mut app, 'q');
+    assert!(app.is_recording());
+    assert!(render(&app).iter().any(|line| line.contains("● REC")));
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "memo");
+    save_and_close(&mut app, false);
+    press_ctrl(&mut app, 'q');
+    assert!(!app.is_recording());
+    assert_eq!(app.get_message(), Some("Recorded a macro of 7 keys"));
+    assert_eq!(files_in(dir.path()).len(), 1);
+
+    thread::sleep(Duration::from_millis(5));
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Char('Q'),
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    )));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    let names = files_in(dir.path());
+    assert_eq!(names.len(), 2);
+    for name in names {
+        assert_eq!(fs::read_to_string(dir.path().join(name)).unwrap(), "memo");
+    }
+}
+
+#[test]
+fn file_is_created_from_a_chosen_template() {
+    let dir = TempDir::new().unwrap();
+    let templates = TempDir::new().unwrap();
+    fs::write(templates.path().join("a"), "first").unwrap();
+    fs::write(templates.path().join("b"), "second").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_manager_mut()
+        .set_templates_dir(templates.path().join("missing"));
+    press_ctrl(&mut app, 't');
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_message(), Some("No templates found"));
+
+    app.get_manager_mut()
+        .set_templates_dir(PathBuf::from(templates.path()));
+    press_ctrl(&mut app, 't');
+    assert_eq!(app.get_mode(), &Mode::Templates);
+    assert!(render(&app).iter().any(|line| line.contains("Templates")));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    let names = files_in(dir.path());
+    assert_eq!(names.len(), 1);
+    assert_eq!(
+        fs::read_to_string(dir.path().join(&names[0])).unwrap(),
+        "second"
+    );
+}
+
+#[test]
+fn viewer_text_is_exported_through_a_prompt() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    fs::write(&path, "hello world").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+
+    // Enter is not taken for the export.
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    press_ctrl(&mut app, 'm');
+    assert!(matches!(app.get_mode(), Mode::Prompt(_)));
+    type_text(&mut app, "out.mdx");
+    press(&mut app, KeyCode::Backspace);
+    assert!(render(&app).iter().any(|line| line.contains("out.md_")));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert!(app.get_message().unwrap().starts_with("Exported to"));
+    let markdown = fs::read_to_string(dir.path().join("out.md")).unwrap();
+    assert!(markdown.contains("words: 2\n"));
+    assert!(app
+        .get_manager_ref()
+        .get_entities_ref()
+        .contains(&ManagerEntity::TextFile(dir.path().join("out.md"))));
+
+    // Exporting again collides and keeps the prompt open.
+    press_ctrl(&mut app, 'm');
+    type_text(&mut app, "out.md");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::NameCollision(_))
+    ));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+}
+
+#[test]
+fn git_failure_does_not_block_the_save() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.enable_git();
+    assert!(matches!(app.get_status(), Some(MystoreError::Git(_))));
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "kept");
+    save_and_close(&mut app, false);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(matches!(app.get_status(), Some(MystoreError::Git(_))));
+    assert_eq!(files_in(dir.path()).len(), 1);
+
+    press(&mut app, KeyCode::Down);
+    assert!(app.get_status().is_none());
+}
+
+#[test]
+fn copied_texts_are_saved_while_watching_the_clipboard() {
+    let dir = TempDir::new().unwrap();
+    let clipboard = RecordingClipboard::default();
+    clipboard.copy("copied before").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_clipboard(Box::new(clipboard.clone()));
+    let watch = KeyEvent::new(
+        KeyCode::Char('V'),
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    );
+    app.handle_event(AppEvent::Key(watch));
+    assert!(app.is_clipboard_watched());
+    assert!(render(&app)[1].contains("[clipboard watch]"));
+
+    clipboard.copy("a quick note").unwrap();
+    for _ in 0..300 {
+        app.handle_event(AppEvent::Tick);
+        if !files_in(dir.path()).is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let names = files_in(dir.path());
+    assert_eq!(names.len(), 1);
+    assert_eq!(
+        fs::read_to_string(dir.path().join(&names[0])).unwrap(),
+        "a quick note"
+    );
+    assert_eq!(
+        app.get_message(),
+        Some(format!("Saved clipboard to {}", names[0]).as_str())
+    );
+
+    // The same text again is not saved twice.
+    clipboard.copy("a quick note").unwrap();
+    thread::sleep(Duration::from_millis(700));
+    app.handle_event(AppEvent::Tick);
+    assert_eq!(files_in(dir.path()).len(), 1);
+
+    app.handle_event(AppEvent::Key(watch));
+    assert!(!app.is_clipboard_watched());
+}
+
+#[test]
+fn selected_path_is_copied() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(sub.join("note.txt"), "").unwrap();
+
+    let clipboard = RecordingClipboard::default();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_clipboard(Box::new(clipboard.clone()));
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(
+        app.get_message(),
+        Some("Nothing to copy: no item is selected")
+    );
+
+    select(&mut app, &ManagerEntity::Folder(sub.clone()));
+    press(&mut app, KeyCode::Enter);
+    select(&mut app, &ManagerEntity::TextFile(sub.join("note.txt")));
+    press(&mut app, KeyCode::Char('y'));
+    press_ctrl(&mut app, 'y');
+    assert_eq!(
+        app.get_message(),
+        Some(format!("Copied {}", Path::new("sub").join("note.txt").display()).as_str())
+    );
+    assert_eq!(
+        *clipboard.copied.lock().unwrap(),
+        vec![
+            sub.join("note.txt").to_string_lossy().into_owned(),
+            Path::new("sub")
+                .join("note.txt")
+                .to_string_lossy()
+                .into_owned(),
+        ]
+    );
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(
+        app.get_message(),
+        Some("Nothing to copy: actions have no path")
+    );
+    assert_eq!(clipboard.copied.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn folder_is_merged_through_a_prompt() {
+    let dir = TempDir::new().unwrap();
+    let source = dir.path().join("source");
+    fs::create_dir(&source).unwrap();
+    fs::write(source.join("a.txt"), "a").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'm');
+    assert!(matches!(app.get_mode(), Mode::Prompt(_)));
+    type_text(&mut app, "source");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(
+        app.get_message(),
+        Some("Copied 1 files, skipped 0 existing")
+    );
+    assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a");
+}
+
+#[test]
+fn tags_are_edited_and_used_as_filters() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    let plain = sub.join("plain.txt");
+    fs::write(&plain, "body").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('f'));
+    assert_eq!(app.get_message(), Some("No tags found"));
+
+    select(&mut app, &ManagerEntity::Folder(sub.clone()));
+    press(&mut app, KeyCode::Enter);
+    select(&mut app, &ManagerEntity::TextFile(plain.clone()));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    press(&mut app, KeyCode::Char('#'));
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::EditTags));
+    type_text(&mut app, "work, ideas");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert_eq!(
+        fs::read_to_string(&plain).unwrap(),
+        "tags: work, ideas\nbody"
+    );
+
+    // Encrypted notes keep their tags encrypted.
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "secret body");
+    save_and_close(&mut app, true);
+    let encrypted = app
+        .get_manager_ref()
+        .get_entities_ref()
+        .iter()
+        .find(|entity| **entity != ManagerEntity::TextFile(plain.clone()))
+        .cloned()
+        .unwrap();
+    select(&mut app, &encrypted);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    press(&mut app, KeyCode::Char('#'));
+    type_text(&mut app, "hidden");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::DecryptedText(String::from("tags: hidden\nsecret body"))
+    );
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Char('f'));
+    assert_eq!(app.get_mode(), &Mode::Tags);
+    assert_eq!(
+        app.get_choices_ref(),
+        &vec![
+            String::from("hidden"),
+            String::from("ideas"),
+            String::from("work")
+        ]
+    );
+    let index = fs::read_to_string(dir.path().join(".mystore").join("tags")).unwrap();
+    assert!(!index.contains("hidden"));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_manager_ref().get_filter_label(), Some("work"));
+    assert_eq!(
+        app.get_manager_ref().get_entities_ref()[0],
+        ManagerEntity::TextFile(plain)
+    );
+    assert!(render(&app).iter().any(|line| line.contains("#work")));
+}
+
+#[test]
+fn marked_files_are_renamed_with_a_preview() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("draft.txt"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'r');
+    assert_eq!(app.get_message(), Some("No files are marked"));
+
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("draft.txt")),
+    );
+    press(&mut app, KeyCode::Char(' '));
+    press_ctrl(&mut app, 'r');
+    assert_eq!(app.get_mode(), &Mode::Rename);
+    type_text(&mut app, "draft");
+    press(&mut app, KeyCode::Enter);
+    type_text(&mut app, "final");
+    assert!(render(&app)
+        .iter()
+        .any(|line| line.contains("draft.txt → final.txt")));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_message(), Some("Renamed 1 files"));
+    assert_eq!(files_in(dir.path()), vec![String::from("final.txt")]);
+}
+
+#[test]
+fn deletions_and_renames_are_undone() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'u');
+    assert_eq!(app.get_message(), Some("Nothing to undo"));
+
+    let draft = app
+        .get_manager_mut()
+        .create_file(b"first\nsecond\n".to_vec(), Some(String::from("draft.txt")))
+        .unwrap();
+    select(&mut app, &ManagerEntity::TextFile(draft.clone()));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(!draft.exists());
+    press_ctrl(&mut app, 'u');
+    assert_eq!(app.get_message(), Some("Undone: restored draft.txt"));
+    assert_eq!(fs::read(&draft).unwrap(), b"first\nsecond\n");
+    assert!(app.get_journal_ref().is_empty());
+
+    select(&mut app, &ManagerEntity::TextFile(draft.clone()));
+    press(&mut app, KeyCode::Char(' '));
+    press_ctrl(&mut app, 'r');
+    type_text(&mut app, "draft");
+    press(&mut app, KeyCode::Enter);
+    type_text(&mut app, "final");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(files_in(dir.path()), ["final.txt"]);
+    press_ctrl(&mut app, 'u');
+    assert_eq!(app.get_message(), Some("Undone: renamed draft.txt back"));
+    assert_eq!(files_in(dir.path()), ["draft.txt"]);
+
+    // The restored file is still one of the session, so deletable.
+    select(&mut app, &ManagerEntity::TextFile(draft.clone()));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(files_in(dir.path()).is_empty());
+    fs::write(&draft, "taken").unwrap();
+    press_ctrl(&mut app, 'u');
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::NameCollision(_))
+    ));
+    assert_eq!(app.get_journal_ref().len(), 1);
+    fs::remove_file(&draft).unwrap();
+    press_ctrl(&mut app, 'u');
+    assert_eq!(fs::read(&draft).unwrap(), b"first\nsecond\n");
+}
+
+#[test]
+fn deleted_duplicates_are_undone() {
+    let dir = TempDir::new().unwrap();
+    let (first, second) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+    fs::write(&first, "same").unwrap();
+    fs::write(&second, "same").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'f');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if *app.get_mode() != Mode::Manager {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(*app.get_mode(), Mode::Duplicates);
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('d'));
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(files_in(dir.path()), ["b.txt"]);
+
+    press_ctrl(&mut app, 'u');
+    assert_eq!(app.get_message(), Some("Undone: restored a.txt"));
+    assert_eq!(fs::read_to_string(&first).unwrap(), "same");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "same");
+}
+
+#[test]
+fn folder_size_is_shown_on_demand() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(sub.join("a.txt"), "12345").unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(dir.path().join("b.txt")));
+    press(&mut app, KeyCode::Char('u'));
+    assert!(matches!(app.get_status(), Some(MystoreError::NotAFile(..))));
+
+    select(&mut app, &ManagerEntity::Folder(sub.clone()));
+    press(&mut app, KeyCode::Char('u'));
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_sizer_ref().get_size(&sub).is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let frame = render(&app);
+    assert!(frame.iter().any(|line| line.contains("sub (5 B)")));
+    assert!(frame
+        .iter()
+        .any(|line| line.contains("sub: 5 B in 1 files")));
+}
+
+#[test]
+fn pages_are_turned_with_page_keys() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..3 {
+        fs::write(dir.path().join(format!("{}.txt", id)), "").unwrap();
+    }
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_manager_mut().set_page_size(2).unwrap();
+    assert!(render(&app).iter().any(|line| line.contains("Page 1/2")));
+    press(&mut app, KeyCode::PageDown);
+    assert_eq!(app.get_manager_ref().get_page(), (2, 2));
+    assert!(render(&app).iter().any(|line| line.contains("Page 2/2")));
+    press(&mut app, KeyCode::PageUp);
+    assert_eq!(app.get_manager_ref().get_page(), (1, 2));
+}
+
+#[test]
+fn end_reads_more_of_a_folder_read_incrementally() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..3 {
+        fs::write(dir.path().join(format!("{}.txt", id)), "").unwrap();
+    }
+
+    let mut app = App::new_incremental(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_manager_mut().set_page_size(2).unwrap();
+    assert_eq!(app.get_manager_ref().get_entities_ref().len(), 2);
+    press(&mut app, KeyCode::End);
+    assert_eq!(app.get_manager_ref().get_entities_ref().len(), 3);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(2));
+    press(&mut app, KeyCode::Home);
+    press(&mut app, KeyCode::End);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(2));
+}
+
+#[test]
+fn sort_key_shows_the_size_column() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("small.txt"), "a").unwrap();
+    fs::write(dir.path().join("large.txt"), "a".repeat(2048)).unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('s'));
+    assert_eq!(app.get_message(), Some("Sorted by size"));
+    let screen = render(&app);
+    // The narrow listing cuts the names to keep the sizes aligned.
+    let large = screen
+        .iter()
+        .position(|line| line.contains("large.t    2.0 KiB"))
+        .unwrap();
+    assert!(screen[large + 1].contains("small.t        1 B"));
+
+    press(&mut app, KeyCode::Char('s'));
+    assert!(!render(&app).iter().any(|line| line.contains("KiB")));
+}
+
+#[test]
+fn table_view_is_toggled_in_the_viewer() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("data.csv"), "a,b\n1,2").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert!(matches!(
+        app.get_viewer_ref().get_entity_ref(),
+        ViewerEntity::Table(_)
+    ));
+
+    press(&mut app, KeyCode::Char('t'));
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("a,b\n1,2"))
+    );
+}
+
+#[test]
+fn extension_filter_is_set_from_a_prompt_and_cleared_with_esc() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.md"), "").unwrap();
+    fs::write(dir.path().join("b.pdf"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::FilterExtensions));
+    type_text(&mut app, "md");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_manager_ref().get_entities_ref().len(), 1);
+    assert!(render(&app).iter().any(|line| line.contains("[.md]")));
+
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.get_input(), "md");
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_manager_ref().get_entities_ref().len(), 2);
+    press(&mut app, KeyCode::Esc);
+    assert!(app.is_finished());
+}
+
+#[test]
+fn go_to_prompt_completes_and_jumps() {
+    let dir = TempDir::new().unwrap();
+    let deep = dir.path().join("a").join("deep");
+    fs::create_dir_all(&deep).unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('g'));
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::GoTo));
+    type_text(&mut app, "a/d");
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.get_input(), "a/deep/");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_manager_ref().get_current(), deep);
+
+    // A bad path keeps the prompt open.
+    press(&mut app, KeyCode::Char('g'));
+    type_text(&mut app, "../..");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::GoTo));
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::OutsideRoot(_))
+    ));
+}
+
+#[test]
+fn grep_opens_the_selected_line() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "one\nneedle\nthree\nneedle again").unwrap();
+    fs::write(dir.path().join("b.txt"), "no match").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'g');
+    assert_eq!(app.get_mode(), &Mode::Grep);
+    type_text(&mut app, "needle");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Grep);
+    assert_eq!(app.get_message(), Some("2 matching lines"));
+    assert_eq!(app.get_grep_results_ref().len(), 2);
+    assert!(render(&app)
+        .iter()
+        .any(|line| line.contains("a.txt:4: needle again")));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.get_grep_selected(), 1);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    wait_for_load(&mut app);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 3);
+    assert_eq!(
+        app.get_manager_ref().get_selected_entity(),
+        Some(&ManagerEntity::TextFile(dir.path().join("a.txt")))
+    );
+
+    press(&mut app, KeyCode::Esc);
+    press_ctrl(&mut app, 'g');
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+}
+
+#[test]
+fn marked_files_are_encrypted_in_batch() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "first").unwrap();
+    fs::write(dir.path().join("b.txt"), "second").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'e');
+    assert_eq!(app.get_message(), Some("No files are marked"));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    for name in ["a.txt", "b.txt"] {
+        select(&mut app, &ManagerEntity::TextFile(dir.path().join(name)));
+        press(&mut app, KeyCode::Char(' '));
+    }
+    press_ctrl(&mut app, 'e');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_batch_ref().get_progress().is_none() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(app.get_message(), Some("2 encrypted, 0 skipped, 0 failed"));
+    assert!(app.get_manager_ref().get_marked_ref().is_empty());
+    assert_ne!(fs::read(dir.path().join("a.txt")).unwrap(), b"first");
+
+    select(&mut app, &ManagerEntity::TextFile(dir.path().join("a.txt")));
+    press(&mut app, KeyCode::Char(' '));
+    press_ctrl(&mut app, 'd');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_batch_ref().get_progress().is_none() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(app.get_message(), Some("1 decrypted, 0 skipped, 0 failed"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+        "first"
+    );
+}
+
+#[test]
+fn viewer_follows_a_growing_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("app.log");
+    fs::write(&path, "started").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(path.clone()));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    press_ctrl(&mut app, 'a');
+    assert!(render(&app)
+        .iter()
+        .any(|line| line.contains("app.log [auto-refresh 1s]")));
+
+    fs::write(&path, "started\nstopped").unwrap();
+    for _ in 0..60 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_viewer_ref().get_entity_ref()
+            == &ViewerEntity::Text(String::from("started\nstopped"))
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("started\nstopped"))
+    );
+
+    press_ctrl(&mut app, 'a');
+    assert_eq!(app.get_viewer_ref().get_auto_refresh(), None);
+}
+
+#[test]
+fn back_and_root_keys_work_without_rows() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_manager_mut()
+        .set_action_placement(ActionPlacement::Hidden)
+        .unwrap();
+    app.get_manager_mut().go_to("a/b").unwrap();
+    assert!(app.get_manager_ref().get_entities_ref().is_empty());
+
+    press(&mut app, KeyCode::Backspace);
+    assert_eq!(app.get_manager_ref().get_current(), dir.path().join("a"));
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.get_manager_ref().get_current(), dir.path());
+
+    app.get_manager_mut().go_to("a/b").unwrap();
+    press(&mut app, KeyCode::Char('~'));
+    assert_eq!(app.get_manager_ref().get_current(), dir.path());
+    assert_eq!(app.get_mode(), &Mode::Manager);
+}
+
+#[test]
+fn folder_size_is_shown_as_a_message() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    fs::write(dir.path().join("subdir").join("a.txt"), "x".repeat(2048)).unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    select(&mut app, &ManagerEntity::TextFile(dir.path().join("b.txt")));
+    press_ctrl(&mut app, 's');
+    assert!(app.get_status().is_some());
+
+    select(&mut app, &ManagerEntity::Folder(dir.path().join("subdir")));
+    press_ctrl(&mut app, 's');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_message().is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(app.get_message(), Some("subdir: 2.0 KiB"));
+
+    // A known size is shown right away.
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Up);
+    press_ctrl(&mut app, 's');
+    assert_eq!(app.get_message(), Some("subdir: 2.0 KiB"));
+}
+
+#[test]
+fn bookmarks_are_kept_for_the_next_visit() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("long.txt");
+    fs::write(&path, "line\n".repeat(30)).unwrap();
+    let store = dir.path().join("bookmarks.toml");
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_bookmarks(Bookmarks::load(&store));
+    select(&mut app, &ManagerEntity::TextFile(path.clone()));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+    }
+    press_ctrl(&mut app, 'b');
+    assert_eq!(app.get_message(), Some("Bookmarked line 5"));
+    assert!(render(&app).iter().any(|line| line.contains("[▸ 1]")));
+    press(&mut app, KeyCode::Esc);
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_bookmarks(Bookmarks::load(&store));
+    select(&mut app, &ManagerEntity::TextFile(path));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert_eq!(app.get_viewer_ref().get_line_bookmarks(), &[5]);
+    press(&mut app, KeyCode::Char(']'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+    press(&mut app, KeyCode::Char('['));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+}
+
+#[test]
+fn entries_of_several_roots_are_labelled() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    fs::write(first.path().join("file.txt"), "").unwrap();
+    fs::write(second.path().join("notes.md"), "# notes").unwrap();
+    let label = |dir: &TempDir, name: &str| {
+        let root = dir.path().file_name().unwrap().to_string_lossy();
+        format!("[{}] {}", root, name)
+    };
+
+    let mut app = App::new_multi_root(
+        &[
+            first.path().to_str().unwrap(),
+            second.path().to_str().unwrap(),
+        ],
+        KEY,
+    )
+    .unwrap();
+    // The listing is narrow, only the start of the label is checked.
+    let screen = render(&app);
+    assert!(screen
+        .iter()
+        .any(|line| line.contains(&label(&first, "file.txt")[..10])));
+    assert!(screen
+        .iter()
+        .any(|line| line.contains(&label(&second, "notes.md")[..10])));
+
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(second.path().join("notes.md")),
+    );
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text(String::from("# notes"))
+    );
+}
+
+#[test]
+fn saving_warns_when_disk_space_runs_low() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    with_distinct_names(&mut app);
+    app.handle_event(AppEvent::Tick);
+    if app.get_free_space().is_none() {
+        // The platform does not tell the free space.
+        return;
+    }
+    assert!(render(&app).iter().any(|line| line.contains(" free")));
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "note");
+    save_and_close(&mut app, false);
+    assert!(app.get_status().is_none());
+
+    app.set_low_space_threshold(u64::MAX);
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "another note");
+    save_and_close(&mut app, false);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert!(matches!(app.get_status(), Some(MystoreError::LowSpace(_))));
+}
+
+#[test]
+fn checksum_is_shown_and_copied() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    fs::write(dir.path().join("abc.txt"), "abc").unwrap();
+
+    let clipboard = RecordingClipboard::default();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_clipboard(Box::new(clipboard.clone()));
+    select(&mut app, &ManagerEntity::Folder(dir.path().join("subdir")));
+    press(&mut app, KeyCode::Char('h'));
+    assert!(app.get_status().is_some());
+
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("abc.txt")),
+    );
+    press(&mut app, KeyCode::Char('h'));
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if *app.get_mode() != Mode::Manager {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    assert!(matches!(app.get_mode(), Mode::Confirm(_)));
+    let screen = render(&app).join("\n");
+    assert!(screen.contains("abc.txt (3 B)"));
+    assert!(screen.contains(&digest[..40]));
+
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(*app.get_mode(), Mode::Manager);
+    assert_eq!(
+        *clipboard.copied.lock().unwrap(),
+        vec![String::from(digest)]
+    );
+}
+
+#[test]
+fn duplicates_are_listed_and_deleted() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("old")).unwrap();
+    let (first, second) = (
+        dir.path().join("a.txt"),
+        dir.path().join("old").join("a.txt"),
+    );
+    fs::write(&first, "same").unwrap();
+    fs::write(&second, "same").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press_ctrl(&mut app, 'f');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if *app.get_mode() != Mode::Manager {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(*app.get_mode(), Mode::Duplicates);
+    assert_eq!(
+        *app.get_duplicates_ref(),
+        vec![vec![first.clone(), second.clone()]]
+    );
+    assert!(render(&app).join("\n").contains("[ ] old/a.txt"));
+
+    // The last unmarked copy cannot be marked.
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char(' '));
+    assert_eq!(app.get_message(), Some("At least one copy is kept"));
+    assert_eq!(*app.get_duplicate_marked_ref(), vec![first.clone()]);
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(*app.get_mode(), Mode::Manager);
+    assert_eq!(
+        app.get_manager_ref().get_selected_entity(),
+        Some(&ManagerEntity::TextFile(second.clone()))
+    );
+
+    press_ctrl(&mut app, 'f');
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if *app.get_mode() != Mode::Manager {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('d'));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(*app.get_mode(), Mode::Duplicates);
+    assert!(first.exists());
+    press(&mut app, KeyCode::Char('d'));
+    press(&mut app, KeyCode::Char('y'));
+    assert!(!first.exists());
+    assert!(second.exists());
+    assert_eq!(app.get_message(), Some("Deleted 1 copies"));
+    assert_eq!(*app.get_mode(), Mode::Manager);
+}
+
+#[test]
+fn session_locks_until_the_password_is_typed_again() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("secret-plans.txt"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_reauth_interval(Some(Duration::from_millis(50)));
+    press(&mut app, KeyCode::Char('e'));
+    app.handle_event(AppEvent::Tick);
+    assert_eq!(*app.get_mode(), Mode::Editor);
+
+    thread::sleep(Duration::from_millis(60));
+    app.handle_event(AppEvent::Tick);
+    assert_eq!(*app.get_mode(), Mode::Reauth(Box::new(Mode::Editor)));
+    let screen = render(&app).join("\n");
+    assert!(screen.contains("Session locked"));
+    assert!(!screen.contains("secret-plans"));
+
+    type_text(&mut app, "wrong");
+    assert!(render(&app).join("\n").contains("Password: *****_"));
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::PasswordMismatch)
+    ));
+    assert_eq!(*app.get_mode(), Mode::Reauth(Box::new(Mode::Editor)));
+    assert_eq!(app.get_password(), "");
+
+    type_text(&mut app, KEY);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(*app.get_mode(), Mode::Editor);
+    app.handle_event(AppEvent::Tick);
+    assert_eq!(*app.get_mode(), Mode::Editor);
+}
+
+#[test]
+fn created_files_are_exported_as_zip() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("old.txt"), "old").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_manager_mut()
+        .create_file(b"new".to_vec(), Some(String::from("new.txt")))
+        .unwrap();
+
+    let out = TempDir::new().unwrap();
+    let plain = out.path().join("plain.zip");
+    app.get_manager_mut().export_as_zip(&plain, false).unwrap();
+    let mut archive = zip::ZipArchive::new(fs::File::open(&plain).unwrap()).unwrap();
+    assert_eq!(archive.file_names().collect::<Vec<_>>(), vec!["new.txt"]);
+    let mut text = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("new.txt").unwrap(), &mut text).unwrap();
+    assert_eq!(text, "new");
+    // An existing file is not overwritten.
+    assert!(matches!(
+        app.get_manager_mut().export_as_zip(&plain, false),
+        Err(MystoreError::NameCollision(_))
+    ));
+    press_ctrl(&mut app, 'z');
+    for _ in 0.."session.zip".len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    type_text(&mut app, "old.txt");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::NameCollision(_))
+    ));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("old.txt")).unwrap(),
+        "old"
+    );
+    // Nor is anything written outside the root.
+    for _ in 0.."old.txt".len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    type_text(&mut app, "../escaped.zip");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(
+        app.get_status(),
+        Some(MystoreError::OutsideRoot(_))
+    ));
+    assert!(!dir.path().parent().unwrap().join("escaped.zip").exists());
+    press(&mut app, KeyCode::Esc);
+
+    // The password is a hash of the session key apart from the keyring's.
+    press_ctrl(&mut app, 'z');
+    assert_eq!(
+        app.get_mode(),
+        &Mode::Prompt(Prompt::ExportZip { encrypt: true })
+    );
+    assert_eq!(app.get_input(), "session.zip");
+    for _ in 0.."session.zip".len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    type_text(&mut app, "secret.zip");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.get_status().is_none());
+    let file = fs::File::open(dir.path().join("secret.zip")).unwrap();
+    let mut archive = zip::ZipArchive::new(file).unwrap();
+    assert!(archive.by_name("new.txt").is_err());
+    assert!(archive.by_name_decrypt("new.txt", KEY.as_bytes()).is_err());
+    // printf 'mystore zip export\n%s' secret | sha256sum
+    let password = "2423ad8854afe399ee3c92fb1dedaa88cdbf20594ec4309eb299149f0d34738a";
+    let mut text = String::new();
+    let mut entry = archive
+        .by_name_decrypt("new.txt", password.as_bytes())
+        .unwrap();
+    std::io::Read::read_to_string(&mut entry, &mut text).unwrap();
+    assert_eq!(text, "new");
+}
+
+fn wheel(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+    app.handle_event(AppEvent::Mouse(MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    }));
+}
+
+#[test]
+fn mouse_wheel_scrolls_the_pane_under_it() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..5 {
+        fs::write(dir.path().join(format!("{}.txt", id)), "line\n".repeat(50)).unwrap();
+    }
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_pane_areas(ui::pane_areas(
+        Rect::new(0, 0, 80, 30),
+        app.get_manager_width(),
+    ));
+    app.set_wheel_lines(2);
+
+    // The manager is on the left, the selection stops at the last file.
+    wheel(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(0));
+    wheel(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(2));
+    wheel(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    wheel(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(4));
+    wheel(&mut app, MouseEventKind::ScrollUp, 5, 10);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(2));
+    // Over the empty viewer it does nothing.
+    wheel(&mut app, MouseEventKind::ScrollDown, 50, 10);
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(2));
+
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    wheel(&mut app, MouseEventKind::ScrollDown, 50, 10);
+    wheel(&mut app, MouseEventKind::ScrollDown, 50, 10);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+    wheel(&mut app, MouseEventKind::ScrollUp, 50, 10);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 2);
+    // Outside of the viewer the wheel is ignored.
+    wheel(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 2);
+}
+
+#[test]
+fn json_lines_expand_into_a_popup() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("log.jsonl"),
+        "{\"id\": 1, \"msg\": \"start\"}\n{\"id\": 2, \"msg\": \"stop\"}\n",
+    )
+    .unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    assert!(render(&app).iter().any(|line| line.contains("id: 2")));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::JsonRecord);
+    let screen = render(&app);
+    assert!(screen.iter().any(|line| line.contains("Line 2")));
+    assert!(screen.iter().any(|line| line.contains("\"msg\": \"stop\"")));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+}
+
+#[test]
+fn viewed_file_is_reloaded_on_request() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    fs::write(&path, "line\n".repeat(40)).unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_viewer_mut().set_pane_size(20, 10);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+    for _ in 0..25 {
+        press(&mut app, KeyCode::Down);
+    }
+
+    // The scroll is kept, within the shorter text.
+    fs::write(&path, "new\n".repeat(30)).unwrap();
+    press(&mut app, KeyCode::Char('r'));
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if app.get_viewer_ref().is_just_reloaded() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text("new\n".repeat(30))
+    );
+    assert_eq!(app.get_viewer_ref().get_scroll(), 20);
+    assert!(render(&app).iter().any(|line| line.contains("[reloaded]")));
+
+    fs::remove_file(&path).unwrap();
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert!(matches!(app.get_status(), Some(MystoreError::Io(_))));
+    assert_eq!(
+        app.get_viewer_ref().get_entity_ref(),
+        &ViewerEntity::Text("new\n".repeat(30))
+    );
+}
+
+#[test]
+fn viewer_keys_follow_less() {
+    let dir = TempDir::new().unwrap();
+    let text: String = (1..=100).map(|line| format!("line {}\n", line)).collect();
+    fs::write(dir.path().join("note.txt"), text).unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_viewer_mut().set_pane_size(20, 10);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('f'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 20);
+    press(&mut app, KeyCode::Char('b'));
+    press(&mut app, KeyCode::Char('d'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 15);
+    press(&mut app, KeyCode::Char('u'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 10);
+    press(&mut app, KeyCode::Char('G'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 90);
+    press(&mut app, KeyCode::Char('g'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 0);
+
+    // Unbound keys keep the file open where it is.
+    press(&mut app, KeyCode::Char('x'));
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+
+    press(&mut app, KeyCode::Char('/'));
+    type_text(&mut app, "^line 5");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 49);
+    press(&mut app, KeyCode::Char('N'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+    press(&mut app, KeyCode::Char('N'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 4);
+    assert_eq!(app.get_message(), Some("Pattern not found: ^line 5"));
+
+    press(&mut app, KeyCode::Char('q'));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+}
+
+#[test]
+fn urls_of_the_viewed_text_are_opened() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("one.txt"),
+        "Docs at https://example.com/docs.",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("two.txt"),
+        "http://example.org and (https://example.net/a?b=c)",
+    )
+    .unwrap();
+    fs::write(dir.path().join("zero.txt"), "No links").unwrap();
+    let opener = RecordingOpener::default();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.set_url_opener(Box::new(opener.clone()));
+    let open = |app: &mut App, name: &str| {
+        select(app, &ManagerEntity::TextFile(dir.path().join(name)));
+        press(app, KeyCode::Enter);
+        wait_for_load(app);
+    };
+
+    // A single URL is opened right away.
+    open(&mut app, "one.txt");
+    press(&mut app, KeyCode::Char('o'));
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert_eq!(app.get_message(), Some("Opened https://example.com/docs"));
+    press(&mut app, KeyCode::Esc);
+
+    open(&mut app, "two.txt");
+    press(&mut app, KeyCode::Char('O'));
+    assert_eq!(app.get_mode(), &Mode::UrlList);
+    assert!(render(&app)
+        .iter()
+        .any(|row| row.contains("https://example.net/a?b=c")));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    press(&mut app, KeyCode::Char('o'));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    press(&mut app, KeyCode::Esc);
+
+    open(&mut app, "zero.txt");
+    press(&mut app, KeyCode::Char('o'));
+    assert_eq!(app.get_message(), Some("No URLs in the text"));
+    assert_eq!(
+        *opener.opened.lock().unwrap(),
+        ["https://example.com/docs", "https://example.net/a?b=c"]
+    );
+}
+
+#[test]
+fn viewed_file_is_shown_read_only_in_the_editor() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("note.txt"), "keep me\nas I am").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "draft");
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('y'));
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("note.txt")),
+    );
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+
+    press(&mut app, KeyCode::Char('v'));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert!(render(&app)
+        .iter()
+        .any(|row| row.contains("Read-only: note.txt")));
+    press(&mut app, KeyCode::Down);
+    type_text(&mut app, "typed");
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Enter);
+    press_ctrl(&mut app, 's');
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(
+        app.get_message(),
+        Some("Nothing is saved from a read-only view")
+    );
+    assert_eq!(
+        app.get_editor_ref().get_textarea_ref().unwrap().lines(),
+        ["keep me", "as I am"]
+    );
+    assert_eq!(files_in(dir.path()), ["note.txt"]);
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('e'));
+    assert_eq!(
+        app.get_editor_ref().get_textarea_ref().unwrap().lines(),
+        ["draft"]
+    );
+}
+
+#[test]
+fn viewer_positions_are_marked_by_letter() {
+    let dir = TempDir::new().unwrap();
+    let text: String = (1..=100).map(|line| format!("line {}\n", line)).collect();
+    fs::write(dir.path().join("note.txt"), text).unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_viewer_mut().set_pane_size(20, 10);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('m'));
+    assert_eq!(app.get_mode(), &Mode::Mark(Mark::Set));
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert!(render(&app).iter().any(|row| row.contains("note.txt ['x]")));
+
+    press(&mut app, KeyCode::Char('G'));
+    press(&mut app, KeyCode::Char('\''));
+    assert!(render(&app).iter().any(|row| row.contains("'x  line 11")));
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 10);
+
+    press(&mut app, KeyCode::Char('\''));
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(app.get_message(), Some("No position is marked as 'y"));
+    // Anything but a letter cancels.
+    press(&mut app, KeyCode::Char('m'));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert_eq!(app.get_viewer_ref().get_marks().len(), 1);
+}
+
+#[test]
+fn two_files_are_compared_in_a_split_view() {
+    let dir = TempDir::new().unwrap();
+    let text: String = (1..=100).map(|line| format!("line {}\n", line)).collect();
+    fs::write(dir.path().join("a.txt"), &text).unwrap();
+    fs::write(dir.path().join("b.txt"), &text).unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.get_viewer_mut().set_pane_size(20, 10);
+    app.get_second_viewer_mut().set_pane_size(20, 10);
+    select(&mut app, &ManagerEntity::TextFile(dir.path().join("a.txt")));
+    press(&mut app, KeyCode::Enter);
+    wait_for_load(&mut app);
+
+    press(&mut app, KeyCode::Char('c'));
+    assert_eq!(app.get_mode(), &Mode::PickSplit);
+    // Only moving around works while picking.
+    press(&mut app, KeyCode::Char('d'));
+    assert_eq!(files_in(dir.path()).len(), 2);
+    select(&mut app, &ManagerEntity::TextFile(dir.path().join("b.txt")));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Split);
+    for _ in 0..400 {
+        app.handle_event(AppEvent::Tick);
+        if !app.get_second_viewer_ref().is_loading() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let screen = render(&app).join("\n");
+    assert!(screen.contains("a.txt"));
+    assert!(screen.contains("▶ b.txt"));
+
+    // The second file has the focus first.
+    press(&mut app, KeyCode::Char(' '));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 0);
+    assert_eq!(app.get_second_viewer_ref().get_scroll(), 10);
+    press(&mut app, KeyCode::Tab);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.get_viewer_ref().get_scroll(), 1);
+    assert_eq!(app.get_second_viewer_ref().get_scroll(), 10);
+    press(&mut app, KeyCode::Char('l'));
+    press(&mut app, KeyCode::Char('d'));
+    assert_eq!(app.get_viewer_ref().get_scroll(), 6);
+    assert_eq!(app.get_second_viewer_ref().get_scroll(), 15);
+
+    press(&mut app, KeyCode::Char('v'));
+    assert_eq!(
+        app.get_split().map(|split| split.layout),
+        Some(SplitLayout::SideBySide)
+    );
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    assert_eq!(app.get_split(), None);
+    assert_eq!(app.get_viewer_ref().get_name(), Some("a.txt"));
+}
+
+#[test]
+fn file_contents_are_inserted_into_the_editor() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sources")).unwrap();
+    fs::write(dir.path().join("sources").join("quote.txt"), "a quote\n").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "Intro: ");
+    press_ctrl(&mut app, 'i');
+    assert_eq!(app.get_mode(), &Mode::InsertFile);
+    assert!(render(&app).iter().any(|row| row.contains("sources")));
+
+    select(&mut app, &ManagerEntity::Folder(dir.path().join("sources")));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::InsertFile);
+    select(
+        &mut app,
+        &ManagerEntity::TextFile(dir.path().join("sources").join("quote.txt")),
+    );
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(app.get_message(), Some("Inserted quote.txt"));
+    type_text(&mut app, "end");
+    assert_eq!(
+        app.get_editor_ref().get_textarea_ref().unwrap().lines(),
+        ["Intro: a quote", "end"]
+    );
+
+    press_ctrl(&mut app, 'i');
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.get_mode(), &Mode::Editor);
+}
+
+#[test]
+fn editor_lines_are_wrapped_at_the_given_column() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "a note that is too long");
+    press_ctrl(&mut app, 'w');
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::HardWrap));
+    assert_eq!(app.get_input(), "80");
+    assert!(render(&app)
+        .iter()
+        .any(|row| row.contains("a note that is too long")));
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_message(), Some("No line is longer than 80"));
+    press_ctrl(&mut app, 'w');
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Backspace);
+    type_text(&mut app, "wide");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.get_status().is_some());
+    assert_eq!(app.get_mode(), &Mode::Prompt(Prompt::HardWrap));
+
+    press(&mut app, KeyCode::Esc);
+    press_ctrl(&mut app, 'w');
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Backspace);
+    type_text(&mut app, "12");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(
+        app.get_editor_ref().get_textarea_ref().unwrap().lines(),
+        ["a note that", "is too long"]
+    );
+}
+
+#[test]
+fn local_time_and_date_are_inserted() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('n'));
+    press_ctrl(&mut app, 't');
+    press(&mut app, KeyCode::Enter);
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Char('T'),
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    )));
+
+    let lines = app.get_editor_ref().get_textarea_ref().unwrap().lines();
+    let timestamp = Regex::new(r"^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d[+-]\d\d:\d\d$").unwrap();
+    assert!(timestamp.is_match(&lines[0]), "{:?}", lines);
+    assert_eq!(lines[1], &lines[0][..10]);
+}
+
+#[test]
+fn tab_inserts_a_tab_or_spaces_as_configured() {
+    let dir = TempDir::new().unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    let lines = |app: &App| {
+        app.get_editor_ref()
+            .get_textarea_ref()
+            .unwrap()
+            .lines()
+            .to_vec()
+    };
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "a");
+    press(&mut app, KeyCode::Tab);
+    type_text(&mut app, "b");
+    assert_eq!(lines(&app), ["a\tb"]);
+    assert!(render(&app).iter().any(|row| row.contains("Tab width: 4")));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('y'));
+    app.get_editor_mut().set_tab_width(2);
+    app.get_editor_mut().set_insert_spaces(true);
+    press(&mut app, KeyCode::Char('n'));
+    press(&mut app, KeyCode::Tab);
+    type_text(&mut app, "abc");
+    press(&mut app, KeyCode::Tab);
+    type_text(&mut app, "d");
+    assert_eq!(lines(&app), ["  abc d"]);
+    assert!(render(&app).iter().any(|row| row.contains("Spaces: 2")));
+
+    // Text that comes in keeps its own indentation.
+    press(&mut app, KeyCode::Enter);
+    app.get_editor_mut()
+        .insert_data(b"\tfrom a file".to_vec())
+        .unwrap();
+    assert_eq!(lines(&app), ["  abc d", "\tfrom a file"]);
+}
+
+#[test]
+fn created_files_are_added_to_the_history() {
+    let dir = TempDir::new().unwrap();
+    let old = dir.path().join("old.txt");
+    fs::write(&old, "from yesterday").unwrap();
+    let history = dir.path().join("history");
+    fs::write(&history, format!("{}\n", old.display())).unwrap();
+
+    let mut app = App::from_history(&history, KEY).unwrap();
+    assert!(app
+        .get_manager_ref()
+        .get_entities_ref()
+        .contains(&ManagerEntity::TextFile(old.clone())));
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "today");
+    save_and_close(&mut app, false);
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    let recorded = fs::read_to_string(&history).unwrap();
+    let recorded: Vec<&str> = recorded.lines().collect();
+    assert_eq!(recorded.len(), 2);
+    assert_eq!(recorded[0], old.to_str().unwrap());
+    assert_eq!(fs::read_to_string(recorded[1]).unwrap(), "today");
+}
+
+#[test]
+fn created_files_are_committed_with_a_message() {
+    let dir = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    git(&["init", "--quiet"]);
+    git(&["config", "user.name", "mystore"]);
+    git(&["config", "user.email", "mystore@example.com"]);
+    fs::write(dir.path().join("old.md"), "untracked").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "hello");
+    save_and_close(&mut app, false);
+    let commit = KeyEvent::new(
+        KeyCode::Char('C'),
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    );
+    app.handle_event(AppEvent::Key(commit));
+    assert_eq!(app.get_mode(), &Mode::Commit);
+    press_ctrl(&mut app, 's');
+    assert_eq!(app.get_message(), Some("The commit message is empty"));
+    type_text(&mut app, "Add a note");
+    press_ctrl(&mut app, 's');
+
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    let oid = app.get_last_commit().unwrap().to_string();
+    assert_eq!(
+        app.get_message(),
+        Some(format!("Committed {}", &oid[..7]).as_str())
+    );
+    assert!(render(&app)[1].contains(&format!("committed {}", &oid[..7])));
+    assert_eq!(
+        git(&["log", "--format=%H %s"]),
+        format!("{} Add a note\n", oid)
+    );
+    // Only the note of the session is committed.
+    let created = app.get_manager_ref().get_created_files()[0].to_path_buf();
+    let name = created.file_name().unwrap().to_str().unwrap();
+    assert_eq!(git(&["ls-files"]), format!("{}\n", name));
+    let start = app.get_manager_ref().get_session_start().timestamp();
+    assert_eq!(git(&["log", "--format=%at"]), format!("{}\n", start));
+}
+
+#[test]
+fn texts_recovered_from_a_crash_are_opened_in_the_editor() {
+    let dir = TempDir::new().unwrap();
+    let older = dir.path().join("mystore_recovery_20240515-090500.txt");
+    let latest = dir.path().join("mystore_recovery_20240516-183007.txt");
+    fs::write(&older, "older text").unwrap();
+    fs::write(&latest, "latest text\nsecond line").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.get_mode(), &Mode::Manager);
+
+    app.set_recovery_files(vec![latest.clone(), older.clone()]);
+    assert!(render(&app)[3].starts_with("┌ R: open mystore_r"));
+    // Ctrl+R still renames the marked files.
+    press_ctrl(&mut app, 'r');
+    assert_eq!(app.get_mode(), &Mode::Manager);
+    assert_eq!(app.get_message(), Some("No files are marked"));
+    assert_eq!(app.get_recovery_files().len(), 2);
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    assert_eq!(
+        app.get_editor_ref().get_lines().unwrap(),
+        ["latest text", "second line"]
+    );
+    assert!(app.get_editor_ref().is_modified());
+    assert_eq!(app.get_recovery_files(), [older]);
+    // The file stays until the text is saved elsewhere.
+    assert!(latest.exists());
+
+    // Text of an encrypted note comes back decrypted.
+    let encrypted = dir.path().join("mystore_recovery_20240517-080000.enc");
+    let sealed = app.get_key_ref().encrypt("private text").unwrap();
+    fs::write(&encrypted, sealed).unwrap();
+    app.set_recovery_files(vec![encrypted]);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('y'));
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.get_editor_ref().get_lines().unwrap(), ["private text"]);
+}
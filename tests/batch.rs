@@ -0,0 +1,82 @@
+use mystore::{
+    batch::{convert_file, BackgroundBatch, BatchOp, BatchSummary},
+    keyring::SessionKey,
+};
+use std::{fs, path::PathBuf, thread, time::Duration};
+use tempfile::TempDir;
+
+const KEY: &str = "secret";
+
+fn key() -> SessionKey {
+    SessionKey::from(KEY)
+}
+
+fn wait_for_batch(batch: &mut BackgroundBatch) -> BatchSummary {
+    for _ in 0..400 {
+        if let Some(summary) = batch.poll() {
+            return summary;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("the batch was not finished in time");
+}
+
+#[test]
+fn files_are_encrypted_and_decrypted_in_place() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    fs::write(&path, "plain note").unwrap();
+
+    assert!(convert_file(&path, BatchOp::Encrypt, &key()).unwrap());
+    assert_ne!(fs::read(&path).unwrap(), b"plain note");
+    // Encrypted files are not encrypted twice.
+    assert!(!convert_file(&path, BatchOp::Encrypt, &key()).unwrap());
+
+    assert!(convert_file(&path, BatchOp::Decrypt, &key()).unwrap());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "plain note");
+    assert!(!convert_file(&path, BatchOp::Decrypt, &key()).unwrap());
+    // Only the converted file is left behind.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn batch_summary_counts_skipped_and_failed_files() {
+    let dir = TempDir::new().unwrap();
+    let plain = dir.path().join("plain.txt");
+    let encrypted = dir.path().join("encrypted.txt");
+    fs::write(&plain, "plain").unwrap();
+    fs::write(&encrypted, "encrypted").unwrap();
+    convert_file(&encrypted, BatchOp::Encrypt, &key()).unwrap();
+    let missing = dir.path().join("missing.txt");
+    let locked = dir.path().join("locked.txt");
+    fs::write(&locked, "in use").unwrap();
+    fs::write(dir.path().join("locked.txt.lock"), "").unwrap();
+
+    let mut batch = BackgroundBatch::new();
+    let files: Vec<PathBuf> = vec![plain.clone(), encrypted.clone(), missing, locked.clone()];
+    assert!(batch.start(files.clone(), BatchOp::Encrypt, key()));
+    assert_eq!(batch.get_progress().map(|(_done, total)| total), Some(4));
+    assert!(!batch.start(files, BatchOp::Encrypt, key()));
+
+    let summary = wait_for_batch(&mut batch);
+    assert_eq!(batch.get_progress(), None);
+    assert_eq!(summary.converted, vec![plain.clone()]);
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(summary.failed.len(), 2);
+    assert!(summary
+        .to_string()
+        .starts_with("1 encrypted, 1 skipped, 2 failed: "));
+    // A locked file is reported and left as it is.
+    assert_eq!(
+        summary.failed[1],
+        (
+            locked.clone(),
+            format!("{} is locked by another session", locked.display())
+        )
+    );
+    assert_eq!(fs::read_to_string(&locked).unwrap(), "in use");
+
+    batch.start(vec![plain, encrypted], BatchOp::Decrypt, key());
+    let summary = wait_for_batch(&mut batch);
+    assert_eq!(summary.to_string(), "2 decrypted, 0 skipped, 0 failed");
+}
@@ -0,0 +1,94 @@
+use mystore::bookmarks::{Bookmarks, ScrollPositions};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+#[test]
+fn bookmarks_are_saved_per_file() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("bookmarks.toml");
+    let odd = Path::new("/notes/say \"hi\"\\.txt");
+
+    let mut bookmarks = Bookmarks::load(&store);
+    assert!(bookmarks.get(Path::new("/notes/a.txt")).is_empty());
+    bookmarks.set(Path::new("/notes/a.txt"), vec![3, 40]);
+    bookmarks.set(odd, vec![1]);
+    bookmarks.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(&store).unwrap(),
+        "\"/notes/a.txt\" = [3, 40]\n\"/notes/say \\\"hi\\\"\\\\.txt\" = [1]\n"
+    );
+
+    let mut bookmarks = Bookmarks::load(&store);
+    assert_eq!(bookmarks.get(Path::new("/notes/a.txt")), &[3, 40]);
+    assert_eq!(bookmarks.get(odd), &[1]);
+
+    // Files without bookmarks are dropped.
+    bookmarks.set(odd, vec![]);
+    bookmarks.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(&store).unwrap(),
+        "\"/notes/a.txt\" = [3, 40]\n"
+    );
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("bookmarks.toml");
+    fs::write(
+        &store,
+        "# comment\n\"/a.txt\" = [2,5]\n\"/b.txt\" = [x]\n/c.txt = [1]\n",
+    )
+    .unwrap();
+
+    let bookmarks = Bookmarks::load(&store);
+    assert_eq!(bookmarks.get(Path::new("/a.txt")), &[2, 5]);
+    assert!(bookmarks.get(Path::new("/b.txt")).is_empty());
+    assert!(bookmarks.get(Path::new("/c.txt")).is_empty());
+}
+
+#[test]
+fn scroll_positions_are_saved_per_name() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("scroll.toml");
+    fs::write(&store, "\"b.txt\" = 7\n\"c.txt\" = far\n").unwrap();
+
+    let mut positions = ScrollPositions::load(&store);
+    assert_eq!(positions.get("b.txt"), Some(7));
+    assert_eq!(positions.get("c.txt"), None);
+    positions.set("a \"1\".txt", 30);
+    // Files left at the top are forgotten.
+    positions.set("b.txt", 0);
+    positions.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(&store).unwrap(),
+        "\"a \\\"1\\\".txt\" = 30\n"
+    );
+    assert_eq!(ScrollPositions::load(&store).get("a \"1\".txt"), Some(30));
+}
+
+#[test]
+fn control_characters_in_names_survive_a_save() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("bookmarks.toml");
+    let odd = Path::new("/notes/line\nbreak\tand\u{7f}.txt");
+
+    let mut bookmarks = Bookmarks::load(&store);
+    bookmarks.set(odd, vec![2]);
+    bookmarks.set(Path::new("/notes/a.txt"), vec![5]);
+    bookmarks.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(&store).unwrap(),
+        "\"/notes/a.txt\" = [5]\n\"/notes/line\\u000Abreak\\u0009and\\u007F.txt\" = [2]\n"
+    );
+
+    let bookmarks = Bookmarks::load(&store);
+    assert_eq!(bookmarks.get(odd), &[2]);
+    assert_eq!(bookmarks.get(Path::new("/notes/a.txt")), &[5]);
+
+    let store = dir.path().join("scroll.toml");
+    let mut positions = ScrollPositions::load(&store);
+    positions.set("tab\there.txt", 12);
+    positions.save().unwrap();
+    assert_eq!(ScrollPositions::load(&store).get("tab\there.txt"), Some(12));
+}
@@ -0,0 +1,69 @@
+use mystore::checksum::{self, BackgroundHasher};
+use std::{fs, sync::atomic::AtomicBool, thread, time::Duration};
+use tempfile::TempDir;
+
+#[test]
+fn digests_match_known_vectors() {
+    let running = AtomicBool::new(false);
+    assert_eq!(
+        checksum::sha256_hex(&b""[..], &running).unwrap(),
+        Some((
+            String::from("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            0
+        ))
+    );
+    assert_eq!(
+        checksum::sha256_hex(&b"abc"[..], &running).unwrap(),
+        Some((
+            String::from("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            3
+        ))
+    );
+
+    let cancelled = AtomicBool::new(true);
+    assert_eq!(checksum::sha256_hex(&b"abc"[..], &cancelled).unwrap(), None);
+}
+
+#[test]
+fn files_are_hashed_over_several_chunks() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.bin");
+    // The million `a` vector spans more than a dozen chunks.
+    fs::write(&path, vec![b'a'; 1_000_000]).unwrap();
+
+    let sum = checksum::file_sha256(&path, &AtomicBool::new(false))
+        .unwrap()
+        .unwrap();
+    assert_eq!(sum.path, path);
+    assert_eq!(sum.size, 1_000_000);
+    assert_eq!(
+        sum.digest,
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+}
+
+#[test]
+fn a_new_hash_replaces_the_running_one() {
+    let dir = TempDir::new().unwrap();
+    let first = dir.path().join("first.bin");
+    let second = dir.path().join("second.txt");
+    fs::write(&first, vec![0; 4_000_000]).unwrap();
+    fs::write(&second, "abc").unwrap();
+
+    let mut hasher = BackgroundHasher::new();
+    hasher.start(first);
+    hasher.start(second.clone());
+    assert_eq!(hasher.get_pending(), Some(second.as_path()));
+    let sum = loop {
+        if let Some(result) = hasher.poll() {
+            break result.unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(sum.path, second);
+    assert_eq!(hasher.get_pending(), None);
+
+    // Nothing of the first file comes later.
+    thread::sleep(Duration::from_millis(100));
+    assert!(hasher.poll().is_none());
+}
@@ -0,0 +1,62 @@
+use mystore::duplicates::{self, BackgroundDuplicates};
+use std::{fs, path::PathBuf, sync::atomic::AtomicBool, thread, time::Duration};
+use tempfile::TempDir;
+
+#[test]
+fn identical_files_are_grouped() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("old").join("older")).unwrap();
+    fs::write(root.join("note.txt"), "same note").unwrap();
+    fs::write(root.join("old").join("note copy.txt"), "same note").unwrap();
+    fs::write(root.join("old").join("older").join("n.txt"), "same note").unwrap();
+    // Same size, other contents.
+    fs::write(root.join("other.txt"), "same nott").unwrap();
+    fs::write(root.join("a.bin"), [1, 2, 3]).unwrap();
+    fs::write(root.join("old").join("b.bin"), [1, 2, 3]).unwrap();
+    fs::write(root.join("empty1"), "").unwrap();
+    fs::write(root.join("empty2"), "").unwrap();
+
+    let groups = duplicates::find_duplicates(&[root.to_path_buf()], &AtomicBool::new(false))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        groups,
+        vec![
+            vec![root.join("a.bin"), root.join("old").join("b.bin")],
+            vec![
+                root.join("note.txt"),
+                root.join("old").join("note copy.txt"),
+                root.join("old").join("older").join("n.txt"),
+            ],
+        ]
+    );
+
+    let cancelled = AtomicBool::new(true);
+    assert_eq!(
+        duplicates::find_duplicates(&[root.to_path_buf()], &cancelled).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn search_runs_in_the_background() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "x").unwrap();
+    fs::write(dir.path().join("b.txt"), "x").unwrap();
+
+    let mut finder = BackgroundDuplicates::new();
+    finder.start(vec![dir.path().join("missing")]);
+    finder.start(vec![dir.path().to_path_buf()]);
+    let groups: Vec<Vec<PathBuf>> = loop {
+        if let Some(result) = finder.poll() {
+            break result.unwrap();
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+    assert_eq!(
+        groups,
+        vec![vec![dir.path().join("a.txt"), dir.path().join("b.txt")]]
+    );
+    assert!(!finder.is_running());
+}
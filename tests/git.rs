@@ -0,0 +1,136 @@
+use mystore::{
+    error::MystoreError,
+    git::GitSync,
+    manager::{FileManager, ManagerEntity, Respond},
+};
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    git(dir.path(), &["config", "user.name", "mystore"]);
+    git(dir.path(), &["config", "user.email", "mystore@example.com"]);
+    git(dir.path(), &["config", "commit.gpgsign", "false"]);
+    dir
+}
+
+#[test]
+fn save_and_delete_are_committed() {
+    let dir = repo();
+    let notes = dir.path().join("notes");
+    fs::create_dir(&notes).unwrap();
+    let path = notes.join("today.md");
+    fs::write(&path, "draft").unwrap();
+    fs::write(dir.path().join("other.txt"), "untouched").unwrap();
+
+    let sync = GitSync::new(dir.path());
+    assert!(sync.commit(&path, "save").unwrap());
+    assert_eq!(
+        git(dir.path(), &["log", "--format=%s"]),
+        "mystore: save notes/today.md\n"
+    );
+    // Only the saved file is part of the commit.
+    assert!(sync.is_dirty().unwrap());
+    assert!(!sync.commit(&path, "save").unwrap());
+
+    fs::remove_file(&path).unwrap();
+    assert!(sync.commit(&path, "delete").unwrap());
+    assert_eq!(
+        git(dir.path(), &["log", "-1", "--format=%s"]),
+        "mystore: delete notes/today.md\n"
+    );
+}
+
+#[test]
+fn changes_staged_by_the_user_are_left_staged() {
+    let dir = repo();
+    fs::write(dir.path().join("staged.txt"), "staged").unwrap();
+    git(dir.path(), &["add", "staged.txt"]);
+    let path = dir.path().join("note.md");
+    fs::write(&path, "note").unwrap();
+
+    let sync = GitSync::new(dir.path());
+    assert!(sync.commit(&path, "save").unwrap());
+    assert_eq!(
+        git(dir.path(), &["show", "--format=", "--name-only", "HEAD"]),
+        "note.md\n"
+    );
+    assert_eq!(
+        git(dir.path(), &["diff", "--cached", "--name-only"]),
+        "staged.txt\n"
+    );
+    // The committed file is not left looking deleted from the index.
+    assert_eq!(git(dir.path(), &["status", "--porcelain", "note.md"]), "");
+}
+
+#[test]
+fn outside_a_repository_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "data").unwrap();
+
+    let sync = GitSync::new(dir.path());
+    assert!(matches!(
+        sync.commit(&path, "save"),
+        Err(MystoreError::Git(_))
+    ));
+    assert!(path.exists());
+}
+
+#[test]
+fn revision_lists_only_tracked_files() {
+    let dir = repo();
+    let root = dir.path().to_str().unwrap();
+    fs::create_dir(dir.path().join("notes")).unwrap();
+    fs::write(dir.path().join("notes").join("a.md"), "first").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "first"]);
+    fs::write(dir.path().join("notes").join("a.md"), "second").unwrap();
+    fs::write(dir.path().join("b.md"), "b").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "second"]);
+    fs::write(dir.path().join("notes").join("a.md"), "unsaved").unwrap();
+    fs::write(dir.path().join("untracked.md"), "new").unwrap();
+
+    let mut manager = FileManager::new_from_git_repo(root).unwrap();
+    assert!(manager.is_readonly());
+    let notes = ManagerEntity::Folder(dir.path().join("notes"));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &[notes, ManagerEntity::TextFile(dir.path().join("b.md"))]
+    );
+
+    // Files come from the object store, not the working tree.
+    let a = dir.path().join("notes").join("a.md");
+    manager.reveal(&a).unwrap();
+    assert!(matches!(
+        manager.action().unwrap(),
+        Respond::Data(path, data) if path == a && data == b"second"
+    ));
+    let mut manager = FileManager::new_from_git_revision(root, "HEAD~1").unwrap();
+    manager.reveal(&a).unwrap();
+    assert!(matches!(
+        manager.action().unwrap(),
+        Respond::Data(_path, data) if data == b"first"
+    ));
+    assert!(matches!(
+        manager.create_file(Vec::new(), Some(String::from("c.md"))),
+        Err(MystoreError::ReadOnly(_))
+    ));
+
+    assert!(matches!(
+        FileManager::new_from_git_revision(root, "no-such-branch"),
+        Err(MystoreError::Git(_))
+    ));
+}
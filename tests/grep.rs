@@ -0,0 +1,67 @@
+use mystore::{
+    editor::Editor,
+    grep::{grep, GrepResult},
+    keyring::SessionKey,
+};
+use std::fs;
+use tempfile::TempDir;
+
+const KEY: &str = "secret";
+
+fn key() -> SessionKey {
+    SessionKey::from(KEY)
+}
+
+#[test]
+fn matching_lines_are_found_ignoring_case() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, "Buy milk\nwalk\nMILKSHAKE recipe").unwrap();
+    let other = dir.path().join("other.txt");
+    fs::write(&other, "nothing here").unwrap();
+
+    let results = grep(&[notes.clone(), other], "milk", &key());
+    assert_eq!(
+        results,
+        vec![
+            GrepResult {
+                path: notes.clone(),
+                line_number: 1,
+                line_text: String::from("Buy milk"),
+            },
+            GrepResult {
+                path: notes,
+                line_number: 3,
+                line_text: String::from("MILKSHAKE recipe"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn encrypted_files_are_searched_and_others_skipped() {
+    let dir = TempDir::new().unwrap();
+    let mut editor = Editor::new(KEY);
+    editor.init();
+    editor
+        .get_textarea_mut()
+        .unwrap()
+        .insert_str("first\nsecret plan");
+    let encrypted = dir.path().join("encrypted");
+    fs::write(&encrypted, editor.finish_encrypt().unwrap()).unwrap();
+    let binary = dir.path().join("binary");
+    fs::write(&binary, [0, 159, 146, 150, 0]).unwrap();
+    let missing = dir.path().join("missing");
+
+    let files = [encrypted.clone(), binary, missing];
+    let results = grep(&files, "PLAN", &key());
+    assert_eq!(
+        results,
+        vec![GrepResult {
+            path: encrypted,
+            line_number: 2,
+            line_text: String::from("secret plan"),
+        }]
+    );
+    assert!(grep(&files, "", &key()).is_empty());
+}
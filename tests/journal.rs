@@ -0,0 +1,48 @@
+use mystore::journal::{HeldFile, Journal, Operation, HELD_IN_MEMORY, JOURNAL_SIZE};
+use std::path::PathBuf;
+
+fn deletion(name: &str) -> Operation {
+    let file = HeldFile::new(PathBuf::from(name), name.as_bytes().to_vec(), None, false);
+    Operation::Delete(vec![file.unwrap()])
+}
+
+#[test]
+fn journal_keeps_the_latest_operations() {
+    let mut journal = Journal::default();
+    assert!(journal.pop().is_none());
+    journal.push(Operation::Delete(Vec::new()));
+    journal.push(Operation::Rename(Vec::new()));
+    assert!(journal.is_empty());
+
+    for id in 0..JOURNAL_SIZE + 5 {
+        journal.push(deletion(&format!("{}.txt", id)));
+    }
+    assert_eq!(journal.len(), JOURNAL_SIZE);
+    let mut names = Vec::new();
+    while let Some(Operation::Delete(mut files)) = journal.pop() {
+        names.push(String::from_utf8(files[0].read_contents().unwrap()).unwrap());
+    }
+    assert_eq!(names.first().map(String::as_str), Some("24.txt"));
+    assert_eq!(names.last().map(String::as_str), Some("5.txt"));
+
+    journal.push(deletion("a.txt"));
+    journal.clear();
+    assert!(journal.is_empty());
+}
+
+#[test]
+fn large_files_are_held_on_disk() {
+    let data = vec![7; HELD_IN_MEMORY + 1];
+    let mut file = HeldFile::new(PathBuf::from("big"), data.clone(), None, true).unwrap();
+    assert!(!file.is_in_memory());
+    assert_eq!(file.read_contents().unwrap(), data);
+    // Reading twice gives the whole contents again.
+    assert_eq!(file.read_contents().unwrap(), data);
+
+    let mut file = HeldFile::new(PathBuf::from("small"), vec![7; HELD_IN_MEMORY], None, true);
+    assert!(file.as_ref().unwrap().is_in_memory());
+    assert_eq!(
+        file.as_mut().unwrap().read_contents().unwrap().len(),
+        HELD_IN_MEMORY
+    );
+}
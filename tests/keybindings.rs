@@ -0,0 +1,203 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use mystore::{
+    app::{App, AppEvent, Mode},
+    keybindings::{parse_key, ActionName, AppMode, KeyBinding, KeyBindings},
+};
+use std::fs;
+use tempfile::TempDir;
+
+const KEY: &str = "secret";
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+#[test]
+fn keys_are_parsed() {
+    let ctrl = KeyModifiers::CONTROL;
+    let shift = KeyModifiers::SHIFT;
+    assert_eq!(parse_key("d"), Ok((KeyCode::Char('d'), KeyModifiers::NONE)));
+    assert_eq!(parse_key("D"), Ok((KeyCode::Char('d'), shift)));
+    assert_eq!(
+        parse_key("Ctrl+Shift+s"),
+        Ok((KeyCode::Char('s'), ctrl | shift))
+    );
+    assert_eq!(parse_key("ctrl++"), Ok((KeyCode::Char('+'), ctrl)));
+    assert_eq!(
+        parse_key("shift+<"),
+        Ok((KeyCode::Char('<'), KeyModifiers::NONE))
+    );
+    assert_eq!(
+        parse_key("pgdn"),
+        Ok((KeyCode::PageDown, KeyModifiers::NONE))
+    );
+    assert_eq!(
+        parse_key("space"),
+        Ok((KeyCode::Char(' '), KeyModifiers::NONE))
+    );
+    assert_eq!(parse_key("alt+F5"), Ok((KeyCode::F(5), KeyModifiers::ALT)));
+    assert!(parse_key("hyper+d").is_err());
+    assert!(parse_key("f13").is_err());
+    assert!(parse_key("escape key").is_err());
+}
+
+#[test]
+fn defaults_bind_every_action_to_its_own_key() {
+    let bindings = KeyBindings::default();
+    for action in ActionName::ALL {
+        let (code, modifiers) = action.default_key();
+        assert_eq!(
+            bindings.get(action.mode(), &key(code, modifiers)),
+            Some(action),
+            "{}",
+            action.name()
+        );
+        assert_eq!(action.name().parse(), Ok(action));
+    }
+    // Capitals come with or without Shift, depending on the terminal.
+    assert_eq!(
+        bindings.get(
+            AppMode::Viewer,
+            &key(KeyCode::Char('G'), KeyModifiers::NONE)
+        ),
+        Some(ActionName::ScrollBottom)
+    );
+    assert_eq!(
+        bindings.get(
+            AppMode::Viewer,
+            &key(KeyCode::Char('x'), KeyModifiers::NONE)
+        ),
+        None
+    );
+}
+
+#[test]
+fn bindings_are_read_from_their_section() {
+    let config = [
+        "[keybindings]",
+        "# Vim-like scrolling",
+        "\"viewer j\" = \"scroll_down\"",
+        "\"viewer k\" = \"scroll_up\"  # and up",
+        "\"manager ctrl+x\" = \"delete_item\"",
+        "\"manager j\" = \"scroll_down\"",
+        "\"editor ctrl+q\" = \"explode\"",
+        "\"pager q\" = \"close_viewer\"",
+        "\"viewer\" = \"reload\"",
+        "\"viewer r\" = 5",
+        "'editor ctrl+d' = 'insert_time'",
+        "",
+        "[other]",
+        "\"manager q\" = \"quit\"",
+    ]
+    .join("\n");
+    let (bindings, errors) = KeyBindings::parse(&config);
+    let none = KeyModifiers::NONE;
+    assert_eq!(
+        bindings.get(AppMode::Viewer, &key(KeyCode::Char('j'), none)),
+        Some(ActionName::ScrollDown)
+    );
+    assert_eq!(
+        bindings.get(AppMode::Viewer, &key(KeyCode::Char('k'), none)),
+        Some(ActionName::ScrollUp)
+    );
+    assert_eq!(
+        bindings.get(
+            AppMode::Manager,
+            &key(KeyCode::Char('x'), KeyModifiers::CONTROL)
+        ),
+        Some(ActionName::DeleteItem)
+    );
+    // Defaults stay, other sections are not bindings.
+    assert_eq!(
+        bindings.get(AppMode::Viewer, &key(KeyCode::Down, none)),
+        Some(ActionName::ScrollDown)
+    );
+    assert_eq!(
+        bindings.get(AppMode::Manager, &key(KeyCode::Char('q'), none)),
+        None
+    );
+    assert_eq!(
+        errors,
+        [
+            "\"viewer r\": expected a quoted action, not 5",
+            "\"editor ctrl+q\": unknown action \"explode\"",
+            "\"manager j\": scroll_down is an action of the viewer, not of the manager",
+            "\"pager q\": unknown mode \"pager\", expected manager, viewer or editor",
+            "\"viewer\": expected a mode and a key in \"viewer\"",
+        ]
+    );
+    // Any TOML string works, literal ones too.
+    assert_eq!(
+        bindings.get(
+            AppMode::Editor,
+            &key(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        ),
+        Some(ActionName::InsertTime)
+    );
+}
+
+#[test]
+fn invalid_toml_gives_the_defaults() {
+    let (bindings, errors) = KeyBindings::parse("[keybindings]\nviewer = reload\n");
+    assert_eq!(bindings, KeyBindings::default());
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("line 2: "), "{}", errors[0]);
+}
+
+#[test]
+fn missing_config_gives_the_defaults() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    assert_eq!(
+        KeyBindings::load(&path),
+        (KeyBindings::default(), Vec::new())
+    );
+    fs::write(
+        &path,
+        "[keybindings]\n\"manager ctrl+n\" = \"new_buffer\"\n",
+    )
+    .unwrap();
+    let (bindings, errors) = KeyBindings::load(&path);
+    assert!(errors.is_empty());
+    assert_ne!(bindings, KeyBindings::default());
+
+    // An unreadable file is a warning, not a failure.
+    let (bindings, errors) = KeyBindings::load(dir.path());
+    assert_eq!(bindings, KeyBindings::default());
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn bound_keys_run_their_actions() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    let mut bindings = KeyBindings::default();
+    bindings.bind(KeyBinding {
+        mode: AppMode::Manager,
+        key: KeyCode::Char('j'),
+        modifiers: KeyModifiers::NONE,
+        action: ActionName::NextItem,
+    });
+    bindings.bind(KeyBinding {
+        mode: AppMode::Manager,
+        key: KeyCode::Char('o'),
+        modifiers: KeyModifiers::CONTROL,
+        action: ActionName::NewBuffer,
+    });
+    app.set_keybindings(bindings);
+
+    app.handle_event(AppEvent::Key(key(KeyCode::Char('j'), KeyModifiers::NONE)));
+    assert_eq!(app.get_manager_ref().get_selected_id(), Some(0));
+    app.handle_event(AppEvent::Key(key(
+        KeyCode::Char('o'),
+        KeyModifiers::CONTROL,
+    )));
+    assert_eq!(app.get_mode(), &Mode::Editor);
+    // Keys of the editor text are not taken by bindings of the manager.
+    app.handle_event(AppEvent::Key(key(KeyCode::Char('j'), KeyModifiers::NONE)));
+    assert_eq!(
+        app.get_editor_ref().get_textarea_ref().unwrap().lines(),
+        ["j"]
+    );
+}
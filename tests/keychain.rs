@@ -0,0 +1,135 @@
+use mystore::{
+    error::MystoreError,
+    keychain::{self, SecretStore},
+    keyring::SessionKey,
+};
+use std::{cell::RefCell, collections::HashMap, fs, io};
+use tempfile::TempDir;
+
+const PASSWORD: &str = "secret";
+
+#[derive(Default)]
+struct MemoryStore {
+    secrets: RefCell<HashMap<String, String>>,
+}
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> io::Result<Option<String>> {
+        Ok(self.secrets.borrow().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> io::Result<()> {
+        self.secrets
+            .borrow_mut()
+            .insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> io::Result<bool> {
+        Ok(self.secrets.borrow_mut().remove(account).is_some())
+    }
+}
+
+/// A keychain whose daemon is not running.
+struct UnreachableStore;
+
+impl SecretStore for UnreachableStore {
+    fn get(&self, _account: &str) -> io::Result<Option<String>> {
+        Err(io::Error::other("No keychain daemon"))
+    }
+
+    fn set(&self, _account: &str, _secret: &str) -> io::Result<()> {
+        Err(io::Error::other("No keychain daemon"))
+    }
+
+    fn delete(&self, _account: &str) -> io::Result<bool> {
+        Err(io::Error::other("No keychain daemon"))
+    }
+}
+
+#[test]
+fn saved_passwords_are_found_by_the_canonical_root() {
+    let dir = TempDir::new().unwrap();
+    SessionKey::open(dir.path(), PASSWORD)
+        .unwrap()
+        .encrypt("note")
+        .unwrap();
+    let store = MemoryStore::default();
+    assert_eq!(keychain::load_password(&store, dir.path()), None);
+
+    keychain::save_password(&store, &dir.path().join("."), PASSWORD).unwrap();
+    let account = keychain::account_for(dir.path()).unwrap();
+    assert_eq!(
+        store.secrets.borrow().keys().collect::<Vec<_>>(),
+        [&account]
+    );
+    assert_eq!(
+        keychain::load_password(&store, dir.path()).as_deref(),
+        Some(PASSWORD)
+    );
+
+    assert!(keychain::clear_password(&store, dir.path()).unwrap());
+    assert!(!keychain::clear_password(&store, dir.path()).unwrap());
+    assert_eq!(keychain::load_password(&store, dir.path()), None);
+}
+
+#[test]
+fn passwords_that_do_not_unlock_the_store_are_not_used_or_saved() {
+    let dir = TempDir::new().unwrap();
+    SessionKey::open(dir.path(), PASSWORD)
+        .unwrap()
+        .encrypt("note")
+        .unwrap();
+
+    let store = MemoryStore::default();
+    assert!(matches!(
+        keychain::save_password(&store, dir.path(), "another"),
+        Err(MystoreError::KeyringLocked)
+    ));
+    assert!(store.secrets.borrow().is_empty());
+
+    // A stale entry, e.g. from before the password was changed.
+    let account = keychain::account_for(dir.path()).unwrap();
+    store.set(&account, "another").unwrap();
+    assert_eq!(keychain::load_password(&store, dir.path()), None);
+}
+
+#[test]
+fn stores_without_a_keyring_need_a_file_encrypted_with_the_password() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("plain.txt"), "note").unwrap();
+    let store = MemoryStore::default();
+    assert!(keychain::save_password(&store, dir.path(), PASSWORD).is_err());
+    assert!(store.secrets.borrow().is_empty());
+    let account = keychain::account_for(dir.path()).unwrap();
+    store.set(&account, PASSWORD).unwrap();
+    assert_eq!(keychain::load_password(&store, dir.path()), None);
+
+    // A file encrypted with the password directly, from before keyrings.
+    let legacy = SessionKey::from(PASSWORD)
+        .encrypt("Ünïcödé note\n")
+        .unwrap();
+    fs::create_dir(dir.path().join("old")).unwrap();
+    fs::write(dir.path().join("old").join("note.txt"), legacy).unwrap();
+    assert_eq!(
+        keychain::load_password(&store, dir.path()).as_deref(),
+        Some(PASSWORD)
+    );
+    assert!(matches!(
+        keychain::save_password(&store, dir.path(), "another"),
+        Err(MystoreError::Io(_))
+    ));
+    keychain::save_password(&store, dir.path(), PASSWORD).unwrap();
+}
+
+#[test]
+fn an_unreachable_keychain_falls_back_to_the_prompt() {
+    let dir = TempDir::new().unwrap();
+    SessionKey::open(dir.path(), PASSWORD)
+        .unwrap()
+        .encrypt("note")
+        .unwrap();
+    assert_eq!(keychain::load_password(&UnreachableStore, dir.path()), None);
+    assert!(keychain::save_password(&UnreachableStore, dir.path(), PASSWORD).is_err());
+    assert!(keychain::clear_password(&UnreachableStore, dir.path()).is_err());
+}
@@ -0,0 +1,228 @@
+use mystore::{
+    error::MystoreError,
+    keyring::{self, SessionKey},
+};
+use std::{fs, path::PathBuf};
+use tempfile::TempDir;
+
+const PASSWORD: &str = "secret";
+
+fn keyring_of(dir: &TempDir) -> PathBuf {
+    dir.path().join(".mystore").join("keyring")
+}
+
+#[test]
+fn keyring_is_saved_with_the_first_encryption() {
+    let dir = TempDir::new().unwrap();
+    let key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    assert!(key.has_keyring());
+    assert!(!keyring_of(&dir).exists());
+
+    let sealed = key.encrypt("first note").unwrap();
+    let stored = fs::read(keyring_of(&dir)).unwrap();
+    assert!(stored.starts_with(b"mystore keyring v2\n"));
+    assert!(keyring::is_sealed(&sealed));
+
+    // Later encryptions keep the keyring as it is.
+    key.encrypt("second note").unwrap();
+    assert_eq!(fs::read(keyring_of(&dir)).unwrap(), stored);
+
+    let reopened = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    assert_eq!(reopened.decrypt(&sealed).unwrap(), "first note");
+}
+
+#[test]
+fn every_file_gets_a_data_key_of_its_own() {
+    let dir = TempDir::new().unwrap();
+    let key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+
+    let first = key.encrypt("same text").unwrap();
+    let second = key.encrypt("same text").unwrap();
+    assert_ne!(first, second);
+    assert_eq!(key.decrypt(&first).unwrap(), "same text");
+    assert_eq!(key.decrypt(&second).unwrap(), "same text");
+    assert_eq!(key.decrypt(&key.encrypt("").unwrap()).unwrap(), "");
+}
+
+#[test]
+fn wrong_password_does_not_unlock_the_keyring() {
+    let dir = TempDir::new().unwrap();
+    SessionKey::open(dir.path(), PASSWORD)
+        .unwrap()
+        .encrypt("note")
+        .unwrap();
+
+    assert!(matches!(
+        SessionKey::open(dir.path(), "secreT"),
+        Err(MystoreError::KeyringLocked)
+    ));
+    // The first bytes alone do not unlock it.
+    assert!(matches!(
+        SessionKey::open(dir.path(), "secret and more"),
+        Err(MystoreError::KeyringLocked)
+    ));
+    assert!(matches!(
+        SessionKey::open(dir.path(), "abc"),
+        Err(MystoreError::WrongKey(5))
+    ));
+}
+
+#[test]
+fn damaged_keyring_is_reported() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join(".mystore")).unwrap();
+    fs::write(keyring_of(&dir), "not a keyring").unwrap();
+    assert!(matches!(
+        SessionKey::open(dir.path(), PASSWORD),
+        Err(MystoreError::Io(_))
+    ));
+
+    // A keyring cut short does not unlock either.
+    fs::write(keyring_of(&dir), b"mystore keyring v2\n\x01\x02").unwrap();
+    assert!(matches!(
+        SessionKey::open(dir.path(), PASSWORD),
+        Err(MystoreError::KeyringLocked)
+    ));
+}
+
+#[test]
+fn password_change_only_rewraps_the_master_key() {
+    let dir = TempDir::new().unwrap();
+    let mut key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    let sealed = key.encrypt("kept as is").unwrap();
+    let before = fs::read(keyring_of(&dir)).unwrap();
+
+    key.change_password("new password").unwrap();
+    assert_eq!(key.get_password(), "new password");
+    assert_ne!(fs::read(keyring_of(&dir)).unwrap(), before);
+    assert_eq!(key.decrypt(&sealed).unwrap(), "kept as is");
+
+    let reopened = SessionKey::open(dir.path(), "new password").unwrap();
+    assert_eq!(reopened.decrypt(&sealed).unwrap(), "kept as is");
+    assert!(matches!(
+        SessionKey::open(dir.path(), PASSWORD),
+        Err(MystoreError::KeyringLocked)
+    ));
+    assert!(matches!(
+        key.change_password("abc"),
+        Err(MystoreError::WrongKey(5))
+    ));
+}
+
+#[test]
+fn password_of_a_store_without_keyring_is_not_changed() {
+    let mut key = SessionKey::from(PASSWORD);
+    assert!(!key.has_keyring());
+    assert!(matches!(
+        key.change_password("new password"),
+        Err(MystoreError::Io(_))
+    ));
+    assert_eq!(key.get_password(), PASSWORD);
+}
+
+#[test]
+fn legacy_files_are_decrypted_with_the_password() {
+    let dir = TempDir::new().unwrap();
+    let legacy = SessionKey::from(PASSWORD).encrypt("old note").unwrap();
+    assert!(!keyring::is_sealed(&legacy));
+
+    let key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    assert_eq!(key.decrypt(&legacy).unwrap(), "old note");
+    // Decrypting does not save a keyring.
+    assert!(!keyring_of(&dir).exists());
+}
+
+#[test]
+fn sealed_files_need_the_master_key_they_were_sealed_with() {
+    let (dir, other_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    let sealed = key.encrypt("private").unwrap();
+
+    // The same password with another master key fails to unwrap.
+    let other = SessionKey::open(other_dir.path(), PASSWORD).unwrap();
+    assert!(matches!(
+        other.decrypt(&sealed),
+        Err(MystoreError::DecryptFailed)
+    ));
+    assert!(matches!(
+        SessionKey::from(PASSWORD).decrypt(&sealed),
+        Err(MystoreError::DecryptFailed)
+    ));
+    // A header without the wrapped data key fails too.
+    assert!(matches!(
+        key.decrypt(&sealed[..24]),
+        Err(MystoreError::DecryptFailed)
+    ));
+}
+
+#[test]
+fn fingerprint_is_a_hash_of_the_master_key() {
+    let dir = TempDir::new().unwrap();
+    let mut key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    let fingerprint = key.fingerprint().unwrap();
+    assert_eq!(fingerprint.len(), 8);
+    key.encrypt("note").unwrap();
+
+    // It stays with the master key whatever the password, and tells two
+    // stores with the same password apart.
+    key.change_password("new password").unwrap();
+    assert_eq!(key.fingerprint().as_ref(), Some(&fingerprint));
+    let reopened = SessionKey::open(dir.path(), "new password").unwrap();
+    assert_eq!(reopened.fingerprint().as_ref(), Some(&fingerprint));
+    let other_dir = TempDir::new().unwrap();
+    let other = SessionKey::open(other_dir.path(), "new password").unwrap();
+    assert_ne!(other.fingerprint().as_ref(), Some(&fingerprint));
+    assert_eq!(SessionKey::from(PASSWORD).fingerprint(), None);
+}
+
+#[test]
+fn fingerprint_of_a_known_key_is_pinned() {
+    // printf 'mystore fingerprint\n' | cat - <(head -c 32 /dev/zero) | sha256sum
+    assert_eq!(
+        keyring::fingerprint_of(&[0; keyring::KEY_BYTES]),
+        "1a49f5fa"
+    );
+    assert_eq!(keyring::fingerprint_of(&[1; keyring::KEY_BYTES]).len(), 8);
+    assert_ne!(
+        keyring::fingerprint_of(&[1; keyring::KEY_BYTES]),
+        "1a49f5fa"
+    );
+}
+
+#[test]
+fn export_password_is_apart_from_the_keyring() {
+    // printf 'mystore zip export\n%s' secret | sha256sum
+    assert_eq!(
+        SessionKey::from(PASSWORD).export_password(),
+        "2423ad8854afe399ee3c92fb1dedaa88cdbf20594ec4309eb299149f0d34738a"
+    );
+}
+
+#[test]
+fn wrapped_keys_only_unwrap_with_their_key() {
+    let key = [7; keyring::KEY_BYTES];
+    let (wrapping_key, other_key) = ([1; keyring::KEY_BYTES], [2; keyring::KEY_BYTES]);
+    let wrapped = keyring::wrap_key(&key, &wrapping_key).unwrap();
+    assert_ne!(keyring::wrap_key(&key, &wrapping_key).unwrap(), wrapped);
+    assert_eq!(keyring::unwrap_key(&wrapped, &wrapping_key), Some(key));
+    assert_eq!(keyring::unwrap_key(&wrapped, &other_key), None);
+    assert_eq!(keyring::unwrap_key(&wrapped[..4], &wrapping_key), None);
+    assert_eq!(keyring::unwrap_key(b"", &wrapping_key), None);
+
+    // Any change to the wrapped key is noticed.
+    let mut altered = wrapped.clone();
+    *altered.last_mut().unwrap() ^= 1;
+    assert_eq!(keyring::unwrap_key(&altered, &wrapping_key), None);
+}
+
+#[test]
+fn altered_files_are_not_decrypted() {
+    let dir = TempDir::new().unwrap();
+    let key = SessionKey::open(dir.path(), PASSWORD).unwrap();
+    let mut sealed = key.encrypt("private").unwrap();
+    *sealed.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        key.decrypt(&sealed),
+        Err(MystoreError::DecryptFailed)
+    ));
+}
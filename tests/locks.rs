@@ -0,0 +1,95 @@
+use mystore::locks::{is_stale, sweep_locks, BackgroundLockSweep, LockOwner};
+use std::{fs, process::Command, sync::atomic::AtomicBool, thread, time::Duration};
+use tempfile::TempDir;
+
+/// A session on this machine that has ended.
+fn dead_owner() -> LockOwner {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    LockOwner {
+        pid: child.id(),
+        ..LockOwner::current()
+    }
+}
+
+#[test]
+fn owners_are_written_and_read_back() {
+    let owner = LockOwner {
+        pid: 42,
+        host: String::from("box"),
+    };
+    assert_eq!(owner.to_string(), "42@box");
+    assert_eq!(LockOwner::parse(b"42@box\n"), Some(owner));
+    assert_eq!(LockOwner::parse(b""), None);
+    assert_eq!(LockOwner::parse(b"box"), None);
+}
+
+#[test]
+fn only_locks_of_ended_sessions_here_are_stale() {
+    let current = LockOwner::current();
+    assert_eq!(current.is_alive(), Some(true));
+    assert!(!is_stale(current.to_string().as_bytes()));
+
+    assert_eq!(dead_owner().is_alive(), Some(false));
+    assert!(is_stale(dead_owner().to_string().as_bytes()));
+
+    let elsewhere = LockOwner {
+        host: format!("{}-elsewhere", current.host),
+        ..dead_owner()
+    };
+    assert_eq!(elsewhere.is_alive(), None);
+    assert!(!is_stale(elsewhere.to_string().as_bytes()));
+    assert!(!is_stale(b""));
+}
+
+#[test]
+fn sweep_keeps_live_empty_and_hidden_locks() {
+    let dir = TempDir::new().unwrap();
+    let git = dir.path().join(".git");
+    fs::create_dir(&git).unwrap();
+    for name in ["dead.txt", "live.txt", "empty.txt"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::write(dir.path().join("dead.txt.lock"), dead_owner().to_string()).unwrap();
+    fs::write(
+        dir.path().join("live.txt.lock"),
+        LockOwner::current().to_string(),
+    )
+    .unwrap();
+    fs::write(dir.path().join("empty.txt.lock"), "").unwrap();
+    fs::write(git.join("index"), "").unwrap();
+    fs::write(git.join("index.lock"), dead_owner().to_string()).unwrap();
+
+    let cleanup = sweep_locks(&[dir.path().to_path_buf()], &AtomicBool::new(false));
+    assert_eq!(cleanup.removed, 1);
+    assert_eq!(cleanup.failed, 0);
+    assert!(!dir.path().join("dead.txt.lock").exists());
+    assert!(dir.path().join("live.txt.lock").exists());
+    assert!(dir.path().join("empty.txt.lock").exists());
+    assert!(git.join("index.lock").exists());
+
+    let missing = sweep_locks(&[dir.path().join("missing")], &AtomicBool::new(false));
+    assert_eq!(missing.unreadable, 1);
+}
+
+#[test]
+fn sweep_runs_on_a_worker() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("a.txt.lock"), dead_owner().to_string()).unwrap();
+
+    let mut sweep = BackgroundLockSweep::new();
+    sweep.start(vec![dir.path().to_path_buf()]);
+    assert!(sweep.is_running());
+    let mut cleanup = None;
+    for _ in 0..400 {
+        cleanup = sweep.poll();
+        if cleanup.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(cleanup.map(|cleanup| cleanup.removed), Some(1));
+    assert!(!sweep.is_running());
+    assert!(!dir.path().join("a.txt.lock").exists());
+}
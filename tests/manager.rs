@@ -0,0 +1,2085 @@
+use chrono::{Local, TimeZone};
+use mystore::{
+    error::MystoreError,
+    journal::{HeldFile, HELD_IN_MEMORY},
+    keyring::SessionKey,
+    locks::LockOwner,
+    manager::{
+        Action, ActionPlacement, ConflictResolution, FileManager, LockCleanup, ManagerEntity,
+        MergeResult, Respond, SortOrder, SshConfig, MAX_TAR_SIZE,
+    },
+    s3::S3Config,
+};
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+fn touch(path: &Path, age_secs: u64) {
+    let file = File::create(path).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+        .unwrap();
+}
+
+/// A session on this machine that has ended.
+fn dead_owner() -> LockOwner {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    LockOwner {
+        pid: child.id(),
+        ..LockOwner::current()
+    }
+}
+
+fn manager_for(dir: &TempDir) -> FileManager {
+    FileManager::new(dir.path().to_str().unwrap()).unwrap()
+}
+
+fn position_of(manager: &FileManager, entity: &ManagerEntity) -> usize {
+    manager
+        .get_entities_ref()
+        .iter()
+        .position(|elem| elem == entity)
+        .unwrap()
+}
+
+#[test]
+fn folders_sort_before_files() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("a.txt"), 0);
+    fs::create_dir(dir.path().join("b")).unwrap();
+    fs::create_dir(dir.path().join("a")).unwrap();
+
+    let manager = manager_for(&dir);
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::Folder(dir.path().join("a")),
+            ManagerEntity::Folder(dir.path().join("b")),
+            ManagerEntity::TextFile(dir.path().join("a.txt")),
+        ]
+    );
+}
+
+#[test]
+fn files_sort_by_mtime_descending() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("old.txt"), 300);
+    touch(&dir.path().join("new.txt"), 0);
+    touch(&dir.path().join("mid.txt"), 100);
+
+    let manager = manager_for(&dir);
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::TextFile(dir.path().join("new.txt")),
+            ManagerEntity::TextFile(dir.path().join("mid.txt")),
+            ManagerEntity::TextFile(dir.path().join("old.txt")),
+        ]
+    );
+}
+
+#[test]
+fn action_on_folder_changes_current() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    touch(&sub.join("note.txt"), 0);
+
+    let mut manager = manager_for(&dir);
+    assert!(manager.select(0));
+    assert_eq!(manager.action().unwrap(), Respond::None);
+    assert_eq!(manager.get_current(), sub);
+    assert_eq!(manager.get_selected_id(), None);
+    assert_eq!(
+        manager.get_entities_ref()[0],
+        ManagerEntity::TextFile(sub.join("note.txt"))
+    );
+}
+
+#[test]
+fn root_listing_has_no_actions() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+
+    let manager = manager_for(&dir);
+    assert!(!manager
+        .get_entities_ref()
+        .iter()
+        .any(|entity| matches!(entity, ManagerEntity::Action(_))));
+}
+
+#[test]
+fn back_and_root_below_root() {
+    let dir = TempDir::new().unwrap();
+    let deep = dir.path().join("a").join("b");
+    fs::create_dir_all(&deep).unwrap();
+
+    let mut manager = manager_for(&dir);
+    manager.select(0);
+    manager.action().unwrap();
+    manager.select(0);
+    manager.action().unwrap();
+    assert_eq!(manager.get_current(), deep);
+
+    let back = ManagerEntity::Action(Action::Back);
+    manager.select(position_of(&manager, &back));
+    manager.action().unwrap();
+    assert_eq!(manager.get_current(), dir.path().join("a"));
+
+    manager.select(0);
+    manager.action().unwrap();
+    let root = ManagerEntity::Action(Action::Root);
+    manager.select(position_of(&manager, &root));
+    manager.action().unwrap();
+    assert_eq!(manager.get_current(), PathBuf::from(dir.path()));
+}
+
+#[test]
+fn action_on_file_requests_it_without_reading() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    touch(&path, 0);
+
+    let mut manager = manager_for(&dir);
+    manager.select(0);
+    assert_eq!(manager.action().unwrap(), Respond::File(path));
+}
+
+#[test]
+fn create_and_delete_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+
+    manager
+        .create_file(b"data".to_vec(), Some(String::from("new.txt")))
+        .unwrap();
+    let path = dir.path().join("new.txt");
+    assert_eq!(fs::read(&path).unwrap(), b"data");
+
+    let created = ManagerEntity::TextFile(path.clone());
+    manager.select(position_of(&manager, &created));
+    manager.delete_selected().unwrap();
+    assert!(!path.exists());
+    assert!(manager.get_entities_ref().is_empty());
+    assert_eq!(manager.get_selected_id(), None);
+}
+
+#[test]
+fn create_file_refuses_to_overwrite() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("taken.txt"), "keep").unwrap();
+
+    let mut manager = manager_for(&dir);
+    let result = manager.create_file(b"lost".to_vec(), Some(String::from("taken.txt")));
+    assert!(matches!(result, Err(MystoreError::NameCollision(_))));
+    assert_eq!(fs::read(dir.path().join("taken.txt")).unwrap(), b"keep");
+}
+
+#[test]
+fn delete_is_restricted_to_session_files() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("old.txt"), 0);
+    fs::create_dir(dir.path().join("sub")).unwrap();
+
+    let mut manager = manager_for(&dir);
+    manager.select(0);
+    assert!(matches!(
+        manager.delete_selected(),
+        Err(MystoreError::NotAFile(..))
+    ));
+    manager.select(1);
+    assert!(matches!(
+        manager.delete_selected(),
+        Err(MystoreError::NotDeletable(_))
+    ));
+    assert!(dir.path().join("old.txt").exists());
+}
+
+#[test]
+fn refresh_follows_the_selected_entity() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("a.txt"), 100);
+    touch(&dir.path().join("b.txt"), 200);
+
+    let mut manager = manager_for(&dir);
+    let selected = ManagerEntity::TextFile(dir.path().join("a.txt"));
+    manager.select(position_of(&manager, &selected));
+
+    // A newer file is listed first and shifts the selected one down.
+    touch(&dir.path().join("c.txt"), 0);
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_selected_entity(), Some(&selected));
+}
+
+#[test]
+fn refresh_clamps_selection_when_entity_disappears() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("a.txt"), 100);
+    touch(&dir.path().join("b.txt"), 200);
+
+    let mut manager = manager_for(&dir);
+    manager.select(1);
+    fs::remove_file(dir.path().join("b.txt")).unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_selected_id(), Some(0));
+
+    fs::remove_file(dir.path().join("a.txt")).unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_selected_id(), None);
+}
+
+#[test]
+fn delete_refuses_a_locked_session_file() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+    manager
+        .create_file(b"data".to_vec(), Some(String::from("new.txt")))
+        .unwrap();
+    touch(&dir.path().join("new.txt.lock"), 0);
+    manager.refresh().unwrap();
+
+    let new = ManagerEntity::TextFile(dir.path().join("new.txt"));
+    manager.select(position_of(&manager, &new));
+    assert!(matches!(
+        manager.delete_selected(),
+        Err(MystoreError::Locked(_))
+    ));
+    assert!(dir.path().join("new.txt").exists());
+}
+
+#[test]
+fn lock_and_unlock_the_selected_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    touch(&path, 0);
+
+    let mut manager = manager_for(&dir);
+    manager.select(0);
+    manager.lock_file().unwrap();
+    assert!(dir.path().join("note.txt.lock").exists());
+    assert!(manager.is_locked(&path));
+    assert!(!manager.is_locked_elsewhere(&path));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![ManagerEntity::TextFile(path.clone())]
+    );
+    assert!(matches!(manager.action(), Ok(Respond::File(_))));
+
+    manager.unlock_file().unwrap();
+    assert!(!dir.path().join("note.txt.lock").exists());
+    assert!(!manager.is_locked(&path));
+}
+
+#[test]
+fn foreign_lock_allows_opening_only() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.txt");
+    touch(&path, 0);
+    touch(&dir.path().join("note.txt.lock"), 0);
+    touch(&dir.path().join("orphan.lock"), 0);
+
+    let mut manager = manager_for(&dir);
+    let note = ManagerEntity::TextFile(path.clone());
+    manager.select(position_of(&manager, &note));
+    assert!(manager.is_locked(&path));
+    assert!(manager.is_locked_elsewhere(&path));
+    assert_eq!(manager.action().unwrap(), Respond::File(path.clone()));
+    assert!(matches!(manager.lock_file(), Err(MystoreError::Locked(_))));
+    assert!(matches!(
+        manager.unlock_file(),
+        Err(MystoreError::Locked(_))
+    ));
+    assert!(manager
+        .get_entities_ref()
+        .contains(&ManagerEntity::TextFile(dir.path().join("orphan.lock"))));
+}
+
+#[test]
+fn cleanup_locks_removes_stale_sidecars_in_the_whole_tree() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    let hidden = dir.path().join(".hidden");
+    fs::create_dir(&sub).unwrap();
+    fs::create_dir(&hidden).unwrap();
+    touch(&dir.path().join("a.txt"), 0);
+    touch(&dir.path().join("a.txt.lock"), 0);
+    touch(&sub.join("b.txt"), 0);
+    fs::write(sub.join("b.txt.lock"), dead_owner().to_string()).unwrap();
+    touch(&sub.join("c.txt"), 0);
+    fs::write(sub.join("c.txt.lock"), LockOwner::current().to_string()).unwrap();
+    touch(&hidden.join("d.txt"), 0);
+    touch(&hidden.join("d.txt.lock"), 0);
+    touch(&dir.path().join("orphan.lock"), 0);
+
+    let mut manager = manager_for(&dir);
+    assert_eq!(manager.cleanup_locks().unwrap(), 2);
+    let cleanup = manager.get_last_cleanup();
+    assert_eq!(cleanup.removed, 2);
+    assert_eq!(cleanup.held, 1);
+    assert_eq!(cleanup.unreadable, 0);
+    assert_eq!(cleanup.to_string(), "Removed 2 lock files, kept 1 in use");
+    let partial = LockCleanup {
+        removed: 1,
+        failed: 3,
+        unreadable: 2,
+        ..LockCleanup::default()
+    };
+    assert_eq!(
+        partial.to_string(),
+        "Removed 1 lock files, failed to remove 3, skipped 2 unreadable folders"
+    );
+    assert!(!dir.path().join("a.txt.lock").exists());
+    assert!(!sub.join("b.txt.lock").exists());
+    assert!(sub.join("c.txt.lock").exists());
+    assert!(hidden.join("d.txt.lock").exists());
+    assert!(dir.path().join("orphan.lock").exists());
+    assert!(!manager.is_locked(&dir.path().join("a.txt")));
+}
+
+#[test]
+fn locks_name_their_session() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("note.txt"), 0);
+
+    let mut manager = manager_for(&dir);
+    manager.select(0);
+    manager.lock_file().unwrap();
+    let data = fs::read(dir.path().join("note.txt.lock")).unwrap();
+    assert_eq!(LockOwner::parse(&data), Some(LockOwner::current()));
+
+    // The lock of a running session survives a cleanup.
+    assert_eq!(manager.cleanup_locks().unwrap(), 0);
+    assert_eq!(manager.get_last_cleanup().held, 1);
+    manager.select(0);
+    manager.unlock_file().unwrap();
+    assert!(!dir.path().join("note.txt.lock").exists());
+}
+
+#[test]
+fn templates_are_listed_and_filled() {
+    let dir = TempDir::new().unwrap();
+    let templates = TempDir::new().unwrap();
+    fs::write(templates.path().join("journal"), "# {{DATE}} {{TIME}}\n").unwrap();
+    fs::write(templates.path().join("blank"), "").unwrap();
+
+    let mut manager = manager_for(&dir);
+    manager.set_templates_dir(templates.path().join("missing"));
+    assert!(manager.get_templates().unwrap().is_empty());
+
+    manager.set_templates_dir(PathBuf::from(templates.path()));
+    assert_eq!(
+        manager.get_templates().unwrap(),
+        vec![String::from("blank"), String::from("journal")]
+    );
+    manager
+        .create_file_from_template("journal", Some(String::from("today.md")))
+        .unwrap();
+    let text = fs::read_to_string(dir.path().join("today.md")).unwrap();
+    let (date, time) = text[2..text.len() - 1].split_once(' ').unwrap();
+    assert!(chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok());
+    assert!(chrono::NaiveTime::parse_from_str(time, "%H:%M:%S").is_ok());
+    assert!(matches!(
+        manager.create_file_from_template("absent", None),
+        Err(MystoreError::Io(_))
+    ));
+}
+
+#[test]
+fn scratch_root_is_removed_with_its_dir() {
+    let (mut manager, dir) = FileManager::new_scratch().unwrap();
+    let root = PathBuf::from(manager.get_root());
+    assert_eq!(root, dir.path());
+    manager
+        .create_file(b"tmp".to_vec(), Some(String::from("a.txt")))
+        .unwrap();
+    assert!(root.join("a.txt").exists());
+
+    drop(dir);
+    assert!(!root.exists());
+}
+
+#[test]
+fn paths_are_formatted_absolute_or_relative() {
+    let root = Path::new("/store");
+    let path = Path::new("/store/notes/a.txt");
+    assert_eq!(
+        FileManager::format_path(path, root, false),
+        "/store/notes/a.txt"
+    );
+    assert_eq!(FileManager::format_path(path, root, true), "notes/a.txt");
+    assert_eq!(
+        FileManager::format_path(Path::new("/elsewhere"), root, true),
+        "/elsewhere"
+    );
+
+    let current = std::env::current_dir().unwrap();
+    assert_eq!(
+        FileManager::format_path(Path::new("store/a.txt"), root, false),
+        current.join("store/a.txt").to_string_lossy()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths_are_formatted_lossily() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let path = Path::new("/store").join(OsStr::from_bytes(b"bad\xff.txt"));
+    assert_eq!(
+        FileManager::format_path(&path, Path::new("/store"), true),
+        "bad\u{fffd}.txt"
+    );
+}
+
+#[test]
+fn selected_path_skips_actions() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    let mut manager = manager_for(&dir);
+    assert_eq!(manager.get_selected_path(false), None);
+
+    manager.select(0);
+    manager.action().unwrap();
+    manager.select(0);
+    assert_eq!(manager.get_selected_path(true), None);
+}
+
+#[test]
+fn merge_dirs_copies_files_and_resolves_conflicts() {
+    let dir = TempDir::new().unwrap();
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("new.txt"), "new").unwrap();
+    fs::write(source.path().join("same.txt"), "theirs").unwrap();
+    fs::create_dir(source.path().join("nested")).unwrap();
+    fs::write(dir.path().join("same.txt"), "ours").unwrap();
+
+    let mut manager = manager_for(&dir);
+    assert_eq!(
+        manager
+            .merge_dirs(source.path(), ConflictResolution::Skip)
+            .unwrap(),
+        MergeResult {
+            copied: 1,
+            skipped_existing: 1
+        }
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("same.txt")).unwrap(),
+        "ours"
+    );
+    assert!(!dir.path().join("nested").exists());
+
+    // Copied files belong to the session and can be deleted.
+    let copied = ManagerEntity::TextFile(dir.path().join("new.txt"));
+    manager.select(position_of(&manager, &copied));
+    manager.delete_selected().unwrap();
+
+    assert_eq!(
+        manager
+            .merge_dirs(source.path(), ConflictResolution::Overwrite)
+            .unwrap(),
+        MergeResult {
+            copied: 2,
+            skipped_existing: 0
+        }
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("same.txt")).unwrap(),
+        "theirs"
+    );
+    // Files that were there before stay out of the session's files.
+    let same = ManagerEntity::TextFile(dir.path().join("same.txt"));
+    manager.select(position_of(&manager, &same));
+    assert!(matches!(
+        manager.delete_selected(),
+        Err(MystoreError::NotDeletable(_))
+    ));
+}
+
+#[test]
+fn zip_export_is_encrypted_with_the_session_key() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+    manager
+        .create_file(b"new".to_vec(), Some(String::from("new.txt")))
+        .unwrap();
+    let out = dir.path().join("out.zip");
+    assert!(matches!(
+        manager.export_as_zip(&out, true),
+        Err(MystoreError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound
+    ));
+    assert!(!out.exists());
+
+    let key = SessionKey::from("secret");
+    manager.set_key(key.clone());
+    manager.export_as_zip(&out, true).unwrap();
+    let mut archive = zip::ZipArchive::new(File::open(&out).unwrap()).unwrap();
+    assert!(archive.by_name("new.txt").is_err());
+    let mut text = String::new();
+    std::io::Read::read_to_string(
+        &mut archive
+            .by_name_decrypt("new.txt", key.export_password().as_bytes())
+            .unwrap(),
+        &mut text,
+    )
+    .unwrap();
+    assert_eq!(text, "new");
+}
+
+#[test]
+fn merge_dirs_leaves_locks_behind_and_keeps_locked_files() {
+    let dir = TempDir::new().unwrap();
+    let source = TempDir::new().unwrap();
+    fs::write(source.path().join("a.txt"), "theirs").unwrap();
+    fs::write(source.path().join("a.txt.lock"), "").unwrap();
+    fs::write(source.path().join("b.txt.lock"), "").unwrap();
+    fs::create_dir(source.path().join(".mystore")).unwrap();
+    fs::write(dir.path().join("b.txt"), "ours").unwrap();
+
+    let mut manager = manager_for(&dir);
+    assert_eq!(
+        manager
+            .merge_dirs(source.path(), ConflictResolution::Overwrite)
+            .unwrap(),
+        MergeResult {
+            copied: 1,
+            skipped_existing: 0
+        }
+    );
+    assert!(!dir.path().join("a.txt.lock").exists());
+    assert!(!dir.path().join("b.txt.lock").exists());
+    assert!(!dir.path().join(".mystore").exists());
+
+    fs::write(dir.path().join("a.txt"), "ours").unwrap();
+    fs::write(dir.path().join("a.txt.lock"), "").unwrap();
+    assert!(matches!(
+        manager.merge_dirs(source.path(), ConflictResolution::Overwrite),
+        Err(MystoreError::Locked(path)) if path == dir.path().join("a.txt")
+    ));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+        "ours"
+    );
+}
+
+#[test]
+fn merge_dirs_refuses_the_current_folder() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "text").unwrap();
+
+    let mut manager = manager_for(&dir);
+    assert!(matches!(
+        manager.merge_dirs(&dir.path().join("."), ConflictResolution::Overwrite),
+        Err(MystoreError::Io(err)) if err.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+        "text"
+    );
+}
+
+#[test]
+fn filter_lists_files_across_folders_until_left() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    touch(&dir.path().join("a.txt"), 0);
+    touch(&sub.join("b.txt"), 100);
+
+    let mut manager = manager_for(&dir);
+    let files = vec![sub.join("b.txt"), dir.path().join("a.txt")];
+    manager.set_filter(String::from("work"), files);
+    assert_eq!(manager.get_filter_label(), Some("work"));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::TextFile(dir.path().join("a.txt")),
+            ManagerEntity::TextFile(sub.join("b.txt")),
+            ManagerEntity::Action(Action::Back),
+            ManagerEntity::Action(Action::Root),
+        ]
+    );
+
+    fs::remove_file(dir.path().join("a.txt")).unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 3);
+
+    manager.select(position_of(&manager, &ManagerEntity::Action(Action::Back)));
+    manager.action().unwrap();
+    assert_eq!(manager.get_filter_label(), None);
+    assert_eq!(manager.get_current(), dir.path());
+}
+
+#[test]
+fn marked_files_are_renamed_in_batch() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("note-1.txt"), 0);
+    touch(&dir.path().join("note-2.txt"), 100);
+    touch(&dir.path().join("other.txt"), 200);
+
+    let mut manager = manager_for(&dir);
+    manager
+        .create_file(b"new".to_vec(), Some(String::from("note-3.txt")))
+        .unwrap();
+    for name in ["note-1.txt", "note-3.txt", "other.txt"] {
+        let entity = ManagerEntity::TextFile(dir.path().join(name));
+        manager.select(position_of(&manager, &entity));
+        manager.toggle_mark();
+    }
+    assert!(manager.is_marked(&dir.path().join("other.txt")));
+    assert!(matches!(
+        manager.preview_rename("(", ""),
+        Err(MystoreError::Io(_))
+    ));
+    assert_eq!(
+        manager.preview_rename(r"note-(\d)", "memo-$1").unwrap(),
+        vec![
+            (dir.path().join("note-1.txt"), dir.path().join("memo-1.txt")),
+            (dir.path().join("note-3.txt"), dir.path().join("memo-3.txt")),
+        ]
+    );
+
+    assert_eq!(manager.rename_batch(r"note-(\d)", "memo-$1").unwrap(), 2);
+    assert_eq!(manager.take_applied_renames().len(), 2);
+    assert!(dir.path().join("memo-1.txt").exists());
+    assert!(dir.path().join("note-2.txt").exists());
+    assert!(manager.get_marked_ref().is_empty());
+
+    // The renamed session file can still be deleted.
+    let renamed = ManagerEntity::TextFile(dir.path().join("memo-3.txt"));
+    manager.select(position_of(&manager, &renamed));
+    manager.delete_selected().unwrap();
+}
+
+#[test]
+fn rename_batch_refuses_collisions() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("a.txt"), 0);
+    touch(&dir.path().join("b.txt"), 100);
+
+    let mut manager = manager_for(&dir);
+    for id in 0..2 {
+        manager.select(id);
+        manager.toggle_mark();
+    }
+    assert!(matches!(
+        manager.rename_batch(r"^a", "b"),
+        Err(MystoreError::NameCollision(_))
+    ));
+    assert!(matches!(
+        manager.rename_batch(r"^.", "c"),
+        Err(MystoreError::NameCollision(_))
+    ));
+    assert!(dir.path().join("a.txt").exists());
+    assert!(manager.take_applied_renames().is_empty());
+}
+
+#[test]
+fn rename_batch_keeps_files_in_their_folder() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    touch(&dir.path().join("a.txt"), 0);
+
+    let mut manager = manager_for(&dir);
+    manager.select(position_of(
+        &manager,
+        &ManagerEntity::TextFile(dir.path().join("a.txt")),
+    ));
+    manager.toggle_mark();
+    for replacement in ["../x", "sub/x", "sub\\x", "..", " x"] {
+        assert!(
+            matches!(
+                manager.rename_batch("a.txt", replacement),
+                Err(MystoreError::Io(err)) if err.kind() == std::io::ErrorKind::InvalidInput
+            ),
+            "{}",
+            replacement
+        );
+    }
+    assert!(dir.path().join("a.txt").exists());
+    assert!(!dir.path().join("sub").join("x").exists());
+    assert!(!dir.path().parent().unwrap().join("x").exists());
+    assert!(manager.take_applied_renames().is_empty());
+}
+
+#[test]
+fn rename_batch_tells_the_renames_done_before_a_failure() {
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join("note-1.txt"), 0);
+    touch(&dir.path().join("note-2.txt"), 0);
+
+    let mut manager = manager_for(&dir);
+    for name in ["note-1.txt", "note-2.txt"] {
+        let entity = ManagerEntity::TextFile(dir.path().join(name));
+        manager.select(position_of(&manager, &entity));
+        manager.toggle_mark();
+    }
+    // The second file is gone by the time it is renamed.
+    fs::remove_file(dir.path().join("note-2.txt")).unwrap();
+    assert!(matches!(
+        manager.rename_batch("note", "memo"),
+        Err(MystoreError::Io(_))
+    ));
+    assert_eq!(
+        manager.take_applied_renames(),
+        [(dir.path().join("note-1.txt"), dir.path().join("memo-1.txt"))]
+    );
+    assert!(dir.path().join("memo-1.txt").exists());
+}
+
+#[test]
+fn listing_is_split_into_pages() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..5 {
+        touch(&dir.path().join(format!("{}.txt", id)), 0);
+    }
+
+    let mut manager = manager_for(&dir);
+    manager.set_page_size(2).unwrap();
+    assert_eq!(manager.get_page(), (1, 3));
+
+    let mut seen = Vec::new();
+    for page in 1..=3 {
+        assert_eq!(manager.get_page(), (page, 3));
+        let entities = manager.get_entities_ref();
+        assert_eq!(entities.len(), if page == 3 { 1 } else { 2 });
+        seen.extend(entities.iter().cloned());
+        manager.next_page().unwrap();
+    }
+    assert_eq!(manager.get_page(), (3, 3));
+    seen.sort_by_key(|entity| format!("{:?}", entity));
+    seen.dedup();
+    assert_eq!(seen.len(), 5);
+
+    // Pages that vanish are clamped to the last one.
+    for id in 0..3 {
+        fs::remove_file(dir.path().join(format!("{}.txt", id))).unwrap();
+    }
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_page(), (1, 1));
+    manager.prev_page().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+}
+
+#[test]
+fn registered_actions_are_listed_and_dispatched() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    let mut manager = manager_for(&dir);
+    manager
+        .register_action(
+            "Open today",
+            Box::new(|manager| Ok(Respond::File(manager.get_current().join("today.txt")))),
+        )
+        .unwrap();
+    manager
+        .register_action(
+            "Fail",
+            Box::new(|_manager| Err(io::Error::other("no luck"))),
+        )
+        .unwrap();
+    let custom = |name: &str| ManagerEntity::Action(Action::Custom(String::from(name)));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &[
+            ManagerEntity::Folder(dir.path().join("sub")),
+            custom("Open today"),
+            custom("Fail"),
+        ]
+    );
+
+    manager.select(1);
+    assert_eq!(
+        manager.action().unwrap(),
+        Respond::File(dir.path().join("today.txt"))
+    );
+    manager.select(2);
+    assert!(matches!(manager.action(), Err(MystoreError::Io(_))));
+
+    // Registering a name again replaces the handler in its place.
+    manager
+        .register_action("Open today", Box::new(|_manager| Ok(Respond::None)))
+        .unwrap();
+    manager.select(0);
+    manager.action().unwrap();
+    assert_eq!(
+        manager.get_entities_ref(),
+        &[
+            ManagerEntity::Action(Action::Back),
+            ManagerEntity::Action(Action::Root),
+            custom("Open today"),
+            custom("Fail"),
+        ]
+    );
+    manager.select(2);
+    assert_eq!(manager.action().unwrap(), Respond::None);
+}
+
+#[test]
+fn large_folders_are_read_a_page_at_a_time() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..5 {
+        touch(&dir.path().join(format!("{}.txt", id)), 0);
+    }
+
+    let mut manager = FileManager::new_incremental(dir.path().to_str().unwrap()).unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 5);
+    manager.set_page_size(2).unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+    assert_eq!(manager.get_page(), (1, 1));
+    assert!(manager.has_pending_entities());
+
+    manager.select(1);
+    let selected = manager.get_selected_entity().cloned();
+    assert!(manager.load_more_entities().unwrap());
+    assert_eq!(manager.get_entities_ref().len(), 4);
+    assert_eq!(manager.get_selected_entity().cloned(), selected);
+    // Listing again keeps what was loaded.
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 4);
+
+    assert!(manager.load_more_entities().unwrap());
+    assert_eq!(manager.get_entities_ref().len(), 5);
+    assert!(!manager.has_pending_entities());
+    assert!(!manager.load_more_entities().unwrap());
+}
+
+#[test]
+fn equal_mtimes_are_ordered_by_name() {
+    let dir = TempDir::new().unwrap();
+    let modified = SystemTime::now() - Duration::from_secs(60);
+    for name in ["c.txt", "a.txt", "note-10.txt", "b.txt", "note-2.txt"] {
+        File::create(dir.path().join(name))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+    touch(&dir.path().join("new.txt"), 0);
+
+    let mut manager = manager_for(&dir);
+    let file = |name: &str| ManagerEntity::TextFile(dir.path().join(name));
+    let expected = vec![
+        file("new.txt"),
+        file("a.txt"),
+        file("b.txt"),
+        file("c.txt"),
+        file("note-2.txt"),
+        file("note-10.txt"),
+    ];
+    for _ in 0..5 {
+        manager.refresh().unwrap();
+        assert_eq!(manager.get_entities_ref(), &expected);
+    }
+    assert!(manager.get_parallel_read());
+    manager.set_parallel_read(false);
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref(), &expected);
+}
+
+#[test]
+fn size_order_puts_largest_files_first() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("small.txt"), "a").unwrap();
+    fs::write(dir.path().join("large.txt"), "abcdef").unwrap();
+    fs::write(dir.path().join("tie-b.txt"), "abc").unwrap();
+    fs::write(dir.path().join("tie-a.txt"), "abc").unwrap();
+    fs::write(dir.path().join("empty.txt"), "").unwrap();
+    fs::create_dir(dir.path().join("folder")).unwrap();
+
+    let mut manager = manager_for(&dir);
+    assert_eq!(manager.get_sort_order(), SortOrder::Modified);
+    assert_eq!(manager.get_file_size(&dir.path().join("large.txt")), None);
+
+    manager.set_sort_order(SortOrder::Size).unwrap();
+    let file = |name: &str| ManagerEntity::TextFile(dir.path().join(name));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::Folder(dir.path().join("folder")),
+            file("large.txt"),
+            file("tie-a.txt"),
+            file("tie-b.txt"),
+            file("small.txt"),
+            file("empty.txt"),
+        ]
+    );
+    assert_eq!(
+        manager.get_file_size(&dir.path().join("large.txt")),
+        Some(6)
+    );
+
+    // Sizes are captured per listing, so a grown file moves on refresh.
+    fs::write(dir.path().join("small.txt"), "abcdefgh").unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref()[1], file("small.txt"));
+    assert_eq!(SortOrder::Size.next(), SortOrder::Name);
+}
+
+#[test]
+fn age_histogram_buckets_files_by_calendar() {
+    let dir = TempDir::new().unwrap();
+    // A Wednesday, so the week started two days before, in the same month.
+    let now = Local.with_ymd_and_hms(2024, 5, 15, 12, 0, 0).unwrap();
+    let files = [
+        (
+            "this-morning.txt",
+            Local.with_ymd_and_hms(2024, 5, 15, 8, 0, 0),
+        ),
+        ("tomorrow.txt", Local.with_ymd_and_hms(2024, 5, 16, 9, 0, 0)),
+        ("monday.txt", Local.with_ymd_and_hms(2024, 5, 13, 0, 0, 0)),
+        ("first.txt", Local.with_ymd_and_hms(2024, 5, 1, 0, 0, 0)),
+        (
+            "last-month.txt",
+            Local.with_ymd_and_hms(2024, 4, 30, 23, 0, 0),
+        ),
+    ];
+    for (name, modified) in files {
+        let file = File::create(dir.path().join(name)).unwrap();
+        file.set_modified(modified.unwrap().into()).unwrap();
+    }
+    fs::create_dir(dir.path().join("folder")).unwrap();
+
+    let manager = manager_for(&dir);
+    let count = |name: &str, count: usize| (String::from(name), count);
+    assert_eq!(
+        manager.age_histogram_at(now),
+        vec![
+            count("today", 2),
+            count("this week", 1),
+            count("this month", 1),
+            count("older", 1),
+        ]
+    );
+}
+
+#[test]
+fn size_histogram_lists_files_largest_first() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("small.txt"), "a").unwrap();
+    fs::write(dir.path().join("tie-b.txt"), "abc").unwrap();
+    fs::write(dir.path().join("tie-a.txt"), "abc").unwrap();
+    fs::write(dir.path().join("large.txt"), "abcdef").unwrap();
+    fs::create_dir(dir.path().join("folder")).unwrap();
+    fs::write(dir.path().join("folder").join("inner.txt"), "abcdefgh").unwrap();
+
+    let manager = manager_for(&dir);
+    let histogram = vec![
+        (String::from("large.txt"), 6),
+        (String::from("tie-a.txt"), 3),
+        (String::from("tie-b.txt"), 3),
+        (String::from("small.txt"), 1),
+    ];
+    assert_eq!(manager.size_histogram(), histogram);
+}
+
+fn wait_for_tree_hash(manager: &mut FileManager) {
+    for _ in 0..400 {
+        if manager.poll_tree_hash() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("the tree was not hashed in time");
+}
+
+#[test]
+fn tree_hash_follows_names_and_contents() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub").join("a.txt"), "one").unwrap();
+    fs::write(dir.path().join("b.txt"), "two").unwrap();
+
+    let mut manager = manager_for(&dir);
+    let hash = manager.hash_tree().unwrap();
+    assert_eq!(hash.len(), 64);
+    // Visiting a folder hashes it for the baseline.
+    assert!(manager.is_hashing());
+    wait_for_tree_hash(&mut manager);
+    assert_eq!(manager.get_last_hash(), Some(hash.as_str()));
+    assert_eq!(manager.is_tree_changed(), None);
+    manager.refresh().unwrap();
+    wait_for_tree_hash(&mut manager);
+    assert_eq!(manager.is_tree_changed(), Some(false));
+
+    // A nested change reaches the root hash.
+    fs::write(dir.path().join("sub").join("a.txt"), "uno").unwrap();
+    manager.refresh().unwrap();
+    wait_for_tree_hash(&mut manager);
+    assert_eq!(manager.is_tree_changed(), Some(true));
+    assert_eq!(manager.get_hash_unreadable(), 0);
+    let changed = manager.hash_tree().unwrap();
+    assert_ne!(changed, hash);
+
+    // An edit that keeps the size and the modification time is seen too.
+    let path = dir.path().join("b.txt");
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    fs::write(&path, "owt").unwrap();
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    assert_ne!(manager.hash_tree().unwrap(), changed);
+    fs::write(&path, "two").unwrap();
+    assert_eq!(manager.hash_tree().unwrap(), changed);
+
+    // So does a rename that keeps the contents.
+    fs::rename(dir.path().join("b.txt"), dir.path().join("c.txt")).unwrap();
+    assert_ne!(manager.hash_tree().unwrap(), changed);
+    fs::rename(dir.path().join("c.txt"), dir.path().join("b.txt")).unwrap();
+    assert_eq!(manager.hash_tree().unwrap(), changed);
+}
+
+#[test]
+fn first_change_after_entering_a_folder_is_seen() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(sub.join("a.txt"), "one").unwrap();
+
+    let mut manager = manager_for(&dir);
+    wait_for_tree_hash(&mut manager);
+    manager.select(position_of(&manager, &ManagerEntity::Folder(sub.clone())));
+    manager.action().unwrap();
+    assert_eq!(manager.get_current(), sub);
+    assert_eq!(manager.get_last_hash(), None);
+    wait_for_tree_hash(&mut manager);
+
+    fs::write(sub.join("a.txt"), "uno").unwrap();
+    manager.refresh().unwrap();
+    wait_for_tree_hash(&mut manager);
+    assert_eq!(manager.is_tree_changed(), Some(true));
+}
+
+#[test]
+fn natural_order_compares_numbers_by_value() {
+    let mut names = vec![
+        "note-10.md",
+        "note-2.md",
+        "note-1.md",
+        "note-02.md",
+        "Note-3.md",
+        "note-١.md",
+        "note-99999999999999999999999.md",
+        "note.md",
+    ];
+    names.sort_by(|a, b| FileManager::natural_cmp(a, b));
+    assert_eq!(
+        names,
+        vec![
+            // Case matters: upper case letters sort first.
+            "Note-3.md",
+            "note-1.md",
+            // Leading zeros only break ties between equal values.
+            "note-2.md",
+            "note-02.md",
+            "note-10.md",
+            "note-99999999999999999999999.md",
+            // Non-ASCII digits are compared as text.
+            "note-١.md",
+            "note.md",
+        ]
+    );
+    assert_eq!(
+        FileManager::natural_cmp("a01b", "a1b"),
+        std::cmp::Ordering::Greater
+    );
+    assert_eq!(
+        FileManager::natural_cmp("same", "same"),
+        std::cmp::Ordering::Equal
+    );
+}
+
+#[test]
+fn name_order_lists_numbered_notes_naturally() {
+    let dir = TempDir::new().unwrap();
+    for id in [10, 2, 1] {
+        fs::write(dir.path().join(format!("note-{}.md", id)), "").unwrap();
+        fs::create_dir(dir.path().join(format!("dir-{}", id))).unwrap();
+    }
+
+    let mut manager = manager_for(&dir);
+    manager.set_sort_order(SortOrder::Name).unwrap();
+    let names: Vec<String> = manager
+        .get_entities_ref()
+        .iter()
+        .map(|entity| match entity {
+            ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => {
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            }
+            ManagerEntity::Action(_act) => String::new(),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "dir-1",
+            "dir-2",
+            "dir-10",
+            "note-1.md",
+            "note-2.md",
+            "note-10.md"
+        ]
+    );
+}
+
+#[test]
+fn names_sort_ignoring_case() {
+    let dir = TempDir::new().unwrap();
+    for name in [
+        "Zebra", "apple", "Éclair", "éclair", "README", "readme", "Äpfel",
+    ] {
+        fs::create_dir(dir.path().join(name)).unwrap();
+        fs::write(dir.path().join(format!("{}.txt", name)), "").unwrap();
+    }
+
+    let mut manager = manager_for(&dir);
+    manager.set_sort_order(SortOrder::Name).unwrap();
+    let names: Vec<String> = manager
+        .get_entities_ref()
+        .iter()
+        .map(|entity| match entity {
+            ManagerEntity::TextFile(path) | ManagerEntity::Folder(path) => {
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            }
+            ManagerEntity::Action(_act) => String::new(),
+        })
+        .collect();
+    // Accented letters fold to lower case too, and sort after ASCII ones.
+    let expected = [
+        "apple", "README", "readme", "Zebra", "Äpfel", "Éclair", "éclair",
+    ];
+    let files: Vec<String> = expected
+        .iter()
+        .map(|name| format!("{}.txt", name))
+        .collect();
+    assert_eq!(names[..7], expected);
+    assert_eq!(names[7..], files[..]);
+
+    // The order holds across refreshes.
+    manager.refresh().unwrap();
+    let again: Vec<&ManagerEntity> = manager.get_entities_ref().iter().collect();
+    assert_eq!(again.len(), 14);
+    assert_eq!(again[1], &ManagerEntity::Folder(dir.path().join("README")));
+}
+
+#[test]
+fn extension_filter_keeps_folders_visible() {
+    let dir = TempDir::new().unwrap();
+    for name in ["a.md", "b.TXT", "c.pdf", "README"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::create_dir(dir.path().join("images.png")).unwrap();
+    let file = |name: &str| ManagerEntity::TextFile(dir.path().join(name));
+
+    let mut manager = manager_for(&dir);
+    manager.set_extension_filter(" md, .txt ,").unwrap();
+    assert_eq!(
+        manager.get_extension_filter(),
+        Some(&[String::from("md"), String::from("txt")][..])
+    );
+    let entities = manager.get_entities_ref();
+    assert_eq!(entities.len(), 3);
+    assert!(entities.contains(&ManagerEntity::Folder(dir.path().join("images.png"))));
+    assert!(entities.contains(&file("a.md")));
+    assert!(entities.contains(&file("b.TXT")));
+
+    // The filter applies on top of a tag filter too.
+    manager.set_filter(
+        String::from("work"),
+        vec![dir.path().join("a.md"), dir.path().join("c.pdf")],
+    );
+    assert_eq!(manager.get_entities_ref()[0], file("a.md"));
+    assert_eq!(manager.get_entities_ref().len(), 3);
+    manager.select(position_of(&manager, &ManagerEntity::Action(Action::Back)));
+    manager.action().unwrap();
+
+    manager.set_extension_filter("").unwrap();
+    assert_eq!(manager.get_extension_filter(), None);
+    assert_eq!(manager.get_entities_ref().len(), 5);
+
+    // A folder change clears the filter.
+    manager.set_extension_filter("pdf").unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+    let folder = ManagerEntity::Folder(dir.path().join("images.png"));
+    manager.select(position_of(&manager, &folder));
+    manager.action().unwrap();
+    assert_eq!(manager.get_extension_filter(), None);
+}
+
+#[test]
+fn extension_predicate_ignores_case() {
+    let extensions = [String::from("md")];
+    assert!(FileManager::has_extension(Path::new("a.MD"), &extensions));
+    assert!(FileManager::has_extension(
+        Path::new("dir/b.c.md"),
+        &extensions
+    ));
+    assert!(!FileManager::has_extension(Path::new("md"), &extensions));
+    assert!(!FileManager::has_extension(Path::new(".md"), &extensions));
+    assert!(!FileManager::has_extension(Path::new("a.mdx"), &extensions));
+}
+
+#[test]
+fn typed_paths_resolve_within_the_root() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("notes").join("work")).unwrap();
+    let manager = manager_for(&dir);
+    let work = dir.path().join("notes").join("work");
+
+    assert_eq!(manager.resolve_path("notes/work").unwrap(), work);
+    assert_eq!(manager.resolve_path(" notes/./x/../work/ ").unwrap(), work);
+    assert_eq!(manager.resolve_path(work.to_str().unwrap()).unwrap(), work);
+    assert_eq!(manager.resolve_path("").unwrap(), dir.path());
+    assert!(matches!(
+        manager.resolve_path("notes/../.."),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+    assert!(matches!(
+        manager.resolve_path("/etc"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+    // The home folder is not within a temporary root.
+    assert!(matches!(
+        manager.resolve_path("~"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+
+    // Names of new files are taken from the current folder.
+    let mut manager = manager;
+    manager.go_to("notes").unwrap();
+    assert_eq!(
+        manager.resolve_in_current("work/out.zip").unwrap(),
+        work.join("out.zip")
+    );
+    assert_eq!(
+        manager.resolve_in_current("../out.zip").unwrap(),
+        dir.path().join("out.zip")
+    );
+    assert!(matches!(
+        manager.resolve_in_current("../../out.zip"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+    assert!(matches!(
+        manager.resolve_in_current("/tmp/out.zip"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+}
+
+#[test]
+fn go_to_opens_folders_and_selects_files() {
+    let dir = TempDir::new().unwrap();
+    let work = dir.path().join("notes").join("work");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(work.join("plan.md"), "").unwrap();
+    fs::write(work.join("todo.md"), "").unwrap();
+
+    let mut manager = manager_for(&dir);
+    manager.go_to("notes/work").unwrap();
+    assert_eq!(manager.get_current(), work);
+
+    manager.go_to("/").unwrap_err();
+    manager.go_to("notes/../notes/work/todo.md").unwrap();
+    assert_eq!(manager.get_current(), work);
+    assert_eq!(
+        manager.get_selected_entity(),
+        Some(&ManagerEntity::TextFile(work.join("todo.md")))
+    );
+
+    assert!(matches!(
+        manager.go_to("notes/missing"),
+        Err(MystoreError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound
+    ));
+    assert_eq!(manager.get_current(), work);
+}
+
+#[test]
+fn go_to_a_file_turns_to_its_page() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..7 {
+        fs::write(dir.path().join(format!("note-{}.md", id)), "").unwrap();
+    }
+
+    let mut manager = manager_for(&dir);
+    manager.set_page_size(2).unwrap();
+    // Pages follow the directory order, which is not the order of names.
+    let last = fs::read_dir(dir.path())
+        .unwrap()
+        .last()
+        .unwrap()
+        .unwrap()
+        .path();
+    let name = last.file_name().unwrap().to_str().unwrap();
+    manager.go_to(name).unwrap();
+    assert_eq!(manager.get_page(), (4, 4));
+    assert_eq!(
+        manager.get_selected_entity(),
+        Some(&ManagerEntity::TextFile(last.clone()))
+    );
+
+    manager.go_to("").unwrap();
+    assert_eq!(manager.get_page(), (1, 4));
+}
+
+#[cfg(unix)]
+#[test]
+fn go_to_does_not_follow_links_out_of_the_root() {
+    let dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    fs::write(outside.path().join("secret.md"), "").unwrap();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+    let mut manager = manager_for(&dir);
+    assert!(matches!(
+        manager.go_to("link/secret.md"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+    assert!(matches!(
+        manager.go_to("link/missing/deeper"),
+        Err(MystoreError::OutsideRoot(_))
+    ));
+}
+
+#[test]
+fn paths_complete_against_folder_entries() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("work")).unwrap();
+    fs::write(notes.join("wiki-a.md"), "").unwrap();
+    fs::write(notes.join("wiki-b.md"), "").unwrap();
+    fs::write(notes.join("ünï.md"), "").unwrap();
+    let manager = manager_for(&dir);
+
+    assert_eq!(manager.complete_path("no"), "notes/");
+    assert_eq!(manager.complete_path("notes/w"), "notes/w");
+    assert_eq!(manager.complete_path("notes/wo"), "notes/work/");
+    assert_eq!(manager.complete_path("notes/wi"), "notes/wiki-");
+    assert_eq!(manager.complete_path("notes/ü"), "notes/ünï.md");
+    assert_eq!(manager.complete_path("notes/x"), "notes/x");
+    assert_eq!(manager.complete_path("../"), "../");
+}
+
+#[test]
+fn folder_entries_are_cached_by_modification_time() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    let mut manager = manager_for(&dir);
+    assert_eq!(manager.get_cached_dirs(), vec![dir.path()]);
+
+    // Adding a file touches the folder, so the cache is invalidated.
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+
+    // With the old time restored the cached entries are served.
+    let modified = fs::metadata(dir.path()).unwrap().modified().unwrap();
+    fs::write(dir.path().join("c.txt"), "").unwrap();
+    File::open(dir.path())
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+
+    manager.clear_cache();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 3);
+
+    // The manager's own changes are listed at once.
+    manager
+        .create_file(Vec::new(), Some(String::from("d.txt")))
+        .unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 4);
+}
+
+#[test]
+fn pages_run_across_several_roots() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    for id in 0..2 {
+        touch(&first.path().join(format!("a-{}.txt", id)), 0);
+        touch(&second.path().join(format!("b-{}.txt", id)), 0);
+    }
+    let mut manager = FileManager::new_multi_root(&[
+        first.path().to_str().unwrap(),
+        second.path().to_str().unwrap(),
+    ])
+    .unwrap();
+    manager.set_page_size(3).unwrap();
+    assert_eq!(manager.get_page(), (1, 2));
+    assert_eq!(manager.get_entities_ref().len(), 3);
+    manager.next_page().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 1);
+}
+
+#[test]
+fn folder_cache_evicts_the_least_recently_used() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..17 {
+        fs::create_dir(dir.path().join(format!("dir-{}", id))).unwrap();
+    }
+    let mut manager = manager_for(&dir);
+    for id in 0..17 {
+        manager.go_to(&format!("dir-{}", id)).unwrap();
+    }
+    manager.go_to("").unwrap();
+
+    let cached = manager.get_cached_dirs();
+    assert_eq!(cached.len(), 16);
+    assert!(!cached.contains(&dir.path().join("dir-0").as_path()));
+    assert!(!cached.contains(&dir.path().join("dir-1").as_path()));
+    assert_eq!(cached.last(), Some(&dir.path()));
+
+    let other = TempDir::new().unwrap();
+    manager.set_root(other.path().to_str().unwrap()).unwrap();
+    assert_eq!(manager.get_root(), other.path());
+    assert_eq!(manager.get_cached_dirs(), vec![other.path()]);
+}
+
+#[test]
+fn back_and_root_rows_follow_the_placement() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    fs::write(sub.join("a.txt"), "").unwrap();
+    fs::write(sub.join("b.txt"), "").unwrap();
+
+    let mut manager = manager_for(&dir);
+    manager.go_to("sub").unwrap();
+    let back = ManagerEntity::Action(Action::Back);
+    let root = ManagerEntity::Action(Action::Root);
+    assert_eq!(manager.get_action_placement(), ActionPlacement::Bottom);
+    assert_eq!(position_of(&manager, &back), 2);
+    assert_eq!(position_of(&manager, &root), 3);
+
+    manager.set_action_placement(ActionPlacement::Top).unwrap();
+    assert_eq!(position_of(&manager, &back), 0);
+    assert_eq!(position_of(&manager, &root), 1);
+    // Wrapping around from the first row lands on the last file.
+    manager.select(0);
+    manager.previous();
+    assert!(matches!(
+        manager.get_selected_entity(),
+        Some(ManagerEntity::TextFile(_))
+    ));
+
+    manager
+        .set_action_placement(ActionPlacement::Hidden)
+        .unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+    manager.select(1);
+    manager.next();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().first()
+    );
+
+    manager.go_back().unwrap();
+    assert_eq!(manager.get_current(), dir.path());
+    // The root has no parent to go back to.
+    manager.go_back().unwrap();
+    assert_eq!(manager.get_current(), dir.path());
+    manager.go_to("sub").unwrap();
+    manager.go_root().unwrap();
+    assert_eq!(manager.get_current(), dir.path());
+}
+
+#[test]
+fn action_placement_is_parsed_from_its_name() {
+    assert_eq!("top".parse(), Ok(ActionPlacement::Top));
+    assert_eq!("Hidden".parse(), Ok(ActionPlacement::Hidden));
+    assert!("left".parse::<ActionPlacement>().is_err());
+    assert_eq!(ActionPlacement::Bottom.to_string(), "bottom");
+}
+
+#[test]
+fn navigation_wraps_or_stops_at_the_ends() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..3 {
+        touch(&dir.path().join(format!("{}.txt", id)), id);
+    }
+    let mut manager = manager_for(&dir);
+    assert!(manager.get_wrap_navigation());
+
+    manager.last();
+    manager.next();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().first()
+    );
+    manager.previous();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().last()
+    );
+
+    manager.set_wrap_navigation(false);
+    manager.next();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().last()
+    );
+    manager.first();
+    manager.previous();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().first()
+    );
+    // A single page has nowhere to turn to.
+    manager.page_down().unwrap();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().last()
+    );
+    manager.page_up().unwrap();
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().first()
+    );
+}
+
+#[test]
+fn page_keys_wrap_or_stop_at_the_last_page() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..3 {
+        touch(&dir.path().join(format!("{}.txt", id)), 0);
+    }
+    let mut manager = manager_for(&dir);
+    manager.set_page_size(2).unwrap();
+
+    manager.page_up().unwrap();
+    assert_eq!(manager.get_page(), (2, 2));
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().last()
+    );
+    manager.page_down().unwrap();
+    assert_eq!(manager.get_page(), (1, 2));
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().first()
+    );
+
+    manager.set_wrap_navigation(false);
+    manager.page_down().unwrap();
+    manager.page_down().unwrap();
+    assert_eq!(manager.get_page(), (2, 2));
+    assert_eq!(
+        manager.get_selected_entity(),
+        manager.get_entities_ref().last()
+    );
+}
+
+#[test]
+fn empty_listing_has_nothing_to_select() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+    for wrap in [true, false] {
+        manager.set_wrap_navigation(wrap);
+        manager.next();
+        manager.previous();
+        manager.first();
+        manager.last();
+        manager.page_down().unwrap();
+        manager.page_up().unwrap();
+        assert_eq!(manager.get_selected_entity(), None);
+    }
+}
+
+#[test]
+fn deleting_selects_the_next_entry_or_the_new_last() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+    manager.set_sort_order(SortOrder::Name).unwrap();
+    for name in ["a", "b", "c"] {
+        manager
+            .create_file(Vec::new(), Some(String::from(name)))
+            .unwrap();
+    }
+    let file = |name: &str| ManagerEntity::TextFile(dir.path().join(name));
+
+    manager.select(1);
+    manager.delete_selected().unwrap();
+    assert_eq!(manager.get_selected_entity(), Some(&file("c")));
+
+    manager.delete_selected().unwrap();
+    assert_eq!(manager.get_selected_entity(), Some(&file("a")));
+
+    manager.delete_selected().unwrap();
+    assert!(manager.get_entities_ref().is_empty());
+    assert_eq!(manager.get_selected_id(), None);
+}
+
+#[test]
+fn selection_survives_a_folder_emptied_outside() {
+    let dir = TempDir::new().unwrap();
+    for id in 0..3 {
+        touch(&dir.path().join(format!("{}.txt", id)), id);
+    }
+    let mut manager = manager_for(&dir);
+    manager.last();
+    assert_eq!(manager.get_selected_id(), Some(2));
+
+    fs::remove_file(dir.path().join("2.txt")).unwrap();
+    fs::remove_file(dir.path().join("1.txt")).unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_selected_id(), Some(0));
+
+    fs::remove_file(dir.path().join("0.txt")).unwrap();
+    manager.refresh().unwrap();
+    assert_eq!(manager.get_selected_id(), None);
+    assert_eq!(manager.get_selected_entity(), None);
+    assert!(!manager.select(0));
+    manager.next();
+    manager.previous();
+    assert_eq!(manager.get_selected_entity(), None);
+    assert!(matches!(manager.action(), Ok(Respond::None)));
+    assert!(manager.delete_selected().is_ok());
+}
+
+#[test]
+fn several_roots_are_listed_as_one() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    fs::write(first.path().join("file.txt"), "").unwrap();
+    fs::create_dir(second.path().join("sub")).unwrap();
+    fs::write(second.path().join("sub").join("notes.md"), "").unwrap();
+
+    let mut manager = FileManager::new_multi_root(&[
+        first.path().to_str().unwrap(),
+        second.path().to_str().unwrap(),
+    ])
+    .unwrap();
+    assert!(manager.is_multi_root());
+    assert_eq!(manager.get_roots().len(), 2);
+    let sub = ManagerEntity::Folder(second.path().join("sub"));
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            sub.clone(),
+            ManagerEntity::TextFile(first.path().join("file.txt")),
+        ]
+    );
+    assert_eq!(
+        manager.get_source_root(&second.path().join("sub").join("notes.md")),
+        Some(second.path())
+    );
+
+    // Folders open at their actual paths, going back returns to all roots.
+    manager.select(position_of(&manager, &sub));
+    manager.action().unwrap();
+    assert_eq!(manager.get_current(), second.path().join("sub"));
+    manager.go_back().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+    manager.go_back().unwrap();
+    assert_eq!(manager.get_entities_ref().len(), 2);
+
+    assert!(FileManager::new_multi_root(&[]).is_err());
+}
+
+#[test]
+fn glob_lists_matches_under_their_common_folder() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("a")).unwrap();
+    fs::create_dir_all(notes.join("b").join("c.enc")).unwrap();
+    fs::write(notes.join("a").join("one.enc"), "").unwrap();
+    fs::write(notes.join("a").join("plain.txt"), "").unwrap();
+    fs::write(notes.join("two.enc"), "").unwrap();
+
+    let pattern = format!("{}/**/*.enc", notes.display());
+    let manager = FileManager::new_from_glob(&pattern).unwrap();
+    assert_eq!(manager.get_root(), notes);
+    assert_eq!(manager.get_current(), notes);
+    assert_eq!(manager.get_filter_label(), Some(pattern.as_str()));
+    let entities = manager.get_entities_ref();
+    assert!(entities.contains(&ManagerEntity::Folder(notes.join("b").join("c.enc"))));
+    assert!(entities.contains(&ManagerEntity::TextFile(notes.join("a").join("one.enc"))));
+    assert!(entities.contains(&ManagerEntity::TextFile(notes.join("two.enc"))));
+    assert!(!entities.contains(&ManagerEntity::TextFile(notes.join("a").join("plain.txt"))));
+
+    // A single file is rooted at its folder.
+    let manager = FileManager::new_from_glob(&format!("{}/a/one.*", notes.display())).unwrap();
+    assert_eq!(manager.get_current(), notes.join("a"));
+
+    assert!(FileManager::new_from_glob(&format!("{}/*.none", notes.display())).is_err());
+    assert!(FileManager::new_from_glob("[").is_err());
+}
+
+#[test]
+fn history_lists_the_recorded_paths_that_still_exist() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("clips")).unwrap();
+    fs::write(notes.join("clips").join("one.txt"), "").unwrap();
+    fs::write(notes.join("two.txt"), "").unwrap();
+    let history = dir.path().join("history");
+    let lines = [
+        notes.join("clips").join("one.txt"),
+        notes.join("gone.txt"),
+        PathBuf::new(),
+        notes.join("two.txt"),
+        notes.join("clips"),
+        notes.join("two.txt"),
+    ];
+    let text: String = lines
+        .iter()
+        .map(|path| format!("{}\n", path.display()))
+        .collect();
+    fs::write(&history, text).unwrap();
+
+    let manager = FileManager::new_from_clipboard_history(&history).unwrap();
+    assert_eq!(manager.get_root(), notes);
+    assert_eq!(manager.get_filter_label(), Some("history"));
+    let entities = manager.get_entities_ref();
+    assert!(entities.contains(&ManagerEntity::Folder(notes.join("clips"))));
+    assert!(entities.contains(&ManagerEntity::TextFile(
+        notes.join("clips").join("one.txt")
+    )));
+    assert!(entities.contains(&ManagerEntity::TextFile(notes.join("two.txt"))));
+    assert!(!entities.contains(&ManagerEntity::TextFile(notes.join("gone.txt"))));
+
+    fs::write(&history, format!("{}\n", notes.join("gone.txt").display())).unwrap();
+    assert!(FileManager::new_from_clipboard_history(&history).is_err());
+    assert!(FileManager::new_from_clipboard_history(&dir.path().join("none")).is_err());
+}
+
+fn write_zip(path: &Path, files: &[(&str, &str)], dirs: &[&str]) {
+    let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for dir in dirs {
+        zip.add_directory(*dir, options).unwrap();
+    }
+    for (name, text) in files {
+        zip.start_file(*name, options).unwrap();
+        std::io::Write::write_all(&mut zip, text.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn zip_archive_is_browsed_read_only() {
+    let dir = TempDir::new().unwrap();
+    let archive = dir.path().join("notes.zip");
+    write_zip(
+        &archive,
+        &[
+            ("top.txt", "top"),
+            ("work/plan.txt", "plan"),
+            ("../evil.txt", "no"),
+        ],
+        &["empty/"],
+    );
+
+    let mut manager = FileManager::new_readonly_from_zip(archive.to_str().unwrap()).unwrap();
+    assert!(manager.is_readonly());
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::Folder(archive.join("empty")),
+            ManagerEntity::Folder(archive.join("work")),
+            ManagerEntity::TextFile(archive.join("top.txt")),
+        ]
+    );
+
+    // Folders only implied by their files are listed and can be opened.
+    manager.select(1);
+    assert_eq!(manager.action().unwrap(), Respond::None);
+    assert_eq!(manager.get_current(), archive.join("work"));
+    manager.select(0);
+    assert_eq!(
+        manager.action().unwrap(),
+        Respond::Data(archive.join("work/plan.txt"), b"plan".to_vec())
+    );
+    manager.go_back().unwrap();
+    assert_eq!(manager.get_current(), archive);
+
+    let denied =
+        |result: mystore::error::Result<()>| matches!(result, Err(MystoreError::ReadOnly(_)));
+    assert!(denied(
+        manager.create_file(b"new".to_vec(), None).map(|_path| ())
+    ));
+    manager.select(2);
+    assert!(denied(manager.delete_selected().map(|_held| ())));
+    assert!(denied(manager.lock_file()));
+    assert!(denied(manager.cleanup_locks().map(|_count| ())));
+    assert!(denied(
+        manager.export_as_zip(&dir.path().join("out.zip"), false)
+    ));
+}
+
+fn write_tar_gz(path: &Path, files: &[(&str, &str)]) {
+    let gzip =
+        flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(gzip);
+    for (name, text) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(text.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, name, text.as_bytes()).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn tarball_is_browsed_from_memory() {
+    let dir = TempDir::new().unwrap();
+    let archive = dir.path().join("notes.tar.gz");
+    write_tar_gz(&archive, &[("./top.txt", "top"), ("work/plan.txt", "plan")]);
+
+    let mut manager = FileManager::new_readonly_from_tar(archive.to_str().unwrap()).unwrap();
+    assert!(manager.is_readonly());
+    assert_eq!(
+        manager.get_entities_ref(),
+        &vec![
+            ManagerEntity::Folder(archive.join("work")),
+            ManagerEntity::TextFile(archive.join("top.txt")),
+        ]
+    );
+    // The archive can go, its files are in memory.
+    fs::remove_file(&archive).unwrap();
+    manager.select(0);
+    assert_eq!(manager.action().unwrap(), Respond::None);
+    manager.select(0);
+    assert_eq!(
+        manager.action().unwrap(),
+        Respond::Data(archive.join("work/plan.txt"), b"plan".to_vec())
+    );
+    assert!(matches!(
+        manager.create_file(b"new".to_vec(), None),
+        Err(MystoreError::ReadOnly(root)) if root == archive
+    ));
+}
+
+#[test]
+fn oversized_tarball_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let archive = dir.path().join("huge.tar");
+    // Only the header is written, the size it claims is checked first.
+    let mut header = tar::Header::new_gnu();
+    header.set_path("huge.bin").unwrap();
+    header.set_size(MAX_TAR_SIZE + 1);
+    header.set_cksum();
+    fs::write(&archive, header.as_bytes()).unwrap();
+
+    assert!(matches!(
+        FileManager::new_readonly_from_tar(archive.to_str().unwrap()),
+        Err(MystoreError::Io(err)) if err.kind() == std::io::ErrorKind::InvalidData
+    ));
+}
+
+#[test]
+fn ssh_addresses_are_parsed() {
+    assert_eq!(
+        "me@notes.example:2222".parse(),
+        Ok(SshConfig {
+            host: String::from("notes.example"),
+            port: 2222,
+            user: String::from("me"),
+        })
+    );
+    assert_eq!(
+        "me@notes.example"
+            .parse::<SshConfig>()
+            .map(|config| config.port),
+        Ok(22)
+    );
+    for address in ["notes.example:22", "@host", "me@", "me@host:port"] {
+        assert!(address.parse::<SshConfig>().is_err(), "{}", address);
+    }
+
+    // Nothing listens on port 1, the error comes back before any login.
+    let config = "me@127.0.0.1:1".parse().unwrap();
+    assert!(matches!(
+        FileManager::new_from_ssh(config, ""),
+        Err(MystoreError::Io(_))
+    ));
+}
+
+#[test]
+fn unreachable_s3_endpoint_is_an_io_error() {
+    // Nothing listens on port 1, the listing fails without retrying.
+    env::set_var("AWS_ENDPOINT_URL", "http://127.0.0.1:1");
+    env::set_var("AWS_ACCESS_KEY_ID", "id");
+    env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+    env::set_var("AWS_MAX_ATTEMPTS", "1");
+    let config = S3Config {
+        bucket: String::from("notes"),
+        prefix: String::new(),
+        region: String::from("us-east-1"),
+    };
+
+    assert!(matches!(
+        FileManager::new_from_s3(config),
+        Err(MystoreError::Io(_))
+    ));
+}
+
+#[test]
+fn unsaved_changes_are_told_from_the_file() {
+    let dir = TempDir::new().unwrap();
+    let manager = manager_for(&dir);
+    let mut editor = mystore::editor::Editor::new("secret");
+    assert!(!manager.has_unsaved_changes(&editor));
+
+    editor.init();
+    assert!(!manager.has_unsaved_changes(&editor));
+    let textarea = editor.get_textarea_mut().unwrap();
+    textarea.insert_str("first");
+    textarea.insert_newline();
+    textarea.insert_str("second");
+    assert!(manager.has_unsaved_changes(&editor));
+
+    editor.set_title(Some(String::from("note.txt")));
+    assert!(manager.has_unsaved_changes(&editor));
+    fs::write(dir.path().join("note.txt"), "first\nsecond\n").unwrap();
+    assert!(!manager.has_unsaved_changes(&editor));
+    fs::write(dir.path().join("note.txt"), "first\r\nsecond").unwrap();
+    assert!(!manager.has_unsaved_changes(&editor));
+    fs::write(dir.path().join("note.txt"), "first").unwrap();
+    assert!(manager.has_unsaved_changes(&editor));
+
+    editor.open_read_only(Some(String::from("note.txt")), "other");
+    assert!(!manager.has_unsaved_changes(&editor));
+}
+
+#[test]
+fn parent_entities_are_read_without_leaving_the_folder() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("b/inner")).unwrap();
+    fs::create_dir(dir.path().join("a")).unwrap();
+    fs::write(dir.path().join("note.txt"), "").unwrap();
+    let mut manager = manager_for(&dir);
+    assert!(manager.get_parent_entities().unwrap().is_empty());
+
+    manager.go_to("b").unwrap();
+    let listed = manager.get_entities_ref().clone();
+    assert_eq!(
+        manager.get_parent_entities().unwrap(),
+        [
+            ManagerEntity::Folder(dir.path().join("a")),
+            ManagerEntity::Folder(dir.path().join("b")),
+            ManagerEntity::TextFile(dir.path().join("note.txt")),
+        ]
+    );
+    assert_eq!(manager.get_current(), dir.path().join("b"));
+    assert_eq!(manager.get_entities_ref(), &listed);
+
+    manager.go_to("b/inner").unwrap();
+    assert_eq!(
+        manager.get_parent_entities().unwrap(),
+        [ManagerEntity::Folder(dir.path().join("b/inner"))]
+    );
+}
+
+#[test]
+fn deleted_files_are_restored_exactly() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = manager_for(&dir);
+    let small = manager
+        .create_file(
+            b"note\r\n\x00bytes".to_vec(),
+            Some(String::from("small.txt")),
+        )
+        .unwrap();
+    let large_data: Vec<u8> = (0..HELD_IN_MEMORY + 10).map(|id| id as u8).collect();
+    let large = dir.path().join("large.bin");
+    fs::write(&large, &large_data).unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    File::options()
+        .write(true)
+        .open(&large)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    manager.refresh().unwrap();
+
+    manager.select(position_of(
+        &manager,
+        &ManagerEntity::TextFile(small.clone()),
+    ));
+    let mut held = vec![
+        manager.delete_selected().unwrap().unwrap(),
+        manager.delete_copy(&large).unwrap(),
+    ];
+    assert!(held[0].is_in_memory());
+    assert!(!held[1].is_in_memory());
+    assert!(!small.exists() && !large.exists());
+
+    manager.restore_files(&mut held).unwrap();
+    assert!(held.is_empty());
+    assert_eq!(fs::read(&small).unwrap(), b"note\r\n\x00bytes");
+    assert_eq!(fs::read(&large).unwrap(), large_data);
+    assert_eq!(large.metadata().unwrap().modified().unwrap(), modified);
+    // The restored session file can be deleted again, the other not.
+    assert_eq!(manager.get_created_files(), [small.as_path()]);
+
+    // A taken name is not overwritten, and nothing is restored.
+    let mut held = vec![
+        manager.hold_file(&small).unwrap(),
+        manager.hold_file(&large).unwrap(),
+    ];
+    fs::remove_file(&small).unwrap();
+    assert!(matches!(
+        manager.restore_files(&mut held),
+        Err(MystoreError::NameCollision(path)) if path == large
+    ));
+    assert!(!small.exists());
+    assert_eq!(fs::read(&large).unwrap(), large_data);
+    assert_eq!(held.len(), 2);
+
+    // After a failure only the files still deleted are left.
+    let gone = dir.path().join("gone").join("note.txt");
+    let mut held = vec![
+        HeldFile::new(gone.clone(), b"gone".to_vec(), None, false).unwrap(),
+        held.remove(0),
+    ];
+    assert!(manager.restore_files(&mut held).is_err());
+    assert!(small.exists());
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].path, gone);
+}
+
+#[test]
+fn renames_are_undone() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("note-1.txt"), "one").unwrap();
+    fs::write(dir.path().join("note-2.txt"), "two").unwrap();
+    let mut manager = manager_for(&dir);
+    for id in 0..2 {
+        manager.select(id);
+        manager.toggle_mark();
+    }
+    assert_eq!(manager.rename_batch("note", "memo").unwrap(), 2);
+    let mut renames = manager.take_applied_renames();
+    assert_eq!(renames.len(), 2);
+
+    fs::write(dir.path().join("note-2.txt"), "new").unwrap();
+    assert!(matches!(
+        manager.undo_renames(&mut renames),
+        Err(MystoreError::NameCollision(_))
+    ));
+    assert!(dir.path().join("memo-1.txt").exists());
+
+    fs::remove_file(dir.path().join("note-2.txt")).unwrap();
+    let mut undone = renames.clone();
+    manager.undo_renames(&mut undone).unwrap();
+    assert!(undone.is_empty());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("note-1.txt")).unwrap(),
+        "one"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("note-2.txt")).unwrap(),
+        "two"
+    );
+    assert!(!dir.path().join("memo-1.txt").exists());
+    assert!(manager.undo_renames(&mut renames).is_err());
+}
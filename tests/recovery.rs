@@ -0,0 +1,69 @@
+use chrono::{Local, TimeZone};
+use mystore::{
+    keyring::{self, SessionKey},
+    recovery::{find_recovery_files, remember, save_remembered, write_recovery},
+};
+use std::fs;
+use tempfile::TempDir;
+
+const KEY: &str = "secret";
+
+#[test]
+fn recovery_files_are_named_after_the_crash_and_found_latest_first() {
+    let dir = TempDir::new().unwrap();
+    let lines = [
+        String::from("first line"),
+        String::new(),
+        String::from("last"),
+    ];
+    let earlier = Local.with_ymd_and_hms(2024, 5, 15, 9, 5, 0).unwrap();
+    let later = Local.with_ymd_and_hms(2024, 5, 16, 18, 30, 7).unwrap();
+
+    let first = write_recovery(dir.path(), &lines, None, earlier).unwrap();
+    let second = write_recovery(dir.path(), &lines[..1], None, later).unwrap();
+    assert_eq!(
+        first,
+        dir.path().join("mystore_recovery_20240515-090500.txt")
+    );
+    assert_eq!(fs::read_to_string(&first).unwrap(), "first line\n\nlast");
+
+    fs::write(dir.path().join("mystore_recovery_notes.md"), "other").unwrap();
+    fs::write(dir.path().join("note.txt"), "other").unwrap();
+    fs::create_dir(dir.path().join("mystore_recovery_folder.txt")).unwrap();
+    assert_eq!(
+        find_recovery_files(dir.path()).unwrap(),
+        vec![second, first]
+    );
+}
+
+#[test]
+fn only_remembered_text_is_saved() {
+    let dir = TempDir::new().unwrap();
+    let key = SessionKey::open(dir.path(), KEY).unwrap();
+    let now = Local.with_ymd_and_hms(2024, 5, 15, 12, 0, 0).unwrap();
+
+    remember(None, false);
+    assert!(save_remembered(dir.path(), &key, now).is_none());
+    remember(Some(&[String::new(), String::new()]), false);
+    assert!(save_remembered(dir.path(), &key, now).is_none());
+    assert!(find_recovery_files(dir.path()).unwrap().is_empty());
+
+    let lines = [String::from("unsaved"), String::from("note")];
+    remember(Some(&lines), false);
+    let path = save_remembered(dir.path(), &key, now).unwrap().unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "unsaved\nnote");
+
+    // The text of an encrypted note stays encrypted.
+    remember(Some(&lines), true);
+    let later = Local.with_ymd_and_hms(2024, 5, 15, 12, 0, 1).unwrap();
+    let path = save_remembered(dir.path(), &key, later).unwrap().unwrap();
+    assert_eq!(
+        path,
+        dir.path().join("mystore_recovery_20240515-120001.enc")
+    );
+    let data = fs::read(&path).unwrap();
+    assert!(keyring::is_sealed(&data));
+    assert_eq!(key.decrypt(&data).unwrap(), "unsaved\nnote");
+    assert_eq!(find_recovery_files(dir.path()).unwrap()[0], path);
+    remember(None, false);
+}
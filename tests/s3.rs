@@ -0,0 +1,247 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use mystore::{
+    app::{App, AppEvent, Mode},
+    manager::ManagerEntity,
+    s3::S3Config,
+};
+use std::{
+    collections::HashMap,
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b'%' if at + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[at + 1..at + 3]).unwrap();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                at += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                at += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap()
+}
+
+/// Answers the S3 requests of a session for the bucket `notes`, path-style,
+/// from the objects in memory.
+fn respond(
+    method: &str,
+    target: &str,
+    headers: &HashMap<String, String>,
+    body: Vec<u8>,
+    objects: &Objects,
+) -> (u16, Vec<u8>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .map(|(name, value)| (decode(name), decode(value)))
+        .collect();
+    let key = decode(path.trim_start_matches("/notes").trim_start_matches('/'));
+    let mut objects = objects.lock().unwrap();
+    match method {
+        "GET" if key.is_empty() => {
+            let prefix = query.get("prefix").cloned().unwrap_or_default();
+            let mut files = String::new();
+            let mut folders: Vec<String> = Vec::new();
+            let mut count = 0;
+            let mut keys: Vec<&String> = objects.keys().collect();
+            keys.sort();
+            for key in keys {
+                let Some(rest) = key.strip_prefix(&prefix) else {
+                    continue;
+                };
+                match rest.split_once('/') {
+                    Some((folder, _rest)) => {
+                        let folder = format!("{}{}/", prefix, folder);
+                        if !folders.contains(&folder) {
+                            folders.push(folder);
+                        }
+                    }
+                    None => {
+                        files.push_str(&format!(
+                            "<Contents><Key>{}</Key><Size>{}</Size></Contents>",
+                            key,
+                            objects[key].len()
+                        ));
+                    }
+                }
+                count += 1;
+            }
+            let folders: String = folders
+                .iter()
+                .map(|folder| {
+                    format!(
+                        "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                        folder
+                    )
+                })
+                .collect();
+            let xml = format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+                 <Name>notes</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount>\
+                 <IsTruncated>false</IsTruncated>{}{}</ListBucketResult>",
+                prefix, count, files, folders
+            );
+            (200, xml.into_bytes())
+        }
+        "GET" | "HEAD" => match objects.get(&key) {
+            Some(data) => (200, data.clone()),
+            None => (
+                404,
+                b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>NoSuchKey</Code></Error>"
+                    .to_vec(),
+            ),
+        },
+        "PUT" => {
+            if headers.get("if-none-match").is_some_and(|tag| tag == "*")
+                && objects.contains_key(&key)
+            {
+                return (412, Vec::new());
+            }
+            objects.insert(key, body);
+            (200, Vec::new())
+        }
+        "DELETE" => {
+            objects.remove(&key);
+            (204, Vec::new())
+        }
+        _ => (400, Vec::new()),
+    }
+}
+
+fn serve(stream: TcpStream, objects: Objects) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = line.split_whitespace();
+        let (method, target) = (
+            parts.next().unwrap().to_owned(),
+            parts.next().unwrap().to_owned(),
+        );
+        let mut headers = HashMap::new();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+            }
+        }
+        let mut body = Vec::new();
+        if let Some(length) = headers.get("content-length") {
+            body.resize(length.parse().unwrap(), 0);
+            reader.read_exact(&mut body).unwrap();
+        }
+
+        let (status, data) = respond(&method, &target, &headers, body, &objects);
+        let head = format!(
+            "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nETag: \"etag\"\r\n\r\n",
+            status,
+            data.len()
+        );
+        stream.write_all(head.as_bytes()).unwrap();
+        if method != "HEAD" {
+            stream.write_all(&data).unwrap();
+        }
+    }
+}
+
+/// Serves a bucket from memory on a free local port, with the environment
+/// of the AWS SDK pointed at it.
+fn start_bucket(objects: &Objects) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let served = Arc::clone(objects);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let objects = Arc::clone(&served);
+            thread::spawn(move || serve(stream.unwrap(), objects));
+        }
+    });
+    env::set_var("AWS_ENDPOINT_URL", format!("http://127.0.0.1:{}", port));
+    env::set_var("AWS_ACCESS_KEY_ID", "id");
+    env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+    env::set_var("AWS_MAX_ATTEMPTS", "1");
+    env::set_var("AWS_REQUEST_CHECKSUM_CALCULATION", "when_required");
+}
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+}
+
+#[test]
+fn exports_of_a_bucket_are_written_to_the_bucket() {
+    let objects: Objects = Arc::default();
+    objects
+        .lock()
+        .unwrap()
+        .insert(String::from("note.txt"), b"hello".to_vec());
+    start_bucket(&objects);
+    let config = S3Config {
+        bucket: String::from("notes"),
+        prefix: String::new(),
+        region: String::from("us-east-1"),
+    };
+
+    let mut app = App::from_s3(config, "secret").unwrap();
+    let root = PathBuf::from("s3://notes");
+    let manager = app.get_manager_mut();
+    assert!(manager.is_remote());
+    manager
+        .create_file(b"new".to_vec(), Some(String::from("new.txt")))
+        .unwrap();
+    manager.export_as_zip(&root.join("out.zip"), false).unwrap();
+    let zip = objects.lock().unwrap()["out.zip"].clone();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip)).unwrap();
+    let mut text = String::new();
+    archive
+        .by_name("new.txt")
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, "new");
+
+    let note = ManagerEntity::TextFile(root.join("note.txt"));
+    while app.get_manager_ref().get_selected_entity() != Some(&note) {
+        press(&mut app, KeyCode::Down);
+    }
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.get_mode(), &Mode::Viewer);
+    app.handle_event(AppEvent::Key(KeyEvent::new(
+        KeyCode::Char('m'),
+        KeyModifiers::CONTROL,
+    )));
+    "note.md"
+        .chars()
+        .for_each(|c| press(&mut app, KeyCode::Char(c)));
+    press(&mut app, KeyCode::Enter);
+    assert!(app.get_status().is_none());
+    let markdown = String::from_utf8(objects.lock().unwrap()["note.md"].clone()).unwrap();
+    assert!(markdown.ends_with("---\nhello"));
+
+    // Nothing landed on the local disk at the remote paths.
+    assert!(!Path::new("s3:").exists());
+}
@@ -0,0 +1,128 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use mystore::{
+    app::{App, AppEvent, DEFAULT_MANAGER_WIDTH},
+    manager::{ManagerEntity, SortOrder},
+    session::SessionState,
+};
+use std::{fs, path::PathBuf};
+use tempfile::TempDir;
+
+const KEY: &str = "secret";
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+}
+
+fn state() -> SessionState {
+    SessionState {
+        dir: PathBuf::from("work/notes"),
+        selected: Some(String::from("plan.txt")),
+        sort_order: SortOrder::Name,
+        show_hidden: false,
+        manager_width: 40,
+    }
+}
+
+#[test]
+fn state_is_saved_and_read_back() {
+    let dir = TempDir::new().unwrap();
+    assert_eq!(SessionState::load(dir.path()), None);
+
+    state().save(dir.path()).unwrap();
+    let text = fs::read_to_string(dir.path().join(".mystore/session.json")).unwrap();
+    assert!(text.contains("\"dir\": \"work/notes\""));
+    assert!(text.contains("\"sort_order\": \"name\""));
+    assert_eq!(SessionState::load(dir.path()), Some(state()));
+
+    let root = SessionState {
+        selected: None,
+        ..SessionState::default()
+    };
+    assert_eq!(
+        SessionState::from_json(&root.to_json().to_string()),
+        Some(root)
+    );
+}
+
+#[test]
+fn corrupt_or_foreign_state_is_ignored() {
+    let mut value = state().to_json();
+    value["version"] = 2.into();
+    assert_eq!(SessionState::from_json(&value.to_string()), None);
+
+    let mut value = state().to_json();
+    value["sort_order"] = "colour".into();
+    assert_eq!(SessionState::from_json(&value.to_string()), None);
+
+    let mut value = state().to_json();
+    value["dir"] = "../elsewhere".into();
+    assert_eq!(SessionState::from_json(&value.to_string()), None);
+
+    assert_eq!(SessionState::from_json("{\"version\": 1, \"dir\""), None);
+    assert_eq!(SessionState::from_json(""), None);
+
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join(".mystore")).unwrap();
+    fs::write(dir.path().join(".mystore/session.json"), [0xff, 0xfe]).unwrap();
+    assert_eq!(SessionState::load(dir.path()), None);
+}
+
+#[test]
+fn session_is_restored_where_it_was_left() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("work/notes")).unwrap();
+    fs::write(dir.path().join("work/notes/plan.txt"), "").unwrap();
+    fs::write(dir.path().join("work/notes/.draft.txt"), "").unwrap();
+
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.restore_session(&state()).unwrap();
+    let manager = app.get_manager_ref();
+    assert_eq!(manager.get_current(), dir.path().join("work/notes"));
+    assert_eq!(
+        manager.get_selected_entity(),
+        Some(&ManagerEntity::TextFile(
+            dir.path().join("work/notes/plan.txt")
+        ))
+    );
+    assert_eq!(manager.get_sort_order(), SortOrder::Name);
+    assert!(!manager
+        .get_entities_ref()
+        .contains(&ManagerEntity::TextFile(
+            dir.path().join("work/notes/.draft.txt")
+        )));
+    assert_eq!(app.get_manager_width(), 40);
+    assert_eq!(app.get_session_state(), state());
+
+    press(&mut app, KeyCode::Char('.'));
+    press(&mut app, KeyCode::Char('>'));
+    let state = app.get_session_state();
+    assert!(state.show_hidden);
+    assert_eq!(state.manager_width, 45);
+}
+
+#[test]
+fn missing_paths_fall_back_to_the_root() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("plan.txt"), "").unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+
+    app.restore_session(&state()).unwrap();
+    let manager = app.get_manager_ref();
+    assert_eq!(manager.get_current(), dir.path());
+    assert_eq!(
+        manager.get_selected_entity(),
+        Some(&ManagerEntity::TextFile(dir.path().join("plan.txt")))
+    );
+
+    let gone = SessionState {
+        dir: PathBuf::new(),
+        selected: Some(String::from("gone.txt")),
+        manager_width: 500,
+        ..SessionState::default()
+    };
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    app.restore_session(&gone).unwrap();
+    assert_eq!(app.get_manager_ref().get_selected_entity(), None);
+    assert_eq!(app.get_manager_width(), 75);
+    assert_ne!(DEFAULT_MANAGER_WIDTH, 75);
+}
@@ -0,0 +1,127 @@
+use mystore::sizes::{
+    calculate_dir_size, folder_size, format_count, format_size, BackgroundSizer, FolderSize,
+};
+use std::{fs, sync::atomic::AtomicBool, thread, time::Duration};
+use tempfile::TempDir;
+
+fn wait_for_size(sizer: &mut BackgroundSizer) {
+    for _ in 0..400 {
+        if sizer.poll().is_some() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("the folder was not sized in time");
+}
+
+#[test]
+fn folder_size_is_recursive() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+    fs::write(dir.path().join("one.txt"), "12345").unwrap();
+    fs::write(dir.path().join("a").join("b").join("two.txt"), "123").unwrap();
+
+    assert_eq!(
+        folder_size(dir.path(), &AtomicBool::new(false)),
+        Some(FolderSize {
+            bytes: 8,
+            files: 2,
+            unreadable: 0
+        })
+    );
+    assert_eq!(folder_size(dir.path(), &AtomicBool::new(true)), None);
+}
+
+#[test]
+fn missing_folder_is_counted_as_unreadable() {
+    let dir = TempDir::new().unwrap();
+    let size = folder_size(&dir.path().join("missing"), &AtomicBool::new(false)).unwrap();
+    assert_eq!(size.unreadable, 1);
+}
+
+#[test]
+fn sizes_are_cached_and_cancelled() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("one.txt"), "12345").unwrap();
+
+    let mut sizer = BackgroundSizer::new();
+    sizer.start(dir.path().to_path_buf());
+    assert_eq!(sizer.get_pending(), Some(dir.path()));
+    wait_for_size(&mut sizer);
+    assert_eq!(sizer.get_pending(), None);
+    assert_eq!(sizer.get_size(dir.path()).unwrap().bytes, 5);
+
+    // A known folder is not sized again.
+    sizer.start(dir.path().to_path_buf());
+    assert_eq!(sizer.get_pending(), None);
+
+    let other = TempDir::new().unwrap();
+    sizer.start(other.path().to_path_buf());
+    sizer.cancel();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(sizer.poll(), None);
+    assert!(sizer.get_size(other.path()).is_none());
+}
+
+#[test]
+fn dir_size_sums_nested_files_and_counts_unreadable_ones() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+    fs::write(dir.path().join("one.txt"), "12345").unwrap();
+    fs::write(dir.path().join("a").join("b").join("two.txt"), "123").unwrap();
+
+    let cancelled = AtomicBool::new(false);
+    assert_eq!(
+        folder_size(dir.path(), &cancelled),
+        Some(FolderSize {
+            bytes: 8,
+            files: 2,
+            unreadable: 0,
+        })
+    );
+    assert_eq!(
+        folder_size(&dir.path().join("missing"), &cancelled),
+        Some(FolderSize {
+            bytes: 0,
+            files: 0,
+            unreadable: 1,
+        })
+    );
+    assert_eq!(format_size(8), "8 B");
+    assert_eq!(format_size(4404019), "4.2 MiB");
+}
+
+#[test]
+fn calculate_dir_size_fails_only_on_the_folder_itself() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("a")).unwrap();
+    fs::write(dir.path().join("one.txt"), "12345").unwrap();
+    fs::write(dir.path().join("a").join("two.txt"), "123").unwrap();
+
+    assert_eq!(calculate_dir_size(dir.path()).unwrap(), 8);
+    assert!(calculate_dir_size(&dir.path().join("missing")).is_err());
+}
+
+#[test]
+fn sizes_are_formatted_with_binary_units() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(532), "532 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(1024), "1.0 KiB");
+    assert_eq!(format_size(1434), "1.4 KiB");
+    assert_eq!(format_size(23 * 1024 * 1024), "23.0 MiB");
+    assert_eq!(format_size(1288490189), "1.2 GiB");
+    // Just below a unit rounds up into the next one, not to 1024.0.
+    assert_eq!(format_size(1024 * 1024 - 1), "1.0 MiB");
+    assert_eq!(format_size(1024 * 1024 - 60), "1023.9 KiB");
+    assert_eq!(format_size(u64::MAX), "16.0 EiB");
+}
+
+#[test]
+fn counts_are_grouped_by_thousands() {
+    assert_eq!(format_count(0), "0");
+    assert_eq!(format_count(999), "999");
+    assert_eq!(format_count(1000), "1,000");
+    assert_eq!(format_count(1234567), "1,234,567");
+    assert_eq!(format_count(u64::MAX), "18,446,744,073,709,551,615");
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Local, TimeZone};
+use mystore::{
+    keyring::SessionKey,
+    stats::{self, FileStat},
+    times::TimeFormats,
+};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tempfile::TempDir;
+
+fn write(path: &Path, data: &[u8], modified: DateTime<Local>) {
+    let mut file = File::create(path).unwrap();
+    file.write_all(data).unwrap();
+    file.set_modified(modified.into()).unwrap();
+}
+
+fn day(day: u32) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap()
+}
+
+fn store() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("notes").join("old");
+    fs::create_dir_all(&sub).unwrap();
+    write(&dir.path().join("a.txt"), b"plain text", day(1));
+    write(&sub.join("b.txt"), &[b'x'; 200], day(3));
+    let sealed = SessionKey::open(dir.path(), "secret")
+        .unwrap()
+        .encrypt("hidden")
+        .unwrap();
+    write(&dir.path().join("notes").join("c.enc"), &sealed, day(2));
+    dir
+}
+
+#[test]
+fn the_tree_is_counted_without_the_keyring() {
+    let dir = store();
+    let sealed_len = fs::metadata(dir.path().join("notes").join("c.enc"))
+        .unwrap()
+        .len();
+    let stats = stats::collect_stats(dir.path(), 2).unwrap();
+
+    assert_eq!(stats.files, 3);
+    assert_eq!(stats.folders, 2);
+    assert_eq!(stats.bytes, 210 + sealed_len);
+    assert_eq!(
+        (stats.encrypted_files, stats.encrypted_bytes),
+        (1, sealed_len)
+    );
+    assert_eq!((stats.plain_files, stats.plain_bytes), (2, 210));
+    assert_eq!(stats.unreadable, 0);
+    let file = |path: &str, bytes: u64, modified: DateTime<Local>| FileStat {
+        path: PathBuf::from(path),
+        bytes,
+        modified: SystemTime::from(modified),
+    };
+    assert_eq!(
+        stats.largest,
+        [
+            file("notes/old/b.txt", 200, day(3)),
+            file("notes/c.enc", sealed_len, day(2)),
+        ]
+    );
+    assert_eq!(
+        stats.newest,
+        [
+            file("notes/old/b.txt", 200, day(3)),
+            file("notes/c.enc", sealed_len, day(2)),
+        ]
+    );
+}
+
+#[test]
+fn reports_are_written_for_people_and_scripts() {
+    let dir = TempDir::new().unwrap();
+    write(&dir.path().join("a.txt"), &[b'a'; 2048], day(1));
+    let stats = stats::collect_stats(dir.path(), 10).unwrap();
+
+    assert_eq!(
+        stats.to_text(&TimeFormats::default()),
+        [
+            "Files: 1 (2.0 KiB)",
+            "Folders: 0",
+            "Encrypted: 0 files, 0 B",
+            "Plain: 1 files, 2.0 KiB",
+            "Largest files:",
+            "     2.0 KiB  a.txt",
+            "Recently modified files:",
+            "  2024-05-01 12:00:00  a.txt",
+        ]
+        .join("\n")
+    );
+    let json = stats.to_json();
+    assert_eq!(json["files"], 1);
+    assert_eq!(json["plain"]["bytes"], 2048);
+    assert_eq!(json["largest"][0]["path"], "a.txt");
+    assert_eq!(
+        json["newest"][0]["modified"],
+        day(1).to_utc().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    );
+}
+
+#[test]
+fn unreadable_folders_are_counted_and_skipped() {
+    let dir = store();
+    let locked = dir.path().join("notes").join("old");
+    let mut permissions = fs::metadata(&locked).unwrap().permissions();
+    permissions.set_readonly(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(0o000);
+    }
+    fs::set_permissions(&locked, permissions).unwrap();
+    let readable = fs::read_dir(&locked).is_ok();
+
+    let stats = stats::collect_stats(dir.path(), 10).unwrap();
+    if readable {
+        // Permissions do not stop this user, e.g. root.
+        assert_eq!(stats.unreadable, 0);
+    } else {
+        assert_eq!(stats.unreadable, 1);
+        assert_eq!(stats.files, 2);
+    }
+    assert!(stats::collect_stats(&dir.path().join("missing"), 10).is_err());
+}
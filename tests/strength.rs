@@ -0,0 +1,57 @@
+use mystore::strength::{is_confirmed, rate_password, Strength, MIN_GUESSES};
+
+#[test]
+fn common_and_repetitive_passwords_are_very_weak() {
+    for password in ["password", "Password", "12345678", "aaaaa", "abcdef"] {
+        let rating = rate_password(password);
+        assert_eq!(rating.strength, Strength::VeryWeak, "{}", password);
+        assert!(rating.is_weak());
+    }
+    assert_eq!(rate_password("password").guesses, 2.0);
+}
+
+#[test]
+fn long_or_varied_passwords_pass_the_threshold() {
+    for password in ["xkcdpass", "hunter2", "k3v!Qz"] {
+        let rating = rate_password(password);
+        assert_eq!(rating.strength, Strength::Strong, "{}", password);
+        assert!(rating.guesses >= MIN_GUESSES);
+    }
+    for password in ["Tr0ub4dor&3", "correct horse battery staple", "пароль123"] {
+        assert_eq!(rate_password(password).strength, Strength::VeryStrong);
+    }
+}
+
+#[test]
+fn ratings_tell_strength_and_guesses_without_the_password() {
+    let weak = rate_password("aaaaa");
+    assert_eq!(
+        weak.to_string(),
+        "Password strength: very weak, guessed in about 416 tries"
+    );
+    assert_eq!(
+        weak.warning(true).unwrap(),
+        "Weak password (very weak) — type YES to proceed"
+    );
+    assert_eq!(
+        weak.warning(false).unwrap(),
+        "Warning: the very weak password of this store is easy to guess"
+    );
+
+    let strong = rate_password("Tr0ub4dor&3");
+    assert_eq!(
+        strong.to_string(),
+        "Password strength: very strong, guessed in about 5.7e21 tries"
+    );
+    assert_eq!(strong.warning(true), None);
+    assert_eq!(strong.warning(false), None);
+}
+
+#[test]
+fn only_an_uppercase_yes_confirms() {
+    assert!(is_confirmed("YES\n"));
+    assert!(is_confirmed("  YES "));
+    assert!(!is_confirmed("yes\n"));
+    assert!(!is_confirmed("Y\n"));
+    assert!(!is_confirmed("\n"));
+}
@@ -0,0 +1,53 @@
+use chrono::{Local, TimeZone, Utc};
+use mystore::{
+    manager::FileManager,
+    times::{self, TimeFormats, DEFAULT_NAME_FORMAT},
+};
+use tempfile::TempDir;
+
+#[test]
+fn default_file_names_are_valid_on_windows() {
+    let time = Local.with_ymd_and_hms(2024, 5, 1, 13, 2, 45).unwrap();
+    let name = TimeFormats::default().file_name(&time);
+    assert_eq!(name, "2024-05-01_13-02-45");
+    assert!(times::is_portable_name(&name));
+    assert!(!name
+        .chars()
+        .any(|ch| ['<', '>', ':', '"', '/', '\\', '|', '?', '*'].contains(&ch)));
+    assert_eq!(
+        times::parse_name_format(DEFAULT_NAME_FORMAT).as_deref(),
+        Ok(DEFAULT_NAME_FORMAT)
+    );
+}
+
+#[test]
+fn name_formats_giving_illegal_names_are_refused() {
+    assert!(times::parse_name_format("%Y-%m-%dT%H:%M:%S").is_err());
+    assert!(times::parse_name_format("%+").is_err());
+    assert!(times::parse_name_format("%D").is_err());
+    assert!(times::parse_name_format("%Q").is_err());
+    assert_eq!(
+        times::parse_name_format("note %Y.%m.%d").as_deref(),
+        Ok("note %Y.%m.%d")
+    );
+}
+
+#[test]
+fn times_are_shown_in_the_local_time_zone() {
+    let time = Utc.with_ymd_and_hms(2024, 5, 1, 13, 2, 45).unwrap();
+    let local = time.with_timezone(&Local);
+    assert_eq!(
+        TimeFormats::default().display(&time),
+        local.format("%Y-%m-%d %H:%M:%S").to_string()
+    );
+}
+
+#[test]
+fn new_files_are_named_with_the_configured_format() {
+    let dir = TempDir::new().unwrap();
+    let mut manager = FileManager::new(dir.path().to_str().unwrap()).unwrap();
+    manager.set_time_formats(TimeFormats::new(DEFAULT_NAME_FORMAT, "note-%Y"));
+    let path = manager.create_file(b"new".to_vec(), None).unwrap();
+    let year = Local::now().format("%Y").to_string();
+    assert_eq!(path, dir.path().join(format!("note-{}", year)));
+}
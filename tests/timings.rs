@@ -0,0 +1,100 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use mystore::{
+    app::{App, AppEvent},
+    timings::{Timings, TRACE_SIZE},
+    ui,
+};
+use std::{fs, time::Duration};
+use tempfile::TempDir;
+use tui::{backend::TestBackend, Terminal};
+
+const KEY: &str = "secret";
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+}
+
+#[test]
+fn nothing_is_recorded_while_disabled() {
+    let mut timings = Timings::default();
+    assert_eq!(timings.start(), None);
+    timings.record_draw(timings.start());
+    timings.record_update(timings.start(), || panic!("label formatted"));
+    timings.record_listing(Duration::from_millis(3), || panic!("label formatted"));
+    timings.trace(String::from("Key"), None);
+    assert_eq!(timings.get_last_draw(), None);
+    assert_eq!(timings.get_last_update(), None);
+    assert_eq!(timings.get_last_listing(), None);
+    assert!(timings.get_trace().is_empty());
+}
+
+#[test]
+fn trace_keeps_the_latest_events() {
+    let mut timings = Timings::default();
+    timings.set_enabled(true);
+    for i in 0..TRACE_SIZE + 7 {
+        timings.trace(format!("event {}", i), None);
+    }
+    let trace = timings.get_trace();
+    assert_eq!(trace.len(), TRACE_SIZE);
+    assert_eq!(trace.front().unwrap().label, "event 7");
+    assert_eq!(
+        trace.back().unwrap().label,
+        format!("event {}", TRACE_SIZE + 6)
+    );
+    assert!(trace.front().unwrap().at <= trace.back().unwrap().at);
+
+    timings.record_update(timings.start(), || String::from("Key Down"));
+    timings.record_listing(Duration::from_millis(3), || String::from("Listed"));
+    assert!(timings.get_last_update().is_some());
+    assert_eq!(timings.get_last_listing(), Some(Duration::from_millis(3)));
+    assert_eq!(timings.get_trace().len(), TRACE_SIZE);
+    assert_eq!(timings.get_trace().back().unwrap().label, "Listed");
+
+    timings.set_enabled(false);
+    assert_eq!(timings.get_last_update(), None);
+    assert!(timings.get_trace().is_empty());
+}
+
+#[test]
+fn f12_shows_the_overlay_with_the_latest_events() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("notes")).unwrap();
+    let mut app = App::new(dir.path().to_str().unwrap(), KEY).unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    let shown = |terminal: &Terminal<TestBackend>| {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(usize::from(buffer.area.width))
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    press(&mut app, KeyCode::Down);
+    terminal.draw(|frame| ui::draw(frame, &app)).unwrap();
+    assert!(!shown(&terminal).contains("Debug"));
+    assert!(app.get_timings_ref().get_trace().is_empty());
+
+    press(&mut app, KeyCode::F(12));
+    assert!(app.get_timings_ref().is_enabled());
+    press(&mut app, KeyCode::Enter);
+    let trace = app.get_timings_ref().get_trace();
+    assert!(trace
+        .iter()
+        .any(|event| event.label.starts_with("Key Enter")));
+    assert!(trace
+        .iter()
+        .any(|event| event.label.starts_with("Listed") && event.label.contains("notes (")));
+    assert!(app.get_timings_ref().get_last_listing().is_some());
+    terminal.draw(|frame| ui::draw(frame, &app)).unwrap();
+    let screen = shown(&terminal);
+    assert!(screen.contains("Debug"));
+    assert!(screen.contains("Entities"));
+    assert!(screen.contains("Key Enter"));
+
+    press(&mut app, KeyCode::F(12));
+    assert!(!app.get_timings_ref().is_enabled());
+    assert!(app.get_timings_ref().get_trace().is_empty());
+}
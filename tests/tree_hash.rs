@@ -0,0 +1,47 @@
+use mystore::tree_hash::{hash_dirs, BackgroundHasher};
+use std::{fs, sync::atomic::AtomicBool, thread, time::Duration};
+use tempfile::TempDir;
+
+#[test]
+fn missing_folders_are_counted_not_fatal() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "one").unwrap();
+    let cancelled = AtomicBool::new(false);
+
+    let alone = hash_dirs(&[dir.path().to_path_buf()], &cancelled);
+    assert_eq!(alone.unreadable, 0);
+    assert_eq!(alone.digest.len(), 64);
+
+    let with_missing = hash_dirs(
+        &[dir.path().to_path_buf(), dir.path().join("missing")],
+        &cancelled,
+    );
+    assert_eq!(with_missing.unreadable, 1);
+    assert_ne!(with_missing.digest, alone.digest);
+}
+
+#[test]
+fn hashes_arrive_from_the_worker_and_cancelled_ones_do_not() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "one").unwrap();
+    let expected = hash_dirs(&[dir.path().to_path_buf()], &AtomicBool::new(false));
+
+    let mut hasher = BackgroundHasher::new();
+    hasher.start(dir.path().to_path_buf(), vec![dir.path().to_path_buf()]);
+    assert_eq!(hasher.get_pending(), Some(dir.path()));
+    let mut arrived = None;
+    for _ in 0..400 {
+        arrived = hasher.poll();
+        if arrived.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(arrived, Some((dir.path().to_path_buf(), expected)));
+    assert_eq!(hasher.get_pending(), None);
+
+    hasher.start(dir.path().to_path_buf(), vec![dir.path().to_path_buf()]);
+    hasher.cancel();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(hasher.poll(), None);
+}
@@ -0,0 +1,532 @@
+use mystore::{
+    bookmarks::ScrollPositions,
+    error::MystoreError,
+    keyring::SessionKey,
+    viewer::{LogStats, Viewer, ViewerEntity, AUTO_REFRESH_INTERVAL},
+};
+use std::{fs, thread, time::Duration};
+use tempfile::TempDir;
+
+#[test]
+fn ansi_escapes_are_stripped_by_default() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("\x1b[31merror\x1b[0m: failed")),
+        None,
+    );
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("error: failed"))
+    );
+}
+
+#[test]
+fn log_lines_are_counted_by_level() {
+    let log = "2024-03-01 INFO started\n\
+               2024-03-01 Warning: disk almost full\n\
+               2024-03-01 ERROR failed, see the info above\n\
+               [debug] retrying\n\
+               error: no luck\n\
+               informational, errors and warnings do not count\n";
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text(String::from(log)), None);
+    assert_eq!(
+        viewer.get_log_stats(),
+        Some(LogStats {
+            errors: 2,
+            warnings: 1,
+            infos: 1,
+            debugs: 1,
+        })
+    );
+
+    viewer.set_entity(ViewerEntity::Text(String::from("Just a note")), None);
+    assert_eq!(viewer.get_log_stats(), None);
+    viewer.set_entity(ViewerEntity::Binary(vec![0, 159, 146, 150]), None);
+    assert_eq!(viewer.get_log_stats(), None);
+}
+
+#[test]
+fn toggling_strip_ansi_restores_raw_text() {
+    let raw = String::from("\x1b[1mbold\x1b[0m");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text(raw.clone()), Some(String::from("a.log")));
+    viewer.scroll_down(3);
+
+    viewer.toggle_strip_ansi();
+    assert!(!viewer.get_strip_ansi());
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Text(raw));
+    assert_eq!(viewer.get_scroll(), 3);
+    assert_eq!(viewer.get_name(), Some("a.log"));
+
+    viewer.toggle_strip_ansi();
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("bold"))
+    );
+}
+
+#[test]
+fn text_is_exported_with_front_matter() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("notes.md");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("# Title\nthree words here")),
+        Some(String::from("notes.txt")),
+    );
+
+    viewer.export_as_markdown(&path).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = markdown.lines().collect();
+    assert_eq!(lines[0], "---");
+    assert_eq!(lines[1], "filename: \"notes.txt\"");
+    assert!(lines[2].starts_with("exported: "));
+    assert_eq!(lines[3], "words: 5");
+    assert_eq!(lines[4], "---");
+    assert!(markdown.ends_with("---\n# Title\nthree words here"));
+
+    assert!(matches!(
+        viewer.export_as_markdown(&path),
+        Err(MystoreError::NameCollision(_))
+    ));
+}
+
+#[test]
+fn front_matter_values_are_quoted_for_yaml() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("notes.md");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("text")),
+        Some(String::from("say \"hi\"\\\tnow\n\u{1b}é.txt")),
+    );
+
+    viewer.export_as_markdown(&path).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        markdown.lines().nth(1),
+        Some(r#"filename: "say \"hi\"\\\tnow\n\u001bé.txt""#)
+    );
+}
+
+#[test]
+fn binary_is_not_exported() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Binary(vec![0, 1, 2]), None);
+    assert!(matches!(
+        viewer.export_as_markdown(&dir.path().join("out.md")),
+        Err(MystoreError::NotAFile(..))
+    ));
+}
+
+#[test]
+fn entities_are_printed_in_ansi_colors() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text(String::from("one\ntwo")), None);
+    assert_eq!(
+        viewer.to_ansi_colored_string(),
+        "\x1b[37mone\x1b[0m\n\x1b[37mtwo\x1b[0m\n"
+    );
+
+    let encrypted = SessionKey::from("secret").encrypt("hidden note").unwrap();
+    viewer.set_entity(ViewerEntity::Binary(encrypted), None);
+    assert_eq!(
+        viewer.to_ansi_colored_string(),
+        "\x1b[36mhidden note\x1b[0m\n"
+    );
+
+    viewer.set_entity(ViewerEntity::Binary((0..18).collect()), None);
+    assert_eq!(
+        viewer.to_ansi_colored_string(),
+        "\x1b[32m00000000  00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\x1b[0m\n\
+         \x1b[32m00000010  10 11\x1b[0m\n"
+    );
+}
+
+#[test]
+fn comma_separated_text_is_shown_as_a_table() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("name;size\na;1")),
+        Some(String::from("data.CSV")),
+    );
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Table(vec![
+            vec![String::from("name;size")],
+            vec![String::from("a;1")]
+        ])
+    );
+
+    let text = String::from("name,note\nmilk,\"2, cold\"\nbread");
+    viewer.set_entity(
+        ViewerEntity::Text(text.clone()),
+        Some(String::from("a.txt")),
+    );
+    let rows = vec![
+        vec![String::from("name"), String::from("note")],
+        vec![String::from("milk"), String::from("2, cold")],
+        vec![String::from("bread")],
+    ];
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Table(rows.clone()));
+
+    viewer.scroll_down(1);
+    viewer.toggle_structured();
+    assert!(!viewer.get_structured());
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Text(text));
+    assert_eq!(viewer.get_scroll(), 1);
+    viewer.toggle_structured();
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Table(rows));
+}
+
+#[test]
+fn tags_line_does_not_make_a_table() {
+    let text = String::from("tags: work, ideas\nbody");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text(text.clone()), None);
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Text(text));
+}
+
+#[test]
+fn table_is_exported_as_a_markdown_table() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("table.md");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("a,b\n1|2\n3,4,5")),
+        Some(String::from("t.csv")),
+    );
+
+    viewer.export_as_markdown(&path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "| a | b |  |\n| --- | --- | --- |\n| 1\\|2 |  |  |\n| 3 | 4 | 5 |\n"
+    );
+}
+
+#[test]
+fn json_is_parsed_from_name_or_content() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("42")),
+        Some(String::from("answer.JSON")),
+    );
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Json(serde_json::json!(42))
+    );
+
+    let text = String::from(" {\"b\": [1, 2], \"a\": null}");
+    viewer.set_entity(
+        ViewerEntity::Text(text.clone()),
+        Some(String::from("a.txt")),
+    );
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Json(serde_json::json!({"b": [1, 2], "a": null}))
+    );
+    viewer.toggle_structured();
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Text(text));
+    viewer.toggle_structured();
+
+    for text in ["42", "[not, json"] {
+        viewer.set_entity(ViewerEntity::Text(String::from(text)), None);
+        assert!(!matches!(viewer.get_entity_ref(), ViewerEntity::Json(_)));
+    }
+}
+
+#[test]
+fn json_lines_are_parsed_and_summarized() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    let text = "{\"id\": 7, \"level\": \"info\"}\n\n{\"name\": \"disk\"}\n[1, 2]\n";
+    viewer.set_entity(
+        ViewerEntity::Text(String::from(text)),
+        Some(String::from("app.ndjson")),
+    );
+    let ViewerEntity::JsonLines(records) = viewer.get_entity_ref() else {
+        panic!("{:?} is not JSON lines", viewer.get_entity_ref());
+    };
+    let summaries: Vec<String> = records.iter().map(Viewer::summarize_record).collect();
+    assert_eq!(summaries, vec!["id: 7", "name: disk", "[1,2]"]);
+
+    // Selecting scrolls the selected line into view.
+    viewer.set_pane_size(20, 2);
+    viewer.select_record(5);
+    assert_eq!(viewer.get_selected_record(), 2);
+    assert_eq!(viewer.get_scroll(), 1);
+    assert_eq!(viewer.get_record_ref(), Some(&serde_json::json!([1, 2])));
+    viewer.select_record(0);
+    assert_eq!(viewer.get_scroll(), 0);
+
+    // One broken line leaves the file as text.
+    viewer.set_entity(
+        ViewerEntity::Text(String::from("{\"id\": 1}\n{broken\n")),
+        Some(String::from("app.jsonl")),
+    );
+    assert!(matches!(viewer.get_entity_ref(), ViewerEntity::Text(_)));
+    assert_eq!(viewer.get_record_ref(), None);
+}
+
+#[test]
+fn json_is_exported_as_a_code_block() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("data.md");
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text(String::from("{\"a\":1}")), None);
+
+    viewer.export_as_markdown(&path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "```json\n{\n  \"a\": 1\n}\n```\n"
+    );
+}
+
+#[test]
+fn auto_refresh_is_due_after_the_interval() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    assert!(!viewer.is_refresh_due());
+    viewer.toggle_auto_refresh();
+    assert_eq!(viewer.get_auto_refresh(), Some(AUTO_REFRESH_INTERVAL));
+    assert!(!viewer.is_refresh_due());
+
+    viewer.set_auto_refresh(Some(Duration::from_millis(10)));
+    thread::sleep(Duration::from_millis(20));
+    assert!(viewer.is_refresh_due());
+
+    // The scroll survives the reload.
+    viewer.set_entity(ViewerEntity::Text(String::from("a\nb\nc")), None);
+    viewer.scroll_down(2);
+    viewer.start_refresh();
+    assert!(!viewer.is_refresh_due());
+    viewer.set_entity(ViewerEntity::Text(String::from("a\nb\nc\nd")), None);
+    assert_eq!(viewer.get_scroll(), 2);
+
+    viewer.toggle_auto_refresh();
+    assert_eq!(viewer.get_auto_refresh(), None);
+}
+
+#[test]
+fn bookmarks_are_toggled_and_jumped_to() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Text("line\n".repeat(50)), None);
+    viewer.scroll_down(9);
+    assert_eq!(viewer.toggle_bookmark(), (10, true));
+    viewer.scroll_down(20);
+    assert_eq!(viewer.toggle_bookmark(), (30, true));
+    viewer.scroll_up(25);
+    viewer.toggle_bookmark();
+    assert_eq!(viewer.get_line_bookmarks(), &[5, 10, 30]);
+
+    viewer.next_bookmark();
+    assert_eq!(viewer.get_scroll(), 9);
+    viewer.next_bookmark();
+    viewer.next_bookmark();
+    assert_eq!(viewer.get_scroll(), 29);
+    viewer.previous_bookmark();
+    assert_eq!(viewer.get_scroll(), 9);
+
+    assert_eq!(viewer.toggle_bookmark(), (10, false));
+    assert_eq!(viewer.get_line_bookmarks(), &[5, 30]);
+    viewer.clear();
+    assert!(viewer.get_line_bookmarks().is_empty());
+}
+
+#[test]
+fn scroll_position_is_restored_on_reopen() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("scroll.toml");
+    let text = "line\n".repeat(50);
+
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_scroll_positions(ScrollPositions::load(&store));
+    viewer.set_entity(
+        ViewerEntity::Text(text.clone()),
+        Some(String::from("a.txt")),
+    );
+    viewer.scroll_down(12);
+    viewer.clear();
+    assert_eq!(fs::read_to_string(&store).unwrap(), "\"a.txt\" = 12\n");
+
+    // A new session starts where the last one left the file.
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_scroll_positions(ScrollPositions::load(&store));
+    viewer.set_entity(
+        ViewerEntity::Text(text.clone()),
+        Some(String::from("a.txt")),
+    );
+    assert_eq!(viewer.get_scroll(), 12);
+    viewer.clear();
+    viewer.set_entity(
+        ViewerEntity::Text(text.clone()),
+        Some(String::from("b.txt")),
+    );
+    assert_eq!(viewer.get_scroll(), 0);
+    viewer.clear();
+
+    // A line asked for explicitly wins over the saved position.
+    viewer.set_loading(Some(String::from("a.txt")));
+    viewer.goto_line(3);
+    viewer.set_entity(ViewerEntity::Text(text), Some(String::from("a.txt")));
+    assert_eq!(viewer.get_scroll(), 2);
+}
+
+#[test]
+fn scrolling_follows_wrapped_rows() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    // Every line takes three rows of ten columns.
+    let line = "word word word word word word\n";
+    viewer.set_pane_size(10, 4);
+    viewer.set_entity(ViewerEntity::Text(line.repeat(10)), None);
+    assert_eq!(viewer.get_layout_ref().get_rows(), 30);
+    assert_eq!(viewer.get_scroll_percent(), Some(13));
+
+    viewer.goto_line(3);
+    assert_eq!(viewer.get_scroll(), 6);
+    viewer.scroll_down(100);
+    assert_eq!(viewer.get_scroll(), 26);
+    assert_eq!(viewer.get_scroll_percent(), Some(100));
+    viewer.goto_line(10);
+    assert_eq!(viewer.get_scroll(), 26);
+
+    // A wider pane keeps the top line in place.
+    viewer.goto_line(4);
+    viewer.set_pane_size(15, 4);
+    assert_eq!(viewer.get_layout_ref().get_rows(), 20);
+    assert_eq!(viewer.get_scroll(), 6);
+    assert_eq!(viewer.toggle_bookmark(), (4, true));
+}
+
+#[test]
+fn utf16_and_latin1_are_shown_as_text() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    let mut utf16 = vec![0xff, 0xfe];
+    utf16.extend("Grüße\n".encode_utf16().flat_map(u16::to_le_bytes));
+    viewer.set_entity(ViewerEntity::Binary(utf16), None);
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("Grüße\n"))
+    );
+    assert_eq!(viewer.get_encoding().map(|e| e.name()), Some("UTF-16LE"));
+
+    // Without a byte order mark the zero bytes tell.
+    let utf16: Vec<u8> = "plain".encode_utf16().flat_map(u16::to_be_bytes).collect();
+    viewer.set_entity(ViewerEntity::Binary(utf16), None);
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("plain"))
+    );
+    assert_eq!(viewer.get_encoding().map(|e| e.name()), Some("UTF-16BE"));
+
+    let latin1 = b"Le caf\xe9 cr\xe8me co\xfbte tr\xe8s cher \xe0 la gare.\n".to_vec();
+    viewer.set_entity(ViewerEntity::Binary(latin1), None);
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("Le café crème coûte très cher à la gare.\n"))
+    );
+    assert_eq!(
+        viewer.get_encoding().map(|e| e.name()),
+        Some("windows-1252")
+    );
+}
+
+#[test]
+fn random_bytes_stay_binary() {
+    let mut state: u32 = 7;
+    let random: Vec<u8> = (0..512)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect();
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_entity(ViewerEntity::Binary(random.clone()), None);
+    assert_eq!(viewer.get_entity_ref(), &ViewerEntity::Binary(random));
+    assert_eq!(viewer.cycle_encoding(), None);
+}
+
+#[test]
+fn encodings_are_detected_with_their_confidence() {
+    let latin1 = b"Le caf\xe9 cr\xe8me co\xfbte tr\xe8s cher \xe0 la gare.\n";
+    let (encoding, confident) = Viewer::detect_encoding(latin1);
+    assert_eq!(encoding.name(), "windows-1252");
+    assert!(confident);
+
+    let (encoding, _confident) = Viewer::detect_encoding("Прощай, немытая Россия".as_bytes());
+    assert_eq!(encoding.name(), "UTF-8");
+}
+
+#[test]
+fn encoding_is_cycled_on_request() {
+    let mut viewer = Viewer::new("secret").unwrap();
+    let latin1 = b"Le caf\xe9 cr\xe8me co\xfbte tr\xe8s cher \xe0 la gare.\n".to_vec();
+    viewer.set_entity(ViewerEntity::Binary(latin1), None);
+
+    assert_eq!(
+        viewer.cycle_encoding().map(|e| e.name()),
+        Some("ISO-8859-2")
+    );
+    let cycled: Vec<&str> = (0..6)
+        .filter_map(|_| viewer.cycle_encoding().map(|e| e.name()))
+        .collect();
+    assert_eq!(
+        cycled,
+        [
+            "windows-1251",
+            "KOI8-R",
+            "Shift_JIS",
+            "UTF-16LE",
+            "UTF-16BE",
+            "UTF-8"
+        ]
+    );
+    // Bytes that are not UTF-8 are replaced.
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from(
+            "Le caf\u{fffd} cr\u{fffd}me co\u{fffd}te tr\u{fffd}s cher \u{fffd} la gare.\n"
+        ))
+    );
+    viewer.cycle_encoding();
+    assert_eq!(
+        viewer.get_entity_ref(),
+        &ViewerEntity::Text(String::from("Le café crème coûte très cher à la gare.\n"))
+    );
+}
+
+#[test]
+fn marks_are_kept_per_file_and_clamped() {
+    let lines =
+        |count: usize| -> String { (1..=count).map(|line| format!("{}\n", line)).collect() };
+    let mut viewer = Viewer::new("secret").unwrap();
+    viewer.set_pane_size(20, 10);
+    viewer.set_entity(ViewerEntity::Text(lines(100)), Some(String::from("a.txt")));
+    assert!(!viewer.jump_to_mark('a'));
+
+    viewer.scroll_down(50);
+    assert!(viewer.set_mark('a'));
+    viewer.scroll_down(30);
+    assert!(viewer.set_mark('Z'));
+    assert!(!viewer.set_mark('1'));
+    viewer.scroll_to_top();
+    assert!(viewer.jump_to_mark('a'));
+    assert_eq!(viewer.get_scroll(), 50);
+    assert_eq!(
+        viewer.get_marks().into_iter().collect::<Vec<_>>(),
+        [('Z', 80), ('a', 50)]
+    );
+
+    viewer.set_entity(ViewerEntity::Text(lines(100)), Some(String::from("b.txt")));
+    assert!(viewer.get_marks().is_empty());
+    assert!(!viewer.jump_to_mark('a'));
+
+    // Reloaded shorter, the marks stay within the text.
+    viewer.set_entity(ViewerEntity::Text(lines(30)), Some(String::from("a.txt")));
+    assert_eq!(
+        viewer.get_marks().into_iter().collect::<Vec<_>>(),
+        [('Z', 20), ('a', 20)]
+    );
+    assert!(viewer.jump_to_mark('a'));
+    assert_eq!(viewer.get_scroll(), 20);
+}
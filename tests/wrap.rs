@@ -0,0 +1,90 @@
+use mystore::wrap::WrapLayout;
+use tui::{
+    backend::TestBackend,
+    widgets::{Paragraph, Wrap},
+    Terminal,
+};
+
+/// The first row a `Paragraph` draws when scrolled down by `scroll` rows.
+fn drawn_row(text: &str, width: u16, trim: bool, scroll: usize) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, 1)).unwrap();
+    terminal
+        .draw(|f| {
+            let paragraph = Paragraph::new(text)
+                .wrap(Wrap { trim })
+                .scroll((scroll as u16, 0));
+            f.render_widget(paragraph, f.size());
+        })
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    let row: String = (0..width)
+        .map(|x| buffer.get(x, 0).symbol.clone())
+        .collect();
+    row.trim_end().to_string()
+}
+
+/// Checks the layout against what a `Paragraph` draws: every line starts
+/// on its row and nothing is drawn past the last row.
+fn assert_matches_paragraph(text: &str, width: u16, trim: bool) -> WrapLayout {
+    let layout = WrapLayout::wrapped(text, width, trim);
+    assert_eq!(layout.get_lines(), text.lines().count());
+    for (id, line) in text.lines().enumerate() {
+        let row = drawn_row(text, width, trim, layout.get_line_start(id));
+        let word = line.split_whitespace().next().unwrap_or_default();
+        let start: String = word.chars().take(2).collect();
+        // Wide characters leave a blank cell behind them.
+        assert!(
+            row.replace(' ', "").starts_with(&start),
+            "line {} {:?} is not on row {:?}",
+            id,
+            line,
+            row
+        );
+    }
+    assert_eq!(drawn_row(text, width, trim, layout.get_rows()), "");
+
+    layout
+}
+
+#[test]
+fn long_words_are_broken_at_the_width() {
+    let layout = assert_matches_paragraph("abcdefghij\nxy", 4, true);
+    assert_eq!(layout.get_rows(), 4);
+    assert_eq!(layout.get_line_start(1), 3);
+    assert_eq!(layout.get_line_at(2), 0);
+    assert_eq!(layout.get_line_at(3), 1);
+    // Lines past the end start after the last row.
+    assert_eq!(layout.get_line_start(2), 4);
+
+    let layout = assert_matches_paragraph("one two three four\nfive", 9, true);
+    assert_eq!(layout.get_rows(), 4);
+    assert_eq!(layout.get_line_start(1), 3);
+}
+
+#[test]
+fn trailing_whitespace_follows_the_trim_setting() {
+    let text = "one two   \n   three\n\nfour";
+    let layout = assert_matches_paragraph(text, 7, true);
+    // The spaces left over after the wrap take a row of their own.
+    assert_eq!(layout.get_rows(), 5);
+    assert_eq!(layout.get_line_start(1), 2);
+    assert_eq!(layout.get_line_start(3), 4);
+
+    assert_matches_paragraph(text, 7, false);
+    assert_matches_paragraph("  indented line that wraps\n    x", 10, false);
+}
+
+#[test]
+fn wide_and_combining_characters_are_measured_in_columns() {
+    let layout = assert_matches_paragraph("日本語のテキスト\nnext", 5, true);
+    assert_eq!(layout.get_rows(), 5);
+    assert_eq!(layout.get_line_start(1), 4);
+
+    // `e` with a combining accent takes one column.
+    let layout = assert_matches_paragraph("cafe\u{301} cafe\u{301}\nok", 5, true);
+    assert_eq!(layout.get_rows(), 3);
+
+    let unwrapped = WrapLayout::unwrapped(3);
+    assert_eq!(unwrapped.get_rows(), 3);
+    assert_eq!(unwrapped.get_line_start(2), 2);
+}